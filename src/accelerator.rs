@@ -0,0 +1,427 @@
+//! Keyboard shortcuts
+//!
+//! An [`Accelerator`] is a key plus modifiers, parsed from strings such as
+//! `"Ctrl+Shift+P"` or `"F5"`. An [`AcceleratorTable`] maps them to the same
+//! [`MenuId`]s menu items use, so a shortcut and its menu item can share a
+//! handler.
+
+use crate::menu::{Menu, MenuId, MenuItemKind};
+
+/// Modifier keys held down with an accelerator
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    /// The Windows key, Command on macOS, Super on Linux
+    pub logo: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers { ctrl: false, alt: false, shift: false, logo: false };
+
+    pub fn is_empty(&self) -> bool {
+        return *self == Modifiers::NONE;
+    }
+}
+
+/// The non-modifier key of an accelerator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Key {
+    /// A key that types a character. Letters are always uppercase.
+    Character(char),
+    /// `F1` to `F24`
+    Function(u8),
+    Enter,
+    Escape,
+    Tab,
+    Space,
+    Backspace,
+    Delete,
+    Insert,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Names accepted for the named keys, the first one is used for display
+const KEY_NAMES: &[(Key, &[&str])] = &[
+    (Key::Enter, &["Enter", "Return"]),
+    (Key::Escape, &["Esc", "Escape"]),
+    (Key::Tab, &["Tab"]),
+    (Key::Space, &["Space"]),
+    (Key::Backspace, &["Backspace"]),
+    (Key::Delete, &["Delete", "Del"]),
+    (Key::Insert, &["Insert", "Ins"]),
+    (Key::Home, &["Home"]),
+    (Key::End, &["End"]),
+    (Key::PageUp, &["PageUp", "PgUp"]),
+    (Key::PageDown, &["PageDown", "PgDn"]),
+    (Key::Left, &["Left"]),
+    (Key::Right, &["Right"]),
+    (Key::Up, &["Up"]),
+    (Key::Down, &["Down"]),
+    (Key::Character('+'), &["Plus"]),
+    (Key::Character('-'), &["Minus"]),
+];
+
+impl Key {
+    fn parse(name: &str) -> Option<Key> {
+        for (key, names) in KEY_NAMES {
+            if names.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+                return Some(*key);
+            }
+        }
+
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            if c.is_control() || c.is_whitespace() {
+                return None;
+            }
+            return Some(Key::Character(c.to_ascii_uppercase()));
+        }
+
+        if let Some(number) = name.strip_prefix(['F', 'f']) {
+            if let Ok(number @ 1..=24) = number.parse::<u8>() {
+                return Some(Key::Function(number));
+            }
+        }
+        return None;
+    }
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Key::Character('+') => return write!(f, "Plus"),
+            Key::Character(c) => return write!(f, "{}", c),
+            Key::Function(number) => return write!(f, "F{}", number),
+            key => {
+                let name = KEY_NAMES
+                    .iter()
+                    .find(|(k, _)| k == key)
+                    .map(|(_, names)| names[0])
+                    .unwrap_or("?");
+                return write!(f, "{}", name);
+            }
+        }
+    }
+}
+
+/// Abstraction to represent an error while parsing an accelerator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseAcceleratorError {
+    /// The string is empty or only has modifiers
+    MissingKey,
+    /// A `+` with nothing on one side, as in `"Ctrl+A+"`
+    EmptyPart,
+    /// The string names more than one non-modifier key
+    MultipleKeys,
+    /// The same modifier appears twice
+    DuplicateModifier(String),
+    /// A part of the string is neither a modifier nor a key
+    UnknownKey(String),
+}
+
+impl std::fmt::Display for ParseAcceleratorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseAcceleratorError::MissingKey => write!(f, "the accelerator has no key"),
+            ParseAcceleratorError::EmptyPart => write!(f, "a \"+\" of the accelerator has nothing on one side"),
+            ParseAcceleratorError::MultipleKeys => write!(f, "the accelerator has more than one key"),
+            ParseAcceleratorError::DuplicateModifier(m) => write!(f, "modifier {:?} appears twice", m),
+            ParseAcceleratorError::UnknownKey(k) => write!(f, "unknown key {:?}", k),
+        }
+    }
+}
+
+impl std::error::Error for ParseAcceleratorError {}
+
+/// A key combination such as `Ctrl+Shift+P`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Accelerator {
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl Accelerator {
+    pub fn new(modifiers: Modifiers, key: Key) -> Self {
+        return Accelerator { modifiers, key };
+    }
+}
+
+impl std::str::FromStr for Accelerator {
+    type Err = ParseAcceleratorError;
+
+    /// Parses `"Ctrl+Shift+P"`, `"alt+f4"`, `"Ctrl++"` and the like.
+    /// Modifiers are `Ctrl`/`Control`, `Alt`/`Option`, `Shift` and
+    /// `Win`/`Super`/`Meta`/`Cmd`, in any order and any case.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        // A trailing "+" after a separator is the plus key: "Ctrl++"
+        let (body, plus_key) = match text.strip_suffix("++") {
+            Some(body) => (body, true),
+            None if text == "+" => ("", true),
+            None => (text, false),
+        };
+
+        let mut modifiers = Modifiers::NONE;
+        let mut key = if plus_key { Some(Key::Character('+')) } else { None };
+        // `"".split('+')` still yields one empty part
+        let parts = if body.is_empty() { None } else { Some(body.split('+').map(str::trim)) };
+        for part in parts.into_iter().flatten() {
+            if part.is_empty() {
+                return Err(ParseAcceleratorError::EmptyPart);
+            }
+            let flag = match part.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" => Some(&mut modifiers.ctrl),
+                "alt" | "option" => Some(&mut modifiers.alt),
+                "shift" => Some(&mut modifiers.shift),
+                "win" | "super" | "meta" | "cmd" | "command" | "logo" => Some(&mut modifiers.logo),
+                _ => None,
+            };
+            match flag {
+                Some(flag) if *flag => return Err(ParseAcceleratorError::DuplicateModifier(part.to_owned())),
+                Some(flag) => *flag = true,
+                None => {
+                    if key.is_some() {
+                        return Err(ParseAcceleratorError::MultipleKeys);
+                    }
+                    key = Some(Key::parse(part).ok_or_else(|| ParseAcceleratorError::UnknownKey(part.to_owned()))?);
+                }
+            }
+        }
+
+        match key {
+            Some(key) => return Ok(Accelerator { modifiers, key }),
+            None => return Err(ParseAcceleratorError::MissingKey),
+        }
+    }
+}
+
+impl std::fmt::Display for Accelerator {
+    /// Formats in the canonical `Ctrl+Alt+Shift+Win+Key` order
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.modifiers.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.alt {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.shift {
+            write!(f, "Shift+")?;
+        }
+        if self.modifiers.logo {
+            write!(f, "Win+")?;
+        }
+        return write!(f, "{}", self.key);
+    }
+}
+
+/// Shortcuts the operating system or desktop already uses. Binding them
+/// either never fires or breaks a shortcut users rely on.
+const RESERVED: &[(Modifiers, Key)] = &[
+    (Modifiers { ctrl: false, alt: true, shift: false, logo: false }, Key::Function(4)),
+    (Modifiers { ctrl: false, alt: true, shift: false, logo: false }, Key::Tab),
+    (Modifiers { ctrl: false, alt: true, shift: true, logo: false }, Key::Tab),
+    (Modifiers { ctrl: false, alt: true, shift: false, logo: false }, Key::Escape),
+    (Modifiers { ctrl: true, alt: false, shift: false, logo: false }, Key::Escape),
+    (Modifiers { ctrl: true, alt: true, shift: false, logo: false }, Key::Delete),
+    (Modifiers { ctrl: true, alt: false, shift: true, logo: false }, Key::Escape),
+    (Modifiers { ctrl: false, alt: false, shift: false, logo: true }, Key::Character('L')),
+    (Modifiers { ctrl: false, alt: false, shift: false, logo: true }, Key::Character('D')),
+    (Modifiers { ctrl: false, alt: false, shift: false, logo: true }, Key::Character('E')),
+    (Modifiers { ctrl: false, alt: false, shift: false, logo: true }, Key::Character('R')),
+    (Modifiers { ctrl: false, alt: false, shift: false, logo: true }, Key::Tab),
+];
+
+/// A problem found by [`AcceleratorTable::conflicts`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AcceleratorConflict {
+    /// The same shortcut is bound to several commands; only the first one fires
+    Duplicate { accelerator: Accelerator, commands: Vec<MenuId> },
+
+    /// The shortcut is used by the system
+    Reserved { accelerator: Accelerator, command: MenuId },
+}
+
+impl std::fmt::Display for AcceleratorConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AcceleratorConflict::Duplicate { accelerator, commands } => {
+                write!(f, "{} is bound to several commands: {:?}", accelerator, commands)
+            }
+            AcceleratorConflict::Reserved { accelerator, command } => {
+                write!(f, "{} (command {:?}) is reserved by the system", accelerator, command)
+            }
+        }
+    }
+}
+
+/// Shortcuts and the commands they trigger
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AcceleratorTable {
+    entries: Vec<(Accelerator, MenuId)>,
+}
+
+impl AcceleratorTable {
+    pub fn new() -> Self {
+        return AcceleratorTable::default();
+    }
+
+    pub fn add(&mut self, accelerator: Accelerator, command: MenuId) -> &mut Self {
+        self.entries.push((accelerator, command));
+        return self;
+    }
+
+    /// Parses and adds a shortcut in one go
+    pub fn add_str(&mut self, accelerator: &str, command: MenuId) -> Result<&mut Self, ParseAcceleratorError> {
+        let accelerator = accelerator.parse()?;
+        return Ok(self.add(accelerator, command));
+    }
+
+    /// Collects the shortcut text of every item of a menu and its submenus,
+    /// so the shortcuts shown in a menu also work
+    pub fn from_menu(menu: &Menu) -> Result<Self, ParseAcceleratorError> {
+        let mut table = AcceleratorTable::new();
+        table.add_menu(menu)?;
+        return Ok(table);
+    }
+
+    fn add_menu(&mut self, menu: &Menu) -> Result<(), ParseAcceleratorError> {
+        for item in menu.items() {
+            match (&item.kind, &item.accelerator) {
+                (MenuItemKind::Submenu(submenu), _) => self.add_menu(submenu)?,
+                (_, Some(accelerator)) => drop(self.add_str(accelerator, item.id)?),
+                _ => {}
+            }
+        }
+        return Ok(());
+    }
+
+    pub fn entries(&self) -> &[(Accelerator, MenuId)] {
+        return &self.entries;
+    }
+
+    /// The command bound to a shortcut. With duplicates the first one wins,
+    /// the same as the native tables do.
+    pub fn lookup(&self, accelerator: &Accelerator) -> Option<MenuId> {
+        return self.entries
+            .iter()
+            .find(|(a, _)| a == accelerator)
+            .map(|(_, command)| *command);
+    }
+
+    /// Finds shortcuts bound more than once and shortcuts reserved by the system
+    pub fn conflicts(&self) -> Vec<AcceleratorConflict> {
+        let mut conflicts = Vec::new();
+        for (index, (accelerator, command)) in self.entries.iter().enumerate() {
+            if RESERVED.iter().any(|(m, k)| *m == accelerator.modifiers && *k == accelerator.key) {
+                conflicts.push(AcceleratorConflict::Reserved { accelerator: *accelerator, command: *command });
+            }
+
+            // report each duplicated shortcut once, at its first occurrence
+            if self.entries[..index].iter().any(|(a, _)| a == accelerator) {
+                continue;
+            }
+            let commands: Vec<MenuId> = self.entries[index..]
+                .iter()
+                .filter(|(a, _)| a == accelerator)
+                .map(|(_, c)| *c)
+                .collect();
+            // binding the same command twice is harmless
+            if commands.iter().any(|c| c != command) {
+                conflicts.push(AcceleratorConflict::Duplicate { accelerator: *accelerator, commands });
+            }
+        }
+        return conflicts;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Accelerator, ParseAcceleratorError> {
+        return text.parse();
+    }
+
+    fn modifiers(ctrl: bool, alt: bool, shift: bool, logo: bool) -> Modifiers {
+        return Modifiers { ctrl, alt, shift, logo };
+    }
+
+    #[test]
+    fn parses_modifiers_and_keys_in_any_case() {
+        assert_eq!(parse("Ctrl+Shift+P"), Ok(Accelerator::new(modifiers(true, false, true, false), Key::Character('P'))));
+        assert_eq!(parse(" alt + f4 "), Ok(Accelerator::new(modifiers(false, true, false, false), Key::Function(4))));
+        assert_eq!(parse("cmd+option+pgdn"), Ok(Accelerator::new(modifiers(false, true, false, true), Key::PageDown)));
+        assert_eq!(parse("F24"), Ok(Accelerator::new(Modifiers::NONE, Key::Function(24))));
+        assert_eq!(parse("Ctrl+é"), Ok(Accelerator::new(modifiers(true, false, false, false), Key::Character('é'))));
+        assert_eq!(parse("F25"), Err(ParseAcceleratorError::UnknownKey("F25".to_owned())));
+        assert_eq!(parse("Ctrl+Foo"), Err(ParseAcceleratorError::UnknownKey("Foo".to_owned())));
+    }
+
+    #[test]
+    fn plus_key_and_empty_parts() {
+        let plus = Key::Character('+');
+        assert_eq!(parse("+"), Ok(Accelerator::new(Modifiers::NONE, plus)));
+        assert_eq!(parse("Ctrl++"), Ok(Accelerator::new(modifiers(true, false, false, false), plus)));
+        assert_eq!(parse("Ctrl+Plus"), Ok(Accelerator::new(modifiers(true, false, false, false), plus)));
+        for text in ["Ctrl+A+", "Ctrl++A", "+A", "Ctrl+", "Ctrl+ +A"] {
+            assert_eq!(parse(text), Err(ParseAcceleratorError::EmptyPart), "{:?}", text);
+        }
+        assert_eq!(parse("A++"), Err(ParseAcceleratorError::MultipleKeys));
+    }
+
+    #[test]
+    fn rejects_missing_duplicate_and_extra_parts() {
+        assert_eq!(parse(""), Err(ParseAcceleratorError::MissingKey));
+        assert_eq!(parse("Ctrl+Shift"), Err(ParseAcceleratorError::MissingKey));
+        assert_eq!(parse("Ctrl+control+A"), Err(ParseAcceleratorError::DuplicateModifier("control".to_owned())));
+        assert_eq!(parse("A+B"), Err(ParseAcceleratorError::MultipleKeys));
+    }
+
+    #[test]
+    fn display_is_canonical_and_parses_back() {
+        for (text, canonical) in [
+            ("shift+win+ctrl+alt+del", "Ctrl+Alt+Shift+Win+Delete"),
+            ("Ctrl++", "Ctrl+Plus"),
+            ("return", "Enter"),
+            ("f1", "F1"),
+        ] {
+            let accelerator = parse(text).unwrap();
+            assert_eq!(accelerator.to_string(), canonical);
+            assert_eq!(parse(canonical), Ok(accelerator));
+        }
+    }
+
+    #[test]
+    fn table_from_menu_and_conflicts() {
+        let menu = Menu::new()
+            .item(MenuId(1), "&Save")
+            .accelerator("Ctrl+S")
+            .submenu(
+                "&File",
+                Menu::new().item(MenuId(2), "Save &All").accelerator("ctrl+s").item(MenuId(3), "&Quit").accelerator("Alt+F4"),
+            );
+        let table = AcceleratorTable::from_menu(&menu).unwrap();
+        assert_eq!(table.entries().len(), 3);
+        assert_eq!(table.lookup(&parse("Ctrl+S").unwrap()), Some(MenuId(1)));
+        assert_eq!(table.lookup(&parse("Ctrl+Q").unwrap()), None);
+        assert_eq!(
+            table.conflicts(),
+            vec![
+                AcceleratorConflict::Duplicate { accelerator: parse("Ctrl+S").unwrap(), commands: vec![MenuId(1), MenuId(2)] },
+                AcceleratorConflict::Reserved { accelerator: parse("Alt+F4").unwrap(), command: MenuId(3) },
+            ],
+        );
+
+        let bad = Menu::new().item(MenuId(1), "Bad").accelerator("Ctrl+");
+        assert_eq!(AcceleratorTable::from_menu(&bad), Err(ParseAcceleratorError::EmptyPart));
+    }
+}
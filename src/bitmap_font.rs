@@ -0,0 +1,994 @@
+//! Bitmap fonts for labels, FPS counters and debug text
+//!
+//! A [`BitmapFont`] is a set of pre-rendered glyphs on one or more coverage
+//! pages. [`BitmapFont::builtin`] is a fixed-size 6x12 font covering ASCII
+//! and Latin-1 that needs no files at all. Other fonts come from the
+//! [BMFont](https://www.angelcode.com/products/bmfont/) format, text or
+//! binary, parsed by [`BmFontFile::parse`] with the page images loaded by
+//! the application.
+//!
+//! Text is drawn into a [`Canvas`], or turned into textured quads with
+//! [`BitmapFont::overlay_vertices`] for a GPU overlay: upload each page of
+//! [`BitmapFont::pages`] as a single channel texture and draw the triangles
+//! with an orthographic projection in pixels.
+
+use std::collections::HashMap;
+
+use crate::canvas::{Canvas, Color};
+use crate::geometry::PointF;
+
+/// Abstraction to represent an error while loading a font
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FontError {
+    /// A line of a BMFont text file can't be parsed. Lines start at 1.
+    Syntax { line: usize, reason: &'static str },
+
+    /// The binary BMFont data is malformed or truncated
+    InvalidBinary(&'static str),
+
+    /// The binary BMFont data has a version we can't read
+    UnsupportedVersion(u8),
+
+    /// There is no `common` line or block
+    MissingCommon,
+
+    /// The number of page images doesn't match the font
+    PageCount { expected: usize, actual: usize },
+
+    /// The pixel buffer doesn't match the page size
+    BadBufferLength { expected: usize, actual: usize },
+
+    /// A glyph lies outside its page, or on a page that doesn't exist
+    GlyphOutsidePage(u32),
+}
+
+impl std::fmt::Display for FontError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FontError::Syntax { line, reason } => write!(f, "BMFont line {}: {}", line, reason),
+            FontError::InvalidBinary(reason) => write!(f, "invalid binary BMFont data: {}", reason),
+            FontError::UnsupportedVersion(version) => write!(f, "unsupported BMFont version {}", version),
+            FontError::MissingCommon => write!(f, "BMFont data has no common block"),
+            FontError::PageCount { expected, actual } => {
+                write!(f, "font has {} pages but {} were given", expected, actual)
+            }
+            FontError::BadBufferLength { expected, actual } => {
+                write!(f, "font page buffer is {} bytes, expected {}", actual, expected)
+            }
+            FontError::GlyphOutsidePage(id) => write!(f, "glyph {} lies outside its page", id),
+        }
+    }
+}
+
+impl std::error::Error for FontError {}
+
+/// A page of glyph coverage, one byte per pixel, rows from top to bottom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontPage {
+    width: u32,
+    height: u32,
+    coverage: Vec<u8>,
+}
+
+impl FontPage {
+    /// A page from coverage values, 255 being fully inside a glyph
+    pub fn from_coverage(width: u32, height: u32, coverage: Vec<u8>) -> Result<Self, FontError> {
+        let expected = width as usize * height as usize;
+        if coverage.len() != expected {
+            return Err(FontError::BadBufferLength { expected, actual: coverage.len() });
+        }
+        return Ok(FontPage { width, height, coverage });
+    }
+
+    /// A page from an RGBA image. Coverage is taken from the alpha channel,
+    /// or from the red channel when the image is fully opaque, which is how
+    /// BMFont exports white-on-transparent and white-on-black pages.
+    pub fn from_rgba(width: u32, height: u32, rgba: &[u8]) -> Result<Self, FontError> {
+        let expected = width as usize * height as usize * 4;
+        if rgba.len() != expected {
+            return Err(FontError::BadBufferLength { expected, actual: rgba.len() });
+        }
+        let opaque = rgba.chunks_exact(4).all(|pixel| pixel[3] == 255);
+        let channel = if opaque { 0 } else { 3 };
+        let coverage = rgba.chunks_exact(4).map(|pixel| pixel[channel]).collect();
+        return Ok(FontPage { width, height, coverage });
+    }
+
+    pub fn width(&self) -> u32 {
+        return self.width;
+    }
+
+    pub fn height(&self) -> u32 {
+        return self.height;
+    }
+
+    pub fn coverage(&self) -> &[u8] {
+        return &self.coverage;
+    }
+}
+
+/// Where a glyph is on its page and how it is placed relative to the pen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Glyph {
+    pub page: u32,
+    /// Top left corner on the page, in pixels
+    pub position: [u32; 2],
+    pub size: [u32; 2],
+    /// From the pen at the top of the line to the top left corner of the glyph
+    pub offset: [i32; 2],
+    /// How far the pen moves after the glyph
+    pub advance: i32,
+}
+
+/// One `char` entry of a BMFont file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BmChar {
+    /// The Unicode code point, or `u32::MAX` for the glyph of missing characters
+    pub id: u32,
+    pub glyph: Glyph,
+}
+
+/// The description of a BMFont, without the page images
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BmFontFile {
+    pub face: String,
+    pub size: i32,
+    pub line_height: u32,
+    /// Distance from the top of a line to the baseline
+    pub base: u32,
+    /// Page image file names, relative to the font file
+    pub pages: Vec<String>,
+    pub chars: Vec<BmChar>,
+    /// `(first, second, amount)` pen adjustments between pairs of characters
+    pub kernings: Vec<(u32, u32, i32)>,
+}
+
+impl BmFontFile {
+    /// Parses a BMFont file in the text or the binary format
+    pub fn parse(data: &[u8]) -> Result<Self, FontError> {
+        if data.starts_with(b"BMF") {
+            return BmFontFile::parse_binary(data);
+        } else {
+            return BmFontFile::parse_text(&String::from_utf8_lossy(data));
+        }
+    }
+
+    /// Parses the text format, made of lines like `char id=65 x=0 y=0 ...`
+    pub fn parse_text(text: &str) -> Result<Self, FontError> {
+        let mut file = BmFontFile::default();
+        let mut has_common = false;
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let mut tokens = tokenize(line).into_iter();
+            let tag = match tokens.next() {
+                Some(tag) => tag,
+                None => continue,
+            };
+            let mut values = HashMap::new();
+            for token in tokens {
+                match token.split_once('=') {
+                    Some((key, value)) => values.insert(key.to_string(), value.to_string()),
+                    None => return Err(FontError::Syntax { line: line_number, reason: "expected key=value" }),
+                };
+            }
+            let number = |key: &'static str| -> Result<i64, FontError> {
+                return match values.get(key) {
+                    Some(value) => value
+                        .parse::<i64>()
+                        .map_err(|_| FontError::Syntax { line: line_number, reason: "expected a number" }),
+                    None => Err(FontError::Syntax { line: line_number, reason: "missing value" }),
+                };
+            };
+            let out_of_range = FontError::Syntax { line: line_number, reason: "number out of range" };
+            // Negative sizes and positions are read as 0, as BMFont tools do
+            let unsigned = |key: &'static str| -> Result<u32, FontError> {
+                return u32::try_from(number(key)?.max(0)).map_err(|_| out_of_range.clone());
+            };
+            let signed = |key: &'static str| -> Result<i32, FontError> {
+                return i32::try_from(number(key)?).map_err(|_| out_of_range.clone());
+            };
+            match tag.as_str() {
+                "info" => {
+                    file.face = values.get("face").cloned().unwrap_or_default();
+                    file.size = values.get("size").and_then(|size| size.parse().ok()).unwrap_or(0);
+                }
+                "common" => {
+                    file.line_height = unsigned("lineHeight")?;
+                    file.base = unsigned("base")?;
+                    has_common = true;
+                }
+                "page" => {
+                    // Page ids are a byte in the binary format
+                    let id = unsigned("id")? as usize;
+                    if id > u8::MAX as usize {
+                        return Err(out_of_range);
+                    }
+                    let name = values
+                        .get("file")
+                        .cloned()
+                        .ok_or(FontError::Syntax { line: line_number, reason: "page without a file" })?;
+                    if file.pages.len() <= id {
+                        file.pages.resize(id + 1, String::new());
+                    }
+                    file.pages[id] = name;
+                }
+                "char" => {
+                    let id = number("id")?;
+                    file.chars.push(BmChar {
+                        id: if id < 0 { u32::MAX } else { u32::try_from(id).map_err(|_| out_of_range.clone())? },
+                        glyph: Glyph {
+                            page: if values.contains_key("page") { unsigned("page")? } else { 0 },
+                            position: [unsigned("x")?, unsigned("y")?],
+                            size: [unsigned("width")?, unsigned("height")?],
+                            offset: [signed("xoffset")?, signed("yoffset")?],
+                            advance: signed("xadvance")?,
+                        },
+                    });
+                }
+                "kerning" => {
+                    file.kernings.push((unsigned("first")?, unsigned("second")?, signed("amount")?));
+                }
+                _ => {}
+            }
+        }
+        if !has_common {
+            return Err(FontError::MissingCommon);
+        }
+        return Ok(file);
+    }
+
+    /// Parses version 3 of the binary format: `BMF`, a version byte and
+    /// blocks made of a type byte, a little endian size and the content
+    pub fn parse_binary(data: &[u8]) -> Result<Self, FontError> {
+        if data.len() < 4 || &data[..3] != b"BMF" {
+            return Err(FontError::InvalidBinary("missing BMF signature"));
+        }
+        if data[3] != 3 {
+            return Err(FontError::UnsupportedVersion(data[3]));
+        }
+        let mut file = BmFontFile::default();
+        let mut has_common = false;
+        let mut rest = &data[4..];
+        while !rest.is_empty() {
+            if rest.len() < 5 {
+                return Err(FontError::InvalidBinary("truncated block header"));
+            }
+            let kind = rest[0];
+            let size = u32::from_le_bytes([rest[1], rest[2], rest[3], rest[4]]) as usize;
+            let block = rest.get(5..5 + size).ok_or(FontError::InvalidBinary("truncated block"))?;
+            rest = &rest[5 + size..];
+            match kind {
+                1 => {
+                    if block.len() < 14 {
+                        return Err(FontError::InvalidBinary("info block too short"));
+                    }
+                    file.size = i16::from_le_bytes([block[0], block[1]]) as i32;
+                    let name = &block[14..];
+                    let end = name.iter().position(|&byte| byte == 0).unwrap_or(name.len());
+                    file.face = String::from_utf8_lossy(&name[..end]).into_owned();
+                }
+                2 => {
+                    if block.len() < 15 {
+                        return Err(FontError::InvalidBinary("common block too short"));
+                    }
+                    file.line_height = u16::from_le_bytes([block[0], block[1]]) as u32;
+                    file.base = u16::from_le_bytes([block[2], block[3]]) as u32;
+                    has_common = true;
+                }
+                3 => {
+                    file.pages = block
+                        .split(|&byte| byte == 0)
+                        .filter(|name| !name.is_empty())
+                        .map(|name| String::from_utf8_lossy(name).into_owned())
+                        .collect();
+                }
+                4 => {
+                    if !block.len().is_multiple_of(20) {
+                        return Err(FontError::InvalidBinary("chars block size is not a multiple of 20"));
+                    }
+                    for entry in block.chunks_exact(20) {
+                        let u16_at = |i: usize| u16::from_le_bytes([entry[i], entry[i + 1]]);
+                        file.chars.push(BmChar {
+                            id: u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]),
+                            glyph: Glyph {
+                                page: entry[18] as u32,
+                                position: [u16_at(4) as u32, u16_at(6) as u32],
+                                size: [u16_at(8) as u32, u16_at(10) as u32],
+                                offset: [u16_at(12) as i16 as i32, u16_at(14) as i16 as i32],
+                                advance: u16_at(16) as i16 as i32,
+                            },
+                        });
+                    }
+                }
+                5 => {
+                    if !block.len().is_multiple_of(10) {
+                        return Err(FontError::InvalidBinary("kerning block size is not a multiple of 10"));
+                    }
+                    for entry in block.chunks_exact(10) {
+                        file.kernings.push((
+                            u32::from_le_bytes([entry[0], entry[1], entry[2], entry[3]]),
+                            u32::from_le_bytes([entry[4], entry[5], entry[6], entry[7]]),
+                            i16::from_le_bytes([entry[8], entry[9]]) as i32,
+                        ));
+                    }
+                }
+                _ => return Err(FontError::InvalidBinary("unknown block type")),
+            }
+        }
+        if !has_common {
+            return Err(FontError::MissingCommon);
+        }
+        return Ok(file);
+    }
+}
+
+/// Splits a BMFont text line on spaces, keeping quoted values together
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ' ' | '\t' if !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    return tokens;
+}
+
+/// A vertex of the textured triangles made by [`BitmapFont::overlay_vertices`]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverlayVertex {
+    /// In pixels from the top left corner
+    pub position: [f32; 2],
+    /// Normalized page coordinates
+    pub uv: [f32; 2],
+    /// Straight RGBA between 0 and 1, to multiply with the coverage
+    pub color: [f32; 4],
+}
+
+/// Glyphs on coverage pages, see the module documentation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitmapFont {
+    line_height: u32,
+    base: u32,
+    pages: Vec<FontPage>,
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), i32>,
+    /// Drawn for characters the font doesn't have
+    missing: Option<Glyph>,
+}
+
+/// Size of a cell of the built-in font
+const BUILTIN_CELL: [u32; 2] = [6, 12];
+
+/// Tabs move the pen to the next multiple of this many spaces
+const TAB_SPACES: i32 = 4;
+
+impl BitmapFont {
+    /// The built-in 6x12 font, covering printable ASCII and Latin-1
+    pub fn builtin() -> Self {
+        let characters: Vec<char> = (' '..='~').chain('\u{A0}'..='\u{FF}').collect();
+        let columns = 16;
+        let rows = (characters.len() as u32).div_ceil(columns);
+        let [cell_width, cell_height] = BUILTIN_CELL;
+        let page_width = columns * cell_width;
+        let mut coverage = vec![0; (page_width * rows * cell_height) as usize];
+        let mut glyphs = HashMap::new();
+        for (index, &c) in characters.iter().enumerate() {
+            let left = index as u32 % columns * cell_width;
+            let top = index as u32 / columns * cell_height;
+            for (y, bits) in builtin_cell(c).iter().enumerate() {
+                for x in 0..5 {
+                    if bits & (0x10 >> x) != 0 {
+                        coverage[((top + y as u32) * page_width + left + x) as usize] = 255;
+                    }
+                }
+            }
+            let glyph = Glyph { page: 0, position: [left, top], size: [5, cell_height], offset: [0, 0], advance: 6 };
+            glyphs.insert(c, glyph);
+        }
+        let page = FontPage { width: page_width, height: rows * cell_height, coverage };
+        return BitmapFont {
+            line_height: cell_height,
+            base: 9,
+            pages: vec![page],
+            missing: glyphs.get(&'?').copied(),
+            glyphs,
+            kerning: HashMap::new(),
+        };
+    }
+
+    /// A font from a parsed BMFont file and its page images, in order
+    pub fn from_bmfont(file: &BmFontFile, pages: Vec<FontPage>) -> Result<Self, FontError> {
+        if pages.len() != file.pages.len() {
+            return Err(FontError::PageCount { expected: file.pages.len(), actual: pages.len() });
+        }
+        let mut glyphs = HashMap::new();
+        let mut missing = None;
+        for &BmChar { id, glyph } in &file.chars {
+            // In u64 so that huge positions from a malformed file can't wrap around
+            let fits = pages.get(glyph.page as usize).is_some_and(|page| {
+                glyph.position[0] as u64 + glyph.size[0] as u64 <= page.width as u64
+                    && glyph.position[1] as u64 + glyph.size[1] as u64 <= page.height as u64
+            });
+            if !fits {
+                return Err(FontError::GlyphOutsidePage(id));
+            }
+            if id == u32::MAX {
+                missing = Some(glyph);
+            } else if let Some(c) = char::from_u32(id) {
+                glyphs.insert(c, glyph);
+            }
+        }
+        let mut kerning = HashMap::new();
+        for &(first, second, amount) in &file.kernings {
+            if let (Some(first), Some(second)) = (char::from_u32(first), char::from_u32(second)) {
+                kerning.insert((first, second), amount);
+            }
+        }
+        let missing = missing.or_else(|| glyphs.get(&'?').copied());
+        return Ok(BitmapFont { line_height: file.line_height, base: file.base, pages, glyphs, kerning, missing });
+    }
+
+    /// Distance between the tops of two lines
+    pub fn line_height(&self) -> u32 {
+        return self.line_height;
+    }
+
+    /// Distance from the top of a line to the baseline
+    pub fn base(&self) -> u32 {
+        return self.base;
+    }
+
+    pub fn pages(&self) -> &[FontPage] {
+        return &self.pages;
+    }
+
+    /// The glyph drawn for `c`, which is the missing glyph if the font
+    /// doesn't have one
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        return self.glyphs.get(&c).or(self.missing.as_ref());
+    }
+
+    pub fn has_glyph(&self, c: char) -> bool {
+        return self.glyphs.contains_key(&c);
+    }
+
+    /// Pen adjustment between two characters, usually negative
+    pub fn kerning(&self, first: char, second: char) -> i32 {
+        return self.kerning.get(&(first, second)).copied().unwrap_or(0);
+    }
+
+    /// Calls `f(glyph, top_left)` for every glyph of `text` drawn with the
+    /// top left corner of its first line at `position`
+    fn layout(&self, text: &str, [x, y]: [i32; 2], mut f: impl FnMut(&Glyph, [i32; 2])) {
+        for (index, line) in text.split('\n').enumerate() {
+            let top = y + index as i32 * self.line_height as i32;
+            let mut pen = 0;
+            let mut previous = None;
+            for c in line.chars() {
+                match c {
+                    '\r' => continue,
+                    '\t' => {
+                        let tab = TAB_SPACES * self.glyph(' ').map_or(0, |glyph| glyph.advance).max(1);
+                        pen = (pen / tab + 1) * tab;
+                        previous = None;
+                        continue;
+                    }
+                    _ => {}
+                }
+                if let Some(previous) = previous {
+                    pen += self.kerning(previous, c);
+                }
+                if let Some(glyph) = self.glyph(c) {
+                    f(glyph, [x + pen + glyph.offset[0], top + glyph.offset[1]]);
+                    pen += glyph.advance;
+                }
+                previous = Some(c);
+            }
+        }
+    }
+
+    /// Width of a single line of text, in pixels
+    fn line_width(&self, line: &str) -> u32 {
+        let mut width = 0;
+        self.layout(line, [0, 0], |glyph, [x, _]| width = width.max(x - glyph.offset[0] + glyph.advance));
+        return width.max(0) as u32;
+    }
+
+    /// Width of the longest line and height of all lines of `text`, in pixels
+    pub fn measure(&self, text: &str) -> [u32; 2] {
+        let width = text.split('\n').map(|line| self.line_width(line)).max().unwrap_or(0);
+        let lines = text.split('\n').count() as u32;
+        return [width, lines * self.line_height];
+    }
+
+    /// Breaks `text` into lines no wider than `max_width` pixels. Lines
+    /// break between words when possible and inside words longer than a
+    /// line otherwise. Existing line breaks are kept.
+    pub fn wrap(&self, text: &str, max_width: u32) -> Vec<String> {
+        let mut lines = Vec::new();
+        for paragraph in text.split('\n') {
+            let mut line = String::new();
+            for word in paragraph.split(' ') {
+                let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+                if self.line_width(&candidate) <= max_width {
+                    line = candidate;
+                    continue;
+                }
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                for c in word.chars() {
+                    line.push(c);
+                    if self.line_width(&line) > max_width && line.chars().count() > 1 {
+                        line.pop();
+                        lines.push(std::mem::replace(&mut line, c.to_string()));
+                    }
+                }
+            }
+            lines.push(line);
+        }
+        return lines;
+    }
+
+    /// Draws `text` with the top left corner of its first line at
+    /// `position`. The canvas transform only moves the text; glyphs are
+    /// drawn pixel for pixel.
+    pub fn draw(&self, canvas: &mut Canvas, position: PointF, text: &str, color: Color) {
+        let [x, y] = canvas.transform().apply(position.into());
+        self.layout(text, [x.round() as i32, y.round() as i32], |glyph, [left, top]| {
+            let page = &self.pages[glyph.page as usize];
+            for row in 0..glyph.size[1] {
+                for column in 0..glyph.size[0] {
+                    let index = (glyph.position[1] + row) * page.width + glyph.position[0] + column;
+                    let coverage = page.coverage[index as usize];
+                    if coverage > 0 {
+                        canvas.blend_pixel(left + column as i32, top + row as i32, color, coverage as f32 / 255.0);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Wraps `text` to `max_width` pixels with [`BitmapFont::wrap`] and draws it
+    pub fn draw_wrapped(&self, canvas: &mut Canvas, position: PointF, text: &str, max_width: u32, color: Color) {
+        self.draw(canvas, position, &self.wrap(text, max_width).join("\n"), color);
+    }
+
+    /// Two triangles per glyph of `text`, grouped by page: the vertices of
+    /// page `i` are at index `i`
+    pub fn overlay_vertices(&self, text: &str, position: PointF, color: Color) -> Vec<Vec<OverlayVertex>> {
+        let mut pages = vec![Vec::new(); self.pages.len()];
+        let color = [color.r, color.g, color.b, color.a].map(|channel| channel as f32 / 255.0);
+        let origin = position.round().into();
+        self.layout(text, origin, |glyph, [left, top]| {
+            if glyph.size[0] == 0 || glyph.size[1] == 0 {
+                return;
+            }
+            let page = &self.pages[glyph.page as usize];
+            let [x0, y0] = [left as f32, top as f32];
+            let [x1, y1] = [x0 + glyph.size[0] as f32, y0 + glyph.size[1] as f32];
+            let [u0, v0] = [glyph.position[0] as f32 / page.width as f32, glyph.position[1] as f32 / page.height as f32];
+            let u1 = (glyph.position[0] + glyph.size[0]) as f32 / page.width as f32;
+            let v1 = (glyph.position[1] + glyph.size[1]) as f32 / page.height as f32;
+            let vertex = |position, uv| OverlayVertex { position, uv, color };
+            pages[glyph.page as usize].extend_from_slice(&[
+                vertex([x0, y0], [u0, v0]),
+                vertex([x1, y0], [u1, v0]),
+                vertex([x1, y1], [u1, v1]),
+                vertex([x0, y0], [u0, v0]),
+                vertex([x1, y1], [u1, v1]),
+                vertex([x0, y1], [u0, v1]),
+            ]);
+        });
+        return pages;
+    }
+}
+
+/// Marks added to letters to make the accented Latin-1 characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Accent {
+    Grave,
+    Acute,
+    Circumflex,
+    Tilde,
+    Diaeresis,
+    Ring,
+    Cedilla,
+}
+
+impl Accent {
+    /// Two rows, drawn above capitals, above the x-height of lowercase
+    /// letters, or under the baseline for the cedilla
+    fn rows(self) -> [u8; 2] {
+        match self {
+            Accent::Grave => return [0x08, 0x04],
+            Accent::Acute => return [0x02, 0x04],
+            Accent::Circumflex => return [0x04, 0x0A],
+            Accent::Tilde => return [0x0D, 0x16],
+            Accent::Diaeresis => return [0x0A, 0x00],
+            Accent::Ring => return [0x0E, 0x0A],
+            Accent::Cedilla => return [0x04, 0x08],
+        }
+    }
+}
+
+/// Rows of a built-in cell, 5 bits wide with the leftmost pixel in bit 4.
+/// Rows 0 and 1 hold accents of capitals, rows 2 to 8 the letters down to
+/// the baseline and rows 9 and 10 the descenders.
+fn builtin_cell(c: char) -> [u8; 12] {
+    let mut cell = [0; 12];
+    if let Some(&(_, base, accent)) = BUILTIN_ACCENTED.iter().find(|entry| entry.0 == c) {
+        let body = if base == 'i' { &DOTLESS_I[..] } else { builtin_rows(base) };
+        cell[2..2 + body.len()].copy_from_slice(body);
+        let row = match accent {
+            Accent::Cedilla => 9,
+            _ if base.is_lowercase() => 2,
+            _ => 0,
+        };
+        cell[row] |= accent.rows()[0];
+        cell[row + 1] |= accent.rows()[1];
+    } else {
+        let rows = builtin_rows(c);
+        cell[2..2 + rows.len()].copy_from_slice(rows);
+    }
+    return cell;
+}
+
+fn builtin_rows(c: char) -> &'static [u8] {
+    return match BUILTIN_GLYPHS.iter().find(|entry| entry.0 == c) {
+        Some((_, rows)) => rows,
+        None => &[],
+    };
+}
+
+const DOTLESS_I: [u8; 7] = [0x00, 0x00, 0x0C, 0x04, 0x04, 0x04, 0x0E];
+
+/// Accented letters made from a base letter and a mark
+const BUILTIN_ACCENTED: &[(char, char, Accent)] = &[
+    ('À', 'A', Accent::Grave), ('Á', 'A', Accent::Acute), ('Â', 'A', Accent::Circumflex),
+    ('Ã', 'A', Accent::Tilde), ('Ä', 'A', Accent::Diaeresis), ('Å', 'A', Accent::Ring),
+    ('Ç', 'C', Accent::Cedilla), ('È', 'E', Accent::Grave), ('É', 'E', Accent::Acute),
+    ('Ê', 'E', Accent::Circumflex), ('Ë', 'E', Accent::Diaeresis), ('Ì', 'I', Accent::Grave),
+    ('Í', 'I', Accent::Acute), ('Î', 'I', Accent::Circumflex), ('Ï', 'I', Accent::Diaeresis),
+    ('Ñ', 'N', Accent::Tilde), ('Ò', 'O', Accent::Grave), ('Ó', 'O', Accent::Acute),
+    ('Ô', 'O', Accent::Circumflex), ('Õ', 'O', Accent::Tilde), ('Ö', 'O', Accent::Diaeresis),
+    ('Ù', 'U', Accent::Grave), ('Ú', 'U', Accent::Acute), ('Û', 'U', Accent::Circumflex),
+    ('Ü', 'U', Accent::Diaeresis), ('Ý', 'Y', Accent::Acute),
+    ('à', 'a', Accent::Grave), ('á', 'a', Accent::Acute), ('â', 'a', Accent::Circumflex),
+    ('ã', 'a', Accent::Tilde), ('ä', 'a', Accent::Diaeresis), ('å', 'a', Accent::Ring),
+    ('ç', 'c', Accent::Cedilla), ('è', 'e', Accent::Grave), ('é', 'e', Accent::Acute),
+    ('ê', 'e', Accent::Circumflex), ('ë', 'e', Accent::Diaeresis), ('ì', 'i', Accent::Grave),
+    ('í', 'i', Accent::Acute), ('î', 'i', Accent::Circumflex), ('ï', 'i', Accent::Diaeresis),
+    ('ñ', 'n', Accent::Tilde), ('ò', 'o', Accent::Grave), ('ó', 'o', Accent::Acute),
+    ('ô', 'o', Accent::Circumflex), ('õ', 'o', Accent::Tilde), ('ö', 'o', Accent::Diaeresis),
+    ('ù', 'u', Accent::Grave), ('ú', 'u', Accent::Acute), ('û', 'u', Accent::Circumflex),
+    ('ü', 'u', Accent::Diaeresis), ('ý', 'y', Accent::Acute), ('ÿ', 'y', Accent::Diaeresis),
+];
+
+/// Glyphs from the top of capitals down; the ninth row, when present, is
+/// the last descender row
+const BUILTIN_GLYPHS: &[(char, &[u8])] = &[
+    (' ', &[]),
+    ('!', &[0x04, 0x04, 0x04, 0x04, 0x00, 0x00, 0x04]),
+    ('"', &[0x0A, 0x0A, 0x0A]),
+    ('#', &[0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A]),
+    ('$', &[0x04, 0x0F, 0x14, 0x0E, 0x05, 0x1E, 0x04]),
+    ('%', &[0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03]),
+    ('&', &[0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D]),
+    ('\'', &[0x0C, 0x04, 0x08]),
+    ('(', &[0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+    (')', &[0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]),
+    ('*', &[0x00, 0x04, 0x15, 0x0E, 0x15, 0x04, 0x00]),
+    ('+', &[0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00]),
+    (',', &[0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08]),
+    ('-', &[0x00, 0x00, 0x00, 0x1F]),
+    ('.', &[0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C]),
+    ('/', &[0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+    ('0', &[0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', &[0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', &[0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', &[0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', &[0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', &[0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', &[0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', &[0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', &[0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', &[0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    (':', &[0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00]),
+    (';', &[0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x04, 0x08]),
+    ('<', &[0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02]),
+    ('=', &[0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00]),
+    ('>', &[0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08]),
+    ('?', &[0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+    ('@', &[0x0E, 0x11, 0x01, 0x0D, 0x15, 0x15, 0x0E]),
+    ('A', &[0x0E, 0x11, 0x11, 0x11, 0x1F, 0x11, 0x11]),
+    ('B', &[0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+    ('C', &[0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+    ('D', &[0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C]),
+    ('E', &[0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+    ('F', &[0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('G', &[0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]),
+    ('H', &[0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('I', &[0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('J', &[0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+    ('K', &[0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', &[0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
+    ('M', &[0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', &[0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', &[0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('P', &[0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+    ('Q', &[0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D]),
+    ('R', &[0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+    ('S', &[0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]),
+    ('T', &[0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', &[0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('V', &[0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('W', &[0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]),
+    ('X', &[0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11]),
+    ('Y', &[0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04]),
+    ('Z', &[0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
+    ('[', &[0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E]),
+    ('\\', &[0x00, 0x10, 0x08, 0x04, 0x02, 0x01, 0x00]),
+    (']', &[0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E]),
+    ('^', &[0x04, 0x0A, 0x11]),
+    ('_', &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F]),
+    ('`', &[0x08, 0x04, 0x02]),
+    ('a', &[0x00, 0x00, 0x0E, 0x01, 0x0F, 0x11, 0x0F]),
+    ('b', &[0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x1E]),
+    ('c', &[0x00, 0x00, 0x0E, 0x10, 0x10, 0x11, 0x0E]),
+    ('d', &[0x01, 0x01, 0x0D, 0x13, 0x11, 0x11, 0x0F]),
+    ('e', &[0x00, 0x00, 0x0E, 0x11, 0x1F, 0x10, 0x0E]),
+    ('f', &[0x06, 0x09, 0x08, 0x1C, 0x08, 0x08, 0x08]),
+    ('g', &[0x00, 0x00, 0x0F, 0x11, 0x11, 0x11, 0x0F, 0x01, 0x0E]),
+    ('h', &[0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x11]),
+    ('i', &[0x04, 0x00, 0x0C, 0x04, 0x04, 0x04, 0x0E]),
+    ('j', &[0x02, 0x00, 0x06, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+    ('k', &[0x10, 0x10, 0x12, 0x14, 0x18, 0x14, 0x12]),
+    ('l', &[0x0C, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('m', &[0x00, 0x00, 0x1A, 0x15, 0x15, 0x11, 0x11]),
+    ('n', &[0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11]),
+    ('o', &[0x00, 0x00, 0x0E, 0x11, 0x11, 0x11, 0x0E]),
+    ('p', &[0x00, 0x00, 0x1E, 0x11, 0x11, 0x11, 0x1E, 0x10, 0x10]),
+    ('q', &[0x00, 0x00, 0x0F, 0x11, 0x11, 0x11, 0x0F, 0x01, 0x01]),
+    ('r', &[0x00, 0x00, 0x16, 0x19, 0x10, 0x10, 0x10]),
+    ('s', &[0x00, 0x00, 0x0E, 0x10, 0x0E, 0x01, 0x1E]),
+    ('t', &[0x08, 0x08, 0x1C, 0x08, 0x08, 0x09, 0x06]),
+    ('u', &[0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0D]),
+    ('v', &[0x00, 0x00, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('w', &[0x00, 0x00, 0x11, 0x11, 0x15, 0x15, 0x0A]),
+    ('x', &[0x00, 0x00, 0x11, 0x0A, 0x04, 0x0A, 0x11]),
+    ('y', &[0x00, 0x00, 0x11, 0x11, 0x11, 0x11, 0x0F, 0x01, 0x0E]),
+    ('z', &[0x00, 0x00, 0x1F, 0x02, 0x04, 0x08, 0x1F]),
+    ('{', &[0x02, 0x04, 0x04, 0x08, 0x04, 0x04, 0x02]),
+    ('|', &[0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('}', &[0x08, 0x04, 0x04, 0x02, 0x04, 0x04, 0x08]),
+    ('~', &[0x00, 0x00, 0x08, 0x15, 0x02]),
+    ('\u{A0}', &[]),
+    ('¡', &[0x04, 0x00, 0x00, 0x04, 0x04, 0x04, 0x04]),
+    ('¢', &[0x04, 0x0E, 0x15, 0x14, 0x15, 0x0E, 0x04]),
+    ('£', &[0x06, 0x09, 0x08, 0x1C, 0x08, 0x09, 0x1E]),
+    ('¤', &[0x00, 0x11, 0x0E, 0x0A, 0x0E, 0x11]),
+    ('¥', &[0x11, 0x0A, 0x1F, 0x04, 0x1F, 0x04, 0x04]),
+    ('¦', &[0x04, 0x04, 0x04, 0x00, 0x04, 0x04, 0x04]),
+    ('§', &[0x0E, 0x10, 0x0E, 0x11, 0x0E, 0x01, 0x0E]),
+    ('¨', &[0x0A]),
+    ('©', &[0x0E, 0x11, 0x17, 0x19, 0x17, 0x11, 0x0E]),
+    ('ª', &[0x0E, 0x01, 0x0F, 0x11, 0x0F, 0x00, 0x1F]),
+    ('«', &[0x00, 0x05, 0x0A, 0x14, 0x0A, 0x05]),
+    ('¬', &[0x00, 0x00, 0x1F, 0x01, 0x01]),
+    ('\u{AD}', &[0x00, 0x00, 0x00, 0x0E]),
+    ('®', &[0x0E, 0x11, 0x1D, 0x1B, 0x1D, 0x1B, 0x0E]),
+    ('¯', &[0x1F]),
+    ('°', &[0x0C, 0x12, 0x12, 0x0C]),
+    ('±', &[0x04, 0x04, 0x1F, 0x04, 0x04, 0x00, 0x1F]),
+    ('²', &[0x0C, 0x02, 0x04, 0x08, 0x0E]),
+    ('³', &[0x0C, 0x02, 0x0C, 0x02, 0x0C]),
+    ('´', &[0x02, 0x04]),
+    ('µ', &[0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x1D, 0x10, 0x10]),
+    ('¶', &[0x0F, 0x1D, 0x1D, 0x0D, 0x05, 0x05, 0x05]),
+    ('·', &[0x00, 0x00, 0x00, 0x0C, 0x0C]),
+    ('¸', &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x08]),
+    ('¹', &[0x04, 0x0C, 0x04, 0x04, 0x0E]),
+    ('º', &[0x0E, 0x11, 0x11, 0x11, 0x0E, 0x00, 0x1F]),
+    ('»', &[0x00, 0x14, 0x0A, 0x05, 0x0A, 0x14]),
+    ('¼', &[0x10, 0x12, 0x14, 0x09, 0x13, 0x07, 0x01]),
+    ('½', &[0x10, 0x12, 0x14, 0x0E, 0x01, 0x02, 0x07]),
+    ('¾', &[0x18, 0x0A, 0x1C, 0x09, 0x13, 0x07, 0x01]),
+    ('¿', &[0x04, 0x00, 0x04, 0x08, 0x10, 0x11, 0x0E]),
+    ('Æ', &[0x0F, 0x14, 0x14, 0x1F, 0x14, 0x14, 0x17]),
+    ('Ð', &[0x1C, 0x12, 0x11, 0x1D, 0x11, 0x12, 0x1C]),
+    ('×', &[0x00, 0x11, 0x0A, 0x04, 0x0A, 0x11]),
+    ('Ø', &[0x0F, 0x13, 0x15, 0x15, 0x15, 0x19, 0x1E]),
+    ('Þ', &[0x10, 0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10]),
+    ('ß', &[0x0C, 0x12, 0x12, 0x16, 0x11, 0x11, 0x16]),
+    ('æ', &[0x00, 0x00, 0x1A, 0x05, 0x1F, 0x14, 0x1B]),
+    ('ð', &[0x0A, 0x04, 0x0A, 0x01, 0x0F, 0x11, 0x0E]),
+    ('÷', &[0x00, 0x04, 0x00, 0x1F, 0x00, 0x04]),
+    ('ø', &[0x00, 0x00, 0x0E, 0x13, 0x15, 0x19, 0x0E]),
+    ('þ', &[0x10, 0x10, 0x1E, 0x11, 0x11, 0x11, 0x1E, 0x10, 0x10]),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font_text(x: &str) -> String {
+        return format!(
+            "info face=\"Test\" size=8\ncommon lineHeight=8 base=6\npage id=0 file=\"test.png\"\n\
+             char id=65 x={} y=0 width=10 height=1 xoffset=0 yoffset=0 xadvance=10 page=0\n",
+            x,
+        );
+    }
+
+    #[test]
+    fn glyph_past_u32_max_is_outside_its_page() {
+        let file = BmFontFile::parse_text(&font_text("4294967290")).unwrap();
+        let page = FontPage::from_coverage(64, 1, vec![255; 64]).unwrap();
+        assert_eq!(BitmapFont::from_bmfont(&file, vec![page]), Err(FontError::GlyphOutsidePage(65)));
+    }
+
+    #[test]
+    fn numbers_above_u32_max_are_rejected() {
+        let error = BmFontFile::parse_text(&font_text("4294967296")).unwrap_err();
+        assert_eq!(error, FontError::Syntax { line: 4, reason: "number out of range" });
+    }
+
+    const TEXT_FONT: &str = "info face=\"Pixel Sans\" size=-8 bold=0\n\
+        common lineHeight=8 base=6 scaleW=16 scaleH=4 pages=1\n\
+        page id=0 file=\"pixel sans_0.png\"\n\
+        chars count=3\n\
+        char id=65 x=0 y=0 width=4 height=4 xoffset=0 yoffset=1 xadvance=5 page=0\n\
+        char id=86 x=4 y=0 width=4 height=4 xoffset=-1 yoffset=1 xadvance=5 page=0\n\
+        char id=-1 x=8 y=0 width=2 height=2 xoffset=0 yoffset=0 xadvance=3 page=0\n\
+        kernings count=1\n\
+        kerning first=65 second=86 amount=-2\n";
+
+    /// The same font as `TEXT_FONT` in the binary format
+    fn binary_font() -> Vec<u8> {
+        let block = |kind: u8, content: &[u8]| {
+            let mut block = vec![kind];
+            block.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            block.extend_from_slice(content);
+            return block;
+        };
+        let char_entry = |id: u32, x: u16, width: u16, height: u16, offset: [i16; 2], advance: i16| {
+            let mut entry = id.to_le_bytes().to_vec();
+            for value in [x, 0, width, height, offset[0] as u16, offset[1] as u16, advance as u16] {
+                entry.extend_from_slice(&value.to_le_bytes());
+            }
+            entry.extend_from_slice(&[0, 15]);
+            return entry;
+        };
+        let mut info = (-8i16).to_le_bytes().to_vec();
+        info.extend_from_slice(&[0; 12]);
+        info.extend_from_slice(b"Pixel Sans\0");
+        let mut common = 8u16.to_le_bytes().to_vec();
+        common.extend_from_slice(&6u16.to_le_bytes());
+        common.extend_from_slice(&[0; 11]);
+        let mut chars = char_entry(65, 0, 4, 4, [0, 1], 5);
+        chars.extend(char_entry(86, 4, 4, 4, [-1, 1], 5));
+        chars.extend(char_entry(u32::MAX, 8, 2, 2, [0, 0], 3));
+        let mut kerning = 65u32.to_le_bytes().to_vec();
+        kerning.extend_from_slice(&86u32.to_le_bytes());
+        kerning.extend_from_slice(&(-2i16).to_le_bytes());
+
+        let mut data = b"BMF\x03".to_vec();
+        data.extend(block(1, &info));
+        data.extend(block(2, &common));
+        data.extend(block(3, b"pixel sans_0.png\0"));
+        data.extend(block(4, &chars));
+        data.extend(block(5, &kerning));
+        return data;
+    }
+
+    fn test_font() -> BitmapFont {
+        let file = BmFontFile::parse(TEXT_FONT.as_bytes()).unwrap();
+        let page = FontPage::from_coverage(16, 4, vec![255; 64]).unwrap();
+        return BitmapFont::from_bmfont(&file, vec![page]).unwrap();
+    }
+
+    #[test]
+    fn text_and_binary_formats_agree() {
+        let text = BmFontFile::parse(TEXT_FONT.as_bytes()).unwrap();
+        assert_eq!(text.face, "Pixel Sans");
+        assert_eq!(text.size, -8);
+        assert_eq!([text.line_height, text.base], [8, 6]);
+        assert_eq!(text.pages, vec!["pixel sans_0.png".to_owned()]);
+        assert_eq!(text.chars.len(), 3);
+        assert_eq!(text.chars[2].id, u32::MAX);
+        assert_eq!(text.chars[1].glyph, Glyph { page: 0, position: [4, 0], size: [4, 4], offset: [-1, 1], advance: 5 });
+        assert_eq!(text.kernings, vec![(65, 86, -2)]);
+        assert_eq!(BmFontFile::parse(&binary_font()), Ok(text));
+    }
+
+    #[test]
+    fn malformed_text_is_rejected_with_its_line() {
+        let syntax = |line, reason| Err(FontError::Syntax { line, reason });
+        assert_eq!(BmFontFile::parse_text("info face=x\nchar id=1"), syntax(2, "missing value"));
+        assert_eq!(BmFontFile::parse_text("common lineHeight=eight base=1"), syntax(1, "expected a number"));
+        assert_eq!(BmFontFile::parse_text("common lineHeight 8"), syntax(1, "expected key=value"));
+        assert_eq!(BmFontFile::parse_text("common lineHeight=8 base=6\npage id=256 file=a.png"), syntax(2, "number out of range"));
+        assert_eq!(BmFontFile::parse_text("info face=x"), Err(FontError::MissingCommon));
+        // Negative sizes are read as 0
+        let file = BmFontFile::parse_text("common lineHeight=-3 base=6").unwrap();
+        assert_eq!(file.line_height, 0);
+    }
+
+    #[test]
+    fn truncated_and_corrupt_binary_is_rejected() {
+        let data = binary_font();
+        assert_eq!(BmFontFile::parse_binary(b"BM"), Err(FontError::InvalidBinary("missing BMF signature")));
+        assert_eq!(BmFontFile::parse_binary(b"BMF\x02"), Err(FontError::UnsupportedVersion(2)));
+        assert_eq!(BmFontFile::parse_binary(&data[..6]), Err(FontError::InvalidBinary("truncated block header")));
+        assert_eq!(BmFontFile::parse_binary(&data[..data.len() - 1]), Err(FontError::InvalidBinary("truncated block")));
+        let mut unknown = data.clone();
+        unknown.extend_from_slice(&[9, 0, 0, 0, 0]);
+        assert_eq!(BmFontFile::parse_binary(&unknown), Err(FontError::InvalidBinary("unknown block type")));
+        // A chars block with a partial entry
+        let mut partial = b"BMF\x03\x04\x13\x00\x00\x00".to_vec();
+        partial.extend_from_slice(&[0; 19]);
+        assert_eq!(
+            BmFontFile::parse_binary(&partial),
+            Err(FontError::InvalidBinary("chars block size is not a multiple of 20")),
+        );
+        assert_eq!(BmFontFile::parse_binary(b"BMF\x03"), Err(FontError::MissingCommon));
+    }
+
+    #[test]
+    fn pages_must_match_the_font() {
+        let file = BmFontFile::parse(TEXT_FONT.as_bytes()).unwrap();
+        assert_eq!(BitmapFont::from_bmfont(&file, Vec::new()), Err(FontError::PageCount { expected: 1, actual: 0 }));
+        let small = FontPage::from_coverage(8, 4, vec![0; 32]).unwrap();
+        assert_eq!(BitmapFont::from_bmfont(&file, vec![small]), Err(FontError::GlyphOutsidePage(u32::MAX)));
+        assert_eq!(FontPage::from_coverage(2, 2, vec![0; 3]), Err(FontError::BadBufferLength { expected: 4, actual: 3 }));
+        // Alpha is the coverage, unless every pixel is opaque
+        assert_eq!(FontPage::from_rgba(2, 1, &[255, 0, 0, 10, 0, 0, 0, 255]).unwrap().coverage(), &[10, 255]);
+        assert_eq!(FontPage::from_rgba(2, 1, &[200, 0, 0, 255, 0, 0, 0, 255]).unwrap().coverage(), &[200, 0]);
+    }
+
+    #[test]
+    fn layout_uses_kerning_and_the_missing_glyph() {
+        let font = test_font();
+        assert_eq!(font.kerning('A', 'V'), -2);
+        assert_eq!(font.measure("AV"), [8, 8]);
+        assert_eq!(font.measure("VA\nA"), [10, 16]);
+        assert!(!font.has_glyph('x'));
+        assert_eq!(font.glyph('x').map(|glyph| glyph.size), Some([2, 2]));
+
+        let vertices = font.overlay_vertices("AV", PointF::new(10.0, 20.0), Color::WHITE);
+        assert_eq!(vertices.len(), 1);
+        assert_eq!(vertices[0].len(), 12);
+        // V starts after A's advance, pulled back by the kerning and its offset
+        assert_eq!(vertices[0][0].position, [10.0, 21.0]);
+        assert_eq!(vertices[0][6].position, [12.0, 21.0]);
+        assert_eq!(vertices[0][6].uv, [0.25, 0.0]);
+    }
+
+    #[test]
+    fn builtin_font_measures_wraps_and_draws() {
+        let font = BitmapFont::builtin();
+        assert!(font.has_glyph('ß') && font.has_glyph('~') && !font.has_glyph('€'));
+        assert_eq!(font.measure("ab\ncde"), [18, 24]);
+        assert_eq!(font.measure("\ta"), [30, 12]);
+        assert_eq!(font.wrap("one two three", 48), vec!["one two", "three"]);
+        assert_eq!(font.wrap("abcdefgh", 24), vec!["abcd", "efgh"]);
+
+        let mut canvas = Canvas::new(12, 12);
+        font.draw(&mut canvas, PointF::new(0.0, 0.0), "I", Color::BLACK);
+        let drawn = canvas.pixels().chunks_exact(4).filter(|pixel| pixel[3] > 0).count();
+        assert!(drawn > 5);
+        assert!((0..12).all(|y| canvas.pixel(5, y) == Some(Color::TRANSPARENT)));
+    }
+}
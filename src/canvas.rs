@@ -0,0 +1,740 @@
+//! Drawing into a CPU pixel buffer
+//!
+//! A [`Canvas`] owns straight (not premultiplied) RGBA pixels, the same
+//! layout [`crate::icon::Icon`] takes, and draws shapes into them with
+//! source-over blending. Everything is computed in software, so a drawing
+//! looks the same on every backend and can be checked without a window.
+//! Backends copy the pixels to the screen, e.g. `draw_canvas` on Win32.
+//!
+//! Shapes are given in user coordinates that the current [`Transform`]
+//! maps to pixels, and only pixels inside the clip rectangle change. A
+//! pixel is covered when its centre is inside the shape, so a 1 pixel
+//! wide line along `y = 10.5` fills exactly row 10. [`Path`]s are drawn
+//! anti-aliased instead, with [`Canvas::fill_path`] and [`Canvas::stroke_path`].
+
+use std::f32::consts::PI;
+
+use crate::geometry::{PointF, Rect, RectF, Size, SizeF};
+use crate::path::{Path, Stroke};
+use crate::raster::{FillRule, Rasterizer};
+
+/// How far flattened curves of paths may be from the real ones, in pixels
+const PATH_TOLERANCE: f32 = 0.2;
+
+/// A straight alpha RGBA colour
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl Color {
+    pub const TRANSPARENT: Color = Color::rgba(0, 0, 0, 0);
+    pub const BLACK: Color = Color::rgb(0, 0, 0);
+    pub const WHITE: Color = Color::rgb(255, 255, 255);
+
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        return Color { r, g, b, a };
+    }
+
+    /// An opaque colour
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        return Color { r, g, b, a: 255 };
+    }
+
+    /// The same colour with another alpha
+    pub const fn with_alpha(self, a: u8) -> Self {
+        return Color { a, ..self };
+    }
+
+    pub const fn to_array(self) -> [u8; 4] {
+        return [self.r, self.g, self.b, self.a];
+    }
+}
+
+impl From<[u8; 3]> for Color {
+    fn from([r, g, b]: [u8; 3]) -> Self {
+        return Color::rgb(r, g, b);
+    }
+}
+
+impl From<[u8; 4]> for Color {
+    fn from([r, g, b, a]: [u8; 4]) -> Self {
+        return Color::rgba(r, g, b, a);
+    }
+}
+
+/// A 2D affine transform
+///
+/// A point `(x, y)` maps to `(a*x + c*y + e, b*x + d*y + f)`, the same
+/// matrix layout as SVG and the HTML canvas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        return Transform::IDENTITY;
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform { a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: 0.0, f: 0.0 };
+
+    pub fn translate(x: f32, y: f32) -> Self {
+        return Transform { e: x, f: y, ..Transform::IDENTITY };
+    }
+
+    pub fn scale(x: f32, y: f32) -> Self {
+        return Transform { a: x, d: y, ..Transform::IDENTITY };
+    }
+
+    /// Rotation by `angle` radians, clockwise on screen since y points down
+    pub fn rotate(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        return Transform { a: cos, b: sin, c: -sin, d: cos, e: 0.0, f: 0.0 };
+    }
+
+    /// `self` applied after `first`
+    pub fn after(&self, first: &Transform) -> Self {
+        return Transform {
+            a: self.a * first.a + self.c * first.b,
+            b: self.b * first.a + self.d * first.b,
+            c: self.a * first.c + self.c * first.d,
+            d: self.b * first.c + self.d * first.d,
+            e: self.a * first.e + self.c * first.f + self.e,
+            f: self.b * first.e + self.d * first.f + self.f,
+        };
+    }
+
+    pub fn apply(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        return [self.a * x + self.c * y + self.e, self.b * x + self.d * y + self.f];
+    }
+
+    /// How much lengths grow on average, used to pick how finely curves
+    /// are approximated
+    pub fn scale_factor(&self) -> f32 {
+        return (self.a * self.d - self.b * self.c).abs().sqrt();
+    }
+}
+
+/// What [`Canvas::save`] remembers
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct State {
+    transform: Transform,
+    /// In pixels, always inside the canvas
+    clip: Rect,
+}
+
+/// RGBA pixels with drawing operations, see the module documentation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    state: State,
+    saved: Vec<State>,
+}
+
+impl Canvas {
+    /// A transparent canvas
+    pub fn new(width: u32, height: u32) -> Self {
+        return Canvas {
+            width,
+            height,
+            pixels: vec![0; width as usize * height as usize * 4],
+            state: State { transform: Transform::IDENTITY, clip: Rect::new(0, 0, width as i32, height as i32) },
+            saved: Vec::new(),
+        };
+    }
+
+    /// A canvas over existing RGBA pixels
+    ///
+    /// **Returns:** `None` if there aren't `width * height * 4` bytes
+    pub fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> Option<Self> {
+        if pixels.len() != width as usize * height as usize * 4 {
+            return None;
+        }
+        let mut canvas = Canvas::new(0, 0);
+        canvas.width = width;
+        canvas.height = height;
+        canvas.pixels = pixels;
+        canvas.state.clip = canvas.bounds();
+        return Some(canvas);
+    }
+
+    pub fn width(&self) -> u32 {
+        return self.width;
+    }
+
+    pub fn height(&self) -> u32 {
+        return self.height;
+    }
+
+    /// The whole canvas as a rectangle at 0, 0
+    pub fn bounds(&self) -> Rect {
+        return Rect::new(0, 0, self.width as i32, self.height as i32);
+    }
+
+    /// Rows of RGBA pixels, top to bottom
+    pub fn pixels(&self) -> &[u8] {
+        return &self.pixels;
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        return &mut self.pixels;
+    }
+
+    pub fn into_pixels(self) -> Vec<u8> {
+        return self.pixels;
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Option<Color> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let p = &self.pixels[i..i + 4];
+        return Some(Color::rgba(p[0], p[1], p[2], p[3]));
+    }
+
+    /// Sets every pixel inside the clip rectangle, without blending
+    pub fn clear(&mut self, color: Color) {
+        let Rect { left, top, right, bottom } = self.state.clip;
+        for y in top..bottom {
+            for x in left..right {
+                let i = (y as usize * self.width as usize + x as usize) * 4;
+                self.pixels[i..i + 4].copy_from_slice(&color.to_array());
+            }
+        }
+    }
+
+    /// Pushes the transform and clip rectangle, to be brought back with [`Canvas::restore`]
+    pub fn save(&mut self) {
+        self.saved.push(self.state);
+    }
+
+    /// Brings back the transform and clip of the last [`Canvas::save`]. Does
+    /// nothing if there is none.
+    pub fn restore(&mut self) {
+        if let Some(state) = self.saved.pop() {
+            self.state = state;
+        }
+    }
+
+    pub fn transform(&self) -> Transform {
+        return self.state.transform;
+    }
+
+    /// Replaces the transform
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.state.transform = transform;
+    }
+
+    /// Applies `transform` to shapes before the current transform
+    pub fn concat(&mut self, transform: Transform) {
+        self.state.transform = self.state.transform.after(&transform);
+    }
+
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.concat(Transform::translate(x, y));
+    }
+
+    pub fn scale(&mut self, x: f32, y: f32) {
+        self.concat(Transform::scale(x, y));
+    }
+
+    pub fn rotate(&mut self, angle: f32) {
+        self.concat(Transform::rotate(angle));
+    }
+
+    /// Narrows the clip rectangle to its intersection with the given one,
+    /// in pixels. The transform doesn't apply.
+    pub fn clip(&mut self, rect: Rect) {
+        self.state.clip = self.state.clip.intersection(&rect).unwrap_or_default();
+    }
+
+    /// Lets drawing reach the whole canvas again
+    pub fn reset_clip(&mut self) {
+        self.state.clip = self.bounds();
+    }
+
+    /// The clip rectangle in pixels, empty when clipping left nothing
+    pub fn clip_rect(&self) -> Rect {
+        return self.state.clip;
+    }
+
+    pub fn fill_rect(&mut self, rect: RectF, color: Color) {
+        self.fill_contours(&[rect_contour(&rect)], color);
+    }
+
+    /// The outline of a rectangle, centred on its edges
+    pub fn stroke_rect(&mut self, rect: RectF, line_width: f32, color: Color) {
+        let half = line_width / 2.0;
+        let outer = rect_contour(&rect.inflate(half, half));
+        let inner = rect_contour(&rect.inflate(-half, -half));
+        self.fill_ring(outer, inner, color);
+    }
+
+    /// A straight line with square ends at the given points
+    pub fn line(&mut self, from: PointF, to: PointF, line_width: f32, color: Color) {
+        self.polyline(&[from, to], line_width, color);
+    }
+
+    /// Connected straight lines. Corners are filled with round joins, and
+    /// overlapping parts are only drawn once.
+    pub fn polyline(&mut self, points: &[PointF], line_width: f32, color: Color) {
+        let half = line_width / 2.0;
+        let mut contours = Vec::new();
+        for pair in points.windows(2) {
+            if let Some(quad) = segment_contour(pair[0], pair[1], half) {
+                contours.push(quad);
+            }
+        }
+        if points.len() > 2 && line_width > 1.0 {
+            let segments = self.curve_segments(half);
+            for &corner in &points[1..points.len() - 1] {
+                contours.push(ellipse_contour(corner, Size::new(half, half), segments));
+            }
+        }
+        self.fill_contours(&contours, color);
+    }
+
+    /// Fills a polygon; it is closed automatically. Self-intersecting
+    /// polygons use the non-zero rule.
+    pub fn fill_polygon(&mut self, points: &[PointF], color: Color) {
+        self.fill_contours(&[points.iter().map(|&point| point.into()).collect()], color);
+    }
+
+    pub fn fill_circle(&mut self, center: PointF, radius: f32, color: Color) {
+        self.fill_ellipse(center, Size::new(radius, radius), color);
+    }
+
+    pub fn stroke_circle(&mut self, center: PointF, radius: f32, line_width: f32, color: Color) {
+        self.stroke_ellipse(center, Size::new(radius, radius), line_width, color);
+    }
+
+    /// An ellipse with its horizontal and vertical radii
+    pub fn fill_ellipse(&mut self, center: PointF, radii: SizeF, color: Color) {
+        let segments = self.curve_segments(radii.width.max(radii.height));
+        self.fill_contours(&[ellipse_contour(center, radii, segments)], color);
+    }
+
+    /// The outline of an ellipse, centred on its edge
+    pub fn stroke_ellipse(&mut self, center: PointF, radii: SizeF, line_width: f32, color: Color) {
+        let half = line_width / 2.0;
+        let segments = self.curve_segments(radii.width.max(radii.height) + half);
+        let outer = ellipse_contour(center, Size::new(radii.width + half, radii.height + half), segments);
+        let inner = ellipse_contour(center, Size::new(radii.width - half, radii.height - half), segments);
+        self.fill_ring(outer, inner, color);
+    }
+
+    /// A rectangle with corners rounded by `radius`, at most half its width or height
+    pub fn fill_rounded_rect(&mut self, rect: RectF, radius: f32, color: Color) {
+        let segments = self.curve_segments(radius);
+        self.fill_contours(&[rounded_rect_contour(&rect, radius, segments)], color);
+    }
+
+    /// The outline of a rounded rectangle, centred on its edges
+    pub fn stroke_rounded_rect(&mut self, rect: RectF, radius: f32, line_width: f32, color: Color) {
+        let half = line_width / 2.0;
+        let segments = self.curve_segments(radius + half);
+        let outer = rounded_rect_contour(&rect.inflate(half, half), radius + half, segments);
+        let inner = rounded_rect_contour(&rect.inflate(-half, -half), radius - half, segments);
+        self.fill_ring(outer, inner, color);
+    }
+
+    /// Fills a path, anti-aliased. Open contours are closed with a straight line.
+    pub fn fill_path(&mut self, path: &Path, fill_rule: FillRule, color: Color) {
+        let polygons: Vec<Vec<[f32; 2]>> = path
+            .transformed(&self.state.transform)
+            .flatten(PATH_TOLERANCE)
+            .into_iter()
+            .map(|polyline| polyline.points)
+            .collect();
+        self.rasterize(&polygons, fill_rule, color);
+    }
+
+    /// Strokes a path, anti-aliased. The stroke width is in user
+    /// coordinates, so it scales with the transform.
+    pub fn stroke_path(&mut self, path: &Path, stroke: &Stroke, color: Color) {
+        let transform = self.state.transform;
+        let tolerance = PATH_TOLERANCE / transform.scale_factor().max(1e-6);
+        let polygons: Vec<Vec<[f32; 2]>> = stroke
+            .outline(&path.flatten(tolerance), tolerance)
+            .into_iter()
+            .map(|polygon| polygon.into_iter().map(|p| transform.apply(p)).collect())
+            .collect();
+        self.rasterize(&polygons, FillRule::NonZero, color);
+    }
+
+    /// Blends the anti-aliased coverage of polygons in pixel coordinates
+    fn rasterize(&mut self, polygons: &[Vec<[f32; 2]>], fill_rule: FillRule, color: Color) {
+        let Rect { left, top, right, bottom } = self.state.clip;
+        let mut bounds = [f32::INFINITY, f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY];
+        for &[x, y] in polygons.iter().flatten() {
+            bounds = [bounds[0].min(x), bounds[1].min(y), bounds[2].max(x), bounds[3].max(y)];
+        }
+        if color.a == 0 || !bounds.iter().all(|b| b.is_finite()) {
+            return;
+        }
+        // Only rasterize the part of the shape inside the clip
+        let x0 = (bounds[0].floor() as i32).max(left);
+        let y0 = (bounds[1].floor() as i32).max(top);
+        let x1 = (bounds[2].ceil() as i32).min(right);
+        let y1 = (bounds[3].ceil() as i32).min(bottom);
+        if x1 <= x0 || y1 <= y0 {
+            return;
+        }
+
+        let mut rasterizer = Rasterizer::new((x1 - x0) as u32, (y1 - y0) as u32);
+        let origin = [x0 as f32, y0 as f32];
+        for polygon in polygons {
+            let shifted: Vec<[f32; 2]> = polygon.iter().map(|p| [p[0] - origin[0], p[1] - origin[1]]).collect();
+            rasterizer.polygon(&shifted);
+        }
+        rasterizer.for_each_coverage(fill_rule, |x, y, coverage| {
+            self.blend_pixel(x0 + x as i32, y0 + y as i32, color, coverage);
+        });
+    }
+
+    /// Fills the area between two contours, the inner one being a hole
+    fn fill_ring(&mut self, outer: Vec<[f32; 2]>, inner: Vec<[f32; 2]>, color: Color) {
+        if inner.len() < 3 {
+            // The line is wider than the shape, which is then filled completely
+            self.fill_contours(&[outer], color);
+        } else {
+            let mut hole = orient_positive(inner);
+            hole.reverse();
+            self.fill_contours(&[orient_positive(outer), hole], color);
+        }
+    }
+
+    /// How many straight segments approximate a full turn of a curve with
+    /// this radius, keeping the error under a quarter pixel
+    fn curve_segments(&self, radius: f32) -> usize {
+        let radius = radius * self.state.transform.scale_factor();
+        if radius <= 0.25 {
+            return 4;
+        }
+        let step = (1.0 - 0.25 / radius).clamp(-1.0, 1.0).acos() * 2.0;
+        return ((2.0 * PI / step).ceil() as usize).clamp(8, 1024);
+    }
+
+    /// Transforms the contours and fills them together with the non-zero
+    /// rule. Every contour should turn the same way (see [`orient_positive`])
+    /// unless it is meant to cut a hole.
+    fn fill_contours(&mut self, contours: &[Vec<[f32; 2]>], color: Color) {
+        let transform = self.state.transform;
+        let mut edges = Vec::new();
+        for contour in contours {
+            let points: Vec<[f32; 2]> = contour.iter().map(|&p| transform.apply(p)).collect();
+            for i in 0..points.len() {
+                let from = points[i];
+                let to = points[(i + 1) % points.len()];
+                if from[1] != to[1] {
+                    edges.push((from, to));
+                }
+            }
+        }
+        self.fill_edges(&edges, color);
+    }
+
+    /// Scanline fill of closed edges in pixel coordinates, sampling at
+    /// pixel centres, with the non-zero rule
+    fn fill_edges(&mut self, edges: &[([f32; 2], [f32; 2])], color: Color) {
+        if edges.is_empty() || color.a == 0 {
+            return;
+        }
+        let Rect { left, top, right, bottom } = self.state.clip;
+        let min_y = edges.iter().map(|(a, b)| a[1].min(b[1])).fold(f32::INFINITY, f32::min);
+        let max_y = edges.iter().map(|(a, b)| a[1].max(b[1])).fold(f32::NEG_INFINITY, f32::max);
+        let first_row = ((min_y - 0.5).ceil() as i32).max(top);
+        let end_row = ((max_y - 0.5).ceil() as i32).min(bottom);
+
+        let mut crossings: Vec<(f32, i32)> = Vec::new();
+        for row in first_row..end_row {
+            let sample = row as f32 + 0.5;
+            crossings.clear();
+            for &(from, to) in edges {
+                let (upper, lower, winding) = if from[1] < to[1] { (from, to, 1) } else { (to, from, -1) };
+                if sample >= upper[1] && sample < lower[1] {
+                    let t = (sample - upper[1]) / (lower[1] - upper[1]);
+                    crossings.push((upper[0] + t * (lower[0] - upper[0]), winding));
+                }
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let mut winding = 0;
+            for pair in crossings.windows(2) {
+                winding += pair[0].1;
+                if winding == 0 {
+                    continue;
+                }
+                // Pixels whose centre is in [start, end)
+                let start = ((pair[0].0 - 0.5).ceil() as i32).max(left);
+                let end = ((pair[1].0 - 0.5).ceil() as i32).min(right);
+                for x in start..end {
+                    self.blend_pixel(x, row, color, 1.0);
+                }
+            }
+        }
+    }
+
+    /// Blends `color` over a pixel, with its alpha scaled by `coverage`
+    /// between 0 and 1. Pixels outside the canvas or the clip are left alone.
+    pub(crate) fn blend_pixel(&mut self, x: i32, y: i32, color: Color, coverage: f32) {
+        let Rect { left, top, right, bottom } = self.state.clip;
+        if x < left || y < top || x >= right || y >= bottom {
+            return;
+        }
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        let source_alpha = color.a as f32 / 255.0 * coverage.clamp(0.0, 1.0);
+        if source_alpha <= 0.0 {
+            return;
+        }
+        let dest = &mut self.pixels[i..i + 4];
+        let dest_alpha = dest[3] as f32 / 255.0;
+        let out_alpha = source_alpha + dest_alpha * (1.0 - source_alpha);
+        let source = [color.r, color.g, color.b];
+        for channel in 0..3 {
+            let value = (source[channel] as f32 * source_alpha
+                + dest[channel] as f32 * dest_alpha * (1.0 - source_alpha))
+                / out_alpha;
+            dest[channel] = value.round().clamp(0.0, 255.0) as u8;
+        }
+        dest[3] = (out_alpha * 255.0).round() as u8;
+    }
+}
+
+fn rect_contour(rect: &RectF) -> Vec<[f32; 2]> {
+    if rect.is_empty() {
+        return Vec::new();
+    }
+    let RectF { left, top, right, bottom } = *rect;
+    return vec![[left, top], [right, top], [right, bottom], [left, bottom]];
+}
+
+/// The rectangle around the segment from `from` to `to`, `half` on each side
+fn segment_contour(from: PointF, to: PointF, half: f32) -> Option<Vec<[f32; 2]>> {
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0.0 || half <= 0.0 {
+        return None;
+    }
+    let (nx, ny) = (-dy / length * half, dx / length * half);
+    let quad = vec![
+        [from.x + nx, from.y + ny],
+        [to.x + nx, to.y + ny],
+        [to.x - nx, to.y - ny],
+        [from.x - nx, from.y - ny],
+    ];
+    return Some(orient_positive(quad));
+}
+
+fn ellipse_contour(center: PointF, radii: SizeF, segments: usize) -> Vec<[f32; 2]> {
+    if radii.is_empty() {
+        return Vec::new();
+    }
+    return (0..segments)
+        .map(|i| {
+            let angle = i as f32 / segments as f32 * 2.0 * PI;
+            [center.x + radii.width * angle.cos(), center.y + radii.height * angle.sin()]
+        })
+        .collect();
+}
+
+/// `segments` is for a full turn; each corner gets a quarter
+fn rounded_rect_contour(rect: &RectF, radius: f32, segments: usize) -> Vec<[f32; 2]> {
+    if rect.is_empty() {
+        return Vec::new();
+    }
+    let radius = radius.min(rect.width() / 2.0).min(rect.height() / 2.0);
+    if radius <= 0.0 {
+        return rect_contour(rect);
+    }
+    let quarter = segments.div_ceil(4).max(1);
+    let RectF { left, top, right, bottom } = *rect;
+    let corners = [
+        ([right - radius, top + radius], -PI / 2.0),
+        ([right - radius, bottom - radius], 0.0),
+        ([left + radius, bottom - radius], PI / 2.0),
+        ([left + radius, top + radius], PI),
+    ];
+    let mut points = Vec::with_capacity(4 * (quarter + 1));
+    for (center, start) in corners {
+        for i in 0..=quarter {
+            let angle = start + i as f32 / quarter as f32 * PI / 2.0;
+            points.push([center[0] + radius * angle.cos(), center[1] + radius * angle.sin()]);
+        }
+    }
+    return points;
+}
+
+/// Twice the signed area; positive when the contour turns clockwise on screen
+fn signed_area(points: &[[f32; 2]]) -> f32 {
+    let mut area = 0.0;
+    for i in 0..points.len() {
+        let [x0, y0] = points[i];
+        let [x1, y1] = points[(i + 1) % points.len()];
+        area += x0 * y1 - x1 * y0;
+    }
+    return area;
+}
+
+/// Reverses a contour if needed so all contours turn the same way and
+/// overlapping ones add up instead of cancelling out
+fn orient_positive(mut points: Vec<[f32; 2]>) -> Vec<[f32; 2]> {
+    if signed_area(&points) < 0.0 {
+        points.reverse();
+    }
+    return points;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::LineCap;
+
+    /// The pixels that aren't fully transparent, row by row
+    fn covered(canvas: &Canvas) -> Vec<[u32; 2]> {
+        let mut pixels = Vec::new();
+        for y in 0..canvas.height() {
+            for x in 0..canvas.width() {
+                if canvas.pixel(x, y).unwrap().a > 0 {
+                    pixels.push([x, y]);
+                }
+            }
+        }
+        return pixels;
+    }
+
+    #[test]
+    fn rectangles_cover_pixel_centres() {
+        let mut canvas = Canvas::new(6, 6);
+        canvas.fill_rect(RectF::new(1.0, 1.0, 3.0, 3.4), Color::BLACK);
+        assert_eq!(covered(&canvas), vec![[1, 1], [2, 1], [1, 2], [2, 2]]);
+
+        // Not reaching the centre of the fourth row or column
+        let mut canvas = Canvas::new(6, 6);
+        canvas.fill_rect(RectF::new(0.6, 0.6, 3.5, 3.5), Color::BLACK);
+        assert_eq!(covered(&canvas).len(), 4);
+    }
+
+    #[test]
+    fn one_pixel_line_on_a_half_row_fills_that_row() {
+        let mut canvas = Canvas::new(16, 16);
+        canvas.line(PointF::new(2.0, 10.5), PointF::new(12.0, 10.5), 1.0, Color::WHITE);
+        let pixels = covered(&canvas);
+        assert_eq!(pixels.len(), 10);
+        assert!(pixels.iter().all(|&[x, y]| y == 10 && (2..12).contains(&x)));
+    }
+
+    #[test]
+    fn stroked_rectangles_are_hollow() {
+        let mut canvas = Canvas::new(10, 10);
+        canvas.stroke_rect(RectF::new(1.5, 1.5, 8.5, 8.5), 1.0, Color::BLACK);
+        assert_eq!(covered(&canvas).len(), 4 * 7);
+        assert_eq!(canvas.pixel(4, 4), Some(Color::TRANSPARENT));
+
+        // A line wider than the rectangle fills it completely
+        let mut canvas = Canvas::new(10, 10);
+        canvas.stroke_rect(RectF::new(3.0, 3.0, 5.0, 5.0), 4.0, Color::BLACK);
+        assert_eq!(covered(&canvas).len(), 36);
+    }
+
+    #[test]
+    fn circles_have_about_the_right_area() {
+        let mut canvas = Canvas::new(40, 40);
+        canvas.fill_circle(PointF::new(20.0, 20.0), 10.0, Color::BLACK);
+        let area = covered(&canvas).len() as f32;
+        assert!((area - PI * 100.0).abs() < 10.0, "{}", area);
+
+        let mut canvas = Canvas::new(40, 40);
+        canvas.fill_ellipse(PointF::new(20.0, 20.0), SizeF::new(0.0, 10.0), Color::BLACK);
+        assert!(covered(&canvas).is_empty());
+    }
+
+    #[test]
+    fn rounded_corners_are_left_out() {
+        let mut canvas = Canvas::new(20, 20);
+        canvas.fill_rounded_rect(RectF::new(0.0, 0.0, 20.0, 20.0), 8.0, Color::BLACK);
+        assert_eq!(canvas.pixel(0, 0), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.pixel(19, 19), Some(Color::TRANSPARENT));
+        assert_eq!(canvas.pixel(10, 0), Some(Color::BLACK));
+        assert_eq!(canvas.pixel(0, 10), Some(Color::BLACK));
+    }
+
+    #[test]
+    fn clip_and_transform_are_saved_and_restored() {
+        let mut canvas = Canvas::new(10, 10);
+        canvas.save();
+        canvas.clip(Rect::new(0, 0, 5, 10));
+        canvas.translate(2.0, 0.0);
+        canvas.fill_rect(RectF::new(0.0, 0.0, 8.0, 1.0), Color::BLACK);
+        assert_eq!(covered(&canvas), vec![[2, 0], [3, 0], [4, 0]]);
+
+        canvas.restore();
+        assert_eq!(canvas.clip_rect(), canvas.bounds());
+        assert_eq!(canvas.transform(), Transform::IDENTITY);
+        canvas.clip(Rect::new(20, 20, 30, 30));
+        assert!(canvas.clip_rect().is_empty());
+        canvas.clear(Color::WHITE);
+        assert_eq!(covered(&canvas).len(), 3);
+    }
+
+    #[test]
+    fn blending_is_source_over_with_straight_alpha() {
+        let mut canvas = Canvas::new(1, 1);
+        canvas.clear(Color::WHITE);
+        canvas.fill_rect(RectF::new(0.0, 0.0, 1.0, 1.0), Color::rgba(255, 0, 0, 128));
+        assert_eq!(canvas.pixel(0, 0), Some(Color::rgb(255, 127, 127)));
+
+        // Over transparent pixels the colour is kept as is
+        let mut canvas = Canvas::new(1, 1);
+        canvas.fill_rect(RectF::new(0.0, 0.0, 1.0, 1.0), Color::rgba(10, 20, 30, 100));
+        assert_eq!(canvas.pixel(0, 0), Some(Color::rgba(10, 20, 30, 100)));
+    }
+
+    #[test]
+    fn transforms_compose_in_order() {
+        let transform = Transform::translate(10.0, 0.0).after(&Transform::scale(2.0, 3.0));
+        assert_eq!(transform.apply([1.0, 1.0]), [12.0, 3.0]);
+        let [x, y] = Transform::rotate(PI / 2.0).apply([1.0, 0.0]);
+        assert!(x.abs() < 1e-6 && (y - 1.0).abs() < 1e-6);
+        assert!((Transform::scale(2.0, 8.0).scale_factor() - 4.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn paths_are_anti_aliased() {
+        let mut canvas = Canvas::new(4, 4);
+        let path = Path::rect([0.0, 0.0], [2.5, 4.0]);
+        canvas.fill_path(&path, FillRule::NonZero, Color::BLACK);
+        assert_eq!(canvas.pixel(1, 1), Some(Color::BLACK));
+        assert_eq!(canvas.pixel(2, 1).map(|c| c.a), Some(128));
+        assert_eq!(canvas.pixel(3, 1), Some(Color::TRANSPARENT));
+
+        let mut canvas = Canvas::new(8, 8);
+        let line = Path::new().move_to([1.0, 4.0]).line_to([7.0, 4.0]);
+        canvas.stroke_path(&line, &Stroke::new(2.0).cap(LineCap::Butt), Color::BLACK);
+        assert_eq!(covered(&canvas).len(), 12);
+        assert_eq!(canvas.pixel(3, 3), Some(Color::BLACK));
+    }
+
+    #[test]
+    fn from_rgba_checks_the_length() {
+        assert!(Canvas::from_rgba(2, 2, vec![0; 15]).is_none());
+        let canvas = Canvas::from_rgba(1, 2, vec![1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+        assert_eq!(canvas.pixel(0, 1), Some(Color::rgba(5, 6, 7, 8)));
+        assert_eq!(canvas.pixel(1, 0), None);
+        assert_eq!(canvas.clip_rect(), Rect::new(0, 0, 1, 2));
+    }
+}
@@ -0,0 +1,350 @@
+//! Clipboard access
+//!
+//! [`Clipboard`] is implemented by every backend. [`MemoryClipboard`] keeps
+//! everything in process, for headless runs and tests. The DIB helpers are
+//! the formats Windows uses for images and are also useful to other backends
+//! that exchange `image/bmp`.
+
+use crate::image::{bmp, ImageError};
+
+/// Abstraction to represent a clipboard error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClipboardError {
+    /// The operating system refused the operation, with its error code.
+    /// On Win32 this usually means another program has the clipboard open.
+    Platform(u32),
+
+    /// The clipboard holds data in the expected format, but it is malformed
+    InvalidData(&'static str),
+
+    /// Custom format names can't be empty
+    InvalidFormatName,
+
+    /// The clipboard holds an image that can't be decoded
+    Image(ImageError),
+}
+
+impl std::fmt::Display for ClipboardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ClipboardError::Platform(code) => write!(f, "clipboard error (os error {})", code),
+            ClipboardError::InvalidData(reason) => write!(f, "invalid clipboard data: {}", reason),
+            ClipboardError::InvalidFormatName => write!(f, "invalid clipboard format name"),
+            ClipboardError::Image(error) => write!(f, "invalid clipboard image: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ClipboardError {}
+
+/// An image on the clipboard, stored as straight (non-premultiplied)
+/// RGBA8 rows from top to bottom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipboardImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl ClipboardImage {
+    pub fn new(width: u32, height: u32, rgba: Vec<u8>) -> Result<Self, ClipboardError> {
+        if rgba.len() as u64 != width as u64 * height as u64 * 4 {
+            return Err(ClipboardError::InvalidData("pixel buffer doesn't match the image size"));
+        }
+        return Ok(ClipboardImage { width, height, rgba });
+    }
+}
+
+/// Read and write access to a clipboard
+///
+/// Every `set_*` call replaces the whole clipboard contents, the same way
+/// copying in any application does.
+pub trait Clipboard {
+    /// **Returns:** `None` when the clipboard holds no text
+    fn get_text(&mut self) -> Result<Option<String>, ClipboardError>;
+    fn set_text(&mut self, text: &str) -> Result<(), ClipboardError>;
+
+    /// **Returns:** `None` when the clipboard holds no image
+    fn get_image(&mut self) -> Result<Option<ClipboardImage>, ClipboardError>;
+    fn set_image(&mut self, image: &ClipboardImage) -> Result<(), ClipboardError>;
+
+    /// Reads application defined data registered under `format`
+    ///
+    /// **Returns:** exactly the bytes given to [`Clipboard::set_custom`]
+    fn get_custom(&mut self, format: &str) -> Result<Option<Vec<u8>>, ClipboardError>;
+    fn set_custom(&mut self, format: &str, data: &[u8]) -> Result<(), ClipboardError>;
+
+    /// Empties the clipboard
+    fn clear(&mut self) -> Result<(), ClipboardError>;
+
+    /// A number that changes every time the clipboard contents change
+    fn sequence_number(&self) -> u64;
+}
+
+/// Polls a clipboard for changes. Backends that get change notifications
+/// from the system also deliver [`crate::event::WindowEvent::ClipboardChanged`].
+#[derive(Debug, Clone)]
+pub struct ClipboardWatcher {
+    last_sequence: u64,
+}
+
+impl ClipboardWatcher {
+    /// Starts watching from the current clipboard contents
+    pub fn new(clipboard: &dyn Clipboard) -> Self {
+        return ClipboardWatcher { last_sequence: clipboard.sequence_number() };
+    }
+
+    /// **Returns:** `true` if the clipboard changed since the last call
+    pub fn poll(&mut self, clipboard: &dyn Clipboard) -> bool {
+        let sequence = clipboard.sequence_number();
+        let changed = sequence != self.last_sequence;
+        self.last_sequence = sequence;
+        return changed;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MemoryContents {
+    Empty,
+    Text(String),
+    Image(ClipboardImage),
+    Custom(String, Vec<u8>),
+}
+
+/// Clipboard that lives in process memory, for headless runs and tests
+#[derive(Debug, Clone)]
+pub struct MemoryClipboard {
+    contents: MemoryContents,
+    sequence: u64,
+}
+
+impl Default for MemoryClipboard {
+    fn default() -> Self {
+        return MemoryClipboard { contents: MemoryContents::Empty, sequence: 0 };
+    }
+}
+
+impl MemoryClipboard {
+    pub fn new() -> Self {
+        return MemoryClipboard::default();
+    }
+
+    fn replace(&mut self, contents: MemoryContents) {
+        self.contents = contents;
+        self.sequence += 1;
+    }
+}
+
+impl Clipboard for MemoryClipboard {
+    fn get_text(&mut self) -> Result<Option<String>, ClipboardError> {
+        match &self.contents {
+            MemoryContents::Text(text) => return Ok(Some(text.clone())),
+            _ => return Ok(None),
+        }
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), ClipboardError> {
+        self.replace(MemoryContents::Text(text.to_owned()));
+        return Ok(());
+    }
+
+    fn get_image(&mut self) -> Result<Option<ClipboardImage>, ClipboardError> {
+        match &self.contents {
+            MemoryContents::Image(image) => return Ok(Some(image.clone())),
+            _ => return Ok(None),
+        }
+    }
+
+    fn set_image(&mut self, image: &ClipboardImage) -> Result<(), ClipboardError> {
+        self.replace(MemoryContents::Image(image.clone()));
+        return Ok(());
+    }
+
+    fn get_custom(&mut self, format: &str) -> Result<Option<Vec<u8>>, ClipboardError> {
+        if format.is_empty() {
+            return Err(ClipboardError::InvalidFormatName);
+        }
+        match &self.contents {
+            MemoryContents::Custom(name, data) if name == format => return Ok(Some(data.clone())),
+            _ => return Ok(None),
+        }
+    }
+
+    fn set_custom(&mut self, format: &str, data: &[u8]) -> Result<(), ClipboardError> {
+        if format.is_empty() {
+            return Err(ClipboardError::InvalidFormatName);
+        }
+        self.replace(MemoryContents::Custom(format.to_owned(), data.to_vec()));
+        return Ok(());
+    }
+
+    fn clear(&mut self) -> Result<(), ClipboardError> {
+        self.replace(MemoryContents::Empty);
+        return Ok(());
+    }
+
+    fn sequence_number(&self) -> u64 {
+        return self.sequence;
+    }
+}
+
+// DIB helpers //
+
+const BITMAPV5HEADER_SIZE: u32 = 124;
+const BI_BITFIELDS: u32 = 3;
+/// `LCS_sRGB` colour space tag ('sRGB')
+const LCS_SRGB: u32 = 0x7352_4742;
+/// `LCS_GM_IMAGES` rendering intent
+const LCS_GM_IMAGES: u32 = 4;
+
+/// Encodes an image as a `CF_DIBV5` clipboard block: a `BITMAPV5HEADER`
+/// followed by 32-bit BGRA pixels, bottom row first, with an alpha mask
+pub fn encode_dibv5(image: &ClipboardImage) -> Vec<u8> {
+    let pixel_bytes = image.width as usize * image.height as usize * 4;
+    let mut out = Vec::with_capacity(BITMAPV5HEADER_SIZE as usize + pixel_bytes);
+
+    out.extend_from_slice(&BITMAPV5HEADER_SIZE.to_le_bytes());
+    out.extend_from_slice(&(image.width as i32).to_le_bytes());
+    // positive height means bottom-up rows, which older readers expect
+    out.extend_from_slice(&(image.height as i32).to_le_bytes());
+    out.extend_from_slice(&1_u16.to_le_bytes()); // planes
+    out.extend_from_slice(&32_u16.to_le_bytes()); // bit count
+    out.extend_from_slice(&BI_BITFIELDS.to_le_bytes());
+    out.extend_from_slice(&(pixel_bytes as u32).to_le_bytes()); // image size
+    out.extend_from_slice(&2835_i32.to_le_bytes()); // 72 DPI in pixels per metre
+    out.extend_from_slice(&2835_i32.to_le_bytes());
+    out.extend_from_slice(&0_u32.to_le_bytes()); // colours used
+    out.extend_from_slice(&0_u32.to_le_bytes()); // colours important
+    out.extend_from_slice(&0x00FF_0000_u32.to_le_bytes()); // red mask
+    out.extend_from_slice(&0x0000_FF00_u32.to_le_bytes()); // green mask
+    out.extend_from_slice(&0x0000_00FF_u32.to_le_bytes()); // blue mask
+    out.extend_from_slice(&0xFF00_0000_u32.to_le_bytes()); // alpha mask
+    out.extend_from_slice(&LCS_SRGB.to_le_bytes());
+    out.extend_from_slice(&[0; 36]); // endpoints, unused for sRGB
+    out.extend_from_slice(&[0; 12]); // gamma red, green, blue
+    out.extend_from_slice(&LCS_GM_IMAGES.to_le_bytes());
+    out.extend_from_slice(&[0; 12]); // profile data, profile size, reserved
+    debug_assert_eq!(out.len(), BITMAPV5HEADER_SIZE as usize);
+
+    let stride = image.width as usize * 4;
+    for row in image.rgba.chunks_exact(stride.max(1)).rev() {
+        for px in row.chunks_exact(4) {
+            out.extend_from_slice(&[px[2], px[1], px[0], px[3]]);
+        }
+    }
+    return out;
+}
+
+/// Decodes a `CF_DIB` or `CF_DIBV5` clipboard block (a bitmap info header
+/// followed by the pixels, without the `BITMAPFILEHEADER` of a .bmp file)
+/// with [`bmp::decode_dib`], which reads every kind of bitmap
+pub fn decode_dib(data: &[u8]) -> Result<ClipboardImage, ClipboardError> {
+    let image = bmp::decode_dib(data).map_err(ClipboardError::Image)?;
+    return ClipboardImage::new(image.width(), image.height(), image.to_rgba());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image() -> ClipboardImage {
+        let rgba = vec![255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 255, 0, 10, 20, 30, 255, 40, 50, 60, 255, 70, 80, 90, 255];
+        return ClipboardImage::new(3, 2, rgba).unwrap();
+    }
+
+    #[test]
+    fn memory_clipboard_keeps_the_last_contents() {
+        let mut clipboard = MemoryClipboard::new();
+        assert_eq!(clipboard.get_text(), Ok(None));
+        clipboard.set_text("hello").unwrap();
+        assert_eq!(clipboard.get_text(), Ok(Some("hello".to_owned())));
+        assert_eq!(clipboard.get_image(), Ok(None));
+
+        clipboard.set_image(&image()).unwrap();
+        assert_eq!(clipboard.get_text(), Ok(None));
+        assert_eq!(clipboard.get_image(), Ok(Some(image())));
+
+        clipboard.set_custom("app/shape", &[1, 2, 3]).unwrap();
+        assert_eq!(clipboard.get_custom("app/shape"), Ok(Some(vec![1, 2, 3])));
+        assert_eq!(clipboard.get_custom("app/other"), Ok(None));
+        assert_eq!(clipboard.get_custom(""), Err(ClipboardError::InvalidFormatName));
+        assert_eq!(clipboard.set_custom("", &[]), Err(ClipboardError::InvalidFormatName));
+
+        clipboard.clear().unwrap();
+        assert_eq!(clipboard.get_custom("app/shape"), Ok(None));
+    }
+
+    #[test]
+    fn watcher_sees_every_change_once() {
+        let mut clipboard = MemoryClipboard::new();
+        clipboard.set_text("before").unwrap();
+        let mut watcher = ClipboardWatcher::new(&clipboard);
+        assert!(!watcher.poll(&clipboard));
+        clipboard.set_text("after").unwrap();
+        clipboard.clear().unwrap();
+        assert!(watcher.poll(&clipboard));
+        assert!(!watcher.poll(&clipboard));
+        // A failed write changes nothing
+        let _ = clipboard.set_custom("", &[1]);
+        assert!(!watcher.poll(&clipboard));
+    }
+
+    #[test]
+    fn image_size_must_match_the_pixels() {
+        assert!(ClipboardImage::new(2, 2, vec![0; 15]).is_err());
+        assert!(ClipboardImage::new(0, 5, Vec::new()).is_ok());
+    }
+
+    #[test]
+    fn dibv5_round_trips() {
+        assert_eq!(decode_dib(&encode_dibv5(&image())), Ok(image()));
+    }
+
+    #[test]
+    fn bottom_up_24_bit_dib_is_opaque() {
+        // 1x2, rows padded to 4 bytes, bottom row first
+        let mut dib = Vec::new();
+        for value in [40u32, 1, 2] {
+            dib.extend_from_slice(&value.to_le_bytes());
+        }
+        dib.extend_from_slice(&1u16.to_le_bytes());
+        dib.extend_from_slice(&24u16.to_le_bytes());
+        dib.extend_from_slice(&[0; 24]);
+        dib.extend_from_slice(&[3, 2, 1, 0, 30, 20, 10, 0]);
+        let decoded = decode_dib(&dib).unwrap();
+        assert_eq!(decoded.rgba, vec![10, 20, 30, 255, 1, 2, 3, 255]);
+    }
+
+    #[test]
+    fn malformed_dibs_are_rejected() {
+        let dib = encode_dibv5(&image());
+        let truncated = Err(ClipboardError::Image(ImageError::Truncated("BMP")));
+        for length in [0, 3, 20, dib.len() - 1] {
+            assert_eq!(decode_dib(&dib[..length]), truncated, "{}", length);
+        }
+        let mut zero_width = dib.clone();
+        zero_width[4..8].copy_from_slice(&0u32.to_le_bytes());
+        assert_eq!(decode_dib(&zero_width), Err(ClipboardError::Image(ImageError::InvalidSize { width: 0, height: 2 })));
+        let mut huge = dib.clone();
+        huge[4..8].copy_from_slice(&i32::MAX.to_le_bytes());
+        huge[8..12].copy_from_slice(&i32::MAX.to_le_bytes());
+        assert!(decode_dib(&huge).is_err());
+        // A palette bitmap needs its colour table
+        let mut eight_bit = dib;
+        eight_bit[14..16].copy_from_slice(&8u16.to_le_bytes());
+        eight_bit[16..20].copy_from_slice(&0u32.to_le_bytes());
+        assert_eq!(decode_dib(&eight_bit), truncated);
+    }
+
+    #[test]
+    fn empty_alpha_and_repeated_masks_are_understood() {
+        // Writers that don't know about alpha leave it zero
+        let mut opaque = encode_dibv5(&ClipboardImage::new(1, 1, vec![1, 2, 3, 0]).unwrap());
+        assert_eq!(decode_dib(&opaque).map(|image| image.rgba), Ok(vec![1, 2, 3, 255]));
+        // Some put the bit fields after the V5 header as well
+        let pixel = opaque.split_off(124);
+        opaque.extend_from_within(40..52);
+        opaque.extend_from_slice(&pixel);
+        assert_eq!(decode_dib(&opaque).map(|image| image.rgba), Ok(vec![1, 2, 3, 255]));
+    }
+}
@@ -0,0 +1,50 @@
+//! Standard controls embedded in a window
+//!
+//! Backends create native buttons, check boxes, edit boxes and list boxes
+//! as child windows and report what happens to them as
+//! [`crate::event::WindowEvent::Control`] events, identified by the
+//! [`ControlId`] given when the control was created.
+
+/// Application assigned identifier of a control
+///
+/// Win32 reports notifications with 16 bits, so that is the range we
+/// support. Keep control ids apart from [`crate::menu::MenuId`]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ControlId(pub u16);
+
+/// Kinds of standard controls
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlKind {
+    Button,
+    CheckBox,
+    /// A single line edit box
+    Edit,
+    /// An edit box with several lines and a vertical scroll bar
+    MultilineEdit,
+    ListBox,
+}
+
+/// What happened to a control
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlEvent {
+    /// A push button was clicked, or pressed with Space
+    Clicked,
+
+    /// A check box was toggled by the user
+    Toggled { checked: bool },
+
+    /// The text of an edit box changed, by the user or the application
+    TextChanged,
+
+    /// The selected item of a list box changed. `None` when nothing is selected.
+    SelectionChanged { index: Option<usize> },
+
+    /// An item of a list box was double clicked
+    ItemActivated { index: usize },
+
+    /// The control received the keyboard focus
+    FocusGained,
+
+    /// The control lost the keyboard focus
+    FocusLost,
+}
@@ -0,0 +1,386 @@
+//! Message boxes and file dialogs
+//!
+//! Dialogs are described with [`MessageBox`] and [`FileDialog`] and shown
+//! by a [`Dialogs`] implementation: the native one of the backend, or
+//! [`ScriptedDialogs`] which answers from a script so code that asks the
+//! user something can be tested.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+/// Abstraction to represent a dialog error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DialogError {
+    /// The operating system couldn't show the dialog, with its error code
+    Platform(u32),
+
+    /// [`ScriptedDialogs`] was asked for a dialog it has no answer for
+    Unscripted(Box<DialogRequest>),
+
+    /// [`ScriptedDialogs`] has an answer of the wrong kind for this dialog
+    MismatchedResponse(Box<DialogRequest>),
+}
+
+impl std::fmt::Display for DialogError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DialogError::Platform(code) => write!(f, "couldn't show the dialog (os error {})", code),
+            DialogError::Unscripted(request) => write!(f, "no scripted response for {:?}", request),
+            DialogError::MismatchedResponse(request) => {
+                write!(f, "scripted response doesn't match {:?}", request)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DialogError {}
+
+/// Which buttons a message box shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageButtons {
+    Ok,
+    OkCancel,
+    AbortRetryIgnore,
+    YesNoCancel,
+    YesNo,
+    RetryCancel,
+    CancelTryContinue,
+}
+
+/// Icon of a message box, which also picks the sound that is played
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageIcon {
+    None,
+    Error,
+    Warning,
+    Information,
+    Question,
+}
+
+/// Button that is focused when the message box opens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultButton {
+    First,
+    Second,
+    Third,
+    Fourth,
+}
+
+/// What the message box blocks while it is open
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modality {
+    /// Blocks the owner window only
+    Window,
+
+    /// Blocks every window of the calling thread, even without an owner
+    Task,
+
+    /// Like `Window`, but stays on top of every other program
+    System,
+}
+
+/// The button the user pressed to close a message box
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageResult {
+    Ok,
+    Cancel,
+    Abort,
+    Retry,
+    Ignore,
+    Yes,
+    No,
+    TryAgain,
+    Continue,
+}
+
+/// Description of a message box
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageBox {
+    pub title: String,
+    pub text: String,
+    pub buttons: MessageButtons,
+    pub icon: MessageIcon,
+    pub default_button: DefaultButton,
+    pub modality: Modality,
+}
+
+impl MessageBox {
+    /// A message box with an OK button and no icon
+    pub fn new(title: &str, text: &str) -> Self {
+        return MessageBox {
+            title: title.to_owned(),
+            text: text.to_owned(),
+            buttons: MessageButtons::Ok,
+            icon: MessageIcon::None,
+            default_button: DefaultButton::First,
+            modality: Modality::Window,
+        };
+    }
+
+    pub fn buttons(mut self, buttons: MessageButtons) -> Self {
+        self.buttons = buttons;
+        return self;
+    }
+
+    pub fn icon(mut self, icon: MessageIcon) -> Self {
+        self.icon = icon;
+        return self;
+    }
+
+    pub fn default_button(mut self, default_button: DefaultButton) -> Self {
+        self.default_button = default_button;
+        return self;
+    }
+
+    pub fn modality(mut self, modality: Modality) -> Self {
+        self.modality = modality;
+        return self;
+    }
+}
+
+/// A named group of file patterns, e.g. `"Images"` with `["*.png", "*.bmp"]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileFilter {
+    pub name: String,
+    pub patterns: Vec<String>,
+}
+
+impl FileFilter {
+    pub fn new(name: &str, patterns: &[&str]) -> Self {
+        return FileFilter {
+            name: name.to_owned(),
+            patterns: patterns.iter().map(|p| p.to_string()).collect(),
+        };
+    }
+}
+
+/// Description of an open file, save file or folder dialog
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileDialog {
+    pub title: Option<String>,
+    /// Folder the dialog starts in
+    pub directory: Option<PathBuf>,
+    /// Prefilled file name (save dialogs)
+    pub file_name: Option<String>,
+    pub filters: Vec<FileFilter>,
+    /// Extension added by save dialogs when the user types none, without the dot
+    pub default_extension: Option<String>,
+}
+
+impl FileDialog {
+    pub fn new() -> Self {
+        return FileDialog::default();
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_owned());
+        return self;
+    }
+
+    pub fn directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.directory = Some(directory.into());
+        return self;
+    }
+
+    pub fn file_name(mut self, file_name: &str) -> Self {
+        self.file_name = Some(file_name.to_owned());
+        return self;
+    }
+
+    pub fn filter(mut self, name: &str, patterns: &[&str]) -> Self {
+        self.filters.push(FileFilter::new(name, patterns));
+        return self;
+    }
+
+    pub fn default_extension(mut self, extension: &str) -> Self {
+        self.default_extension = Some(extension.trim_start_matches('.').to_owned());
+        return self;
+    }
+}
+
+/// Shows dialogs to the user. Every method blocks until the dialog closes
+/// and returns `Ok(None)` when the user cancels a file dialog.
+pub trait Dialogs {
+    fn message_box(&mut self, message: &MessageBox) -> Result<MessageResult, DialogError>;
+
+    fn open_file(&mut self, dialog: &FileDialog) -> Result<Option<PathBuf>, DialogError>;
+
+    /// Like `open_file`, but the user can select several files
+    fn open_files(&mut self, dialog: &FileDialog) -> Result<Option<Vec<PathBuf>>, DialogError>;
+
+    fn save_file(&mut self, dialog: &FileDialog) -> Result<Option<PathBuf>, DialogError>;
+
+    fn pick_folder(&mut self, dialog: &FileDialog) -> Result<Option<PathBuf>, DialogError>;
+}
+
+/// A dialog that was asked for, as recorded by [`ScriptedDialogs`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DialogRequest {
+    MessageBox(MessageBox),
+    OpenFile(FileDialog),
+    OpenFiles(FileDialog),
+    SaveFile(FileDialog),
+    PickFolder(FileDialog),
+}
+
+/// The answer [`ScriptedDialogs`] gives to the next dialog
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptedResponse {
+    /// Answer to a message box
+    Message(MessageResult),
+
+    /// Answer to any file or folder dialog. `None` or an empty list is a
+    /// cancel, and single-path dialogs take the first path.
+    Paths(Option<Vec<PathBuf>>),
+}
+
+/// Answers dialogs from a script instead of asking the user, and records
+/// every request so a test can check what would have been shown
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedDialogs {
+    responses: VecDeque<ScriptedResponse>,
+    requests: Vec<DialogRequest>,
+}
+
+impl ScriptedDialogs {
+    pub fn new() -> Self {
+        return ScriptedDialogs::default();
+    }
+
+    /// Queues the answer to the next unanswered dialog
+    pub fn respond(&mut self, response: ScriptedResponse) -> &mut Self {
+        self.responses.push_back(response);
+        return self;
+    }
+
+    /// Every dialog that was requested so far, oldest first
+    pub fn requests(&self) -> &[DialogRequest] {
+        return &self.requests;
+    }
+
+    /// Scripted responses that were never used
+    pub fn remaining(&self) -> usize {
+        return self.responses.len();
+    }
+
+    fn next(&mut self, request: DialogRequest) -> Result<ScriptedResponse, DialogError> {
+        self.requests.push(request.clone());
+        return self.responses.pop_front().ok_or(DialogError::Unscripted(Box::new(request)));
+    }
+
+    fn next_paths(&mut self, request: DialogRequest) -> Result<Option<Vec<PathBuf>>, DialogError> {
+        match self.next(request.clone())? {
+            ScriptedResponse::Paths(paths) => return Ok(paths.filter(|paths| !paths.is_empty())),
+            ScriptedResponse::Message(_) => return Err(DialogError::MismatchedResponse(Box::new(request))),
+        }
+    }
+}
+
+impl Dialogs for ScriptedDialogs {
+    fn message_box(&mut self, message: &MessageBox) -> Result<MessageResult, DialogError> {
+        let request = DialogRequest::MessageBox(message.clone());
+        match self.next(request.clone())? {
+            ScriptedResponse::Message(result) => return Ok(result),
+            ScriptedResponse::Paths(_) => return Err(DialogError::MismatchedResponse(Box::new(request))),
+        }
+    }
+
+    fn open_file(&mut self, dialog: &FileDialog) -> Result<Option<PathBuf>, DialogError> {
+        let paths = self.next_paths(DialogRequest::OpenFile(dialog.clone()))?;
+        return Ok(paths.and_then(|paths| paths.into_iter().next()));
+    }
+
+    fn open_files(&mut self, dialog: &FileDialog) -> Result<Option<Vec<PathBuf>>, DialogError> {
+        return self.next_paths(DialogRequest::OpenFiles(dialog.clone()));
+    }
+
+    fn save_file(&mut self, dialog: &FileDialog) -> Result<Option<PathBuf>, DialogError> {
+        let paths = self.next_paths(DialogRequest::SaveFile(dialog.clone()))?;
+        return Ok(paths.and_then(|paths| paths.into_iter().next()));
+    }
+
+    fn pick_folder(&mut self, dialog: &FileDialog) -> Result<Option<PathBuf>, DialogError> {
+        let paths = self.next_paths(DialogRequest::PickFolder(dialog.clone()))?;
+        return Ok(paths.and_then(|paths| paths.into_iter().next()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What an application might do on "Save As": ask for a path, and
+    /// confirm before replacing an existing file
+    fn save_as(dialogs: &mut dyn Dialogs, exists: bool) -> Result<Option<PathBuf>, DialogError> {
+        let dialog = FileDialog::new().title("Save As").filter("Images", &["*.png"]).default_extension(".png");
+        let path = match dialogs.save_file(&dialog)? {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        if exists {
+            let question = MessageBox::new("Save As", "Replace the file?").buttons(MessageButtons::YesNo);
+            if dialogs.message_box(&question)? != MessageResult::Yes {
+                return Ok(None);
+            }
+        }
+        return Ok(Some(path));
+    }
+
+    #[test]
+    fn scripted_dialogs_answer_in_order_and_record_requests() {
+        let mut dialogs = ScriptedDialogs::new();
+        dialogs
+            .respond(ScriptedResponse::Paths(Some(vec![PathBuf::from("out.png")])))
+            .respond(ScriptedResponse::Message(MessageResult::No));
+        assert_eq!(save_as(&mut dialogs, true), Ok(None));
+        assert_eq!(dialogs.remaining(), 0);
+
+        let requests = dialogs.requests();
+        assert_eq!(requests.len(), 2);
+        match &requests[0] {
+            DialogRequest::SaveFile(dialog) => {
+                assert_eq!(dialog.title.as_deref(), Some("Save As"));
+                assert_eq!(dialog.default_extension.as_deref(), Some("png"));
+                assert_eq!(dialog.filters, vec![FileFilter::new("Images", &["*.png"])]);
+            }
+            other => panic!("unexpected request {:?}", other),
+        }
+        assert!(matches!(&requests[1], DialogRequest::MessageBox(m) if m.buttons == MessageButtons::YesNo));
+    }
+
+    #[test]
+    fn empty_path_lists_are_a_cancel() {
+        let mut dialogs = ScriptedDialogs::new();
+        dialogs
+            .respond(ScriptedResponse::Paths(Some(Vec::new())))
+            .respond(ScriptedResponse::Paths(None))
+            .respond(ScriptedResponse::Paths(Some(vec![PathBuf::from("a"), PathBuf::from("b")])))
+            .respond(ScriptedResponse::Paths(Some(vec![PathBuf::from("a"), PathBuf::from("b")])));
+        assert_eq!(dialogs.open_files(&FileDialog::new()), Ok(None));
+        assert_eq!(dialogs.pick_folder(&FileDialog::new()), Ok(None));
+        assert_eq!(dialogs.open_files(&FileDialog::new()), Ok(Some(vec![PathBuf::from("a"), PathBuf::from("b")])));
+        assert_eq!(dialogs.open_file(&FileDialog::new()), Ok(Some(PathBuf::from("a"))));
+    }
+
+    #[test]
+    fn unscripted_and_mismatched_dialogs_fail() {
+        let mut dialogs = ScriptedDialogs::new();
+        dialogs.respond(ScriptedResponse::Message(MessageResult::Ok));
+        let dialog = FileDialog::new().directory("/tmp");
+        assert_eq!(
+            dialogs.open_file(&dialog),
+            Err(DialogError::MismatchedResponse(Box::new(DialogRequest::OpenFile(dialog.clone())))),
+        );
+        assert_eq!(
+            dialogs.pick_folder(&dialog),
+            Err(DialogError::Unscripted(Box::new(DialogRequest::PickFolder(dialog)))),
+        );
+        dialogs.respond(ScriptedResponse::Paths(None));
+        let message = MessageBox::new("Title", "Text");
+        assert_eq!(
+            dialogs.message_box(&message),
+            Err(DialogError::MismatchedResponse(Box::new(DialogRequest::MessageBox(message)))),
+        );
+        assert_eq!(dialogs.requests().len(), 3);
+    }
+}
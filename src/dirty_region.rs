@@ -0,0 +1,228 @@
+//! Tracking which parts of a window need repainting
+//!
+//! Repainting a whole window for a blinking caret or a progress bar wastes
+//! time, so changes are recorded as [`Rect`]s in a [`DirtyRegion`]
+//! and only those are invalidated and repainted. The region merges
+//! rectangles that overlap, or that sit side by side and form a bigger
+//! rectangle, and it never holds more than [`MAX_DIRTY_RECTS`]: past that
+//! the two rectangles whose bounding box adds the least area are merged.
+//! Merging can only grow the region, so nothing damaged is ever missed.
+//!
+//! Paint callbacks get the damaged region the same way, to skip drawing
+//! whatever doesn't intersect it.
+
+use crate::geometry::Rect;
+
+/// Most rectangles a [`DirtyRegion`] keeps apart
+pub const MAX_DIRTY_RECTS: usize = 16;
+
+/// Damaged rectangles waiting to be repainted, see the module documentation
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirtyRegion {
+    rects: Vec<Rect>,
+}
+
+impl DirtyRegion {
+    pub fn new() -> Self {
+        return DirtyRegion { rects: Vec::new() };
+    }
+
+    /// Marks a rectangle as damaged. Empty rectangles are ignored.
+    pub fn add(&mut self, rect: Rect) {
+        if rect.is_empty() {
+            return;
+        }
+        let mut rect = rect;
+        // A merged rectangle can reach others, so keep going until none is left
+        while let Some(i) = self.rects.iter().position(|other| should_merge(other, &rect)) {
+            rect = rect.union(&self.rects.swap_remove(i));
+        }
+        self.rects.push(rect);
+
+        if self.rects.len() > MAX_DIRTY_RECTS {
+            let mut best = (0, 1, i64::MAX);
+            for i in 0..self.rects.len() {
+                for j in i + 1..self.rects.len() {
+                    let (a, b) = (&self.rects[i], &self.rects[j]);
+                    let waste = a.union(b).area() - a.area() - b.area();
+                    if waste < best.2 {
+                        best = (i, j, waste);
+                    }
+                }
+            }
+            let (i, j, _) = best;
+            let merged = self.rects[i].union(&self.rects[j]);
+            self.rects.swap_remove(j);
+            self.rects.swap_remove(i);
+            self.add(merged);
+        }
+    }
+
+    /// Marks everything damaged in another region too
+    pub fn add_region(&mut self, other: &DirtyRegion) {
+        for &rect in &other.rects {
+            self.add(rect);
+        }
+    }
+
+    /// The damaged rectangles, which don't overlap
+    pub fn rects(&self) -> &[Rect] {
+        return &self.rects;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.rects.is_empty();
+    }
+
+    /// The bounding box of the whole region, `None` when it is empty
+    pub fn bounds(&self) -> Option<Rect> {
+        return self.rects.iter().copied().reduce(|bounds, rect| bounds.union(&rect));
+    }
+
+    /// Whether any damaged pixel is inside `rect`, to skip drawing what
+    /// doesn't need it
+    pub fn intersects(&self, rect: &Rect) -> bool {
+        return self.rects.iter().any(|damaged| damaged.intersects(rect));
+    }
+
+    /// Drops the damage outside `bounds`, usually the client area
+    pub fn clip(&mut self, bounds: &Rect) {
+        self.rects = self.rects.iter().filter_map(|rect| rect.intersection(bounds)).collect();
+    }
+
+    pub fn clear(&mut self) {
+        self.rects.clear();
+    }
+
+    /// The damage so far, leaving the region empty
+    pub fn take(&mut self) -> DirtyRegion {
+        return std::mem::take(self);
+    }
+}
+
+impl From<Rect> for DirtyRegion {
+    fn from(rect: Rect) -> Self {
+        let mut region = DirtyRegion::new();
+        region.add(rect);
+        return region;
+    }
+}
+
+impl Extend<Rect> for DirtyRegion {
+    fn extend<I: IntoIterator<Item = Rect>>(&mut self, rects: I) {
+        for rect in rects {
+            self.add(rect);
+        }
+    }
+}
+
+impl FromIterator<Rect> for DirtyRegion {
+    fn from_iter<I: IntoIterator<Item = Rect>>(rects: I) -> Self {
+        let mut region = DirtyRegion::new();
+        region.extend(rects);
+        return region;
+    }
+}
+
+/// Rectangles that overlap, or that are neighbours forming a rectangle
+/// together, cost nothing to merge
+fn should_merge(a: &Rect, b: &Rect) -> bool {
+    return a.intersects(b) || a.union(b).area() == a.area() + b.area();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Point;
+
+    fn sorted(region: &DirtyRegion) -> Vec<Rect> {
+        let mut rects = region.rects().to_vec();
+        rects.sort_by_key(|rect| (rect.top, rect.left));
+        return rects;
+    }
+
+    #[test]
+    fn overlapping_and_adjacent_rects_merge() {
+        let mut region = DirtyRegion::from(Rect::new(0, 0, 10, 10));
+        region.add(Rect::new(5, 5, 15, 15));
+        assert_eq!(region.rects(), [Rect::new(0, 0, 15, 15)]);
+        region.add(Rect::new(2, 2, 4, 4));
+        region.add(Rect::new(15, 0, 20, 15));
+        assert_eq!(region.rects(), [Rect::new(0, 0, 20, 15)]);
+
+        // Corners touching or sides that don't line up stay apart
+        let mut region: DirtyRegion = [Rect::new(0, 0, 10, 10), Rect::new(10, 10, 20, 20)].into_iter().collect();
+        region.add(Rect::new(10, 5, 20, 9));
+        assert_eq!(region.rects().len(), 3);
+        // Filling the gap between two rectangles merges all three
+        let mut region: DirtyRegion = [Rect::new(0, 0, 10, 10), Rect::new(20, 0, 30, 10)].into_iter().collect();
+        region.add(Rect::new(10, 0, 20, 10));
+        assert_eq!(region.rects(), [Rect::new(0, 0, 30, 10)]);
+    }
+
+    #[test]
+    fn too_many_rects_merge_the_closest() {
+        let mut region: DirtyRegion = (0..MAX_DIRTY_RECTS as i32).map(|i| Rect::new(10 * i, 0, 10 * i + 1, 1)).collect();
+        assert_eq!(region.rects().len(), MAX_DIRTY_RECTS);
+        region.add(Rect::new(52, 0, 53, 1));
+        assert_eq!(region.rects().len(), MAX_DIRTY_RECTS);
+        assert!(region.rects().contains(&Rect::new(50, 0, 53, 1)));
+        assert!(!region.rects().contains(&Rect::new(50, 0, 51, 1)));
+
+        // Scattered damage stays within the limit, apart and fully covered
+        let mut added = Vec::new();
+        let mut seed = 7u32;
+        let mut next = |range: u32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            return ((seed >> 16) % range) as i32;
+        };
+        let mut region = DirtyRegion::new();
+        for _ in 0..200 {
+            let (x, y) = (next(300), next(200));
+            let rect = Rect::new(x, y, x + 1 + next(8), y + 1 + next(8));
+            added.push(rect);
+            region.add(rect);
+            assert!(region.rects().len() <= MAX_DIRTY_RECTS);
+        }
+        for (i, a) in region.rects().iter().enumerate() {
+            assert!(region.rects()[i + 1..].iter().all(|b| !a.intersects(b)));
+        }
+        for rect in added {
+            for y in rect.top..rect.bottom {
+                for x in rect.left..rect.right {
+                    assert!(region.rects().iter().any(|damaged| damaged.contains(Point::new(x, y))));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn clipping_keeps_the_damage_inside() {
+        let mut region: DirtyRegion =
+            [Rect::new(-5, -5, 5, 5), Rect::new(50, 50, 60, 60), Rect::new(30, 0, 45, 10)].into_iter().collect();
+        assert_eq!(region.bounds(), Some(Rect::new(-5, -5, 60, 60)));
+        region.clip(&Rect::new(0, 0, 40, 40));
+        assert_eq!(sorted(&region), vec![Rect::new(0, 0, 5, 5), Rect::new(30, 0, 40, 10)]);
+        assert!(region.intersects(&Rect::new(4, 4, 8, 8)) && !region.intersects(&Rect::new(5, 0, 30, 40)));
+        region.clip(&Rect::new(100, 100, 200, 200));
+        assert!(region.is_empty());
+    }
+
+    #[test]
+    fn empty_rects_are_ignored() {
+        let mut region = DirtyRegion::from(Rect::new(5, 5, 5, 10));
+        region.extend([Rect::new(0, 0, 10, 0), Rect::new(10, 10, 0, 0), Rect::default()]);
+        assert!(region.is_empty());
+        assert_eq!(region.bounds(), None);
+        assert!(!region.intersects(&Rect::new(-100, -100, 100, 100)));
+
+        region.add(Rect::new(1, 1, 2, 2));
+        region.add_region(&DirtyRegion::new());
+        let taken = region.take();
+        assert_eq!((taken.rects(), region.is_empty()), ([Rect::new(1, 1, 2, 2)].as_slice(), true));
+        let mut copy = taken.clone();
+        copy.add_region(&taken);
+        copy.clear();
+        assert_eq!(copy, DirtyRegion::new());
+    }
+}
@@ -0,0 +1,217 @@
+//! Helpers shared by the drag and drop implementations
+//!
+//! Win32 gets paths straight from `CF_HDROP`. X11 (XDND) and Wayland
+//! hand over a `text/uri-list` which [`parse_uri_list`] turns into paths.
+
+use std::path::PathBuf;
+
+use crate::event::DragDrop;
+use crate::geometry::Point;
+
+/// Keeps track of a drag in progress and turns the backend's callbacks
+/// into [`DragDrop`] events
+///
+/// Backends call it for every notification the system sends. It drops
+/// repeated positions (Win32 calls `DragOver` continuously even when the
+/// cursor doesn't move) and remembers the paths from the enter notification
+/// for protocols that don't repeat them on drop.
+#[derive(Debug, Default, Clone)]
+pub struct DropTracker {
+    hovering: Option<Hover>,
+}
+
+#[derive(Debug, Clone)]
+struct Hover {
+    paths: Vec<PathBuf>,
+    position: Point,
+}
+
+impl DropTracker {
+    pub fn new() -> Self {
+        return DropTracker::default();
+    }
+
+    /// `true` while files are hovering over the window
+    pub fn is_hovering(&self) -> bool {
+        return self.hovering.is_some();
+    }
+
+    /// The drag entered the window. Drags without any file are ignored.
+    pub fn enter(&mut self, paths: Vec<PathBuf>, position: Point) -> Option<DragDrop> {
+        if paths.is_empty() {
+            self.hovering = None;
+            return None;
+        }
+        self.hovering = Some(Hover { paths: paths.clone(), position });
+        return Some(DragDrop::HoverEntered { paths, position });
+    }
+
+    /// The cursor moved while dragging
+    pub fn moved(&mut self, position: Point) -> Option<DragDrop> {
+        let hover = self.hovering.as_mut()?;
+        if hover.position == position {
+            return None;
+        }
+        hover.position = position;
+        return Some(DragDrop::HoverMoved { position });
+    }
+
+    /// The drag left the window or was cancelled
+    pub fn leave(&mut self) -> Option<DragDrop> {
+        return self.hovering.take().map(|_| DragDrop::Cancelled);
+    }
+
+    /// The files were dropped. When `paths` is `None` the paths from
+    /// [`DropTracker::enter`] are used.
+    pub fn drop(&mut self, paths: Option<Vec<PathBuf>>, position: Point) -> Option<DragDrop> {
+        let hover = self.hovering.take();
+        let paths = match (paths, hover) {
+            (Some(paths), _) => paths,
+            (None, Some(hover)) => hover.paths,
+            (None, None) => return None,
+        };
+        if paths.is_empty() {
+            return None;
+        }
+        return Some(DragDrop::Dropped { paths, position });
+    }
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => return Some(byte - b'0'),
+        b'a'..=b'f' => return Some(byte - b'a' + 10),
+        b'A'..=b'F' => return Some(byte - b'A' + 10),
+        _ => return None,
+    }
+}
+
+/// Decodes `%XX` escapes. Invalid escapes are kept as they are.
+fn percent_decode(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let (Some(hi), Some(lo)) = (
+                bytes.get(i + 1).copied().and_then(hex_value),
+                bytes.get(i + 2).copied().and_then(hex_value),
+            ) {
+                out.push(hi << 4 | lo);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    return out;
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    return PathBuf::from(std::ffi::OsString::from_vec(bytes));
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    return PathBuf::from(String::from_utf8_lossy(&bytes).into_owned());
+}
+
+/// Parses a `text/uri-list` (RFC 2483) into local file paths
+///
+/// Comment lines and URIs that aren't local `file://` URIs are skipped.
+/// The host part must be empty or `localhost`.
+pub fn parse_uri_list(list: &str) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for line in list.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let rest = match line.get(..7) {
+            Some(scheme) if scheme.eq_ignore_ascii_case("file://") => &line[7..],
+            _ => continue,
+        };
+        // "file:///path" or "file://localhost/path"
+        let path = match rest.find('/') {
+            Some(0) => rest,
+            Some(slash) if rest[..slash].eq_ignore_ascii_case("localhost") => &rest[slash..],
+            _ => continue,
+        };
+        let mut bytes = percent_decode(path);
+        // "file:///C:/dir" on Windows
+        if bytes.len() >= 3 && bytes[0] == b'/' && bytes[1].is_ascii_alphabetic() && bytes[2] == b':' {
+            bytes.remove(0);
+        }
+        paths.push(path_from_bytes(bytes));
+    }
+    return paths;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(list: &[&str]) -> Vec<PathBuf> {
+        return list.iter().map(PathBuf::from).collect();
+    }
+
+    #[test]
+    fn uri_lists_become_paths() {
+        let list = "# from the file manager\r\nfile:///home/me/a%20b.txt\r\n\r\n FILE://localhost/tmp/%C3%A9 \r\n";
+        assert_eq!(parse_uri_list(list), paths(&["/home/me/a b.txt", "/tmp/é"]));
+        // Invalid escapes are kept
+        assert_eq!(parse_uri_list("file:///100%25%zz%4"), paths(&["/100%%zz%4"]));
+        assert_eq!(parse_uri_list("file:///C:/Users/me%2Fx\n"), paths(&["C:/Users/me/x"]));
+    }
+
+    #[test]
+    fn only_local_files_are_kept() {
+        let list = "https://example.com/a\nfile://server/share/b\nfile:relative\nfile://\n#file:///c\nfile:///d";
+        assert_eq!(parse_uri_list(list), paths(&["/d"]));
+        assert_eq!(parse_uri_list(""), Vec::<PathBuf>::new());
+    }
+
+    #[test]
+    fn drags_enter_move_and_drop() {
+        let mut tracker = DropTracker::new();
+        assert_eq!(tracker.moved(Point::new(1, 1)), None);
+        assert_eq!(tracker.drop(None, Point::new(1, 1)), None);
+
+        let entered = tracker.enter(paths(&["/a"]), Point::new(1, 2));
+        assert_eq!(entered, Some(DragDrop::HoverEntered { paths: paths(&["/a"]), position: Point::new(1, 2) }));
+        assert!(tracker.is_hovering());
+        // Repeated positions are dropped
+        assert_eq!(tracker.moved(Point::new(1, 2)), None);
+        assert_eq!(tracker.moved(Point::new(3, 4)), Some(DragDrop::HoverMoved { position: Point::new(3, 4) }));
+        assert_eq!(tracker.moved(Point::new(3, 4)), None);
+
+        // The paths from the enter notification are used when the drop has none
+        let dropped = tracker.drop(None, Point::new(5, 6));
+        assert_eq!(dropped, Some(DragDrop::Dropped { paths: paths(&["/a"]), position: Point::new(5, 6) }));
+        assert!(!tracker.is_hovering());
+        assert_eq!(tracker.leave(), None);
+
+        tracker.enter(paths(&["/a"]), Point::new(0, 0));
+        let dropped = tracker.drop(Some(paths(&["/b"])), Point::new(0, 0));
+        assert_eq!(dropped, Some(DragDrop::Dropped { paths: paths(&["/b"]), position: Point::new(0, 0) }));
+    }
+
+    #[test]
+    fn drags_can_leave_or_carry_no_files() {
+        let mut tracker = DropTracker::new();
+        tracker.enter(paths(&["/a"]), Point::new(0, 0));
+        assert_eq!(tracker.leave(), Some(DragDrop::Cancelled));
+        assert_eq!(tracker.leave(), None);
+        assert_eq!(tracker.moved(Point::new(1, 1)), None);
+
+        // A drag without files replaces the one before it and is ignored
+        tracker.enter(paths(&["/a"]), Point::new(0, 0));
+        assert_eq!(tracker.enter(Vec::new(), Point::new(0, 0)), None);
+        assert!(!tracker.is_hovering());
+        assert_eq!(tracker.drop(None, Point::new(0, 0)), None);
+        assert_eq!(tracker.drop(Some(Vec::new()), Point::new(0, 0)), None);
+    }
+}
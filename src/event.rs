@@ -0,0 +1,168 @@
+//! Platform independent window events
+//!
+//! Backends translate their native messages into [`WindowEvent`]s and
+//! push them into an [`EventQueue`] that the application drains from
+//! its main loop.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use crate::control::{ControlEvent, ControlId};
+use crate::geometry::Point;
+use crate::menu::MenuId;
+use crate::tray::{TrayEvent, TrayId};
+
+/// Events produced by a window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WindowEvent {
+    /// A character of text input. Surrogate pairs and dead keys have
+    /// already been combined. Control characters such as backspace (`'\u{8}'`)
+    /// and carriage return (`'\r'`) are delivered as well.
+    Text(char),
+
+    /// A dead key was pressed. The character that follows will be
+    /// combined with it, so this is only useful to show a pending accent.
+    DeadKey(char),
+
+    /// Input method editor (IME) composition
+    Ime(Ime),
+
+    /// The contents of the system clipboard changed
+    ClipboardChanged,
+
+    /// Files are being dragged over or dropped onto the window
+    DragDrop(DragDrop),
+
+    /// An item of the window's menu bar or of a context menu was chosen
+    Menu(MenuId),
+
+    /// A keyboard shortcut of the window's accelerator table was pressed
+    Accelerator(MenuId),
+
+    /// A system wide shortcut was pressed, possibly while another
+    /// program had the focus
+    GlobalShortcut(MenuId),
+
+    /// Something happened to a standard control inside the window
+    Control { id: ControlId, event: ControlEvent },
+
+    /// Something happened to one of the application's tray icons
+    Tray { id: TrayId, event: TrayEvent },
+
+    /// The user asked for a context menu, with a right click or the menu
+    /// key. `position` is in client coordinates, or `None` when it came from
+    /// the keyboard and the application should pick a place (e.g. the selection).
+    ContextMenu { position: Option<Point> },
+
+    /// Something changed in the system the window runs on
+    System(SystemEvent),
+}
+
+/// Changes of the system rather than of the window
+///
+/// A renderer can react to them by dropping and re-creating GPU
+/// resources: drivers may lose them across a suspend, a display change
+/// can move the window to another adapter, and freeing caches helps
+/// when memory runs low.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemEvent {
+    /// Monitors were added, removed or rearranged, or a resolution changed
+    DisplayChanged,
+
+    /// The system is about to sleep or hibernate. There is little time,
+    /// so only save what would be lost.
+    Suspend,
+
+    /// The system woke up after a [`SystemEvent::Suspend`]
+    Resume,
+
+    /// The user locked the session; nothing is visible until it is unlocked
+    SessionLocked,
+
+    SessionUnlocked,
+
+    /// The user switched between light and dark mode, or changed the
+    /// system colours. It can arrive more than once for one change.
+    ThemeChanged,
+
+    /// Physical memory is running low. Sent once each time it starts
+    /// running low.
+    LowMemory,
+}
+
+/// Input method editor events, used to type languages such as Chinese,
+/// Japanese and Korean
+///
+/// A composition is a `Start`, any number of `Preedit` and `Commit`
+/// events, and an `End`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Ime {
+    /// A composition started. The application should start drawing the preedit text.
+    Start,
+
+    /// The text being composed changed. It is not part of the document
+    /// yet and should be drawn inline (usually underlined).
+    ///
+    /// `cursor` is a byte offset into `text` when the IME reports one.
+    /// An empty `text` clears the preedit.
+    Preedit { text: String, cursor: Option<usize> },
+
+    /// The user confirmed some text, which should be inserted into the document
+    Commit(String),
+
+    /// The composition ended
+    End,
+}
+
+/// File drag and drop events. Positions are in client coordinates.
+///
+/// A drag is a `HoverEntered`, any number of `HoverMoved`, and then either
+/// `Dropped` or `Cancelled` (the drag left the window or was aborted).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DragDrop {
+    /// Files were dragged into the window
+    HoverEntered { paths: Vec<PathBuf>, position: Point },
+
+    /// The files being dragged moved inside the window
+    HoverMoved { position: Point },
+
+    /// The files were dropped onto the window
+    Dropped { paths: Vec<PathBuf>, position: Point },
+
+    /// The drag left the window without dropping
+    Cancelled,
+}
+
+/// FIFO of events waiting for the application
+#[derive(Debug, Default)]
+pub struct EventQueue {
+    events: VecDeque<WindowEvent>,
+}
+
+impl EventQueue {
+    pub fn new() -> Self {
+        return EventQueue::default();
+    }
+
+    pub fn push(&mut self, event: WindowEvent) {
+        self.events.push_back(event);
+    }
+
+    /// Takes the oldest event out of the queue
+    pub fn pop(&mut self) -> Option<WindowEvent> {
+        return self.events.pop_front();
+    }
+
+    /// Takes every queued event out, oldest first
+    pub fn drain(&mut self) -> impl Iterator<Item = WindowEvent> + '_ {
+        return self.events.drain(..);
+    }
+
+    pub fn len(&self) -> usize {
+        return self.events.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.events.is_empty();
+    }
+}
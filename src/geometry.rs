@@ -0,0 +1,432 @@
+//! Points, sizes and rectangles
+//!
+//! The geometry of windows, paint regions and canvases, in whole pixels
+//! ([`Point`], [`Size`], [`Rect`]) or fractional ones ([`PointF`],
+//! [`SizeF`], [`RectF`]). A rectangle is its left, top, right and bottom
+//! edges like a Win32 `RECT`, with the right column and bottom row
+//! excluded, so rectangles side by side share an edge value without
+//! overlapping. Rectangles whose right isn't past their left, or bottom
+//! past their top, are empty.
+//!
+//! Win32 works in physical pixels. At [`DEFAULT_DPI`] those are the
+//! logical pixels layouts are written in; `to_physical` and `to_logical`
+//! convert for any other DPI, rounding to the nearest pixel. Integer
+//! arithmetic saturates, so a rectangle reaching `i32::MAX` is fine.
+
+use std::ops::{Add, Mul, Sub};
+
+/// The DPI at which logical and physical pixels are the same
+pub const DEFAULT_DPI: u32 = 96;
+
+/// The number types geometry is made of, `i32` and `f32`
+pub trait Scalar: Copy + PartialOrd + Default + std::fmt::Debug + Mul<Output = Self> {
+    fn add(self, other: Self) -> Self;
+    fn sub(self, other: Self) -> Self;
+}
+
+impl Scalar for i32 {
+    fn add(self, other: Self) -> Self {
+        return self.saturating_add(other);
+    }
+
+    fn sub(self, other: Self) -> Self {
+        return self.saturating_sub(other);
+    }
+}
+
+impl Scalar for f32 {
+    fn add(self, other: Self) -> Self {
+        return self + other;
+    }
+
+    fn sub(self, other: Self) -> Self {
+        return self - other;
+    }
+}
+
+fn min<T: Scalar>(a: T, b: T) -> T {
+    return if b < a { b } else { a };
+}
+
+fn max<T: Scalar>(a: T, b: T) -> T {
+    return if b > a { b } else { a };
+}
+
+/// `value * numerator / denominator`, rounded half away from zero like
+/// Win32's `MulDiv`
+fn mul_div(value: i32, numerator: u32, denominator: u32) -> i32 {
+    let (product, denominator) = (value as i64 * numerator as i64, denominator.max(1) as i64);
+    let rounded = (product.abs() + denominator / 2) / denominator * product.signum();
+    return rounded.clamp(i32::MIN as i64, i32::MAX as i64) as i32;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Point<T = i32> {
+    pub x: T,
+    pub y: T,
+}
+
+pub type PointF = Point<f32>;
+
+impl<T: Scalar> Point<T> {
+    pub fn new(x: T, y: T) -> Self {
+        return Point { x, y };
+    }
+
+    pub fn offset(self, dx: T, dy: T) -> Self {
+        return Point::new(self.x.add(dx), self.y.add(dy));
+    }
+}
+
+impl<T: Scalar> Add for Point<T> {
+    type Output = Point<T>;
+
+    fn add(self, other: Point<T>) -> Point<T> {
+        return self.offset(other.x, other.y);
+    }
+}
+
+impl<T: Scalar> Sub for Point<T> {
+    type Output = Point<T>;
+
+    fn sub(self, other: Point<T>) -> Point<T> {
+        return Point::new(self.x.sub(other.x), self.y.sub(other.y));
+    }
+}
+
+impl<T> From<[T; 2]> for Point<T> {
+    fn from([x, y]: [T; 2]) -> Self {
+        return Point { x, y };
+    }
+}
+
+impl<T> From<Point<T>> for [T; 2] {
+    fn from(point: Point<T>) -> Self {
+        return [point.x, point.y];
+    }
+}
+
+impl Point {
+    pub fn to_f32(self) -> PointF {
+        return Point::new(self.x as f32, self.y as f32);
+    }
+
+    /// From logical pixels to the physical pixels of `dpi`
+    pub fn to_physical(self, dpi: u32) -> Self {
+        return Point::new(mul_div(self.x, dpi, DEFAULT_DPI), mul_div(self.y, dpi, DEFAULT_DPI));
+    }
+
+    /// From the physical pixels of `dpi` to logical pixels
+    pub fn to_logical(self, dpi: u32) -> Self {
+        return Point::new(mul_div(self.x, DEFAULT_DPI, dpi), mul_div(self.y, DEFAULT_DPI, dpi));
+    }
+}
+
+impl PointF {
+    pub fn round(self) -> Point {
+        return Point::new(self.x.round() as i32, self.y.round() as i32);
+    }
+
+    pub fn scale(self, factor: f32) -> Self {
+        return Point::new(self.x * factor, self.y * factor);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Size<T = i32> {
+    pub width: T,
+    pub height: T,
+}
+
+pub type SizeF = Size<f32>;
+
+impl<T: Scalar> Size<T> {
+    pub fn new(width: T, height: T) -> Self {
+        return Size { width, height };
+    }
+
+    /// Whether either side is zero or negative
+    pub fn is_empty(&self) -> bool {
+        return !(self.width > T::default() && self.height > T::default());
+    }
+}
+
+impl<T> From<[T; 2]> for Size<T> {
+    fn from([width, height]: [T; 2]) -> Self {
+        return Size { width, height };
+    }
+}
+
+impl<T> From<Size<T>> for [T; 2] {
+    fn from(size: Size<T>) -> Self {
+        return [size.width, size.height];
+    }
+}
+
+impl Size {
+    pub fn to_f32(self) -> SizeF {
+        return Size::new(self.width as f32, self.height as f32);
+    }
+
+    /// From logical pixels to the physical pixels of `dpi`
+    pub fn to_physical(self, dpi: u32) -> Self {
+        return Size::new(mul_div(self.width, dpi, DEFAULT_DPI), mul_div(self.height, dpi, DEFAULT_DPI));
+    }
+
+    /// From the physical pixels of `dpi` to logical pixels
+    pub fn to_logical(self, dpi: u32) -> Self {
+        return Size::new(mul_div(self.width, DEFAULT_DPI, dpi), mul_div(self.height, DEFAULT_DPI, dpi));
+    }
+}
+
+impl SizeF {
+    pub fn round(self) -> Size {
+        return Size::new(self.width.round() as i32, self.height.round() as i32);
+    }
+
+    pub fn scale(self, factor: f32) -> Self {
+        return Size::new(self.width * factor, self.height * factor);
+    }
+}
+
+/// A rectangle by its edges, see the module documentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rect<T = i32> {
+    pub left: T,
+    pub top: T,
+    pub right: T,
+    pub bottom: T,
+}
+
+pub type RectF = Rect<f32>;
+
+impl<T: Scalar> Rect<T> {
+    pub fn new(left: T, top: T, right: T, bottom: T) -> Self {
+        return Rect { left, top, right, bottom };
+    }
+
+    pub fn from_origin_size(origin: Point<T>, size: Size<T>) -> Self {
+        return Rect::new(origin.x, origin.y, origin.x.add(size.width), origin.y.add(size.height));
+    }
+
+    /// The top left corner
+    pub fn origin(&self) -> Point<T> {
+        return Point::new(self.left, self.top);
+    }
+
+    pub fn size(&self) -> Size<T> {
+        return Size::new(self.width(), self.height());
+    }
+
+    /// The width, 0 for an empty rectangle
+    pub fn width(&self) -> T {
+        return max(self.right.sub(self.left), T::default());
+    }
+
+    /// The height, 0 for an empty rectangle
+    pub fn height(&self) -> T {
+        return max(self.bottom.sub(self.top), T::default());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return !(self.right > self.left && self.bottom > self.top);
+    }
+
+    /// Whether the point is inside, right and bottom edges excluded
+    pub fn contains(&self, point: Point<T>) -> bool {
+        return point.x >= self.left && point.x < self.right && point.y >= self.top && point.y < self.bottom;
+    }
+
+    /// Whether every point of `other` is inside. Empty rectangles are
+    /// inside any rectangle.
+    pub fn contains_rect(&self, other: &Rect<T>) -> bool {
+        return other.is_empty()
+            || (other.left >= self.left && other.top >= self.top && other.right <= self.right && other.bottom <= self.bottom);
+    }
+
+    /// Whether the rectangles share some area
+    pub fn intersects(&self, other: &Rect<T>) -> bool {
+        return self.intersection(other).is_some();
+    }
+
+    /// The area both rectangles cover, `None` if they don't overlap
+    pub fn intersection(&self, other: &Rect<T>) -> Option<Rect<T>> {
+        let rect = Rect::new(
+            max(self.left, other.left),
+            max(self.top, other.top),
+            min(self.right, other.right),
+            min(self.bottom, other.bottom),
+        );
+        return if rect.is_empty() { None } else { Some(rect) };
+    }
+
+    /// The bounding box of both rectangles. Empty rectangles are ignored.
+    pub fn union(&self, other: &Rect<T>) -> Rect<T> {
+        if other.is_empty() {
+            return *self;
+        } else if self.is_empty() {
+            return *other;
+        }
+        return Rect::new(
+            min(self.left, other.left),
+            min(self.top, other.top),
+            max(self.right, other.right),
+            max(self.bottom, other.bottom),
+        );
+    }
+
+    /// The rectangle moved by `dx` and `dy`
+    pub fn offset(&self, dx: T, dy: T) -> Rect<T> {
+        return Rect::new(self.left.add(dx), self.top.add(dy), self.right.add(dx), self.bottom.add(dy));
+    }
+
+    /// The rectangle grown by `dx` on the left and right and `dy` on the top
+    /// and bottom, or shrunk for negative amounts
+    pub fn inflate(&self, dx: T, dy: T) -> Rect<T> {
+        return Rect::new(self.left.sub(dx), self.top.sub(dy), self.right.add(dx), self.bottom.add(dy));
+    }
+}
+
+impl Rect {
+    /// The number of pixels inside, exact even when the width or height
+    /// doesn't fit an `i32`
+    pub fn area(&self) -> i64 {
+        let width = (self.right as i64 - self.left as i64).max(0);
+        let height = (self.bottom as i64 - self.top as i64).max(0);
+        return width * height;
+    }
+
+    pub fn to_f32(&self) -> RectF {
+        return Rect::new(self.left as f32, self.top as f32, self.right as f32, self.bottom as f32);
+    }
+
+    /// From logical pixels to the physical pixels of `dpi`. Edges are
+    /// scaled rather than the size, so rectangles that touch still do.
+    pub fn to_physical(&self, dpi: u32) -> Self {
+        let scale = |value| mul_div(value, dpi, DEFAULT_DPI);
+        return Rect::new(scale(self.left), scale(self.top), scale(self.right), scale(self.bottom));
+    }
+
+    /// From the physical pixels of `dpi` to logical pixels
+    pub fn to_logical(&self, dpi: u32) -> Self {
+        let scale = |value| mul_div(value, DEFAULT_DPI, dpi);
+        return Rect::new(scale(self.left), scale(self.top), scale(self.right), scale(self.bottom));
+    }
+}
+
+impl RectF {
+    pub fn area(&self) -> f32 {
+        return self.width() * self.height();
+    }
+
+    /// Every edge rounded to the nearest pixel
+    pub fn round(&self) -> Rect {
+        return Rect::new(self.left.round() as i32, self.top.round() as i32, self.right.round() as i32, self.bottom.round() as i32);
+    }
+
+    /// The smallest pixel rectangle that covers this one, e.g. the pixels
+    /// an anti-aliased shape touches
+    pub fn round_out(&self) -> Rect {
+        return Rect::new(self.left.floor() as i32, self.top.floor() as i32, self.right.ceil() as i32, self.bottom.ceil() as i32);
+    }
+
+    pub fn scale(&self, factor: f32) -> Self {
+        return Rect::new(self.left * factor, self.top * factor, self.right * factor, self.bottom * factor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_and_sizes_convert() {
+        let point = Point::new(3, -4);
+        assert_eq!(point + Point::new(1, 1), Point::new(4, -3));
+        assert_eq!(point - Point::new(1, 1), Point::new(2, -5));
+        assert_eq!(point.offset(-3, 4), Point::default());
+        assert_eq!(<[i32; 2]>::from(point), [3, -4]);
+        assert_eq!(Point::from([3, -4]), point);
+        assert_eq!(Point::new(i32::MAX, i32::MIN) + Point::new(1, -1), Point::new(i32::MAX, i32::MIN));
+        assert_eq!(PointF::new(1.5, -1.5).round(), Point::new(2, -2));
+        assert_eq!(point.to_f32().scale(0.5), PointF::new(1.5, -2.0));
+
+        assert_eq!(Size::from([2, 0]), Size::new(2, 0));
+        assert!(Size::new(2, 0).is_empty() && Size::new(-1, 5).is_empty() && !Size::new(1, 1).is_empty());
+        assert_eq!(<[f32; 2]>::from(Size::new(2, 3).to_f32().scale(1.5)), [3.0, 4.5]);
+        assert_eq!(SizeF::new(2.5, 2.4).round(), Size::new(3, 2));
+    }
+
+    #[test]
+    fn rectangles_exclude_their_right_and_bottom_edges() {
+        let rect = Rect::new(10, 20, 30, 60);
+        assert_eq!(Rect::from_origin_size(Point::new(10, 20), Size::new(20, 40)), rect);
+        assert_eq!((rect.origin(), rect.size(), rect.area()), (Point::new(10, 20), Size::new(20, 40), 800));
+        assert!(rect.contains(Point::new(10, 20)) && rect.contains(Point::new(29, 59)));
+        assert!(!rect.contains(Point::new(30, 20)) && !rect.contains(Point::new(10, 60)));
+        assert!(rect.contains_rect(&rect) && rect.contains_rect(&Rect::new(100, 100, 100, 200)));
+        assert!(!rect.contains_rect(&Rect::new(10, 20, 31, 60)));
+
+        let inverted = Rect::new(5, 5, 0, 10);
+        assert!(inverted.is_empty() && Rect::new(0, 0, 5, 0).is_empty());
+        assert_eq!((inverted.width(), inverted.size(), inverted.area()), (0, Size::new(0, 5), 0));
+        assert!(!inverted.contains(Point::new(2, 7)));
+    }
+
+    #[test]
+    fn rectangles_combine() {
+        let a = Rect::new(0, 0, 10, 10);
+        assert_eq!(a.intersection(&Rect::new(5, -5, 15, 5)), Some(Rect::new(5, 0, 10, 5)));
+        // Rectangles side by side share an edge but no pixels
+        assert_eq!(a.intersection(&Rect::new(10, 0, 20, 10)), None);
+        assert!(!a.intersects(&Rect::new(10, 0, 20, 10)) && a.intersects(&Rect::new(9, 9, 20, 20)));
+        assert_eq!(a.union(&Rect::new(20, -5, 25, 0)), Rect::new(0, -5, 25, 10));
+        assert_eq!(a.union(&Rect::new(50, 50, 50, 60)), a);
+        assert_eq!(Rect::default().union(&a), a);
+        assert_eq!(a.offset(-5, 5), Rect::new(-5, 5, 5, 15));
+        assert_eq!(a.inflate(2, -1), Rect::new(-2, 1, 12, 9));
+        assert!(a.inflate(-5, 0).is_empty());
+    }
+
+    #[test]
+    fn integer_rectangles_saturate() {
+        let wide = Rect::new(i32::MIN, 0, i32::MAX, 2);
+        assert_eq!(wide.width(), i32::MAX);
+        assert_eq!(wide.area(), 2 * (u32::MAX as i64));
+        assert_eq!(Rect::new(i32::MAX - 1, 0, i32::MAX, 1).offset(10, 0), Rect::new(i32::MAX, 0, i32::MAX, 1));
+        assert_eq!(Rect::from_origin_size(Point::new(i32::MAX - 5, 0), Size::new(10, 1)).right, i32::MAX);
+        assert_eq!(Rect::new(0, 0, 1, 1).inflate(i32::MAX, 0), Rect::new(-i32::MAX, 0, i32::MAX, 1));
+    }
+
+    #[test]
+    fn dpi_scaling_rounds_like_mul_div() {
+        assert_eq!(Point::new(10, -10).to_physical(144), Point::new(15, -15));
+        // Halves round away from zero
+        assert_eq!(Point::new(1, -1).to_physical(144), Point::new(2, -2));
+        assert_eq!(Size::new(3, 5).to_physical(120), Size::new(4, 6));
+        assert_eq!(Size::new(4, 6).to_logical(120), Size::new(3, 5));
+        assert_eq!(Point::new(15, 7).to_logical(DEFAULT_DPI), Point::new(15, 7));
+        assert_eq!(Rect::new(i32::MAX, 0, 0, 0).to_physical(192).left, i32::MAX);
+        // A zero DPI is taken as 1 instead of dividing by zero
+        assert_eq!(Point::new(1, 0).to_logical(0), Point::new(96, 0));
+
+        // Edges scale, so rectangles that touch keep touching
+        let (left, right) = (Rect::new(0, 0, 3, 3), Rect::new(3, 0, 7, 3));
+        let (left, right) = (left.to_physical(120), right.to_physical(120));
+        assert_eq!((left.right, right.left), (4, 4));
+        assert_eq!(left.to_logical(120), Rect::new(0, 0, 3, 3));
+    }
+
+    #[test]
+    fn fractional_rectangles_round() {
+        let rect = RectF::new(0.4, 0.5, 2.5, 3.6);
+        assert!((rect.area() - 6.51).abs() < 1e-5);
+        assert_eq!(rect.round(), Rect::new(0, 1, 3, 4));
+        assert_eq!(rect.round_out(), Rect::new(0, 0, 3, 4));
+        assert_eq!(RectF::new(-0.5, -1.2, 0.2, 0.0).round_out(), Rect::new(-1, -2, 1, 0));
+        assert_eq!(rect.scale(2.0), RectF::new(0.8, 1.0, 5.0, 7.2));
+        assert_eq!(Rect::new(1, 2, 3, 4).to_f32(), RectF::new(1.0, 2.0, 3.0, 4.0));
+        assert!(rect.contains(PointF::new(2.49, 0.5)) && !rect.contains(PointF::new(2.5, 1.0)));
+        assert_eq!(RectF::new(2.0, 0.0, 1.0, 1.0).area(), 0.0);
+    }
+}
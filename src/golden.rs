@@ -0,0 +1,431 @@
+//! Golden image (snapshot) tests
+//!
+//! A test renders a frame headlessly, into a [`Canvas`] or an [`Image`],
+//! and hands it to [`Golden::check`] with a name. The frame is compared to
+//! the reference PNG of that name: pixels may differ by up to
+//! [`Tolerance::channel`] in every channel, and a [`Tolerance::max_ratio`]
+//! of the pixels may differ by more, which absorbs anti-aliasing that
+//! changes between compilers and platforms.
+//!
+//! When a frame doesn't match, the frame and an image of the differences
+//! are written to the output directory, red where pixels are outside the
+//! tolerance, yellow where they are different but within it and a faded
+//! copy of the reference elsewhere.
+//!
+//! Running the tests with [`BLESS_ENV_VAR`] set, `GLTEST_BLESS=1 cargo
+//! test`, writes every frame as its new reference instead of comparing.
+//! [`Golden::bless`] overrides the variable for one checker.
+
+use std::path::{Path, PathBuf};
+
+use crate::canvas::Canvas;
+use crate::image::{Image, ImageError, ImageFormat, PixelFormat};
+
+/// Makes [`Golden::check`] replace the references when set to anything
+/// other than empty or `0`, unless [`Golden::bless`] says otherwise
+pub const BLESS_ENV_VAR: &str = "GLTEST_BLESS";
+
+/// Abstraction to represent a failed golden image check
+#[derive(Debug)]
+pub enum GoldenError {
+    /// A reference or output file couldn't be read or written
+    Io { path: PathBuf, error: std::io::Error },
+
+    /// The reference isn't an image we can read, or the frame is empty
+    Image { path: PathBuf, error: ImageError },
+
+    /// There is no reference yet; the frame was written to `actual`
+    MissingReference { reference: PathBuf, actual: PathBuf },
+
+    /// The frame and the reference have different sizes
+    SizeMismatch { expected: [u32; 2], actual: [u32; 2] },
+
+    /// Too many pixels are outside the tolerance
+    Mismatch { comparison: Box<Comparison>, actual: PathBuf, diff: PathBuf },
+}
+
+impl std::fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GoldenError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            GoldenError::Image { path, error } => write!(f, "{}: {}", path.display(), error),
+            GoldenError::MissingReference { reference, actual } => write!(
+                f,
+                "no reference image {}, the frame is in {} (set {} to create it)",
+                reference.display(),
+                actual.display(),
+                BLESS_ENV_VAR,
+            ),
+            GoldenError::SizeMismatch { expected, actual } => write!(
+                f,
+                "frame is {}x{}, the reference is {}x{}",
+                actual[0], actual[1], expected[0], expected[1],
+            ),
+            GoldenError::Mismatch { comparison, actual, diff } => write!(
+                f,
+                "{} of {} pixels ({:.3}%) differ, by up to {}; the frame is in {} and the differences in {}",
+                comparison.differing_pixels,
+                comparison.total_pixels(),
+                comparison.differing_ratio() * 100.0,
+                comparison.max_difference,
+                actual.display(),
+                diff.display(),
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+/// How different a frame may be from its reference
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// Largest difference of a channel, alpha included, that still counts
+    /// as the same pixel
+    pub channel: u8,
+
+    /// Largest fraction of the pixels, between 0 and 1, that may be
+    /// different beyond `channel`
+    pub max_ratio: f64,
+}
+
+impl Tolerance {
+    /// Every pixel must be identical
+    pub const EXACT: Tolerance = Tolerance { channel: 0, max_ratio: 0.0 };
+
+    pub fn new(channel: u8, max_ratio: f64) -> Self {
+        return Tolerance { channel, max_ratio };
+    }
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        return Tolerance::EXACT;
+    }
+}
+
+/// The result of comparing two images of the same size
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparison {
+    pub width: u32,
+    pub height: u32,
+
+    /// Pixels with a channel different by more than the tolerance
+    pub differing_pixels: u64,
+
+    /// Largest difference of any channel of any pixel
+    pub max_difference: u8,
+
+    /// The differences as RGBA, see the module documentation
+    pub diff: Image,
+}
+
+impl Comparison {
+    pub fn total_pixels(&self) -> u64 {
+        return self.width as u64 * self.height as u64;
+    }
+
+    pub fn differing_ratio(&self) -> f64 {
+        return self.differing_pixels as f64 / self.total_pixels() as f64;
+    }
+
+    /// Whether few enough pixels differ for `tolerance`
+    pub fn passes(&self, tolerance: Tolerance) -> bool {
+        return self.differing_ratio() <= tolerance.max_ratio;
+    }
+}
+
+/// Compares two images pixel by pixel as straight RGBA, counting the
+/// pixels with a channel that differs by more than `channel_tolerance`
+pub fn compare(actual: &Image, expected: &Image, channel_tolerance: u8) -> Result<Comparison, GoldenError> {
+    let (width, height) = (expected.width(), expected.height());
+    if [actual.width(), actual.height()] != [width, height] {
+        return Err(GoldenError::SizeMismatch { expected: [width, height], actual: [actual.width(), actual.height()] });
+    }
+    let actual = actual.to_rgba();
+    // Starts as the reference and is overwritten pixel by pixel
+    let mut diff = expected.convert(PixelFormat::Rgba8);
+    let (mut differing_pixels, mut max_difference) = (0, 0);
+    for (a, output) in actual.chunks_exact(4).zip(diff.data_mut().chunks_exact_mut(4)) {
+        let e = [output[0], output[1], output[2], output[3]];
+        let difference = a.iter().zip(e).map(|(a, e)| a.abs_diff(e)).max().unwrap_or(0);
+        max_difference = max_difference.max(difference);
+        if difference > channel_tolerance {
+            differing_pixels += 1;
+            output.copy_from_slice(&[255, 0, 0, 255]);
+        } else if difference > 0 {
+            output.copy_from_slice(&[255, 200, 0, 255]);
+        } else {
+            // A faded grey copy of the reference, composited over white
+            let gray = (e[0] as u32 * 299 + e[1] as u32 * 587 + e[2] as u32 * 114) / 1000;
+            let gray = 255 - (255 - gray) * e[3] as u32 / 255;
+            let faded = (170 + gray / 3) as u8;
+            output.copy_from_slice(&[faded, faded, faded, 255]);
+        }
+    }
+    return Ok(Comparison { width, height, differing_pixels, max_difference, diff });
+}
+
+/// Whether [`BLESS_ENV_VAR`] asks for the references to be replaced
+pub fn blessing() -> bool {
+    return std::env::var_os(BLESS_ENV_VAR).is_some_and(|value| !value.is_empty() && value != "0");
+}
+
+/// Checks frames against the reference PNGs of a directory, see the
+/// module documentation
+#[derive(Debug, Clone)]
+pub struct Golden {
+    reference_dir: PathBuf,
+    output_dir: PathBuf,
+    tolerance: Tolerance,
+    bless: bool,
+}
+
+impl Golden {
+    /// References are `<name>.png` in `reference_dir`. Failed frames go to
+    /// its `failures` subdirectory unless [`output_dir`](Self::output_dir)
+    /// says otherwise.
+    pub fn new(reference_dir: impl Into<PathBuf>) -> Self {
+        let reference_dir = reference_dir.into();
+        let output_dir = reference_dir.join("failures");
+        return Golden { reference_dir, output_dir, tolerance: Tolerance::EXACT, bless: blessing() };
+    }
+
+    pub fn output_dir(mut self, output_dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = output_dir.into();
+        return self;
+    }
+
+    pub fn tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.tolerance = tolerance;
+        return self;
+    }
+
+    /// Whether checks replace the references instead of comparing, by
+    /// default [`blessing`]
+    pub fn bless(mut self, bless: bool) -> Self {
+        self.bless = bless;
+        return self;
+    }
+
+    pub fn reference_path(&self, name: &str) -> PathBuf {
+        return self.reference_dir.join(format!("{}.png", name));
+    }
+
+    /// Compares a frame to the reference called `name`, or replaces the
+    /// reference when [blessing](Self::bless)
+    pub fn check(&self, name: &str, actual: &Image) -> Result<(), GoldenError> {
+        return self.check_with(name, actual, self.tolerance);
+    }
+
+    /// [`check`](Self::check) with another tolerance for one frame
+    pub fn check_with(&self, name: &str, actual: &Image, tolerance: Tolerance) -> Result<(), GoldenError> {
+        let reference = self.reference_path(name);
+        let actual_path = self.output_dir.join(format!("{}.actual.png", name));
+        let diff_path = self.output_dir.join(format!("{}.diff.png", name));
+        if self.bless {
+            return write_image(&reference, actual);
+        }
+
+        let data = match std::fs::read(&reference) {
+            Ok(data) => data,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                write_image(&actual_path, actual)?;
+                return Err(GoldenError::MissingReference { reference, actual: actual_path });
+            }
+            Err(error) => return Err(GoldenError::Io { path: reference, error }),
+        };
+        let expected = Image::decode(&data).map_err(|error| GoldenError::Image { path: reference.clone(), error })?;
+        let comparison = match compare(actual, &expected, tolerance.channel) {
+            Err(e @ GoldenError::SizeMismatch { .. }) => {
+                write_image(&actual_path, actual)?;
+                return Err(e);
+            }
+            result => result?,
+        };
+        if comparison.passes(tolerance) {
+            // Leftovers of an earlier failure would be misleading
+            let _ = std::fs::remove_file(&actual_path);
+            let _ = std::fs::remove_file(&diff_path);
+            return Ok(());
+        }
+        write_image(&actual_path, actual)?;
+        write_image(&diff_path, &comparison.diff)?;
+        return Err(GoldenError::Mismatch { comparison: Box::new(comparison), actual: actual_path, diff: diff_path });
+    }
+
+    /// [`check`](Self::check) for a canvas frame
+    pub fn check_canvas(&self, name: &str, canvas: &Canvas) -> Result<(), GoldenError> {
+        let image = Image::from_canvas(canvas)
+            .map_err(|error| GoldenError::Image { path: self.reference_path(name), error })?;
+        return self.check(name, &image);
+    }
+
+    /// [`check`](Self::check) that panics with the reason, for `#[test]`s
+    #[track_caller]
+    pub fn assert(&self, name: &str, actual: &Image) {
+        if let Err(e) = self.check(name, actual) {
+            panic!("golden image {:?}: {}", name, e);
+        }
+    }
+}
+
+fn write_image(path: &Path, image: &Image) -> Result<(), GoldenError> {
+    let io_error = |error| GoldenError::Io { path: path.to_path_buf(), error };
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(io_error)?;
+    }
+    return std::fs::write(path, image.encode(ImageFormat::Png)).map_err(io_error);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bitmap_font::BitmapFont;
+    use crate::canvas::Color;
+    use crate::geometry::{PointF, RectF, SizeF};
+
+    /// An empty directory of its own for every test
+    fn scratch_dir(name: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("gltest-golden-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        return directory;
+    }
+
+    fn rgba(width: u32, height: u32, pixels: &[[u8; 4]]) -> Image {
+        return Image::from_data(width, height, PixelFormat::Rgba8, width as usize * 4, pixels.concat()).unwrap();
+    }
+
+    fn read(path: &Path) -> Image {
+        return Image::decode(&std::fs::read(path).unwrap()).unwrap();
+    }
+
+    /// Shapes and text covering the canvas features the references depend on
+    fn scene() -> Canvas {
+        let mut canvas = Canvas::new(64, 48);
+        canvas.clear(Color::WHITE);
+        canvas.fill_rect(RectF::new(4.0, 4.0, 28.0, 20.0), Color::rgb(40, 120, 220));
+        canvas.fill_circle(PointF::new(44.0, 14.0), 10.5, Color::rgba(220, 60, 40, 200));
+        canvas.stroke_rounded_rect(RectF::new(2.5, 24.5, 61.5, 45.5), 6.0, 1.5, Color::rgb(30, 30, 30));
+        canvas.stroke_ellipse(PointF::new(48.0, 35.0), SizeF::new(9.0, 5.0), 1.0, Color::rgb(20, 160, 60));
+        canvas.line(PointF::new(4.0, 22.0), PointF::new(60.0, 2.0), 1.0, Color::BLACK.with_alpha(160));
+        BitmapFont::builtin().draw(&mut canvas, PointF::new(7.0, 29.0), "Ok", Color::BLACK);
+        return canvas;
+    }
+
+    #[test]
+    fn scene_matches_its_reference() {
+        // Keeps the setting of the run, so blessing updates this reference
+        let golden = Golden::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden"));
+        let golden = golden.tolerance(Tolerance::new(2, 0.001));
+        golden.assert("canvas_scene", &Image::from_canvas(&scene()).unwrap());
+        assert!(golden.check_canvas("canvas_scene", &scene()).is_ok());
+    }
+
+    #[test]
+    fn comparison_counts_and_marks_differences() {
+        let expected = rgba(4, 1, &[[0, 0, 0, 255], [10, 20, 30, 255], [100, 100, 100, 255], [255, 255, 255, 0]]);
+        let actual = rgba(4, 1, &[[0, 0, 0, 255], [12, 20, 30, 255], [100, 100, 100, 250], [255, 255, 255, 0]]);
+        let comparison = compare(&actual, &expected, 2).unwrap();
+        assert_eq!((comparison.differing_pixels, comparison.max_difference), (1, 5));
+        assert_eq!(comparison.total_pixels(), 4);
+        assert_eq!(comparison.differing_ratio(), 0.25);
+        assert!(comparison.passes(Tolerance::new(2, 0.25)) && !comparison.passes(Tolerance::new(2, 0.2)));
+        // Faded reference, yellow within the tolerance, red beyond it, and white where it is transparent
+        let diff = comparison.diff.data().chunks_exact(4).map(|pixel| pixel.to_vec()).collect::<Vec<_>>();
+        assert_eq!(diff, vec![vec![170, 170, 170, 255], vec![255, 200, 0, 255], vec![255, 0, 0, 255], vec![255; 4]]);
+
+        assert_eq!(compare(&actual, &expected, 5).map(|comparison| comparison.differing_pixels).unwrap(), 0);
+        // Formats don't matter, only the pixels as RGBA
+        let gray = Image::from_data(1, 1, PixelFormat::Gray8, 1, vec![100]).unwrap();
+        let color = rgba(1, 1, &[[100, 100, 100, 255]]);
+        assert_eq!(compare(&gray, &color, 0).map(|comparison| comparison.max_difference).unwrap(), 0);
+        assert!(matches!(
+            compare(&gray, &expected, 0),
+            Err(GoldenError::SizeMismatch { expected: [4, 1], actual: [1, 1] }),
+        ));
+    }
+
+    #[test]
+    fn missing_references_keep_the_frame() {
+        let directory = scratch_dir("missing");
+        let golden = Golden::new(&directory).bless(false);
+        let frame = rgba(1, 1, &[[1, 2, 3, 255]]);
+        match golden.check("frame", &frame) {
+            Err(GoldenError::MissingReference { reference, actual }) => {
+                assert_eq!(reference, directory.join("frame.png"));
+                assert_eq!(actual, directory.join("failures").join("frame.actual.png"));
+                assert_eq!(read(&actual), frame);
+                assert!(!reference.exists());
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn mismatches_write_the_frame_and_the_differences() {
+        let directory = scratch_dir("mismatch");
+        let output = directory.join("out");
+        let golden = Golden::new(&directory).output_dir(&output).bless(false);
+        let reference = rgba(2, 1, &[[0, 0, 0, 255], [255, 255, 255, 255]]);
+        std::fs::write(golden.reference_path("frame"), reference.encode(ImageFormat::Png)).unwrap();
+
+        let frame = rgba(2, 1, &[[0, 0, 0, 255], [250, 255, 255, 255]]);
+        let error = golden.check("frame", &frame).unwrap_err();
+        let GoldenError::Mismatch { comparison, actual, diff } = &error else {
+            panic!("unexpected {:?}", error);
+        };
+        assert_eq!((comparison.differing_pixels, comparison.max_difference), (1, 5));
+        assert_eq!((actual, diff), (&output.join("frame.actual.png"), &output.join("frame.diff.png")));
+        assert_eq!(read(actual), frame);
+        assert_eq!(read(diff), comparison.diff);
+        assert!(error.to_string().starts_with("1 of 2 pixels (50.000%) differ, by up to 5;"));
+
+        // A looser tolerance passes and removes what the failure left
+        assert!(golden.check_with("frame", &frame, Tolerance::new(5, 0.0)).is_ok());
+        assert!(!output.join("frame.actual.png").exists() && !output.join("frame.diff.png").exists());
+        assert!(golden.tolerance(Tolerance::new(0, 0.5)).check("frame", &frame).is_ok());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn unusable_references_are_reported() {
+        let directory = scratch_dir("unusable");
+        let golden = Golden::new(&directory).bless(false);
+        let frame = rgba(1, 1, &[[1, 2, 3, 255]]);
+        std::fs::write(golden.reference_path("wide"), rgba(2, 1, &[[0; 4]; 2]).encode(ImageFormat::Png)).unwrap();
+        assert!(matches!(
+            golden.check("wide", &frame),
+            Err(GoldenError::SizeMismatch { expected: [2, 1], actual: [1, 1] }),
+        ));
+        assert_eq!(read(&directory.join("failures").join("wide.actual.png")), frame);
+
+        std::fs::write(golden.reference_path("corrupt"), b"not an image").unwrap();
+        assert!(matches!(
+            golden.check("corrupt", &frame),
+            Err(GoldenError::Image { error: ImageError::UnknownFormat, .. }),
+        ));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn blessing_replaces_references() {
+        let directory = scratch_dir("bless");
+        let golden = Golden::new(directory.join("references")).bless(false);
+        let old = rgba(1, 1, &[[0, 0, 0, 255]]);
+        let new = rgba(1, 2, &[[9, 9, 9, 255], [8, 8, 8, 255]]);
+        std::fs::create_dir_all(directory.join("references")).unwrap();
+        std::fs::write(golden.reference_path("frame"), old.encode(ImageFormat::Png)).unwrap();
+
+        assert!(golden.check("frame", &new).is_err());
+        let blessed = golden.clone().bless(true);
+        assert!(blessed.check("frame", &new).is_ok());
+        assert!(blessed.check("another", &old).is_ok());
+        assert_eq!(read(&golden.reference_path("frame")), new);
+        assert_eq!(read(&golden.reference_path("another")), old);
+        assert!(golden.check("frame", &new).is_ok());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
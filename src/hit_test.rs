@@ -0,0 +1,212 @@
+//! Hit testing: which part of a window is under the mouse
+//!
+//! Backends ask a hit-test hook for every mouse position in the window
+//! (`WM_NCHITTEST` on Win32) and let the answer decide whether the click
+//! goes to the application, drags the window or passes through it.
+//! [`HitRegions`] builds such a hook from rectangles.
+//!
+//! Windows that draw their own title bar also answer with the frame
+//! parts: resize borders and the window buttons. [`frame_edge`] finds
+//! the resize border under a point.
+
+use crate::geometry::{Point, Rect, Size};
+
+/// Answer of a hit-test hook for a point of the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HitTest {
+    /// Whatever the platform would normally do
+    Default,
+
+    /// The application handles the mouse here
+    Client,
+
+    /// Dragging here moves the window, like a title bar
+    Caption,
+
+    /// The mouse goes to whatever is below this window. On Win32 this
+    /// only reaches windows of the same thread; make the whole window
+    /// click-through to reach other programs.
+    PassThrough,
+
+    /// Dragging here resizes the window
+    Border(ResizeEdge),
+
+    /// Clicking here opens the system menu, double clicking closes the window
+    SystemMenu,
+
+    /// The minimize button of a custom title bar
+    MinimizeButton,
+
+    /// The maximize/restore button of a custom title bar. Windows 11 shows
+    /// its snap layouts when hovering it.
+    MaximizeButton,
+
+    /// The close button of a custom title bar
+    CloseButton,
+}
+
+/// Which edge or corner of the window a resize border is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResizeEdge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// The resize border under a point of a window of `size`, with borders
+/// `thickness` pixels wide inside the window. Corners are a little larger
+/// than the borders so they are easier to grab. In a window too small for
+/// that, each half of the window belongs to the side it is on.
+pub fn frame_edge(Point { x, y }: Point, Size { width, height }: Size, thickness: i32) -> Option<ResizeEdge> {
+    if x < 0 || y < 0 || x >= width || y >= height {
+        return None;
+    }
+    let (half_width, half_height) = (width / 2, height / 2);
+    let corner = thickness.saturating_mul(2);
+    let (corner_x, corner_y) = (corner.min(half_width), corner.min(half_height));
+    let (thickness_x, thickness_y) = (thickness.min(half_width), thickness.min(half_height));
+    let left = x < thickness_x;
+    let right = x >= width - thickness_x;
+    let top = y < thickness_y;
+    let bottom = y >= height - thickness_y;
+    let near_left = x < corner_x;
+    let near_right = x >= width - corner_x;
+    let near_top = y < corner_y;
+    let near_bottom = y >= height - corner_y;
+
+    if (top && near_left) || (left && near_top) {
+        return Some(ResizeEdge::TopLeft);
+    } else if (top && near_right) || (right && near_top) {
+        return Some(ResizeEdge::TopRight);
+    } else if (bottom && near_left) || (left && near_bottom) {
+        return Some(ResizeEdge::BottomLeft);
+    } else if (bottom && near_right) || (right && near_bottom) {
+        return Some(ResizeEdge::BottomRight);
+    } else if left {
+        return Some(ResizeEdge::Left);
+    } else if right {
+        return Some(ResizeEdge::Right);
+    } else if top {
+        return Some(ResizeEdge::Top);
+    } else if bottom {
+        return Some(ResizeEdge::Bottom);
+    } else {
+        return None;
+    }
+}
+
+/// Rectangles of a window with their hit-test answers
+///
+/// Positions are in client coordinates. Regions added later are on top
+/// of earlier ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HitRegions {
+    regions: Vec<(Rect, HitTest)>,
+    fallback: HitTest,
+}
+
+impl Default for HitRegions {
+    fn default() -> Self {
+        return HitRegions { regions: Vec::new(), fallback: HitTest::Default };
+    }
+}
+
+impl HitRegions {
+    pub fn new() -> Self {
+        return HitRegions::default();
+    }
+
+    /// The answer for points outside every region, `HitTest::Default` unless changed
+    pub fn fallback(mut self, fallback: HitTest) -> Self {
+        self.fallback = fallback;
+        return self;
+    }
+
+    pub fn region(mut self, rect: Rect, hit: HitTest) -> Self {
+        self.regions.push((rect, hit));
+        return self;
+    }
+
+    pub fn clear(&mut self) {
+        self.regions.clear();
+    }
+
+    /// The answer for a point in client coordinates
+    pub fn test(&self, point: Point) -> HitTest {
+        return self.regions.iter().rev().find(|(rect, _)| rect.contains(point)).map_or(self.fallback, |&(_, hit)| hit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn later_regions_are_on_top() {
+        let regions = HitRegions::new()
+            .region(Rect::new(0, 0, 100, 30), HitTest::Caption)
+            .region(Rect::new(70, 0, 100, 30), HitTest::CloseButton)
+            .region(Rect::new(0, 30, 100, 100), HitTest::Client);
+        assert_eq!(regions.test(Point::new(10, 10)), HitTest::Caption);
+        assert_eq!(regions.test(Point::new(70, 0)), HitTest::CloseButton);
+        // The bottom and right edges belong to the next region or nothing
+        assert_eq!(regions.test(Point::new(10, 30)), HitTest::Client);
+        assert_eq!(regions.test(Point::new(100, 50)), HitTest::Default);
+    }
+
+    fn edges(size: Size, thickness: i32, points: &[[i32; 2]]) -> Vec<Option<ResizeEdge>> {
+        return points.iter().map(|&point| frame_edge(point.into(), size, thickness)).collect();
+    }
+
+    #[test]
+    fn corners_are_larger_than_edges() {
+        use ResizeEdge::*;
+        let size = Size::new(100, 80);
+        let top = edges(size, 4, &[[0, 0], [7, 0], [8, 0], [50, 3], [91, 0], [92, 3], [99, 0]]);
+        assert_eq!(top, [TopLeft, TopLeft, Top, Top, Top, TopRight, TopRight].map(Some).to_vec());
+        let sides = edges(size, 4, &[[0, 7], [3, 8], [0, 72], [3, 71], [96, 40], [99, 72], [50, 76], [4, 79]]);
+        assert_eq!(sides, [TopLeft, Left, BottomLeft, Left, Right, BottomRight, Bottom, BottomLeft].map(Some).to_vec());
+        assert_eq!(edges(size, 4, &[[4, 4], [50, 40], [95, 75]]), vec![None; 3]);
+        assert_eq!(edges(size, 0, &[[0, 0], [99, 79]]), vec![None; 2]);
+    }
+
+    #[test]
+    fn points_outside_the_window_are_no_edge() {
+        let size = Size::new(100, 80);
+        assert_eq!(edges(size, 4, &[[-1, 0], [0, -1], [100, 0], [0, 80], [i32::MIN, i32::MAX]]), vec![None; 5]);
+        assert_eq!(edges(Size::new(0, 0), 4, &[[0, 0]]), vec![None]);
+    }
+
+    #[test]
+    fn small_windows_split_into_halves() {
+        use ResizeEdge::*;
+        // Narrower and shorter than two corners
+        let size = Size::new(10, 6);
+        let points = [[0, 0], [4, 2], [5, 0], [9, 2], [0, 3], [4, 5], [5, 3], [9, 5]];
+        let expected = [TopLeft, TopLeft, TopRight, TopRight, BottomLeft, BottomLeft, BottomRight, BottomRight];
+        assert_eq!(edges(size, 4, &points), expected.map(Some).to_vec());
+        // Only as tall as two corners, so the sides keep their edges
+        let size = Size::new(100, 16);
+        let short = edges(size, 4, &[[0, 7], [0, 8], [50, 4], [99, 15]]);
+        assert_eq!(short, vec![Some(TopLeft), Some(BottomLeft), None, Some(BottomRight)]);
+        let huge = edges(Size::new(100, 80), i32::MAX, &[[49, 10], [50, 39], [60, 79]]);
+        assert_eq!(huge, vec![Some(TopLeft), Some(TopRight), Some(BottomRight)]);
+    }
+
+    #[test]
+    fn points_outside_every_region_get_the_fallback() {
+        let mut regions = HitRegions::new()
+            .fallback(HitTest::PassThrough)
+            .region(Rect::new(10, 10, 20, 20), HitTest::Client);
+        assert_eq!(regions.test(Point::new(15, 15)), HitTest::Client);
+        assert_eq!(regions.test(Point::new(5, 15)), HitTest::PassThrough);
+        regions.clear();
+        assert_eq!(regions.test(Point::new(15, 15)), HitTest::PassThrough);
+        assert_eq!(HitRegions::new().test(Point::new(0, 0)), HitTest::Default);
+    }
+}
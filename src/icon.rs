@@ -0,0 +1,408 @@
+//! Platform independent icon data
+//!
+//! An [`Icon`] is a set of RGBA images of different sizes. Backends pick
+//! the closest size for what they need (the small title bar icon, the large
+//! taskbar/alt-tab icon) and convert it into their native representation.
+
+use crate::image::bmp::{self, read_u16, read_u32};
+use crate::image::{png, Image, ImageError};
+
+/// Abstraction to represent an error while building an icon
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IconError {
+    /// The width or height is zero or too large
+    InvalidSize { width: u32, height: u32 },
+
+    /// The pixel buffer doesn't match `width * height * 4`
+    BadBufferLength { expected: usize, actual: usize },
+
+    /// The ICO data is malformed or truncated
+    InvalidIco(&'static str),
+
+    /// The PNG or bitmap of an ICO entry can't be decoded
+    Image(ImageError),
+
+    /// The icon contains no images
+    Empty,
+}
+
+impl std::fmt::Display for IconError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            IconError::InvalidSize { width, height } => {
+                write!(f, "invalid icon size {}x{}", width, height)
+            }
+            IconError::BadBufferLength { expected, actual } => {
+                write!(f, "icon buffer is {} bytes, expected {}", actual, expected)
+            }
+            IconError::InvalidIco(reason) => write!(f, "invalid ICO data: {}", reason),
+            IconError::Image(error) => write!(f, "invalid ICO entry: {}", error),
+            IconError::Empty => write!(f, "icon contains no images"),
+        }
+    }
+}
+
+impl std::error::Error for IconError {}
+
+/// Largest icon dimension we accept. ICO files can't go past 256 and
+/// nothing on screen needs more than that.
+pub const MAX_ICON_SIZE: u32 = 1024;
+
+/// A single icon image, stored as straight (non-premultiplied) RGBA8
+/// rows from top to bottom
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IconImage {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+impl IconImage {
+    /// Create an icon image from a tightly packed RGBA8 buffer
+    pub fn from_rgba(width: u32, height: u32, rgba: Vec<u8>) -> Result<Self, IconError> {
+        if width == 0 || height == 0 || width > MAX_ICON_SIZE || height > MAX_ICON_SIZE {
+            return Err(IconError::InvalidSize { width, height });
+        }
+        let expected = width as usize * height as usize * 4;
+        if rgba.len() != expected {
+            return Err(IconError::BadBufferLength { expected, actual: rgba.len() });
+        }
+        return Ok(IconImage { width, height, rgba });
+    }
+
+    /// Create an icon image from a decoded image of any pixel format
+    pub fn from_image(image: &Image) -> Result<Self, IconError> {
+        return IconImage::from_rgba(image.width(), image.height(), image.to_rgba());
+    }
+
+    pub fn width(&self) -> u32 {
+        return self.width;
+    }
+
+    pub fn height(&self) -> u32 {
+        return self.height;
+    }
+
+    /// The RGBA8 pixels, top row first
+    pub fn rgba(&self) -> &[u8] {
+        return &self.rgba;
+    }
+
+    /// Resample the image to the given size using a box filter when
+    /// shrinking and nearest neighbour when growing
+    pub fn resized(&self, width: u32, height: u32) -> Result<IconImage, IconError> {
+        if width == self.width && height == self.height {
+            return Ok(self.clone());
+        }
+        if width == 0 || height == 0 || width > MAX_ICON_SIZE || height > MAX_ICON_SIZE {
+            return Err(IconError::InvalidSize { width, height });
+        }
+
+        let mut out = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height {
+            // source rows covered by this destination row
+            let sy0 = (y as u64 * self.height as u64 / height as u64) as u32;
+            let sy1 = (((y + 1) as u64 * self.height as u64).div_ceil(height as u64) as u32)
+                .max(sy0 + 1);
+            for x in 0..width {
+                let sx0 = (x as u64 * self.width as u64 / width as u64) as u32;
+                let sx1 = (((x + 1) as u64 * self.width as u64).div_ceil(width as u64) as u32)
+                    .max(sx0 + 1);
+
+                // Average with alpha weighting so transparent pixels
+                // don't bleed their (meaningless) colour into the result
+                let mut sum = [0_u64; 4];
+                let mut count = 0_u64;
+                for sy in sy0..sy1 {
+                    for sx in sx0..sx1 {
+                        let i = (sy as usize * self.width as usize + sx as usize) * 4;
+                        let a = self.rgba[i + 3] as u64;
+                        sum[0] += self.rgba[i] as u64 * a;
+                        sum[1] += self.rgba[i + 1] as u64 * a;
+                        sum[2] += self.rgba[i + 2] as u64 * a;
+                        sum[3] += a;
+                        count += 1;
+                    }
+                }
+                match std::num::NonZeroU64::new(sum[3]) {
+                    None => out.extend_from_slice(&[0, 0, 0, 0]),
+                    Some(alpha) => {
+                        out.push((sum[0] / alpha) as u8);
+                        out.push((sum[1] / alpha) as u8);
+                        out.push((sum[2] / alpha) as u8);
+                        out.push((sum[3] / count) as u8);
+                    }
+                }
+            }
+        }
+
+        return Ok(IconImage { width, height, rgba: out });
+    }
+}
+
+/// A set of images for the same icon at different sizes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Icon {
+    images: Vec<IconImage>,
+}
+
+impl Icon {
+    /// Build an icon from one or more images. The images should be
+    /// square but this is not required.
+    pub fn new(images: Vec<IconImage>) -> Result<Self, IconError> {
+        if images.is_empty() {
+            return Err(IconError::Empty);
+        }
+        return Ok(Icon { images });
+    }
+
+    /// Shortcut for an icon with a single RGBA8 image
+    pub fn from_rgba(width: u32, height: u32, rgba: Vec<u8>) -> Result<Self, IconError> {
+        return Icon::new(vec![IconImage::from_rgba(width, height, rgba)?]);
+    }
+
+    /// Parse a Windows `.ico` file
+    ///
+    /// Entries stored as 32, 24, 8, 4 or 1 bit DIBs and PNG-compressed
+    /// entries are supported; at least one entry has to decode for the
+    /// icon to load.
+    pub fn from_ico(bytes: &[u8]) -> Result<Self, IconError> {
+        let (images, first_error) = parse_ico(bytes)?;
+        if images.is_empty() {
+            return Err(first_error.unwrap_or(IconError::Empty));
+        }
+        return Icon::new(images);
+    }
+
+    pub fn images(&self) -> &[IconImage] {
+        return &self.images;
+    }
+
+    /// Pick the image that is closest to `size` pixels, preferring
+    /// images that are larger (downscaling looks better than upscaling)
+    pub fn best_image(&self, size: u32) -> &IconImage {
+        let score = |image: &IconImage| -> (bool, u32) {
+            let edge = image.width.max(image.height);
+            // false sorts first: images at least as large as requested
+            return (edge < size, edge.abs_diff(size));
+        };
+        return self.images
+            .iter()
+            .min_by_key(|image| score(image))
+            .expect("an icon always has at least one image");
+    }
+
+    /// The best image resampled to exactly `size` x `size` pixels
+    pub fn image_for_size(&self, size: u32) -> Result<IconImage, IconError> {
+        return self.best_image(size).resized(size, size);
+    }
+
+    /// Convert the icon to the `_NET_WM_ICON` property layout used on X11
+    ///
+    /// Each image is a `width`, `height` pair followed by `width * height`
+    /// pixels packed as `0xAARRGGBB`. Note that Xlib expects format-32
+    /// properties as an array of C `long`, so on 64-bit systems each value
+    /// must be widened before calling `XChangeProperty`.
+    pub fn to_net_wm_icon(&self) -> Vec<u32> {
+        let capacity = self.images
+            .iter()
+            .map(|image| 2 + image.width as usize * image.height as usize)
+            .sum();
+        let mut out = Vec::with_capacity(capacity);
+        for image in &self.images {
+            out.push(image.width);
+            out.push(image.height);
+            for px in image.rgba.chunks_exact(4) {
+                out.push(
+                    (px[3] as u32) << 24 | (px[0] as u32) << 16 | (px[1] as u32) << 8 | px[2] as u32
+                );
+            }
+        }
+        return out;
+    }
+}
+
+/// Parse every entry in an ICO file. Entries that fail to decode are
+/// skipped and the first such error is returned alongside the images.
+fn parse_ico(bytes: &[u8]) -> Result<(Vec<IconImage>, Option<IconError>), IconError> {
+    // ICONDIR: reserved (0), type (1 = icon, 2 = cursor), count
+    let reserved = read_u16(bytes, 0).ok_or(IconError::InvalidIco("truncated header"))?;
+    let kind = read_u16(bytes, 2).ok_or(IconError::InvalidIco("truncated header"))?;
+    let count = read_u16(bytes, 4).ok_or(IconError::InvalidIco("truncated header"))?;
+    if reserved != 0 || (kind != 1 && kind != 2) {
+        return Err(IconError::InvalidIco("not an ICO file"));
+    }
+
+    let mut images = Vec::new();
+    let mut first_error = None;
+    for index in 0..count as usize {
+        // ICONDIRENTRY is 16 bytes
+        let entry = 6 + index * 16;
+        let size = read_u32(bytes, entry + 8).ok_or(IconError::InvalidIco("truncated directory"))?;
+        let offset = read_u32(bytes, entry + 12).ok_or(IconError::InvalidIco("truncated directory"))?;
+        let data = bytes
+            .get(offset as usize..(offset as usize).saturating_add(size as usize))
+            .ok_or(IconError::InvalidIco("entry points outside of the file"));
+
+        let decoded = data.and_then(|data| {
+            let image = if data.starts_with(&png::SIGNATURE) { png::decode(data) } else { bmp::decode_icon(data) };
+            return IconImage::from_image(&image.map_err(IconError::Image)?);
+        });
+        match decoded {
+            Ok(image) => images.push(image),
+            Err(e) => {
+                if first_error.is_none() {
+                    first_error = Some(e);
+                }
+            }
+        }
+    }
+
+    return Ok((images, first_error));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::PixelFormat;
+
+    /// A `BITMAPINFOHEADER` icon entry, whose height covers the colour
+    /// bitmap and the AND mask in `rest`
+    fn dib_entry(width: u32, height: u32, bit_count: u16, rest: &[u8]) -> Vec<u8> {
+        let mut data = 40u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&width.to_le_bytes());
+        data.extend_from_slice(&(height * 2).to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&bit_count.to_le_bytes());
+        data.extend_from_slice(&[0; 24]);
+        data.extend_from_slice(rest);
+        return data;
+    }
+
+    /// An ICO file with a directory entry for each of `entries`
+    fn ico(entries: &[Vec<u8>]) -> Vec<u8> {
+        let mut file = vec![0, 0, 1, 0];
+        file.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+        let mut offset = 6 + 16 * entries.len();
+        for entry in entries {
+            file.extend_from_slice(&[0; 8]);
+            file.extend_from_slice(&(entry.len() as u32).to_le_bytes());
+            file.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset += entry.len();
+        }
+        for entry in entries {
+            file.extend_from_slice(entry);
+        }
+        return file;
+    }
+
+    /// A 2x1 32-bit entry with real alpha and an empty AND mask
+    fn alpha_entry() -> Vec<u8> {
+        return dib_entry(2, 1, 32, &[3, 2, 1, 255, 9, 9, 9, 0, 0, 0, 0, 0]);
+    }
+
+    fn solid(size: u32, rgba: [u8; 4]) -> IconImage {
+        return IconImage::from_rgba(size, size, rgba.repeat(size as usize * size as usize)).unwrap();
+    }
+
+    #[test]
+    fn ico_entries_decode() {
+        // 24-bit rows padded to four bytes and stored bottom-up, then the
+        // AND mask which makes the bottom right pixel transparent
+        let mut rest = vec![30, 20, 10, 60, 50, 40, 0, 0, 90, 80, 70, 120, 110, 100, 0, 0];
+        rest.extend_from_slice(&[0b0100_0000, 0, 0, 0, 0, 0, 0, 0]);
+        let masked = dib_entry(2, 2, 24, &rest);
+        let palette = dib_entry(2, 1, 1, &[0, 0, 0, 0, 255, 255, 255, 0, 0b0100_0000, 0, 0, 0, 0, 0, 0, 0]);
+        let png = png::encode(&Image::from_data(1, 1, PixelFormat::Rgba8, 4, vec![5, 6, 7, 8]).unwrap());
+
+        let icon = Icon::from_ico(&ico(&[alpha_entry(), masked, palette, png])).unwrap();
+        let pixels: Vec<&[u8]> = icon.images().iter().map(|image| image.rgba()).collect();
+        assert_eq!(pixels[0], &[1, 2, 3, 255, 9, 9, 9, 0]);
+        assert_eq!(pixels[1], &[70, 80, 90, 255, 100, 110, 120, 255, 10, 20, 30, 255, 40, 50, 60, 0]);
+        assert_eq!(pixels[2], &[0, 0, 0, 255, 255, 255, 255, 255]);
+        assert_eq!(pixels[3], &[5, 6, 7, 8]);
+        assert_eq!((icon.images()[1].width(), icon.images()[1].height()), (2, 2));
+
+        // A 32-bit entry without a mask is opaque when its alpha is empty
+        let unmasked = dib_entry(1, 1, 32, &[3, 2, 1, 0]);
+        assert_eq!(Icon::from_ico(&ico(&[unmasked])).unwrap().images()[0].rgba(), &[1, 2, 3, 255]);
+    }
+
+    #[test]
+    fn malformed_ico_files_are_rejected() {
+        let file = ico(&[alpha_entry()]);
+        for length in [0, 5] {
+            assert_eq!(Icon::from_ico(&file[..length]), Err(IconError::InvalidIco("truncated header")));
+        }
+        assert_eq!(Icon::from_ico(&file[..21]), Err(IconError::InvalidIco("truncated directory")));
+        assert_eq!(Icon::from_ico(&[1, 0, 1, 0, 0, 0]), Err(IconError::InvalidIco("not an ICO file")));
+        assert_eq!(Icon::from_ico(&[0, 0, 3, 0, 0, 0]), Err(IconError::InvalidIco("not an ICO file")));
+        assert_eq!(Icon::from_ico(&ico(&[])), Err(IconError::Empty));
+
+        // Entries past the end of the file, or larger than it, are skipped
+        let mut outside = file.clone();
+        outside[18..22].copy_from_slice(&1000u32.to_le_bytes());
+        assert_eq!(Icon::from_ico(&outside), Err(IconError::InvalidIco("entry points outside of the file")));
+        let mut oversized = file.clone();
+        oversized[14..18].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(Icon::from_ico(&oversized), Err(IconError::InvalidIco("entry points outside of the file")));
+        assert!(Icon::from_ico(&file[..file.len() - 1]).is_err());
+
+        // A broken entry doesn't stop the others from loading
+        let mut mixed = ico(&[alpha_entry(), alpha_entry()]);
+        mixed[14..18].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(Icon::from_ico(&mixed).unwrap().images().len(), 1);
+
+        // Bitmap errors are those of the image decoders, and PNG data goes in a PNG entry
+        let mut compressed = alpha_entry();
+        compressed[16] = 5;
+        let error = IconError::Image(ImageError::Unsupported("compressed icon bitmap"));
+        assert_eq!(Icon::from_ico(&ico(&[compressed])), Err(error));
+        let truncated = IconError::Image(ImageError::Truncated("BMP"));
+        assert_eq!(Icon::from_ico(&ico(&[dib_entry(2, 2, 24, &[0; 8])])), Err(truncated));
+
+        let huge = dib_entry(MAX_ICON_SIZE + 1, 1, 32, &vec![0; 4 * (MAX_ICON_SIZE as usize + 1)]);
+        assert_eq!(Icon::from_ico(&ico(&[huge])), Err(IconError::InvalidSize { width: MAX_ICON_SIZE + 1, height: 1 }));
+    }
+
+    #[test]
+    fn images_check_their_size() {
+        assert_eq!(IconImage::from_rgba(0, 1, Vec::new()), Err(IconError::InvalidSize { width: 0, height: 1 }));
+        assert_eq!(
+            IconImage::from_rgba(MAX_ICON_SIZE + 1, 1, Vec::new()),
+            Err(IconError::InvalidSize { width: MAX_ICON_SIZE + 1, height: 1 }),
+        );
+        assert_eq!(IconImage::from_rgba(1, 1, vec![0; 3]), Err(IconError::BadBufferLength { expected: 4, actual: 3 }));
+        assert_eq!(Icon::new(Vec::new()), Err(IconError::Empty));
+    }
+
+    #[test]
+    fn images_resize() {
+        // Transparent pixels don't darken what they are averaged with
+        let image = IconImage::from_rgba(2, 1, vec![200, 100, 0, 255, 0, 0, 0, 0]).unwrap();
+        assert_eq!(image.resized(1, 1).unwrap().rgba(), &[200, 100, 0, 127]);
+        assert_eq!(solid(2, [0; 4]).resized(1, 1).unwrap().rgba(), &[0; 4]);
+
+        let grown = image.resized(4, 2).unwrap();
+        assert_eq!(grown.rgba()[..16], [200, 100, 0, 255, 200, 100, 0, 255, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(grown.rgba()[..16], grown.rgba()[16..]);
+        assert_eq!(image.resized(2, 1).unwrap(), image);
+        assert_eq!(image.resized(0, 1), Err(IconError::InvalidSize { width: 0, height: 1 }));
+    }
+
+    #[test]
+    fn the_closest_larger_image_is_preferred() {
+        let icon = Icon::new(vec![solid(16, [1; 4]), solid(48, [3; 4]), solid(32, [2; 4])]).unwrap();
+        let best = |size: u32| icon.best_image(size).width();
+        assert_eq!((best(8), best(16), best(20), best(32), best(40), best(64)), (16, 16, 32, 32, 48, 48));
+        let resized = icon.image_for_size(24).unwrap();
+        assert_eq!((resized.width(), resized.height(), &resized.rgba()[..4]), (24, 24, &[2; 4][..]));
+    }
+
+    #[test]
+    fn net_wm_icon_lists_sizes_then_argb_pixels() {
+        let wide = IconImage::from_rgba(2, 1, vec![0x11, 0x22, 0x33, 0x44, 0xAA, 0xBB, 0xCC, 0xDD]).unwrap();
+        let icon = Icon::new(vec![wide, solid(1, [1, 2, 3, 4])]).unwrap();
+        assert_eq!(icon.to_net_wm_icon(), vec![2, 1, 0x4411_2233, 0xDDAA_BBCC, 1, 1, 0x0401_0203]);
+    }
+}
@@ -0,0 +1,553 @@
+//! BMP and DIB decoding
+//!
+//! Reads `.bmp` files and the headerless device independent bitmaps
+//! Windows puts on the clipboard (`CF_DIB`, `CF_DIBV5`). Every header
+//! version is understood, from the OS/2 `BITMAPCOREHEADER` to
+//! `BITMAPV5HEADER`, with 1, 4, 8, 16, 24 and 32 bits per pixel, RLE4 and
+//! RLE8 compression, bit field masks and embedded PNG data. Bitmaps are
+//! opaque unless they have an alpha mask and some pixel isn't fully
+//! transparent: writers that ignore alpha leave it at zero.
+//! [`decode_icon`] reads the bitmaps inside ICO files.
+//!
+//! [`encode`] writes opaque images as 24-bit bitmaps with the plain info
+//! header, which every reader understands, and images with alpha as 32-bit
+//! bitmaps with a `BITMAPV4HEADER` that declares the alpha mask.
+
+use super::{check_size, png, scale_sample, to_rgba, Image, ImageError, PixelFormat};
+
+const BI_RGB: u32 = 0;
+const BI_RLE8: u32 = 1;
+const BI_RLE4: u32 = 2;
+const BI_BITFIELDS: u32 = 3;
+const BI_JPEG: u32 = 4;
+const BI_PNG: u32 = 5;
+const BI_ALPHABITFIELDS: u32 = 6;
+
+pub(crate) fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    return data.get(at..at + 2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]));
+}
+
+pub(crate) fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    return data.get(at..at + 4).map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+}
+
+/// Reads a `.bmp` file, which is a `BITMAPFILEHEADER` followed by a DIB
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    if !data.starts_with(b"BM") {
+        return Err(ImageError::Invalid("missing BM signature"));
+    }
+    let pixels = read_u32(data, 10).ok_or(ImageError::Truncated("BMP"))? as usize;
+    let pixels = pixels.checked_sub(14).ok_or(ImageError::Invalid("pixel data inside the file header"))?;
+    let dib = data.get(14..).ok_or(ImageError::Truncated("BMP"))?;
+    let info = read_info(dib)?;
+    return decode_with_info(dib, &info, pixels);
+}
+
+/// Reads a packed DIB: a bitmap header, the colour table or masks, then
+/// the pixels
+pub fn decode_dib(data: &[u8]) -> Result<Image, ImageError> {
+    let info = read_info(data)?;
+    let mut pixels = info.header_size + info.mask_bytes + info.palette.len() * info.palette_entry_size;
+    // Some clipboard writers repeat the bit fields after a V4 or V5 header
+    if info.compression == BI_BITFIELDS && info.header_size > 40 && data.len() == pixels + 12 + pixel_bytes(&info) {
+        pixels += 12;
+    }
+    return decode_with_info(data, &info, pixels);
+}
+
+/// Reads the bitmap of an ICO entry: a DIB whose height covers the colour
+/// bitmap and the 1-bit AND mask after it. 32-bit entries keep their
+/// alpha in the fourth byte; the others are transparent where the mask is
+/// set, and opaque when the mask is missing.
+pub fn decode_icon(data: &[u8]) -> Result<Image, ImageError> {
+    let mut info = read_info(data)?;
+    if info.compression != BI_RGB && info.compression != BI_BITFIELDS {
+        return Err(ImageError::Unsupported("compressed icon bitmap"));
+    }
+    info.height /= 2;
+    if info.height == 0 {
+        return Err(ImageError::InvalidSize { width: info.width, height: 0 });
+    }
+    if info.bit_count == 32 && info.compression == BI_RGB {
+        info.masks[3] = 0xFF00_0000;
+    }
+    let pixels = info.header_size + info.mask_bytes + info.palette.len() * info.palette_entry_size;
+    let image = decode_with_info(data, &info, pixels)?;
+    if image.format().has_alpha() {
+        return Ok(image);
+    }
+
+    let mut image = image.convert(PixelFormat::Rgba8);
+    let stride = (info.width as usize).div_ceil(32) * 4;
+    let mask_start = pixels + pixel_bytes(&info);
+    let Some(mask) = data.get(mask_start..mask_start + stride * info.height as usize) else {
+        return Ok(image);
+    };
+    for (stored_row, bits) in mask.chunks_exact(stride).enumerate() {
+        let y = if info.top_down { stored_row as u32 } else { info.height - 1 - stored_row as u32 };
+        let row = image.row_mut(y);
+        for x in 0..info.width as usize {
+            if bits[x / 8] & (0x80 >> (x % 8)) != 0 {
+                row[4 * x + 3] = 0;
+            }
+        }
+    }
+    return Ok(image);
+}
+
+/// Writes an image as a bottom-up `.bmp` file, see the module documentation
+pub fn encode(image: &Image) -> Vec<u8> {
+    let has_alpha = image.format().has_alpha();
+    let (header_size, bit_count, compression) = if has_alpha { (108, 32, BI_BITFIELDS) } else { (40, 24, BI_RGB) };
+    let (width, height) = (image.width(), image.height());
+    let stride = (width as usize * bit_count).div_ceil(32) * 4;
+    let pixels_start = 14 + header_size;
+    let file_size = pixels_start + stride * height as usize;
+
+    let mut output = Vec::with_capacity(file_size);
+    output.extend_from_slice(b"BM");
+    output.extend_from_slice(&(file_size as u32).to_le_bytes());
+    output.extend_from_slice(&[0; 4]);
+    output.extend_from_slice(&(pixels_start as u32).to_le_bytes());
+
+    output.extend_from_slice(&(header_size as u32).to_le_bytes());
+    output.extend_from_slice(&(width as i32).to_le_bytes());
+    output.extend_from_slice(&(height as i32).to_le_bytes());
+    output.extend_from_slice(&1u16.to_le_bytes());
+    output.extend_from_slice(&(bit_count as u16).to_le_bytes());
+    output.extend_from_slice(&compression.to_le_bytes());
+    output.extend_from_slice(&((stride * height as usize) as u32).to_le_bytes());
+    // 72 DPI in pixels per metre, then no colour table
+    output.extend_from_slice(&2835i32.to_le_bytes());
+    output.extend_from_slice(&2835i32.to_le_bytes());
+    output.extend_from_slice(&[0; 8]);
+    if has_alpha {
+        for mask in [0x00FF_0000u32, 0x0000_FF00, 0x0000_00FF, 0xFF00_0000] {
+            output.extend_from_slice(&mask.to_le_bytes());
+        }
+        // LCS_sRGB, which makes the endpoints and gamma that follow unused
+        output.extend_from_slice(b"BGRs");
+        output.extend_from_slice(&[0; 48]);
+    }
+
+    let size = image.format().bytes_per_pixel();
+    for y in (0..height).rev() {
+        let start = output.len();
+        for pixel in image.row(y).chunks_exact(size) {
+            let [r, g, b, a] = to_rgba(image.format(), pixel);
+            output.extend_from_slice(&[b, g, r, a][..bit_count / 8]);
+        }
+        output.resize(start + stride, 0);
+    }
+    return output;
+}
+
+/// What the bitmap header says
+struct Info {
+    header_size: usize,
+    width: u32,
+    height: u32,
+    top_down: bool,
+    bit_count: u16,
+    compression: u32,
+    /// Red, green, blue and alpha masks of 16 and 32-bit pixels
+    masks: [u32; 4],
+    /// Bytes of masks stored after the header
+    mask_bytes: usize,
+    palette: Vec<[u8; 3]>,
+    palette_entry_size: usize,
+}
+
+fn read_info(data: &[u8]) -> Result<Info, ImageError> {
+    let truncated = ImageError::Truncated("BMP");
+    let header_size = read_u32(data, 0).ok_or(truncated.clone())? as usize;
+    let (width, height, bit_count, compression) = match header_size {
+        12 => {
+            let width = read_u16(data, 4).ok_or(truncated.clone())? as i32;
+            let height = read_u16(data, 6).ok_or(truncated.clone())? as i16 as i32;
+            (width, height, read_u16(data, 10).ok_or(truncated.clone())?, BI_RGB)
+        }
+        40 | 52 | 56 | 64 | 108 | 124 => (
+            read_u32(data, 4).ok_or(truncated.clone())? as i32,
+            read_u32(data, 8).ok_or(truncated.clone())? as i32,
+            read_u16(data, 14).ok_or(truncated.clone())?,
+            read_u32(data, 16).ok_or(truncated.clone())?,
+        ),
+        _ => return Err(ImageError::Unsupported("unknown bitmap header size")),
+    };
+    if width <= 0 || height == 0 || height == i32::MIN {
+        return Err(ImageError::InvalidSize { width: width.max(0) as u32, height: height.unsigned_abs() });
+    }
+    let (width, top_down, height) = (width as u32, height < 0, height.unsigned_abs());
+    check_size(width, height)?;
+
+    let mut masks = match bit_count {
+        16 => [0x7C00, 0x03E0, 0x001F, 0],
+        32 => [0x00FF_0000, 0x0000_FF00, 0x0000_00FF, 0],
+        _ => [0; 4],
+    };
+    let mut mask_bytes = 0;
+    match compression {
+        BI_BITFIELDS | BI_ALPHABITFIELDS => {
+            if bit_count != 16 && bit_count != 32 {
+                return Err(ImageError::Invalid("bit fields need 16 or 32 bits per pixel"));
+            }
+            let count = if compression == BI_ALPHABITFIELDS || header_size >= 56 { 4 } else { 3 };
+            for (i, mask) in masks.iter_mut().enumerate().take(count) {
+                *mask = read_u32(data, 40 + 4 * i).ok_or(truncated.clone())?;
+            }
+            // Newer headers hold the masks; only the plain info header is followed by them
+            if header_size == 40 {
+                mask_bytes = 4 * count;
+            }
+        }
+        BI_RGB if header_size >= 56 && bit_count == 32 => {
+            // BI_RGB with a V3+ header may still declare an alpha mask
+            masks[3] = read_u32(data, 52).ok_or(truncated.clone())?;
+        }
+        BI_RGB | BI_RLE8 | BI_RLE4 | BI_PNG => {}
+        BI_JPEG => return Err(ImageError::Unsupported("JPEG compressed bitmap")),
+        _ => return Err(ImageError::Unsupported("unknown bitmap compression")),
+    }
+    if (compression == BI_RLE8 && bit_count != 8) || (compression == BI_RLE4 && bit_count != 4) {
+        return Err(ImageError::Invalid("RLE compression doesn't match the bit count"));
+    }
+
+    let palette_entry_size = if header_size == 12 { 3 } else { 4 };
+    let palette_length = match bit_count {
+        1 | 4 | 8 => {
+            let used = if header_size == 12 { 0 } else { read_u32(data, 32).ok_or(truncated.clone())? };
+            if used == 0 || used > 1 << bit_count { 1 << bit_count } else { used as usize }
+        }
+        16 | 24 | 32 => 0,
+        0 if compression == BI_PNG => 0,
+        _ => return Err(ImageError::Unsupported("unknown bit count")),
+    };
+    let palette_start = header_size + mask_bytes;
+    let palette_data = data
+        .get(palette_start..palette_start + palette_length * palette_entry_size)
+        .ok_or(truncated.clone())?;
+    let palette = palette_data.chunks_exact(palette_entry_size).map(|bgr| [bgr[2], bgr[1], bgr[0]]).collect();
+
+    return Ok(Info {
+        header_size,
+        width,
+        height,
+        top_down,
+        bit_count,
+        compression,
+        masks,
+        mask_bytes,
+        palette,
+        palette_entry_size,
+    });
+}
+
+/// The size of uncompressed pixel rows padded to four bytes
+fn pixel_bytes(info: &Info) -> usize {
+    let stride = (info.width as usize * info.bit_count as usize).div_ceil(32) * 4;
+    return stride * info.height as usize;
+}
+
+fn decode_with_info(data: &[u8], info: &Info, pixels: usize) -> Result<Image, ImageError> {
+    let pixels = data.get(pixels..).ok_or(ImageError::Truncated("BMP"))?;
+    if info.compression == BI_PNG {
+        return png::decode(pixels);
+    }
+    let has_alpha = info.masks[3] != 0;
+    let format = if has_alpha { PixelFormat::Rgba8 } else { PixelFormat::Rgb8 };
+    let mut image = Image::new(info.width, info.height, format)?;
+    let size = format.bytes_per_pixel();
+
+    if info.compression == BI_RLE8 || info.compression == BI_RLE4 {
+        let indices = decode_rle(pixels, info)?;
+        for (stored_row, row_indices) in indices.chunks_exact(info.width as usize).enumerate() {
+            let y = if info.top_down { stored_row as u32 } else { info.height - 1 - stored_row as u32 };
+            let row = image.row_mut(y);
+            for (x, &index) in row_indices.iter().enumerate() {
+                let [r, g, b] = info.palette.get(index as usize).copied().unwrap_or([0, 0, 0]);
+                row[x * size..x * size + 3].copy_from_slice(&[r, g, b]);
+            }
+        }
+        return Ok(image);
+    }
+
+    let stride = (info.width as usize * info.bit_count as usize).div_ceil(32) * 4;
+    if pixels.len() < stride * (info.height as usize - 1) + (info.width as usize * info.bit_count as usize).div_ceil(8) {
+        return Err(ImageError::Truncated("BMP"));
+    }
+    let channel = |pixel: u32, mask: u32| -> u8 {
+        if mask == 0 {
+            return 0;
+        }
+        return scale_sample((pixel & mask) >> mask.trailing_zeros(), mask >> mask.trailing_zeros());
+    };
+    for stored_row in 0..info.height as usize {
+        let source = &pixels[stored_row * stride..];
+        let y = if info.top_down { stored_row as u32 } else { info.height - 1 - stored_row as u32 };
+        let row = image.row_mut(y);
+        for x in 0..info.width as usize {
+            let rgba = match info.bit_count {
+                1 | 4 | 8 => {
+                    let bits = info.bit_count as usize;
+                    let bit = x * bits;
+                    let index = (source[bit / 8] >> (8 - bits - bit % 8)) as usize & ((1 << bits) - 1);
+                    let [r, g, b] = info.palette.get(index).copied().unwrap_or([0, 0, 0]);
+                    [r, g, b, 255]
+                }
+                24 => [source[3 * x + 2], source[3 * x + 1], source[3 * x], 255],
+                _ => {
+                    let pixel = if info.bit_count == 16 {
+                        u16::from_le_bytes([source[2 * x], source[2 * x + 1]]) as u32
+                    } else {
+                        u32::from_le_bytes([source[4 * x], source[4 * x + 1], source[4 * x + 2], source[4 * x + 3]])
+                    };
+                    let [red, green, blue, alpha] = info.masks;
+                    [channel(pixel, red), channel(pixel, green), channel(pixel, blue), channel(pixel, alpha)]
+                }
+            };
+            row[x * size..(x + 1) * size].copy_from_slice(&rgba[..size]);
+        }
+    }
+    if has_alpha && image.data().chunks_exact(4).all(|pixel| pixel[3] == 0) {
+        return Ok(image.convert(PixelFormat::Rgb8));
+    }
+    return Ok(image);
+}
+
+/// Expands RLE4 or RLE8 data into palette indices, in stored row order.
+/// Pixels the data skips over keep index 0.
+fn decode_rle(data: &[u8], info: &Info) -> Result<Vec<u8>, ImageError> {
+    let (width, height) = (info.width as usize, info.height as usize);
+    let mut indices = vec![0; width * height];
+    let (mut x, mut y) = (0usize, 0usize);
+    let mut at = 0;
+    let mut next = || -> Result<u8, ImageError> {
+        let byte = *data.get(at).ok_or(ImageError::Truncated("BMP"))?;
+        at += 1;
+        return Ok(byte);
+    };
+    let rle4 = info.compression == BI_RLE4;
+    let mut put = |x: &mut usize, y: usize, index: u8| {
+        if *x < width && y < height {
+            indices[y * width + *x] = index;
+        }
+        *x += 1;
+    };
+    loop {
+        let count = next()? as usize;
+        let value = next()?;
+        if count > 0 {
+            for i in 0..count {
+                let index = if !rle4 { value } else if i % 2 == 0 { value >> 4 } else { value & 0x0F };
+                put(&mut x, y, index);
+            }
+            continue;
+        }
+        match value {
+            0 => (x, y) = (0, y + 1),
+            1 => break,
+            2 => {
+                x += next()? as usize;
+                y += next()? as usize;
+            }
+            literal => {
+                let literal = literal as usize;
+                let bytes = if rle4 { literal.div_ceil(2) } else { literal };
+                let mut byte = 0;
+                for i in 0..literal {
+                    if !rle4 {
+                        put(&mut x, y, next()?);
+                    } else {
+                        if i % 2 == 0 {
+                            byte = next()?;
+                        }
+                        put(&mut x, y, if i % 2 == 0 { byte >> 4 } else { byte & 0x0F });
+                    }
+                }
+                // Literal runs are padded to a 16-bit boundary
+                if bytes % 2 == 1 {
+                    next()?;
+                }
+            }
+        }
+        if y >= height {
+            break;
+        }
+    }
+    return Ok(indices);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `BITMAPINFOHEADER` followed by `rest`: masks, colours and pixels
+    fn dib(width: i32, height: i32, bit_count: u16, compression: u32, colors_used: u32, rest: &[u8]) -> Vec<u8> {
+        let mut data = 40u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&width.to_le_bytes());
+        data.extend_from_slice(&height.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&bit_count.to_le_bytes());
+        data.extend_from_slice(&compression.to_le_bytes());
+        data.extend_from_slice(&[0; 12]);
+        data.extend_from_slice(&colors_used.to_le_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(rest);
+        return data;
+    }
+
+    const BLACK_WHITE: [u8; 8] = [0, 0, 0, 0, 255, 255, 255, 0];
+
+    #[test]
+    fn palette_bitmaps_decode() {
+        // Bottom-up rows padded to four bytes
+        let mut rest = BLACK_WHITE.to_vec();
+        rest.extend_from_slice(&[0b1010_0000, 0, 0, 0, 0b0100_0000, 0, 0, 0]);
+        let image = decode_dib(&dib(3, 2, 1, BI_RGB, 0, &rest)).unwrap();
+        assert_eq!(image.format(), PixelFormat::Rgb8);
+        assert_eq!(image.row(0), &[0, 0, 0, 255, 255, 255, 0, 0, 0]);
+        assert_eq!(image.row(1), &[255, 255, 255, 0, 0, 0, 255, 255, 255]);
+
+        // A top-down bitmap with a two colour table
+        let mut rest = BLACK_WHITE.to_vec();
+        rest.extend_from_slice(&[0x10, 0, 0, 0]);
+        let image = decode_dib(&dib(2, -1, 4, BI_RGB, 2, &rest)).unwrap();
+        assert_eq!(image.data(), &[255, 255, 255, 0, 0, 0]);
+    }
+
+    #[test]
+    fn masks_and_old_headers_decode() {
+        let image = decode_dib(&dib(2, 1, 16, BI_RGB, 0, &[0x00, 0x7C, 0x1F, 0x00])).unwrap();
+        assert_eq!(image.data(), &[255, 0, 0, 0, 0, 255]);
+
+        let mut rest = Vec::new();
+        for mask in [0x0000_00FFu32, 0x0000_FF00, 0x00FF_0000, 0xFF00_0000] {
+            rest.extend_from_slice(&mask.to_le_bytes());
+        }
+        rest.extend_from_slice(&[1, 2, 3, 4]);
+        let image = decode_dib(&dib(1, 1, 32, BI_ALPHABITFIELDS, 0, &rest)).unwrap();
+        assert_eq!((image.format(), image.data()), (PixelFormat::Rgba8, &[1, 2, 3, 4][..]));
+
+        // An OS/2 core header with a 24-bit pixel
+        let mut core = 12u32.to_le_bytes().to_vec();
+        core.extend_from_slice(&[1, 0, 1, 0, 1, 0, 24, 0, 10, 20, 30, 0]);
+        assert_eq!(decode_dib(&core).unwrap().data(), &[30, 20, 10]);
+    }
+
+    #[test]
+    fn empty_alpha_is_opaque() {
+        let mut rest = Vec::new();
+        for mask in [0x00FF_0000u32, 0x0000_FF00, 0x0000_00FF, 0xFF00_0000] {
+            rest.extend_from_slice(&mask.to_le_bytes());
+        }
+        rest.extend_from_slice(&[1, 2, 3, 0, 4, 5, 6, 0]);
+        let image = decode_dib(&dib(2, 1, 32, BI_ALPHABITFIELDS, 0, &rest)).unwrap();
+        assert_eq!((image.format(), image.data()), (PixelFormat::Rgb8, &[3, 2, 1, 6, 5, 4][..]));
+    }
+
+    #[test]
+    fn icon_bitmaps_use_alpha_or_the_mask() {
+        // Twice the height, then a mask hiding the bottom left pixel
+        let icon = decode_icon(&dib(2, 4, 24, BI_RGB, 0, &[[1; 8], [2; 8], [0x80, 0, 0, 0, 0, 0, 0, 0]].concat()));
+        let alpha: Vec<u8> = icon.unwrap().data().chunks_exact(4).map(|pixel| pixel[3]).collect();
+        assert_eq!(alpha, vec![255, 255, 0, 255]);
+        // The fourth byte of 32-bit pixels is alpha, and a missing mask hides nothing
+        let icon = decode_icon(&dib(1, 2, 32, BI_RGB, 0, &[3, 2, 1, 9])).unwrap();
+        assert_eq!((icon.width(), icon.height(), icon.data()), (1, 1, &[1, 2, 3, 9][..]));
+        let icon = decode_icon(&dib(1, 2, 32, BI_RGB, 0, &[3, 2, 1, 0]));
+        assert_eq!(icon.map(|icon| icon.to_rgba()), Ok(vec![1, 2, 3, 255]));
+
+        let flat = dib(1, 1, 24, BI_RGB, 0, &[0; 4]);
+        assert_eq!(decode_icon(&flat), Err(ImageError::InvalidSize { width: 1, height: 0 }));
+        let rle = dib(1, 2, 8, BI_RLE8, 1, &[0; 8]);
+        assert_eq!(decode_icon(&rle), Err(ImageError::Unsupported("compressed icon bitmap")));
+    }
+
+    #[test]
+    fn run_length_bitmaps_decode() {
+        let palette = [0, 0, 0, 0, 0, 0, 255, 0, 255, 0, 0, 0];
+        // A literal run, end of line, a delta then a repeated run
+        let mut rest = palette.to_vec();
+        rest.extend_from_slice(&[0, 3, 2, 1, 2, 0, 1, 1, 0, 0, 0, 2, 1, 0, 2, 2, 0, 1]);
+        let image = decode_dib(&dib(4, 2, 8, BI_RLE8, 3, &rest)).unwrap();
+        let colors = |image: &Image| -> Vec<u8> {
+            return image.data().chunks_exact(3).map(|rgb| rgb[0] / 255 + rgb[2] / 255 * 2).collect();
+        };
+        assert_eq!(colors(&image), vec![0, 2, 2, 0, 2, 1, 2, 1]);
+
+        let mut rest = palette.to_vec();
+        rest.extend_from_slice(&[4, 0x12, 0, 1]);
+        let image = decode_dib(&dib(4, 1, 4, BI_RLE4, 3, &rest)).unwrap();
+        assert_eq!(colors(&image), vec![1, 2, 1, 2]);
+    }
+
+    #[test]
+    fn files_start_with_a_file_header() {
+        let info = dib(1, 1, 24, BI_RGB, 0, &[]);
+        let mut file = b"BM".to_vec();
+        file.extend_from_slice(&[0; 8]);
+        file.extend_from_slice(&(14 + info.len() as u32 + 2).to_le_bytes());
+        file.extend_from_slice(&info);
+        file.extend_from_slice(&[0xEE, 0xEE, 1, 2, 3, 0]);
+        assert_eq!(decode(&file).unwrap().data(), &[3, 2, 1]);
+        for length in 0..file.len() - 1 {
+            assert!(decode(&file[..length]).is_err(), "{} bytes", length);
+        }
+        file[10] = 13;
+        assert_eq!(decode(&file), Err(ImageError::Invalid("pixel data inside the file header")));
+        assert_eq!(decode(b"MB"), Err(ImageError::Invalid("missing BM signature")));
+    }
+
+    #[test]
+    fn malformed_headers_are_rejected() {
+        let mut unknown = dib(1, 1, 24, BI_RGB, 0, &[0; 4]);
+        unknown[0] = 20;
+        assert_eq!(decode_dib(&unknown), Err(ImageError::Unsupported("unknown bitmap header size")));
+        assert_eq!(decode_dib(&dib(0, 1, 24, BI_RGB, 0, &[])), Err(ImageError::InvalidSize { width: 0, height: 1 }));
+        assert_eq!(
+            decode_dib(&dib(1, i32::MIN, 24, BI_RGB, 0, &[])),
+            Err(ImageError::InvalidSize { width: 1, height: 1 << 31 }),
+        );
+        assert_eq!(decode_dib(&dib(1, 1, 24, BI_JPEG, 0, &[])), Err(ImageError::Unsupported("JPEG compressed bitmap")));
+        assert_eq!(decode_dib(&dib(1, 1, 24, 9, 0, &[])), Err(ImageError::Unsupported("unknown bitmap compression")));
+        assert_eq!(decode_dib(&dib(1, 1, 7, BI_RGB, 0, &[])), Err(ImageError::Unsupported("unknown bit count")));
+        assert_eq!(
+            decode_dib(&dib(1, 1, 24, BI_BITFIELDS, 0, &[0; 16])),
+            Err(ImageError::Invalid("bit fields need 16 or 32 bits per pixel")),
+        );
+        assert_eq!(
+            decode_dib(&dib(1, 1, 4, BI_RLE8, 0, &[0; 64])),
+            Err(ImageError::Invalid("RLE compression doesn't match the bit count")),
+        );
+        assert_eq!(decode_dib(&dib(2, 2, 24, BI_RGB, 0, &[0; 12])), Err(ImageError::Truncated("BMP")));
+        assert_eq!(decode_dib(&dib(1, 1, 8, BI_RGB, 0, &[0; 1020])), Err(ImageError::Truncated("BMP")));
+        assert_eq!(decode_dib(&dib(2, 1, 8, BI_RLE8, 1, &[0; 4])), Err(ImageError::Truncated("BMP")));
+    }
+
+    #[test]
+    fn opaque_images_encode_as_24_bit() {
+        let image = Image::from_data(3, 2, PixelFormat::Rgb8, 9, (1..=18).collect()).unwrap();
+        let file = encode(&image);
+        assert_eq!(file.len(), 14 + 40 + 2 * 12);
+        assert_eq!(read_u32(&file, 2), Some(file.len() as u32));
+        assert_eq!(read_u32(&file, 10), Some(54));
+        assert_eq!((read_u32(&file, 14), read_u16(&file, 28), read_u32(&file, 30)), (Some(40), Some(24), Some(BI_RGB)));
+        // The bottom row comes first, as BGR padded to four bytes
+        assert_eq!(file[54..66], [12, 11, 10, 15, 14, 13, 18, 17, 16, 0, 0, 0]);
+        assert_eq!(decode(&file), Ok(image));
+    }
+
+    #[test]
+    fn images_with_alpha_encode_as_32_bit_with_masks() {
+        let image = Image::from_data(1, 1, PixelFormat::GrayAlpha8, 2, vec![90, 60]).unwrap();
+        let file = encode(&image);
+        assert_eq!(file.len(), 14 + 108 + 4);
+        let header = (read_u32(&file, 14), read_u16(&file, 28), read_u32(&file, 30));
+        assert_eq!(header, (Some(108), Some(32), Some(BI_BITFIELDS)));
+        assert_eq!(read_u32(&file, 14 + 52), Some(0xFF00_0000));
+        assert_eq!(&file[14 + 56..14 + 60], b"BGRs");
+        assert_eq!(file[122..], [90, 90, 90, 60]);
+        assert_eq!(decode(&file).map(|image| image.into_data()), Ok(vec![90, 90, 90, 60]));
+    }
+}
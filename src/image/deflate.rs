@@ -0,0 +1,219 @@
+//! Compression of zlib streams, as used by PNG
+//!
+//! The counterpart of [`inflate`](super::inflate): greedy LZ77 matching
+//! over hash chains, written as one DEFLATE block with the fixed Huffman
+//! code. That is far from the best ratio, but screenshots and rendered
+//! images are mostly long runs, which any LZ77 match finder catches, and
+//! it keeps the encoder small.
+
+use super::inflate::{adler32, DISTANCE_BASE, DISTANCE_EXTRA, LENGTH_BASE, LENGTH_EXTRA};
+
+/// Distance limit of DEFLATE
+const WINDOW: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const HASH_BITS: u32 = 15;
+/// How many earlier positions with the same hash are tried for a match
+const MAX_CHAIN: usize = 64;
+const NONE: u32 = u32::MAX;
+
+/// Bit writer for DEFLATE data, least significant bit first
+#[derive(Default)]
+struct BitWriter {
+    output: Vec<u8>,
+    bit_buffer: u64,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    fn bits(&mut self, value: u32, count: u32) {
+        self.bit_buffer |= (value as u64) << self.bit_count;
+        self.bit_count += count;
+        while self.bit_count >= 8 {
+            self.output.push(self.bit_buffer as u8);
+            self.bit_buffer >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    /// Huffman codes are stored from their most significant bit
+    fn code(&mut self, code: u32, length: u32) {
+        self.bits(code.reverse_bits() >> (32 - length), length);
+    }
+
+    /// A literal, end of block or length symbol of the fixed code
+    fn literal(&mut self, symbol: u16) {
+        match symbol {
+            0..=143 => self.code(0x30 + symbol as u32, 8),
+            144..=255 => self.code(0x190 + (symbol - 144) as u32, 9),
+            256..=279 => self.code((symbol - 256) as u32, 7),
+            _ => self.code(0xC0 + (symbol - 280) as u32, 8),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.output.push(self.bit_buffer as u8);
+        }
+        return self.output;
+    }
+}
+
+/// Index of the last entry of `bases` that is at most `value`
+fn base_index(bases: &[u16], value: usize) -> usize {
+    return bases.iter().rposition(|&base| base as usize <= value).unwrap_or(0);
+}
+
+fn hash(bytes: &[u8]) -> usize {
+    let value = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+    return (value.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize;
+}
+
+/// Hash chains of the positions seen so far: `head` holds the latest
+/// position of every hash and `previous` the one before each position,
+/// for the last [`WINDOW`] positions
+struct Matcher<'a> {
+    data: &'a [u8],
+    head: Vec<u32>,
+    previous: Vec<u32>,
+}
+
+impl Matcher<'_> {
+    fn insert(&mut self, at: usize) {
+        if at + MIN_MATCH <= self.data.len() {
+            let hash = hash(&self.data[at..]);
+            self.previous[at % WINDOW] = self.head[hash];
+            self.head[hash] = at as u32;
+        }
+    }
+
+    /// Length and distance of the longest earlier match of the bytes at `at`
+    fn longest_match(&self, at: usize) -> (usize, usize) {
+        let limit = MAX_MATCH.min(self.data.len() - at);
+        if limit < MIN_MATCH {
+            return (0, 0);
+        }
+        let (mut best_length, mut best_distance) = (0, 0);
+        let mut candidate = self.head[hash(&self.data[at..])];
+        for _ in 0..MAX_CHAIN {
+            if candidate == NONE || at - candidate as usize > WINDOW {
+                break;
+            }
+            let start = candidate as usize;
+            let length = self.data[start..start + limit]
+                .iter()
+                .zip(&self.data[at..at + limit])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length > best_length {
+                (best_length, best_distance) = (length, at - start);
+                if length == limit {
+                    break;
+                }
+            }
+            let next = self.previous[start % WINDOW];
+            // Older entries of the ring have been overwritten by newer positions
+            if next != NONE && next as usize >= start {
+                break;
+            }
+            candidate = next;
+        }
+        return (best_length, best_distance);
+    }
+}
+
+/// Compresses `data` into raw DEFLATE data
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    // The only and last block, with the fixed code
+    writer.bits(1, 1);
+    writer.bits(1, 2);
+
+    let mut matcher = Matcher { data, head: vec![NONE; 1 << HASH_BITS], previous: vec![NONE; WINDOW] };
+    let mut at = 0;
+    while at < data.len() {
+        let (length, distance) = matcher.longest_match(at);
+        if length < MIN_MATCH {
+            writer.literal(data[at] as u16);
+            matcher.insert(at);
+            at += 1;
+            continue;
+        }
+        let index = base_index(&LENGTH_BASE, length);
+        writer.literal(257 + index as u16);
+        writer.bits((length - LENGTH_BASE[index] as usize) as u32, LENGTH_EXTRA[index] as u32);
+        let index = base_index(&DISTANCE_BASE, distance);
+        writer.code(index as u32, 5);
+        writer.bits((distance - DISTANCE_BASE[index] as usize) as u32, DISTANCE_EXTRA[index] as u32);
+        for position in at..at + length {
+            matcher.insert(position);
+        }
+        at += length;
+    }
+    writer.literal(256);
+    return writer.finish();
+}
+
+/// Compresses `data` into a zlib stream
+pub fn zlib_compress(data: &[u8]) -> Vec<u8> {
+    // Deflate with a 32 KiB window, no dictionary, the fastest level
+    let mut output = vec![0x78, 0x01];
+    output.extend_from_slice(&deflate(data));
+    output.extend_from_slice(&adler32(data).to_be_bytes());
+    return output;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::inflate::{inflate, zlib_decompress};
+
+    /// Bytes that don't compress, from a xorshift generator
+    fn noise(length: usize, mut state: u32) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(length);
+        for _ in 0..length {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            bytes.push(state as u8);
+        }
+        return bytes;
+    }
+
+    fn round_trip(data: &[u8]) -> Vec<u8> {
+        return inflate(&deflate(data), data.len()).unwrap();
+    }
+
+    #[test]
+    fn data_round_trips() {
+        for data in [&b""[..], b"a", b"ab", b"abc", b"abcabcabcabcabc", b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa"] {
+            assert_eq!(round_trip(data), data);
+        }
+        let noise = noise(100_000, 1);
+        assert_eq!(round_trip(&noise), noise);
+        let mut mixed = noise.clone();
+        mixed.extend_from_slice(&[7; 1000]);
+        mixed.extend_from_slice(&noise[..5000]);
+        assert_eq!(round_trip(&mixed), mixed);
+    }
+
+    #[test]
+    fn matches_reach_across_the_whole_window() {
+        let start = noise(300, 2);
+        for gap in [WINDOW - 300, WINDOW - 299, WINDOW + 100] {
+            let mut data = start.clone();
+            data.extend_from_slice(&noise(gap, 3));
+            data.extend_from_slice(&start);
+            assert_eq!(round_trip(&data), data, "gap {}", gap);
+        }
+    }
+
+    #[test]
+    fn runs_compress() {
+        let zeros = vec![0; 100_000];
+        assert!(deflate(&zeros).len() < 1000);
+        let compressed = zlib_compress(&zeros);
+        assert_eq!(compressed[..2], [0x78, 0x01]);
+        assert_eq!(zlib_decompress(&compressed, zeros.len()), Ok(zeros));
+    }
+}
@@ -0,0 +1,325 @@
+//! Decompression of zlib streams, as used by PNG
+//!
+//! A straightforward implementation of DEFLATE (RFC 1951) inside the zlib
+//! wrapper (RFC 1950). Huffman codes are decoded one bit at a time with
+//! the canonical code counts, which is small and plenty fast for images.
+//! The output is limited to a size given by the caller so a malicious
+//! stream can't expand into gigabytes.
+
+use super::ImageError;
+
+/// Bit reader over DEFLATE data, least significant bit first
+struct Bits<'a> {
+    data: &'a [u8],
+    position: usize,
+    bit_buffer: u32,
+    bit_count: u32,
+}
+
+impl Bits<'_> {
+    fn bits(&mut self, count: u32) -> Result<u32, ImageError> {
+        while self.bit_count < count {
+            let byte = *self.data.get(self.position).ok_or(ImageError::Truncated("deflate"))?;
+            self.position += 1;
+            self.bit_buffer |= (byte as u32) << self.bit_count;
+            self.bit_count += 8;
+        }
+        let value = self.bit_buffer & ((1u64 << count) - 1) as u32;
+        self.bit_buffer >>= count;
+        self.bit_count -= count;
+        return Ok(value);
+    }
+
+    /// Drops the bits left in the current byte
+    fn align(&mut self) {
+        self.bit_buffer = 0;
+        self.bit_count = 0;
+    }
+}
+
+const MAX_CODE_BITS: usize = 15;
+
+/// A canonical Huffman code: how many codes have each length and the
+/// symbols sorted by code
+struct Huffman {
+    counts: [u16; MAX_CODE_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, ImageError> {
+        let mut counts = [0u16; MAX_CODE_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        // Every length doubles the codes left; using more than that is an error
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = left * 2 - count as i32;
+            if left < 0 {
+                return Err(ImageError::Invalid("over-subscribed Huffman code"));
+            }
+        }
+        let mut offsets = [0u16; MAX_CODE_BITS + 2];
+        for length in 1..=MAX_CODE_BITS {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[length as usize] as usize] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        return Ok(Huffman { counts, symbols });
+    }
+
+    fn decode(&self, bits: &mut Bits) -> Result<u16, ImageError> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &self.counts[1..] {
+            code |= bits.bits(1)? as i32;
+            let count = count as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        return Err(ImageError::Invalid("invalid Huffman code"));
+    }
+}
+
+pub(super) const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+pub(super) const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+pub(super) const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097,
+    6145, 8193, 12289, 16385, 24577,
+];
+pub(super) const DISTANCE_EXTRA: [u8; 30] =
+    [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// Order in which the lengths of the code length code are stored
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Decompresses raw DEFLATE data, failing if it would produce more than
+/// `limit` bytes
+pub fn inflate(data: &[u8], limit: usize) -> Result<Vec<u8>, ImageError> {
+    return inflate_stream(data, limit).map(|(output, _)| output);
+}
+
+/// [`inflate`] that also returns how many bytes of `data` the stream used
+fn inflate_stream(data: &[u8], limit: usize) -> Result<(Vec<u8>, usize), ImageError> {
+    let mut bits = Bits { data, position: 0, bit_buffer: 0, bit_count: 0 };
+    let mut output = Vec::new();
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => {
+                bits.align();
+                let header = data.get(bits.position..bits.position + 4).ok_or(ImageError::Truncated("deflate"))?;
+                let length = u16::from_le_bytes([header[0], header[1]]);
+                if length != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err(ImageError::Invalid("stored block length mismatch"));
+                }
+                let start = bits.position + 4;
+                let block = data.get(start..start + length as usize).ok_or(ImageError::Truncated("deflate"))?;
+                if output.len() + block.len() > limit {
+                    return Err(ImageError::Invalid("decompressed data is larger than the image"));
+                }
+                output.extend_from_slice(block);
+                bits.position = start + length as usize;
+            }
+            1 => {
+                let mut lengths = [0u8; 288];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..].fill(8);
+                let literals = Huffman::new(&lengths)?;
+                let distances = Huffman::new(&[5; 30])?;
+                inflate_block(&mut bits, &mut output, &literals, &distances, limit)?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_codes(&mut bits)?;
+                inflate_block(&mut bits, &mut output, &literals, &distances, limit)?;
+            }
+            _ => return Err(ImageError::Invalid("invalid deflate block type")),
+        }
+        if last {
+            return Ok((output, bits.position));
+        }
+    }
+}
+
+fn read_dynamic_codes(bits: &mut Bits) -> Result<(Huffman, Huffman), ImageError> {
+    let literal_count = bits.bits(5)? as usize + 257;
+    let distance_count = bits.bits(5)? as usize + 1;
+    let code_length_count = bits.bits(4)? as usize + 4;
+    if literal_count > 286 || distance_count > 30 {
+        return Err(ImageError::Invalid("too many Huffman codes"));
+    }
+    let mut code_lengths = [0u8; 19];
+    for &index in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[index] = bits.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths)?;
+
+    let mut lengths = vec![0u8; literal_count + distance_count];
+    let mut index = 0;
+    while index < lengths.len() {
+        let symbol = code_length_code.decode(bits)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths[..index].last().ok_or(ImageError::Invalid("repeat without a length"))?;
+                (previous, 3 + bits.bits(2)? as usize)
+            }
+            17 => (0, 3 + bits.bits(3)? as usize),
+            _ => (0, 11 + bits.bits(7)? as usize),
+        };
+        if index + repeat > lengths.len() {
+            return Err(ImageError::Invalid("code lengths overflow"));
+        }
+        lengths[index..index + repeat].fill(value);
+        index += repeat;
+    }
+    if lengths[256] == 0 {
+        return Err(ImageError::Invalid("missing end of block code"));
+    }
+    return Ok((Huffman::new(&lengths[..literal_count])?, Huffman::new(&lengths[literal_count..])?));
+}
+
+fn inflate_block(
+    bits: &mut Bits,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+    limit: usize,
+) -> Result<(), ImageError> {
+    loop {
+        let symbol = literals.decode(bits)? as usize;
+        if symbol < 256 {
+            if output.len() >= limit {
+                return Err(ImageError::Invalid("decompressed data is larger than the image"));
+            }
+            output.push(symbol as u8);
+            continue;
+        }
+        if symbol == 256 {
+            return Ok(());
+        }
+        let symbol = symbol - 257;
+        if symbol >= LENGTH_BASE.len() {
+            return Err(ImageError::Invalid("invalid length code"));
+        }
+        let length = LENGTH_BASE[symbol] as usize + bits.bits(LENGTH_EXTRA[symbol] as u32)? as usize;
+        let symbol = distances.decode(bits)? as usize;
+        if symbol >= DISTANCE_BASE.len() {
+            return Err(ImageError::Invalid("invalid distance code"));
+        }
+        let distance = DISTANCE_BASE[symbol] as usize + bits.bits(DISTANCE_EXTRA[symbol] as u32)? as usize;
+        if distance > output.len() {
+            return Err(ImageError::Invalid("distance before the start of the data"));
+        }
+        if output.len() + length > limit {
+            return Err(ImageError::Invalid("decompressed data is larger than the image"));
+        }
+        let start = output.len() - distance;
+        for i in 0..length {
+            output.push(output[start + i]);
+        }
+    }
+}
+
+/// Adler-32 checksum of the zlib trailer
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    return (b << 16) | a;
+}
+
+/// Decompresses a zlib stream and checks its checksum
+pub fn zlib_decompress(data: &[u8], limit: usize) -> Result<Vec<u8>, ImageError> {
+    if data.len() < 6 {
+        return Err(ImageError::Truncated("zlib"));
+    }
+    let (method, flags) = (data[0], data[1]);
+    if method & 0x0F != 8 || method >> 4 > 7 || !(method as u16 * 256 + flags as u16).is_multiple_of(31) {
+        return Err(ImageError::Invalid("bad zlib header"));
+    }
+    if flags & 0x20 != 0 {
+        return Err(ImageError::Unsupported("zlib preset dictionary"));
+    }
+    let (output, used) = inflate_stream(&data[2..], limit)?;
+    let trailer = data.get(2 + used..6 + used).ok_or(ImageError::Truncated("zlib"))?;
+    if u32::from_be_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]) != adler32(&output) {
+        return Err(ImageError::Invalid("zlib checksum mismatch"));
+    }
+    return Ok(output);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STORED: [u8; 16] = [120, 1, 1, 5, 0, 250, 255, 104, 101, 108, 108, 111, 6, 44, 2, 21];
+
+    const FIXED: [u8; 13] = [120, 218, 75, 76, 74, 78, 132, 33, 0, 29, 224, 4, 153];
+
+    const TEXT: &[u8] = b"It was the best of times, it was the worst of times, it was the age of wisdom, \
+        it was the age of foolishness, it was the epoch of belief, it was the epoch of incredulity";
+
+    /// `TEXT` compressed by zlib with a dynamic Huffman code
+    const DYNAMIC: [u8; 87] = [
+        120, 218, 117, 203, 219, 13, 128, 32, 12, 70, 225, 85, 58, 128, 139, 56, 6, 151, 31, 105, 2, 212, 208, 26,
+        226, 246, 134, 39, 99, 130, 207, 223, 57, 187, 209, 112, 74, 150, 65, 30, 106, 36, 137, 140, 43, 116, 35, 126,
+        101, 72, 255, 35, 119, 96, 194, 96, 141, 82, 87, 146, 68, 10, 107, 110, 208, 239, 136, 83, 66, 158, 129, 71,
+        97, 164, 181, 113, 11, 29, 241, 42, 108, 247, 3, 141, 242, 59, 141,
+    ];
+
+    #[test]
+    fn every_block_type_decompresses() {
+        assert_eq!(zlib_decompress(&STORED, 100), Ok(b"hello".to_vec()));
+        assert_eq!(zlib_decompress(&FIXED, 100), Ok(b"abcabcabcabc".to_vec()));
+        assert_eq!(zlib_decompress(&DYNAMIC, TEXT.len()), Ok(TEXT.to_vec()));
+        assert_eq!(adler32(b"hello"), 0x062C_0215);
+        assert_eq!(adler32(&[]), 1);
+    }
+
+    #[test]
+    fn output_stops_at_the_limit() {
+        let too_large = Err(ImageError::Invalid("decompressed data is larger than the image"));
+        assert_eq!(zlib_decompress(&STORED, 4), too_large);
+        assert_eq!(zlib_decompress(&FIXED, 11), too_large);
+        assert_eq!(zlib_decompress(&DYNAMIC, TEXT.len() - 1), too_large);
+    }
+
+    #[test]
+    fn malformed_streams_are_rejected() {
+        assert_eq!(zlib_decompress(&[0x78, 0x02, 0, 0, 0, 0], 10), Err(ImageError::Invalid("bad zlib header")));
+        let dictionary = [0x78, 0xBB, 0, 0, 0, 0];
+        assert_eq!(zlib_decompress(&dictionary, 10), Err(ImageError::Unsupported("zlib preset dictionary")));
+        let mut checksum = STORED;
+        checksum[15] ^= 1;
+        assert_eq!(zlib_decompress(&checksum, 100), Err(ImageError::Invalid("zlib checksum mismatch")));
+        assert_eq!(inflate(&[0x07], 10), Err(ImageError::Invalid("invalid deflate block type")));
+        assert_eq!(inflate(&[0x01, 5, 0, 0, 0], 10), Err(ImageError::Invalid("stored block length mismatch")));
+        // A fixed block whose first symbol copies from before the start
+        assert_eq!(inflate(&[0x03, 0x02], 10), Err(ImageError::Invalid("distance before the start of the data")));
+        for stream in [&STORED[..], &FIXED, &DYNAMIC] {
+            for length in 0..stream.len() {
+                assert!(zlib_decompress(&stream[..length], 1000).is_err(), "{} bytes", length);
+            }
+        }
+    }
+}
@@ -0,0 +1,451 @@
+//! Images in memory and the file formats they are read from and written to
+//!
+//! An [`Image`] is a block of pixel rows in one of a few [`PixelFormat`]s,
+//! with a stride so rows can be padded the way GPU uploads and GDI bitmaps
+//! want. Decoders keep the format of the file when it has a matching one
+//! (a greyscale PNG decodes to [`PixelFormat::Gray8`]) and
+//! [`Image::convert`] turns any image into the format a consumer needs.
+//!
+//! The decoders are written for untrusted files: every size is checked
+//! against the data before it is used, images larger than
+//! [`MAX_IMAGE_DIMENSION`] or [`MAX_IMAGE_PIXELS`] are refused, and
+//! compressed data is never inflated past the size the header announces.
+//!
+//! Images are written as PNG, BMP or PPM with [`Image::encode`] and
+//! [`Image::save`], which is how window captures end up in bug reports.
+
+use std::path::Path;
+
+use crate::canvas::Canvas;
+
+pub mod bmp;
+pub mod deflate;
+pub mod inflate;
+pub mod png;
+pub mod pnm;
+pub mod tga;
+
+/// Abstraction to represent an error while reading or building an image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    /// The data doesn't start like any format we know
+    UnknownFormat,
+
+    /// The data ends before the image does
+    Truncated(&'static str),
+
+    /// The data is malformed
+    Invalid(&'static str),
+
+    /// The image uses a feature of its format we can't decode
+    Unsupported(&'static str),
+
+    /// The image is empty or larger than the decoders accept
+    InvalidSize { width: u32, height: u32 },
+
+    /// The pixel buffer doesn't match the size, format and stride
+    BadBufferLength { expected: usize, actual: usize },
+}
+
+impl std::fmt::Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ImageError::UnknownFormat => write!(f, "unknown image format"),
+            ImageError::Truncated(format) => write!(f, "truncated {} data", format),
+            ImageError::Invalid(reason) => write!(f, "invalid image data: {}", reason),
+            ImageError::Unsupported(reason) => write!(f, "unsupported image data: {}", reason),
+            ImageError::InvalidSize { width, height } => write!(f, "invalid image size {}x{}", width, height),
+            ImageError::BadBufferLength { expected, actual } => {
+                write!(f, "image buffer is {} bytes, expected {}", actual, expected)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+/// Largest width or height the decoders accept
+pub const MAX_IMAGE_DIMENSION: u32 = 32768;
+
+/// Largest number of pixels the decoders accept, 256 MiB as RGBA
+pub const MAX_IMAGE_PIXELS: u64 = 1 << 26;
+
+/// How the bytes of a pixel are laid out, 8 bits per channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PixelFormat {
+    Gray8,
+    /// Grey then straight alpha
+    GrayAlpha8,
+    Rgb8,
+    /// Straight (non-premultiplied) alpha, like icons and the canvas
+    Rgba8,
+    /// The order of Win32 DIBs and most framebuffers
+    Bgra8,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Gray8 => return 1,
+            PixelFormat::GrayAlpha8 => return 2,
+            PixelFormat::Rgb8 => return 3,
+            PixelFormat::Rgba8 | PixelFormat::Bgra8 => return 4,
+        }
+    }
+
+    pub fn has_alpha(self) -> bool {
+        return matches!(self, PixelFormat::GrayAlpha8 | PixelFormat::Rgba8 | PixelFormat::Bgra8);
+    }
+}
+
+/// A file format images can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    Png,
+    Bmp,
+    /// PGM for grey images, PPM otherwise
+    Pnm,
+}
+
+impl ImageFormat {
+    /// The format a file name's extension stands for, ignoring case
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => return Some(ImageFormat::Png),
+            "bmp" | "dib" => return Some(ImageFormat::Bmp),
+            "ppm" | "pgm" | "pnm" => return Some(ImageFormat::Pnm),
+            _ => return None,
+        }
+    }
+}
+
+/// Checks a decoded size against the limits of the module documentation
+pub(crate) fn check_size(width: u32, height: u32) -> Result<(), ImageError> {
+    if width == 0
+        || height == 0
+        || width > MAX_IMAGE_DIMENSION
+        || height > MAX_IMAGE_DIMENSION
+        || width as u64 * height as u64 > MAX_IMAGE_PIXELS
+    {
+        return Err(ImageError::InvalidSize { width, height });
+    }
+    return Ok(());
+}
+
+/// Pixel rows, top to bottom, see the module documentation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    /// Bytes from the start of a row to the start of the next one
+    stride: usize,
+    data: Vec<u8>,
+}
+
+impl Image {
+    /// A zeroed image with tightly packed rows
+    pub fn new(width: u32, height: u32, format: PixelFormat) -> Result<Self, ImageError> {
+        check_size(width, height)?;
+        let stride = width as usize * format.bytes_per_pixel();
+        return Ok(Image { width, height, format, stride, data: vec![0; stride * height as usize] });
+    }
+
+    /// An image over existing rows of `stride` bytes. The last row doesn't
+    /// need its padding.
+    pub fn from_data(
+        width: u32,
+        height: u32,
+        format: PixelFormat,
+        stride: usize,
+        data: Vec<u8>,
+    ) -> Result<Self, ImageError> {
+        check_size(width, height)?;
+        let row = width as usize * format.bytes_per_pixel();
+        if stride < row {
+            return Err(ImageError::Invalid("stride is shorter than a row"));
+        }
+        let minimum = stride * (height as usize - 1) + row;
+        if data.len() < minimum || data.len() > stride * height as usize {
+            return Err(ImageError::BadBufferLength { expected: stride * height as usize, actual: data.len() });
+        }
+        return Ok(Image { width, height, format, stride, data });
+    }
+
+    /// Reads a PNG, BMP, TGA, PPM or PGM file, recognized from its content
+    pub fn decode(data: &[u8]) -> Result<Self, ImageError> {
+        if data.starts_with(&png::SIGNATURE) {
+            return png::decode(data);
+        } else if data.starts_with(b"BM") {
+            return bmp::decode(data);
+        } else if data.len() >= 2 && data[0] == b'P' && (b'1'..=b'6').contains(&data[1]) {
+            return pnm::decode(data);
+        } else if tga::looks_like_tga(data) {
+            return tga::decode(data);
+        } else {
+            return Err(ImageError::UnknownFormat);
+        }
+    }
+
+    /// A copy of the pixels of a canvas, which is how headless rendering is
+    /// read back
+    pub fn from_canvas(canvas: &Canvas) -> Result<Self, ImageError> {
+        let stride = canvas.width() as usize * 4;
+        return Image::from_data(canvas.width(), canvas.height(), PixelFormat::Rgba8, stride, canvas.pixels().to_vec());
+    }
+
+    /// The image as a file in `format`
+    pub fn encode(&self, format: ImageFormat) -> Vec<u8> {
+        match format {
+            ImageFormat::Png => return png::encode(self),
+            ImageFormat::Bmp => return bmp::encode(self),
+            ImageFormat::Pnm => return pnm::encode(self),
+        }
+    }
+
+    /// Writes the image to a file in the format of its extension
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let format = ImageFormat::from_path(path).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "unknown image file extension")
+        })?;
+        return std::fs::write(path, self.encode(format));
+    }
+
+    pub fn width(&self) -> u32 {
+        return self.width;
+    }
+
+    pub fn height(&self) -> u32 {
+        return self.height;
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        return self.format;
+    }
+
+    pub fn stride(&self) -> usize {
+        return self.stride;
+    }
+
+    pub fn data(&self) -> &[u8] {
+        return &self.data;
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        return &mut self.data;
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        return self.data;
+    }
+
+    /// The pixels of row `y`, without padding
+    pub fn row(&self, y: u32) -> &[u8] {
+        let start = y as usize * self.stride;
+        return &self.data[start..start + self.width as usize * self.format.bytes_per_pixel()];
+    }
+
+    pub fn row_mut(&mut self, y: u32) -> &mut [u8] {
+        let start = y as usize * self.stride;
+        let length = self.width as usize * self.format.bytes_per_pixel();
+        return &mut self.data[start..start + length];
+    }
+
+    /// A pixel as straight RGBA, or `None` outside the image
+    pub fn pixel_rgba(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let size = self.format.bytes_per_pixel();
+        return Some(to_rgba(self.format, &self.row(y)[x as usize * size..][..size]));
+    }
+
+    /// The same pixels in another format with tightly packed rows. Colours
+    /// become grey with the Rec. 601 weights and missing alpha is opaque.
+    pub fn convert(&self, format: PixelFormat) -> Image {
+        if format == self.format && self.stride == self.width as usize * format.bytes_per_pixel() {
+            return self.clone();
+        }
+        let size = self.format.bytes_per_pixel();
+        let mut data = Vec::with_capacity(self.width as usize * self.height as usize * format.bytes_per_pixel());
+        for y in 0..self.height {
+            for pixel in self.row(y).chunks_exact(size) {
+                let [r, g, b, a] = to_rgba(self.format, pixel);
+                let gray = || ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114 + 500) / 1000) as u8;
+                match format {
+                    PixelFormat::Gray8 => data.push(gray()),
+                    PixelFormat::GrayAlpha8 => data.extend_from_slice(&[gray(), a]),
+                    PixelFormat::Rgb8 => data.extend_from_slice(&[r, g, b]),
+                    PixelFormat::Rgba8 => data.extend_from_slice(&[r, g, b, a]),
+                    PixelFormat::Bgra8 => data.extend_from_slice(&[b, g, r, a]),
+                }
+            }
+        }
+        let stride = self.width as usize * format.bytes_per_pixel();
+        return Image { width: self.width, height: self.height, format, stride, data };
+    }
+
+    /// Tightly packed straight RGBA, the layout of icons and the canvas
+    pub fn to_rgba(&self) -> Vec<u8> {
+        return self.convert(PixelFormat::Rgba8).data;
+    }
+}
+
+fn to_rgba(format: PixelFormat, pixel: &[u8]) -> [u8; 4] {
+    match format {
+        PixelFormat::Gray8 => return [pixel[0], pixel[0], pixel[0], 255],
+        PixelFormat::GrayAlpha8 => return [pixel[0], pixel[0], pixel[0], pixel[1]],
+        PixelFormat::Rgb8 => return [pixel[0], pixel[1], pixel[2], 255],
+        PixelFormat::Rgba8 => return [pixel[0], pixel[1], pixel[2], pixel[3]],
+        PixelFormat::Bgra8 => return [pixel[2], pixel[1], pixel[0], pixel[3]],
+    }
+}
+
+/// Scales a sample between 0 and `max` to a byte
+pub(crate) fn scale_sample(value: u32, max: u32) -> u8 {
+    if max == 0 {
+        return 0;
+    }
+    let (value, max) = (value.min(max) as u64, max as u64);
+    return ((value * 255 + max / 2) / max) as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_are_recognized_from_content() {
+        assert_eq!(Image::decode(b"P5 1 1 255\n\x07").map(|image| image.into_data()), Ok(vec![7]));
+        assert_eq!(Image::decode(b"BM"), Err(ImageError::Truncated("BMP")));
+        assert_eq!(Image::decode(&png::SIGNATURE), Err(ImageError::Truncated("PNG")));
+        let tga = [0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 1, 0, 8, 0, 42];
+        assert_eq!(Image::decode(&tga).map(|image| image.into_data()), Ok(vec![42]));
+        assert_eq!(Image::decode(b"GIF89a"), Err(ImageError::UnknownFormat));
+        assert_eq!(Image::decode(&[]), Err(ImageError::UnknownFormat));
+    }
+
+    #[test]
+    fn sizes_and_buffers_are_checked() {
+        assert_eq!(check_size(MAX_IMAGE_DIMENSION, 1), Ok(()));
+        assert_eq!(check_size(MAX_IMAGE_DIMENSION + 1, 1), Err(ImageError::InvalidSize { width: 32769, height: 1 }));
+        assert_eq!(check_size(16384, 8192), Err(ImageError::InvalidSize { width: 16384, height: 8192 }));
+        assert_eq!(Image::new(1, 0, PixelFormat::Gray8), Err(ImageError::InvalidSize { width: 1, height: 0 }));
+
+        // The last row may leave out its padding
+        let image = Image::from_data(2, 2, PixelFormat::Rgb8, 8, vec![1; 14]).unwrap();
+        assert_eq!((image.stride(), image.row(1)), (8, &[1; 6][..]));
+        assert!(Image::from_data(2, 2, PixelFormat::Rgb8, 8, vec![1; 16]).is_ok());
+        assert_eq!(
+            Image::from_data(2, 2, PixelFormat::Rgb8, 8, vec![1; 13]),
+            Err(ImageError::BadBufferLength { expected: 16, actual: 13 }),
+        );
+        assert_eq!(
+            Image::from_data(2, 2, PixelFormat::Rgb8, 8, vec![1; 17]),
+            Err(ImageError::BadBufferLength { expected: 16, actual: 17 }),
+        );
+        assert_eq!(
+            Image::from_data(2, 2, PixelFormat::Rgb8, 5, vec![1; 12]),
+            Err(ImageError::Invalid("stride is shorter than a row")),
+        );
+    }
+
+    #[test]
+    fn images_convert_between_formats() {
+        let data = vec![255, 0, 0, 128, 0, 0, 0, 0, 10, 20, 30, 255];
+        let image = Image::from_data(1, 2, PixelFormat::Bgra8, 8, data).unwrap();
+        assert_eq!(image.pixel_rgba(0, 0), Some([0, 0, 255, 128]));
+        assert_eq!([image.pixel_rgba(1, 0), image.pixel_rgba(0, 2)], [None, None]);
+        // Padding is dropped
+        assert_eq!(image.to_rgba(), vec![0, 0, 255, 128, 30, 20, 10, 255]);
+        let gray = image.convert(PixelFormat::GrayAlpha8);
+        assert_eq!((gray.stride(), gray.data()), (2, &[29, 128, 22, 255][..]));
+        assert_eq!(gray.convert(PixelFormat::Rgb8).data(), &[29, 29, 29, 22, 22, 22]);
+        let opaque = gray.convert(PixelFormat::Gray8).convert(PixelFormat::Rgba8);
+        assert_eq!(opaque.data(), &[29, 29, 29, 255, 22, 22, 22, 255]);
+    }
+
+    #[test]
+    fn samples_scale_to_bytes() {
+        assert_eq!([scale_sample(0, 1), scale_sample(1, 1), scale_sample(7, 15)], [0, 255, 119]);
+        assert_eq!(scale_sample(9, 3), 255);
+        assert_eq!([scale_sample(32768, 65535), scale_sample(5, 0)], [128, 0]);
+    }
+
+    #[test]
+    fn file_extensions_name_formats() {
+        assert_eq!(ImageFormat::from_path(Path::new("shot.PNG")), Some(ImageFormat::Png));
+        assert_eq!(ImageFormat::from_path(Path::new("a/b.dib")), Some(ImageFormat::Bmp));
+        assert_eq!(ImageFormat::from_path(Path::new("frame.pgm")), Some(ImageFormat::Pnm));
+        assert_eq!(ImageFormat::from_path(Path::new("photo.jpg")), None);
+        assert_eq!(ImageFormat::from_path(Path::new("png")), None);
+    }
+
+    /// A pattern with every channel different and some alpha
+    fn pattern(width: u32, height: u32, format: PixelFormat) -> Image {
+        let mut image = Image::new(width, height, format).unwrap();
+        for (i, byte) in image.data_mut().iter_mut().enumerate() {
+            *byte = (i * 37 % 251) as u8;
+        }
+        return image;
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        let formats = [
+            PixelFormat::Gray8,
+            PixelFormat::GrayAlpha8,
+            PixelFormat::Rgb8,
+            PixelFormat::Rgba8,
+            PixelFormat::Bgra8,
+        ];
+        for format in formats {
+            let image = pattern(13, 7, format);
+            // PNG keeps every format but BGRA, which it stores as RGBA
+            let png = Image::decode(&image.encode(ImageFormat::Png)).unwrap();
+            let expected = if format == PixelFormat::Bgra8 { PixelFormat::Rgba8 } else { format };
+            assert_eq!(png, image.convert(expected), "{:?} as PNG", format);
+            // BMP keeps colours, and alpha when there is some
+            let bmp = Image::decode(&image.encode(ImageFormat::Bmp)).unwrap();
+            let expected = if format.has_alpha() { PixelFormat::Rgba8 } else { PixelFormat::Rgb8 };
+            assert_eq!(bmp, image.convert(expected), "{:?} as BMP", format);
+            // Netpbm keeps grey or colour and drops alpha
+            let pnm = Image::decode(&image.encode(ImageFormat::Pnm)).unwrap();
+            let gray = matches!(format, PixelFormat::Gray8 | PixelFormat::GrayAlpha8);
+            let expected = if gray { PixelFormat::Gray8 } else { PixelFormat::Rgb8 };
+            assert_eq!(pnm, image.convert(expected), "{:?} as PNM", format);
+        }
+    }
+
+    #[test]
+    fn padded_rows_encode_without_padding() {
+        let mut data = vec![0xEE; 2 * 8];
+        data[..6].copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+        data[8..14].copy_from_slice(&[7, 8, 9, 10, 11, 12]);
+        let image = Image::from_data(2, 2, PixelFormat::Rgb8, 8, data).unwrap();
+        for format in [ImageFormat::Png, ImageFormat::Bmp, ImageFormat::Pnm] {
+            let decoded = Image::decode(&image.encode(format)).unwrap();
+            assert_eq!(decoded.data(), &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12], "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn canvases_are_saved_by_extension() {
+        let mut canvas = Canvas::new(3, 2);
+        canvas.pixels_mut()[..4].copy_from_slice(&[10, 20, 30, 40]);
+        let image = Image::from_canvas(&canvas).unwrap();
+        assert_eq!((image.format(), image.pixel_rgba(0, 0)), (PixelFormat::Rgba8, Some([10, 20, 30, 40])));
+
+        let directory = std::env::temp_dir().join(format!("gltest-image-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("canvas.png");
+        image.save(&path).unwrap();
+        assert_eq!(Image::decode(&std::fs::read(&path).unwrap()), Ok(image.clone()));
+        let error = image.save(directory.join("canvas.gif")).unwrap_err();
+        assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(!directory.join("canvas.gif").exists());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
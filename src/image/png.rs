@@ -0,0 +1,559 @@
+//! PNG decoding and encoding
+//!
+//! Every standard colour type and bit depth is read, interlaced (Adam7)
+//! images included. Palette images and `tRNS` transparency decode to RGB
+//! or RGBA, 16-bit samples are reduced to 8 bits, and ancillary chunks
+//! other than `tRNS` are ignored. Chunk CRCs are checked.
+//!
+//! [`encode`] writes 8-bit grey, grey and alpha, RGB or RGBA images
+//! without interlacing, picking the filter of every row with the usual
+//! smallest sum of absolute differences heuristic.
+
+use super::deflate::zlib_compress;
+use super::inflate::zlib_decompress;
+use super::{check_size, scale_sample, Image, ImageError, PixelFormat};
+
+/// The first eight bytes of every PNG file
+pub const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            bit += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+};
+
+/// CRC-32 as used by PNG chunks, over the concatenation of `parts`
+pub fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut crc = 0xFFFF_FFFF;
+    for part in parts {
+        for &byte in *part {
+            crc = CRC_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+        }
+    }
+    return crc ^ 0xFFFF_FFFF;
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    return data.get(at..at + 4).map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+}
+
+/// Starting column, starting row, column step and row step of the seven Adam7 passes
+const ADAM7: [(u32, u32, u32, u32); 7] =
+    [(0, 0, 8, 8), (4, 0, 8, 8), (0, 4, 4, 8), (2, 0, 4, 4), (0, 2, 2, 4), (1, 0, 2, 2), (0, 1, 1, 2)];
+
+/// What the `IHDR` chunk says about the samples
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+    interlaced: bool,
+}
+
+impl Header {
+    fn channels(&self) -> usize {
+        match self.color_type {
+            2 => return 3,
+            4 => return 2,
+            6 => return 4,
+            _ => return 1,
+        }
+    }
+
+    /// Bytes of a filtered row of `width` pixels, without the filter byte
+    fn row_bytes(&self, width: u32) -> usize {
+        return (width as usize * self.channels() * self.bit_depth as usize).div_ceil(8);
+    }
+
+    /// Distance in bytes to the same byte of the previous pixel, for filters
+    fn filter_distance(&self) -> usize {
+        return (self.channels() * self.bit_depth as usize / 8).max(1);
+    }
+}
+
+/// Reads a PNG file
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    if !data.starts_with(&SIGNATURE) {
+        return Err(ImageError::Invalid("missing PNG signature"));
+    }
+    let mut header: Option<Header> = None;
+    let mut palette: Vec<[u8; 3]> = Vec::new();
+    let mut transparency: Option<Vec<u8>> = None;
+    let mut compressed = Vec::new();
+    let mut at = SIGNATURE.len();
+    loop {
+        let length = read_u32(data, at).ok_or(ImageError::Truncated("PNG"))? as usize;
+        if length > 0x7FFF_FFFF {
+            return Err(ImageError::Invalid("chunk length too large"));
+        }
+        let kind = data.get(at + 4..at + 8).ok_or(ImageError::Truncated("PNG"))?;
+        let content = data.get(at + 8..at + 8 + length).ok_or(ImageError::Truncated("PNG"))?;
+        let crc = read_u32(data, at + 8 + length).ok_or(ImageError::Truncated("PNG"))?;
+        if crc != crc32(&[kind, content]) {
+            return Err(ImageError::Invalid("chunk CRC mismatch"));
+        }
+        at += 12 + length;
+        if header.is_none() && kind != b"IHDR" {
+            return Err(ImageError::Invalid("the first chunk isn't IHDR"));
+        }
+        match kind {
+            b"IHDR" => {
+                if header.is_some() {
+                    return Err(ImageError::Invalid("more than one IHDR chunk"));
+                }
+                header = Some(read_header(content)?);
+            }
+            b"PLTE" => {
+                if content.len() % 3 != 0 || content.len() > 3 * 256 {
+                    return Err(ImageError::Invalid("bad palette length"));
+                }
+                palette = content.chunks_exact(3).map(|rgb| [rgb[0], rgb[1], rgb[2]]).collect();
+            }
+            b"tRNS" => transparency = Some(content.to_vec()),
+            b"IDAT" => compressed.extend_from_slice(content),
+            b"IEND" => break,
+            _ if kind[0].is_ascii_uppercase() => return Err(ImageError::Unsupported("unknown critical PNG chunk")),
+            _ => {}
+        }
+    }
+    let header = header.ok_or(ImageError::Invalid("missing IHDR chunk"))?;
+    if header.color_type == 3 && palette.is_empty() {
+        return Err(ImageError::Invalid("palette image without PLTE chunk"));
+    }
+    if compressed.is_empty() {
+        return Err(ImageError::Invalid("missing IDAT chunk"));
+    }
+
+    let passes: Vec<(u32, u32, u32, u32)> = if header.interlaced { ADAM7.to_vec() } else { vec![(0, 0, 1, 1)] };
+    let pass_size = |&(x0, y0, dx, dy): &(u32, u32, u32, u32)| -> (u32, u32) {
+        let width = if header.width > x0 { (header.width - x0).div_ceil(dx) } else { 0 };
+        let height = if header.height > y0 { (header.height - y0).div_ceil(dy) } else { 0 };
+        return (width, height);
+    };
+    let expected: usize = passes
+        .iter()
+        .map(|pass| {
+            let (width, height) = pass_size(pass);
+            return if width == 0 { 0 } else { (header.row_bytes(width) + 1) * height as usize };
+        })
+        .sum();
+    let raw = zlib_decompress(&compressed, expected)?;
+    if raw.len() < expected {
+        return Err(ImageError::Truncated("PNG"));
+    }
+
+    let format = output_format(&header, transparency.is_some());
+    let mut image = Image::new(header.width, header.height, format)?;
+    let mut offset = 0;
+    for pass in &passes {
+        let (width, height) = pass_size(pass);
+        if width == 0 || height == 0 {
+            continue;
+        }
+        let row_bytes = header.row_bytes(width);
+        let mut previous = vec![0; row_bytes];
+        let mut current = vec![0; row_bytes];
+        let (x0, y0, dx, dy) = *pass;
+        for row in 0..height {
+            let filter = raw[offset];
+            current.copy_from_slice(&raw[offset + 1..offset + 1 + row_bytes]);
+            offset += row_bytes + 1;
+            unfilter(filter, header.filter_distance(), &mut current, &previous)?;
+            for column in 0..width {
+                let pixel = read_pixel(&header, &current, column as usize, &palette, transparency.as_deref());
+                let size = format.bytes_per_pixel();
+                let x = (x0 + column * dx) as usize;
+                image.row_mut(y0 + row * dy)[x * size..(x + 1) * size].copy_from_slice(&pixel[..size]);
+            }
+            std::mem::swap(&mut previous, &mut current);
+        }
+    }
+    return Ok(image);
+}
+
+/// Writes an image as a PNG file. [`PixelFormat::Bgra8`] is stored as RGBA.
+pub fn encode(image: &Image) -> Vec<u8> {
+    let image = match image.format() {
+        PixelFormat::Bgra8 => image.convert(PixelFormat::Rgba8),
+        _ => image.clone(),
+    };
+    let color_type = match image.format() {
+        PixelFormat::Gray8 => 0,
+        PixelFormat::GrayAlpha8 => 4,
+        PixelFormat::Rgb8 => 2,
+        _ => 6,
+    };
+    let distance = image.format().bytes_per_pixel();
+    let row_bytes = image.width() as usize * distance;
+
+    let mut raw = Vec::with_capacity((row_bytes + 1) * image.height() as usize);
+    let mut previous = vec![0; row_bytes];
+    let mut candidate = Vec::with_capacity(row_bytes);
+    let mut best = Vec::with_capacity(row_bytes);
+    for y in 0..image.height() {
+        let row = image.row(y);
+        let (mut best_filter, mut best_score) = (0, u64::MAX);
+        for filter in 0..5 {
+            candidate.clear();
+            apply_filter(filter, distance, row, &previous, &mut candidate);
+            // Filtered bytes read as signed, smaller means more zeros and repeats
+            let score = candidate.iter().map(|&byte| (byte as i8).unsigned_abs() as u64).sum();
+            if score < best_score {
+                (best_filter, best_score) = (filter, score);
+                std::mem::swap(&mut best, &mut candidate);
+            }
+        }
+        raw.push(best_filter);
+        raw.extend_from_slice(&best);
+        previous.copy_from_slice(row);
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&image.width().to_be_bytes());
+    header.extend_from_slice(&image.height().to_be_bytes());
+    header.extend_from_slice(&[8, color_type, 0, 0, 0]);
+
+    let mut output = SIGNATURE.to_vec();
+    write_chunk(&mut output, b"IHDR", &header);
+    write_chunk(&mut output, b"IDAT", &zlib_compress(&raw));
+    write_chunk(&mut output, b"IEND", &[]);
+    return output;
+}
+
+fn write_chunk(output: &mut Vec<u8>, kind: &[u8; 4], content: &[u8]) {
+    output.extend_from_slice(&(content.len() as u32).to_be_bytes());
+    output.extend_from_slice(kind);
+    output.extend_from_slice(content);
+    output.extend_from_slice(&crc32(&[kind, content]).to_be_bytes());
+}
+
+/// Filters one row against the previous unfiltered row, the inverse of [`unfilter`]
+fn apply_filter(filter: u8, distance: usize, row: &[u8], previous: &[u8], output: &mut Vec<u8>) {
+    for i in 0..row.len() {
+        let left = if i >= distance { row[i - distance] } else { 0 };
+        let upper_left = if i >= distance { previous[i - distance] } else { 0 };
+        let prediction = match filter {
+            0 => 0,
+            1 => left,
+            2 => previous[i],
+            3 => ((left as u16 + previous[i] as u16) / 2) as u8,
+            _ => paeth(left, previous[i], upper_left),
+        };
+        output.push(row[i].wrapping_sub(prediction));
+    }
+}
+
+fn read_header(content: &[u8]) -> Result<Header, ImageError> {
+    if content.len() != 13 {
+        return Err(ImageError::Invalid("bad IHDR length"));
+    }
+    let header = Header {
+        width: read_u32(content, 0).unwrap_or(0),
+        height: read_u32(content, 4).unwrap_or(0),
+        bit_depth: content[8],
+        color_type: content[9],
+        interlaced: match content[12] {
+            0 => false,
+            1 => true,
+            _ => return Err(ImageError::Unsupported("unknown PNG interlace method")),
+        },
+    };
+    if content[10] != 0 || content[11] != 0 {
+        return Err(ImageError::Unsupported("unknown PNG compression or filter method"));
+    }
+    let valid_depth = match header.color_type {
+        0 => matches!(header.bit_depth, 1 | 2 | 4 | 8 | 16),
+        3 => matches!(header.bit_depth, 1 | 2 | 4 | 8),
+        2 | 4 | 6 => matches!(header.bit_depth, 8 | 16),
+        _ => return Err(ImageError::Invalid("unknown PNG colour type")),
+    };
+    if !valid_depth {
+        return Err(ImageError::Invalid("bit depth not allowed for the colour type"));
+    }
+    check_size(header.width, header.height)?;
+    return Ok(header);
+}
+
+fn output_format(header: &Header, has_transparency: bool) -> PixelFormat {
+    match (header.color_type, has_transparency) {
+        (0, false) => return PixelFormat::Gray8,
+        (0, true) | (4, _) => return PixelFormat::GrayAlpha8,
+        (2, false) | (3, false) => return PixelFormat::Rgb8,
+        _ => return PixelFormat::Rgba8,
+    }
+}
+
+/// Undoes the filter of one row, given the unfiltered previous row
+fn unfilter(filter: u8, distance: usize, row: &mut [u8], previous: &[u8]) -> Result<(), ImageError> {
+    match filter {
+        0 => {}
+        1 => {
+            for i in distance..row.len() {
+                row[i] = row[i].wrapping_add(row[i - distance]);
+            }
+        }
+        2 => {
+            for (value, above) in row.iter_mut().zip(previous) {
+                *value = value.wrapping_add(*above);
+            }
+        }
+        3 => {
+            for i in 0..row.len() {
+                let left = if i >= distance { row[i - distance] } else { 0 };
+                row[i] = row[i].wrapping_add(((left as u16 + previous[i] as u16) / 2) as u8);
+            }
+        }
+        4 => {
+            for i in 0..row.len() {
+                let left = if i >= distance { row[i - distance] } else { 0 };
+                let upper_left = if i >= distance { previous[i - distance] } else { 0 };
+                row[i] = row[i].wrapping_add(paeth(left, previous[i], upper_left));
+            }
+        }
+        _ => return Err(ImageError::Invalid("unknown PNG filter type")),
+    }
+    return Ok(());
+}
+
+fn paeth(left: u8, above: u8, upper_left: u8) -> u8 {
+    let estimate = left as i16 + above as i16 - upper_left as i16;
+    let (to_left, to_above, to_upper_left) =
+        ((estimate - left as i16).abs(), (estimate - above as i16).abs(), (estimate - upper_left as i16).abs());
+    if to_left <= to_above && to_left <= to_upper_left {
+        return left;
+    } else if to_above <= to_upper_left {
+        return above;
+    } else {
+        return upper_left;
+    }
+}
+
+/// Sample `index` of an unfiltered row, at its original bit depth
+fn sample(row: &[u8], index: usize, bit_depth: u8) -> u32 {
+    match bit_depth {
+        16 => return u16::from_be_bytes([row[2 * index], row[2 * index + 1]]) as u32,
+        8 => return row[index] as u32,
+        _ => {
+            let bits = bit_depth as usize;
+            let bit = index * bits;
+            return ((row[bit / 8] >> (8 - bits - bit % 8)) as u32) & ((1 << bits) - 1);
+        }
+    }
+}
+
+/// Pixel `x` of an unfiltered row in the output format, padded to four bytes
+fn read_pixel(header: &Header, row: &[u8], x: usize, palette: &[[u8; 3]], transparency: Option<&[u8]>) -> [u8; 4] {
+    let depth = header.bit_depth;
+    let max = (1u32 << depth) - 1;
+    let channels = header.channels();
+    let eight_bits = |value: u32| if depth == 16 { (value >> 8) as u8 } else { scale_sample(value, max) };
+    // A tRNS chunk on grey or RGB images is one colour that is transparent
+    let key = |index: usize| transparency.and_then(|t| t.get(2 * index..2 * index + 2)).map(|v| u16::from_be_bytes([v[0], v[1]]) as u32);
+    match header.color_type {
+        0 => {
+            let value = sample(row, x, depth);
+            let alpha = if key(0) == Some(value) { 0 } else { 255 };
+            return [eight_bits(value), alpha, 0, 0];
+        }
+        2 => {
+            let rgb = [sample(row, 3 * x, depth), sample(row, 3 * x + 1, depth), sample(row, 3 * x + 2, depth)];
+            let transparent = transparency.is_some() && (0..3).all(|i| key(i) == Some(rgb[i]));
+            return [eight_bits(rgb[0]), eight_bits(rgb[1]), eight_bits(rgb[2]), if transparent { 0 } else { 255 }];
+        }
+        3 => {
+            let index = sample(row, x, depth) as usize;
+            let [r, g, b] = palette.get(index).copied().unwrap_or([0, 0, 0]);
+            let alpha = transparency.and_then(|t| t.get(index).copied()).unwrap_or(255);
+            return [r, g, b, alpha];
+        }
+        _ => {
+            let mut pixel = [0; 4];
+            for (channel, value) in pixel.iter_mut().enumerate().take(channels) {
+                *value = eight_bits(sample(row, channels * x + channel, depth));
+            }
+            return pixel;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(width: u32, height: u32, bit_depth: u8, color_type: u8, interlace: u8) -> Vec<u8> {
+        let mut header = width.to_be_bytes().to_vec();
+        header.extend_from_slice(&height.to_be_bytes());
+        header.extend_from_slice(&[bit_depth, color_type, 0, 0, interlace]);
+        return header;
+    }
+
+    /// A PNG file of the given chunks, with `IEND` added
+    fn png_file(chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut output = SIGNATURE.to_vec();
+        for (kind, content) in chunks {
+            write_chunk(&mut output, kind, content);
+        }
+        write_chunk(&mut output, b"IEND", &[]);
+        return output;
+    }
+
+    /// A one chunk image of filtered rows
+    fn simple_png(header: &[u8], raw: &[u8]) -> Vec<u8> {
+        return png_file(&[(b"IHDR", header), (b"IDAT", &zlib_compress(raw))]);
+    }
+
+    #[test]
+    fn crc_matches_the_standard() {
+        assert_eq!(crc32(&[b"123456789"]), 0xCBF4_3926);
+        assert_eq!(crc32(&[b"1234", b"56789"]), 0xCBF4_3926);
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    #[test]
+    fn low_bit_depths_palettes_and_transparency_decode() {
+        let gray = decode(&simple_png(&header(3, 1, 1, 0, 0), &[0, 0b1010_0000])).unwrap();
+        assert_eq!((gray.format(), gray.data()), (PixelFormat::Gray8, &[255, 0, 255][..]));
+
+        let palette = [10, 20, 30, 40, 50, 60, 70, 80, 90];
+        let indexed = png_file(&[
+            (b"IHDR", &header(2, 1, 2, 3, 0)),
+            (b"PLTE", &palette),
+            (b"tRNS", &[255, 0]),
+            (b"IDAT", &zlib_compress(&[0, 0b0110_0000])),
+        ]);
+        let indexed = decode(&indexed).unwrap();
+        assert_eq!(indexed.format(), PixelFormat::Rgba8);
+        assert_eq!(indexed.data(), &[40, 50, 60, 0, 70, 80, 90, 255]);
+
+        // A 16-bit colour key only hides the exact colour
+        let key = [0x12, 0x34, 0, 0, 0xFF, 0xFF];
+        let pixels = [0, 0x12, 0x34, 0, 0, 0xFF, 0xFF, 0x12, 0x35, 0, 0, 0xFF, 0xFF];
+        let rgb = png_file(&[(b"IHDR", &header(2, 1, 16, 2, 0)), (b"tRNS", &key), (b"IDAT", &zlib_compress(&pixels))]);
+        assert_eq!(decode(&rgb).unwrap().data(), &[0x12, 0, 0xFF, 0, 0x12, 0, 0xFF, 255]);
+
+        let gray_alpha = decode(&simple_png(&header(1, 1, 8, 4, 0), &[0, 200, 100])).unwrap();
+        assert_eq!((gray_alpha.format(), gray_alpha.data()), (PixelFormat::GrayAlpha8, &[200, 100][..]));
+    }
+
+    #[test]
+    fn every_filter_is_undone() {
+        // Sub, Paeth, Average then Up
+        let raw = [1, 10, 5, 5, 4, 0, 0, 0, 3, 1, 1, 1, 2, 1, 2, 3];
+        let image = decode(&simple_png(&header(3, 4, 8, 0, 0), &raw)).unwrap();
+        assert_eq!(image.data(), &[10, 15, 20, 10, 15, 20, 6, 11, 16, 7, 13, 19]);
+        let mut raw = raw;
+        raw[0] = 5;
+        let unknown_filter = simple_png(&header(3, 4, 8, 0, 0), &raw);
+        assert_eq!(decode(&unknown_filter), Err(ImageError::Invalid("unknown PNG filter type")));
+    }
+
+    #[test]
+    fn interlaced_passes_are_placed() {
+        // 3x3 pixels whose value is their index, in Adam7 order
+        let raw = [0, 0, 0, 2, 0, 6, 8, 0, 1, 0, 7, 0, 3, 4, 5];
+        let image = decode(&simple_png(&header(3, 3, 8, 0, 1), &raw)).unwrap();
+        assert_eq!(image.data(), &[0, 1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn image_data_may_span_chunks() {
+        let compressed = zlib_compress(&[0, 1, 2, 3, 0, 4, 5, 6]);
+        let (first, second) = compressed.split_at(5);
+        let header = header(1, 2, 8, 2, 0);
+        let file = png_file(&[(b"IHDR", &header), (b"IDAT", first), (b"tEXt", b"a\0b"), (b"IDAT", second)]);
+        assert_eq!(decode(&file).unwrap().data(), &[1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        let ihdr = header(2, 2, 8, 0, 0);
+        let idat = zlib_compress(&[0, 1, 2, 0, 3, 4]);
+        let file = simple_png(&ihdr, &[0, 1, 2, 0, 3, 4]);
+        assert!(decode(&file).is_ok());
+        assert_eq!(decode(b"GIF89a"), Err(ImageError::Invalid("missing PNG signature")));
+        for length in 0..file.len() {
+            assert!(decode(&file[..length]).is_err(), "{} bytes", length);
+        }
+        let mut corrupt = file.clone();
+        corrupt[20] ^= 1;
+        assert_eq!(decode(&corrupt), Err(ImageError::Invalid("chunk CRC mismatch")));
+
+        let invalid = |chunks: &[(&[u8; 4], &[u8])]| decode(&png_file(chunks));
+        assert_eq!(invalid(&[(b"IDAT", &idat)]), Err(ImageError::Invalid("the first chunk isn't IHDR")));
+        assert_eq!(invalid(&[(b"IHDR", &ihdr), (b"IHDR", &ihdr)]), Err(ImageError::Invalid("more than one IHDR chunk")));
+        assert_eq!(invalid(&[(b"IHDR", &ihdr)]), Err(ImageError::Invalid("missing IDAT chunk")));
+        assert_eq!(
+            invalid(&[(b"IHDR", &ihdr), (b"ABCD", &[])]),
+            Err(ImageError::Unsupported("unknown critical PNG chunk")),
+        );
+        assert_eq!(invalid(&[(b"IHDR", &ihdr), (b"PLTE", &[1, 2])]), Err(ImageError::Invalid("bad palette length")));
+        assert_eq!(
+            invalid(&[(b"IHDR", &header(2, 2, 8, 3, 0)), (b"IDAT", &idat)]),
+            Err(ImageError::Invalid("palette image without PLTE chunk")),
+        );
+        assert_eq!(invalid(&[(b"IHDR", &ihdr[..12])]), Err(ImageError::Invalid("bad IHDR length")));
+        assert_eq!(
+            invalid(&[(b"IHDR", &header(2, 2, 4, 2, 0))]),
+            Err(ImageError::Invalid("bit depth not allowed for the colour type")),
+        );
+        assert_eq!(invalid(&[(b"IHDR", &header(2, 2, 8, 5, 0))]), Err(ImageError::Invalid("unknown PNG colour type")));
+        assert_eq!(
+            invalid(&[(b"IHDR", &header(2, 2, 8, 0, 2))]),
+            Err(ImageError::Unsupported("unknown PNG interlace method")),
+        );
+        assert_eq!(invalid(&[(b"IHDR", &header(0, 2, 8, 0, 0))]), Err(ImageError::InvalidSize { width: 0, height: 2 }));
+        assert_eq!(
+            invalid(&[(b"IHDR", &header(40000, 40000, 8, 0, 0))]),
+            Err(ImageError::InvalidSize { width: 40000, height: 40000 }),
+        );
+
+        // Too little image data, then too much
+        assert_eq!(decode(&simple_png(&ihdr, &[0, 1, 2, 0])), Err(ImageError::Truncated("PNG")));
+        assert_eq!(
+            decode(&simple_png(&ihdr, &[0; 7])),
+            Err(ImageError::Invalid("decompressed data is larger than the image")),
+        );
+    }
+
+    /// The filtered rows of a file written by [`encode`]
+    fn filtered_rows(file: &[u8]) -> Vec<u8> {
+        let length = read_u32(file, 33).unwrap() as usize;
+        return zlib_decompress(&file[41..41 + length], usize::MAX).unwrap();
+    }
+
+    #[test]
+    fn encoder_picks_a_filter_per_row() {
+        // A horizontal ramp is smallest with Sub, the same row again with Up
+        let ramp: Vec<u8> = (0..16).map(|x| x * 10).collect();
+        let mut data = ramp.clone();
+        data.extend_from_slice(&ramp);
+        let image = Image::from_data(16, 2, PixelFormat::Gray8, 16, data).unwrap();
+        let file = encode(&image);
+        let raw = filtered_rows(&file);
+        assert_eq!((raw[0], raw[17]), (1, 2));
+        assert!(raw[2..17].iter().all(|&byte| byte == 10) && raw[18..].iter().all(|&byte| byte == 0));
+        assert_eq!(decode(&file), Ok(image));
+    }
+
+    #[test]
+    fn encoder_writes_8_bit_headers() {
+        let image = Image::new(300, 2, PixelFormat::Bgra8).unwrap();
+        let file = encode(&image);
+        assert!(file.starts_with(&SIGNATURE));
+        assert_eq!(&file[12..16], b"IHDR");
+        assert_eq!(file[16..29], header(300, 2, 8, 6, 0)[..]);
+        assert!(file.ends_with(&[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]));
+        assert_eq!(decode(&file), Ok(image.convert(PixelFormat::Rgba8)));
+    }
+}
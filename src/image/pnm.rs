@@ -0,0 +1,181 @@
+//! Netpbm decoding: PBM, PGM and PPM
+//!
+//! All six variants are read, plain text (`P1` to `P3`) and binary (`P4`
+//! to `P6`). Bitmaps and grey maps decode to [`PixelFormat::Gray8`],
+//! pixmaps to [`PixelFormat::Rgb8`], and samples with a maximum value
+//! other than 255 (up to 65535) are scaled to 8 bits.
+//!
+//! [`encode`] writes binary grey maps (`P5`) for grey images and binary
+//! pixmaps (`P6`) for everything else. Netpbm has no alpha, so it is
+//! dropped.
+
+use super::{check_size, scale_sample, Image, ImageError, PixelFormat};
+
+/// Reads the whitespace separated numbers of the header and of plain
+/// files, skipping `#` comments
+struct Tokens<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl Tokens<'_> {
+    fn skip_space(&mut self) {
+        while let Some(&byte) = self.data.get(self.at) {
+            if byte == b'#' {
+                while self.data.get(self.at).is_some_and(|&byte| byte != b'\n' && byte != b'\r') {
+                    self.at += 1;
+                }
+            } else if byte.is_ascii_whitespace() {
+                self.at += 1;
+            } else {
+                return;
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<u32, ImageError> {
+        self.skip_space();
+        let start = self.at;
+        while self.data.get(self.at).is_some_and(|byte| byte.is_ascii_digit()) {
+            self.at += 1;
+        }
+        if start == self.at {
+            return Err(if self.at >= self.data.len() {
+                ImageError::Truncated("PNM")
+            } else {
+                ImageError::Invalid("expected a number")
+            });
+        }
+        let digits = std::str::from_utf8(&self.data[start..self.at]).unwrap_or("");
+        return digits.parse().map_err(|_| ImageError::Invalid("number too large"));
+    }
+
+    /// One digit of a plain bitmap, where `0110` is four pixels
+    fn bit(&mut self) -> Result<u32, ImageError> {
+        self.skip_space();
+        match self.data.get(self.at) {
+            Some(&byte @ (b'0' | b'1')) => {
+                self.at += 1;
+                return Ok((byte - b'0') as u32);
+            }
+            Some(_) => return Err(ImageError::Invalid("expected 0 or 1")),
+            None => return Err(ImageError::Truncated("PNM")),
+        }
+    }
+}
+
+/// Reads a PBM, PGM or PPM file
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    let kind = match data {
+        [b'P', kind @ b'1'..=b'6', ..] => kind - b'0',
+        _ => return Err(ImageError::Invalid("missing netpbm signature")),
+    };
+    let mut tokens = Tokens { data, at: 2 };
+    let width = tokens.number()?;
+    let height = tokens.number()?;
+    let max = if kind == 1 || kind == 4 { 1 } else { tokens.number()? };
+    if max == 0 || max > 65535 {
+        return Err(ImageError::Invalid("maximum value outside 1 to 65535"));
+    }
+    check_size(width, height)?;
+
+    let format = if kind == 3 || kind == 6 { PixelFormat::Rgb8 } else { PixelFormat::Gray8 };
+    let mut image = Image::new(width, height, format)?;
+    let samples_per_row = width as usize * format.bytes_per_pixel();
+    // Bitmaps store 1 for black
+    let value = |sample: u32| if kind == 1 || kind == 4 { if sample == 0 { 255 } else { 0 } } else { scale_sample(sample, max) };
+
+    if kind <= 3 {
+        for y in 0..height {
+            let row = image.row_mut(y);
+            for sample in row.iter_mut() {
+                *sample = value(if kind == 1 { tokens.bit()? } else { tokens.number()? });
+            }
+        }
+        return Ok(image);
+    }
+
+    // Binary data starts after exactly one whitespace byte
+    let start = tokens.at + 1;
+    let (row_bytes, sample_bytes) = match kind {
+        4 => ((width as usize).div_ceil(8), 0),
+        _ if max > 255 => (samples_per_row * 2, 2),
+        _ => (samples_per_row, 1),
+    };
+    let pixels = data
+        .get(start..start + row_bytes * height as usize)
+        .ok_or(ImageError::Truncated("PNM"))?;
+    for (y, source) in pixels.chunks_exact(row_bytes).enumerate() {
+        let row = image.row_mut(y as u32);
+        for (i, sample) in row.iter_mut().enumerate() {
+            let raw = match sample_bytes {
+                0 => ((source[i / 8] >> (7 - i % 8)) & 1) as u32,
+                1 => source[i] as u32,
+                _ => u16::from_be_bytes([source[2 * i], source[2 * i + 1]]) as u32,
+            };
+            *sample = value(raw);
+        }
+    }
+    return Ok(image);
+}
+
+/// Writes an image as a binary PGM or PPM file, see the module documentation
+pub fn encode(image: &Image) -> Vec<u8> {
+    let gray = matches!(image.format(), PixelFormat::Gray8 | PixelFormat::GrayAlpha8);
+    let image = image.convert(if gray { PixelFormat::Gray8 } else { PixelFormat::Rgb8 });
+    let mut output = format!("P{}\n{} {}\n255\n", if gray { 5 } else { 6 }, image.width(), image.height()).into_bytes();
+    output.extend_from_slice(image.data());
+    return output;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_files_decode() {
+        let bitmap = decode(b"P1\n# a comment\n3 2\n0 1 0\n101").unwrap();
+        assert_eq!((bitmap.format(), bitmap.data()), (PixelFormat::Gray8, &[255, 0, 255, 0, 255, 0][..]));
+        let graymap = decode(b"P2 2 1 15 0 15").unwrap();
+        assert_eq!(graymap.data(), &[0, 255]);
+        let pixmap = decode(b"P3\n1 1\n#max\n100\n100 50 0\n").unwrap();
+        assert_eq!((pixmap.format(), pixmap.data()), (PixelFormat::Rgb8, &[255, 128, 0][..]));
+    }
+
+    #[test]
+    fn binary_files_decode() {
+        let bitmap = decode(b"P4 10 1\n\x80\x40").unwrap();
+        assert_eq!(bitmap.data(), &[0, 255, 255, 255, 255, 255, 255, 255, 255, 0]);
+        let graymap = decode(b"P5 2 1 65535\n\xFF\xFF\x80\x00").unwrap();
+        assert_eq!(graymap.data(), &[255, 128]);
+        // The byte after the header is whitespace even when it looks like a sample
+        let pixmap = decode(b"P6 1 1 255\n\n\x20\x30").unwrap();
+        assert_eq!(pixmap.data(), &[10, 32, 48]);
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        assert_eq!(decode(b"P7 1 1 255\n"), Err(ImageError::Invalid("missing netpbm signature")));
+        assert_eq!(decode(b"P2 2"), Err(ImageError::Truncated("PNM")));
+        assert_eq!(decode(b"P2 2 x"), Err(ImageError::Invalid("expected a number")));
+        assert_eq!(decode(b"P2 99999999999 1"), Err(ImageError::Invalid("number too large")));
+        assert_eq!(decode(b"P2 1 1 0 0"), Err(ImageError::Invalid("maximum value outside 1 to 65535")));
+        assert_eq!(decode(b"P2 1 1 65536 0"), Err(ImageError::Invalid("maximum value outside 1 to 65535")));
+        assert_eq!(decode(b"P2 0 1 255"), Err(ImageError::InvalidSize { width: 0, height: 1 }));
+        assert_eq!(decode(b"P1 2 1 0 2"), Err(ImageError::Invalid("expected 0 or 1")));
+        let file = b"P6 2 1 255\n\x01\x02\x03\x04\x05\x06";
+        assert_eq!(decode(file).unwrap().data(), &[1, 2, 3, 4, 5, 6]);
+        for length in 0..file.len() {
+            assert!(decode(&file[..length]).is_err(), "{} bytes", length);
+        }
+    }
+
+    #[test]
+    fn grey_and_colour_images_encode() {
+        let gray = Image::from_data(2, 1, PixelFormat::GrayAlpha8, 4, vec![10, 0, 20, 255]).unwrap();
+        assert_eq!(encode(&gray), b"P5\n2 1\n255\n\x0a\x14");
+        let color = Image::from_data(1, 1, PixelFormat::Bgra8, 4, vec![1, 2, 3, 4]).unwrap();
+        assert_eq!(encode(&color), b"P6\n1 1\n255\n\x03\x02\x01");
+        assert_eq!(decode(&encode(&color)).map(|image| image.into_data()), Ok(vec![3, 2, 1]));
+    }
+}
@@ -0,0 +1,216 @@
+//! TGA (Truevision TARGA) decoding
+//!
+//! Colour-mapped, true colour and greyscale images are read, raw or run
+//! length encoded, at 8, 15, 16, 24 and 32 bits per pixel, in any of the
+//! four origins. TGA has no signature, so [`looks_like_tga`] checks that a
+//! header is plausible before [`Image::decode`] tries it.
+
+use super::{check_size, Image, ImageError, PixelFormat};
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    return data.get(at..at + 2).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]));
+}
+
+const HEADER_SIZE: usize = 18;
+
+/// Whether `data` starts with a header this decoder would accept
+pub fn looks_like_tga(data: &[u8]) -> bool {
+    if data.len() < HEADER_SIZE {
+        return false;
+    }
+    let (color_map_type, image_type, map_depth, depth) = (data[1], data[2], data[7], data[16]);
+    let has_size = data[12..16].iter().any(|&byte| byte != 0);
+    let valid = match image_type {
+        1 | 9 => color_map_type == 1 && matches!(depth, 8 | 16) && matches!(map_depth, 15 | 16 | 24 | 32),
+        2 | 10 => color_map_type <= 1 && matches!(depth, 15 | 16 | 24 | 32),
+        3 | 11 => color_map_type <= 1 && matches!(depth, 8 | 16),
+        _ => false,
+    };
+    return valid && has_size;
+}
+
+/// Reads a TGA file
+pub fn decode(data: &[u8]) -> Result<Image, ImageError> {
+    if !looks_like_tga(data) {
+        return Err(ImageError::Invalid("not a TGA header"));
+    }
+    let truncated = ImageError::Truncated("TGA");
+    let id_length = data[0] as usize;
+    let image_type = data[2];
+    let first_entry = read_u16(data, 3).ok_or(truncated.clone())? as usize;
+    let map_length = read_u16(data, 5).ok_or(truncated.clone())? as usize;
+    let map_depth = data[7];
+    let width = read_u16(data, 12).ok_or(truncated.clone())? as u32;
+    let height = read_u16(data, 14).ok_or(truncated.clone())? as u32;
+    let depth = data[16];
+    let descriptor = data[17];
+    let alpha_bits = descriptor & 0x0F;
+    let right_to_left = descriptor & 0x10 != 0;
+    let top_to_bottom = descriptor & 0x20 != 0;
+    check_size(width, height)?;
+
+    // The colour map, converted to RGBA once
+    let map_start = HEADER_SIZE + id_length;
+    let map_entry_size = (map_depth as usize).div_ceil(8);
+    let map_bytes = if data[1] == 1 { map_length * map_entry_size } else { 0 };
+    let map_data = data.get(map_start..map_start + map_bytes).ok_or(truncated.clone())?;
+    let color_map: Vec<[u8; 4]> =
+        map_data.chunks_exact(map_entry_size.max(1)).map(|entry| to_rgba(entry, map_depth, true)).collect();
+
+    let pixel_size = (depth as usize).div_ceil(8);
+    let pixel_count = width as usize * height as usize;
+    let pixels = data.get(map_start + map_bytes..).ok_or(truncated.clone())?;
+    let raw = if image_type & 8 != 0 {
+        decode_rle(pixels, pixel_size, pixel_count)?
+    } else {
+        pixels.get(..pixel_count * pixel_size).ok_or(truncated.clone())?.to_vec()
+    };
+
+    let kind = image_type & !8;
+    let format = match kind {
+        1 if map_depth == 32 || (map_depth == 16 && alpha_bits > 0) => PixelFormat::Rgba8,
+        1 => PixelFormat::Rgb8,
+        2 if depth == 32 || (depth == 16 && alpha_bits > 0) => PixelFormat::Rgba8,
+        2 => PixelFormat::Rgb8,
+        _ if depth == 16 => PixelFormat::GrayAlpha8,
+        _ => PixelFormat::Gray8,
+    };
+    let mut image = Image::new(width, height, format)?;
+    let size = format.bytes_per_pixel();
+    for (i, pixel) in raw.chunks_exact(pixel_size).enumerate() {
+        let (stored_x, stored_y) = (i as u32 % width, i as u32 / width);
+        let x = if right_to_left { width - 1 - stored_x } else { stored_x };
+        let y = if top_to_bottom { stored_y } else { height - 1 - stored_y };
+        let rgba = match kind {
+            1 => {
+                let index = if depth == 16 { u16::from_le_bytes([pixel[0], pixel[1]]) as usize } else { pixel[0] as usize };
+                index.checked_sub(first_entry).and_then(|index| color_map.get(index)).copied().unwrap_or([0, 0, 0, 255])
+            }
+            2 => to_rgba(pixel, depth, alpha_bits > 0),
+            _ => [pixel[0], *pixel.get(1).unwrap_or(&255), 0, 0],
+        };
+        image.row_mut(y)[x as usize * size..(x as usize + 1) * size].copy_from_slice(&rgba[..size]);
+    }
+    return Ok(image);
+}
+
+/// A true colour pixel or colour map entry as RGBA
+fn to_rgba(pixel: &[u8], depth: u8, use_alpha: bool) -> [u8; 4] {
+    match depth {
+        15 | 16 => {
+            let value = u16::from_le_bytes([pixel[0], pixel[1]]);
+            let five = |shift: u16| (((value >> shift) & 0x1F) as u32 * 255 / 31) as u8;
+            let alpha = if depth == 16 && use_alpha && value & 0x8000 == 0 { 0 } else { 255 };
+            return [five(10), five(5), five(0), alpha];
+        }
+        24 => return [pixel[2], pixel[1], pixel[0], 255],
+        _ => return [pixel[2], pixel[1], pixel[0], pixel[3]],
+    }
+}
+
+/// Expands run length encoded pixels: a packet header with the high bit
+/// set repeats the next pixel, otherwise raw pixels follow
+fn decode_rle(data: &[u8], pixel_size: usize, pixel_count: usize) -> Result<Vec<u8>, ImageError> {
+    let mut output = Vec::with_capacity(pixel_count * pixel_size);
+    let mut at = 0;
+    while output.len() < pixel_count * pixel_size {
+        let header = *data.get(at).ok_or(ImageError::Truncated("TGA"))?;
+        at += 1;
+        let count = (header & 0x7F) as usize + 1;
+        if header & 0x80 != 0 {
+            let pixel = data.get(at..at + pixel_size).ok_or(ImageError::Truncated("TGA"))?;
+            at += pixel_size;
+            for _ in 0..count {
+                output.extend_from_slice(pixel);
+            }
+        } else {
+            let pixels = data.get(at..at + count * pixel_size).ok_or(ImageError::Truncated("TGA"))?;
+            at += count * pixel_size;
+            output.extend_from_slice(pixels);
+        }
+    }
+    // A packet may run past the last pixel
+    output.truncate(pixel_count * pixel_size);
+    return Ok(output);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A header without image ID or colour map
+    fn header(image_type: u8, width: u16, height: u16, depth: u8, descriptor: u8) -> Vec<u8> {
+        let mut header = vec![0, 0, image_type, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        header.extend_from_slice(&width.to_le_bytes());
+        header.extend_from_slice(&height.to_le_bytes());
+        header.extend_from_slice(&[depth, descriptor]);
+        return header;
+    }
+
+    #[test]
+    fn true_colour_images_decode_in_every_origin() {
+        // Bottom-up by default, after a two byte image ID
+        let mut file = header(2, 2, 2, 24, 0);
+        file[0] = 2;
+        file.extend_from_slice(&[b'i', b'd', 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
+        let image = decode(&file).unwrap();
+        assert_eq!(image.format(), PixelFormat::Rgb8);
+        assert_eq!(image.data(), &[9, 8, 7, 12, 11, 10, 3, 2, 1, 6, 5, 4]);
+
+        let mut file = header(2, 2, 1, 24, 0x30);
+        file.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+        assert_eq!(decode(&file).unwrap().data(), &[6, 5, 4, 3, 2, 1]);
+
+        // 15 and 16 bits, where the top bit is the alpha of 16-bit pixels with alpha bits
+        let mut file = header(2, 2, 1, 16, 0x21);
+        file.extend_from_slice(&[0x00, 0xFC, 0x1F, 0x00]);
+        let image = decode(&file).unwrap();
+        assert_eq!((image.format(), image.data()), (PixelFormat::Rgba8, &[255, 0, 0, 255, 0, 0, 255, 0][..]));
+        let mut file = header(2, 1, 1, 15, 0);
+        file.extend_from_slice(&[0xE0, 0x03]);
+        assert_eq!(decode(&file).unwrap().data(), &[0, 255, 0]);
+    }
+
+    #[test]
+    fn run_length_and_grey_images_decode() {
+        let mut file = header(10, 3, 1, 32, 0x28);
+        file.extend_from_slice(&[0x81, 1, 2, 3, 4, 0x00, 5, 6, 7, 8]);
+        let image = decode(&file).unwrap();
+        assert_eq!(image.data(), &[3, 2, 1, 4, 3, 2, 1, 4, 7, 6, 5, 8]);
+        for length in 0..file.len() {
+            assert!(decode(&file[..length]).is_err(), "{} bytes", length);
+        }
+        // A run longer than the image is cut
+        let mut file = header(11, 2, 1, 16, 0x20);
+        file.extend_from_slice(&[0x83, 200, 100]);
+        let image = decode(&file).unwrap();
+        assert_eq!((image.format(), image.data()), (PixelFormat::GrayAlpha8, &[200, 100, 200, 100][..]));
+    }
+
+    #[test]
+    fn colour_mapped_images_decode() {
+        let mut file = header(1, 3, 1, 8, 0x20);
+        file[1] = 1;
+        file[3..8].copy_from_slice(&[1, 0, 2, 0, 24]);
+        file.extend_from_slice(&[30, 20, 10, 60, 50, 40]);
+        // Indices before the first entry are black
+        file.extend_from_slice(&[1, 2, 0]);
+        let image = decode(&file).unwrap();
+        assert_eq!((image.format(), image.data()), (PixelFormat::Rgb8, &[10, 20, 30, 40, 50, 60, 0, 0, 0][..]));
+        assert_eq!(decode(&file[..24]), Err(ImageError::Truncated("TGA")));
+    }
+
+    #[test]
+    fn implausible_headers_are_refused() {
+        assert!(looks_like_tga(&header(2, 1, 1, 24, 0)));
+        assert!(!looks_like_tga(&header(2, 1, 1, 24, 0)[..17]));
+        assert!(!looks_like_tga(&header(0, 1, 1, 24, 0)));
+        assert!(!looks_like_tga(&header(2, 0, 0, 24, 0)));
+        assert!(!looks_like_tga(&header(2, 1, 1, 12, 0)));
+        assert!(!looks_like_tga(&header(1, 1, 1, 8, 0)));
+        assert!(!looks_like_tga(&header(3, 1, 1, 24, 0)));
+        assert_eq!(decode(&header(5, 1, 1, 24, 0)), Err(ImageError::Invalid("not a TGA header")));
+        assert_eq!(decode(&header(2, 0, 1, 24, 0)), Err(ImageError::InvalidSize { width: 0, height: 1 }));
+        assert_eq!(decode(&header(2, 2, 2, 24, 0)), Err(ImageError::Truncated("TGA")));
+    }
+}
@@ -0,0 +1,74 @@
+//! Pixel data for windows with per-pixel transparency
+//!
+//! Both Win32 layered windows (`UpdateLayeredWindow`) and X11 windows with
+//! a 32-bit ARGB visual take premultiplied alpha: every colour channel
+//! already multiplied by the pixel's alpha. In memory that is B, G, R, A
+//! bytes, which is also what an X11 ARGB32 pixel looks like on a
+//! little-endian machine.
+
+/// Converts straight RGBA pixels to premultiplied BGRA
+///
+/// **Returns:** `None` if the length isn't a multiple of four
+pub fn premultiplied_bgra(rgba: &[u8]) -> Option<Vec<u8>> {
+    if !rgba.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut bgra = Vec::with_capacity(rgba.len());
+    for px in rgba.chunks_exact(4) {
+        let a = px[3];
+        bgra.extend_from_slice(&[premultiply(px[2], a), premultiply(px[1], a), premultiply(px[0], a), a]);
+    }
+    return Some(bgra);
+}
+
+/// Premultiplies BGRA (or RGBA) pixels in place
+pub fn premultiply_in_place(pixels: &mut [u8]) {
+    for px in pixels.chunks_exact_mut(4) {
+        let a = px[3];
+        px[0] = premultiply(px[0], a);
+        px[1] = premultiply(px[1], a);
+        px[2] = premultiply(px[2], a);
+    }
+}
+
+/// `channel * alpha / 255`, rounded
+fn premultiply(channel: u8, alpha: u8) -> u8 {
+    let product = channel as u32 * alpha as u32 + 128;
+    return ((product + (product >> 8)) >> 8) as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels_are_multiplied_by_alpha_and_rounded() {
+        assert_eq!(premultiply(200, 0), 0);
+        assert_eq!(premultiply(200, 255), 200);
+        assert_eq!(premultiply(255, 128), 128);
+        assert_eq!(premultiply(200, 128), 100);
+        // 0.502 rounds up and 0.498 rounds down
+        assert_eq!((premultiply(1, 128), premultiply(1, 127)), (1, 0));
+        for channel in 0..=255 {
+            for alpha in 0..=255 {
+                let exact = (channel as f32 * alpha as f32 / 255.0).round() as u8;
+                assert_eq!(premultiply(channel, alpha), exact, "{} * {}", channel, alpha);
+            }
+        }
+    }
+
+    #[test]
+    fn rgba_becomes_premultiplied_bgra() {
+        let rgba = [10, 20, 30, 255, 200, 100, 50, 128, 90, 90, 90, 0];
+        assert_eq!(premultiplied_bgra(&rgba), Some(vec![30, 20, 10, 255, 25, 50, 100, 128, 0, 0, 0, 0]));
+        assert_eq!(premultiplied_bgra(&[]), Some(Vec::new()));
+        for length in [1, 3, 5, 7] {
+            assert_eq!(premultiplied_bgra(&rgba[..length]), None);
+        }
+
+        // A trailing partial pixel is left alone
+        let mut pixels = [200, 100, 50, 128, 7, 7];
+        premultiply_in_place(&mut pixels);
+        assert_eq!(pixels, [100, 50, 25, 128, 7, 7]);
+    }
+}
@@ -1,2 +1,29 @@
+pub mod accelerator;
+pub mod bitmap_font;
+pub mod canvas;
+pub mod clipboard;
+pub mod control;
+pub mod dialog;
+pub mod dirty_region;
+pub mod drag_drop;
+pub mod event;
+pub mod geometry;
+pub mod golden;
+pub mod hit_test;
+pub mod icon;
+pub mod image;
+pub mod layered;
+pub mod menu;
+pub mod path;
+pub mod placement;
+pub mod raster;
+pub mod render3d;
+pub mod text_input;
+pub mod theme;
+pub mod trace;
+pub mod truetype;
+pub mod tray;
+pub mod window_builder;
+
 #[cfg(windows)]
 pub mod win32;
\ No newline at end of file
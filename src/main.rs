@@ -1,46 +1,319 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use gltest::accelerator::AcceleratorTable;
+use gltest::bitmap_font::BitmapFont;
+use gltest::canvas::{Canvas, Color};
+use gltest::path::{LineCap, LineJoin, Path, Stroke};
+use gltest::raster::FillRule;
+use gltest::clipboard::Clipboard;
+use gltest::control::{ControlEvent, ControlId, ControlKind};
+use gltest::dialog::{FileDialog, MessageBox, MessageButtons, MessageIcon, MessageResult};
+use gltest::event::{EventQueue, SystemEvent, WindowEvent};
+use gltest::geometry::{Point, PointF, Rect, RectF, Size, SizeF};
+use gltest::hit_test::{frame_edge, HitRegions, HitTest};
+use gltest::icon::Icon;
+use gltest::menu::{Menu, MenuId};
+use gltest::placement::WindowPlacement;
+use gltest::text_input::{DeadKeys, TextInput};
+use gltest::theme::{SysColor, Theme};
+use gltest::trace::{MessageTracer, TraceFilter};
+use gltest::truetype::{Font, FontStack, GlyphCache};
+use gltest::tray::{Notification, NotificationIcon, TrayEvent, TrayIcon, TrayId};
+use gltest::window_builder::WindowBuilder;
 use gltest::win32::{
     self, 
     window,
 };
 
+/// State owned by the sample window, stored in its userdata pointer
+struct AppState {
+    paint_count: i32,
+    text_input: TextInput,
+    events: EventQueue,
+    menu: Menu,
+    menu_bar: win32::types::HMENU,
+    tray: Option<win32::wrapper::Win32TrayIcon>,
+    system_events: Option<win32::wrapper::Win32SystemEvents>,
+    hit_regions: HitRegions,
+    custom_frame: bool,
+    theme: Theme,
+    /// Segoe UI with a symbol fallback, if the fonts could be read
+    ui_font: Option<(FontStack, GlyphCache)>,
+    /// Icons created for the window and its class, destroyed with the window
+    icons: Vec<win32::types::HICON>,
+}
+
+const ID_FILE_OPEN: MenuId = MenuId(100);
+const ID_FILE_EXIT: MenuId = MenuId(101);
+const ID_FILE_CAPTURE: MenuId = MenuId(102);
+const ID_VIEW_WIREFRAME: MenuId = MenuId(200);
+const ID_VIEW_SHADED: MenuId = MenuId(201);
+const ID_VIEW_TEXTURED: MenuId = MenuId(202);
+const ID_VIEW_CUSTOM_FRAME: MenuId = MenuId(203);
+const ID_VIEW_FULLSCREEN: MenuId = MenuId(204);
+const ID_HELP_ABOUT: MenuId = MenuId(300);
+const ID_EDIT_COPY: MenuId = MenuId(400);
+const ID_EDIT_PASTE: MenuId = MenuId(401);
+const ID_GLOBAL_SHOW: MenuId = MenuId(500);
+const ID_TRAY_SHOW: MenuId = MenuId(600);
+const TRAY_ID: TrayId = TrayId(1);
+const ID_RENDER_BUTTON: ControlId = ControlId(1000);
+const ID_VSYNC_CHECK: ControlId = ControlId(1001);
+const ID_NAME_EDIT: ControlId = ControlId(1002);
+const ID_SCENE_LIST: ControlId = ControlId(1003);
+
+const TITLE_BAR_HEIGHT: i32 = 32;
+const TITLE_BUTTON_WIDTH: i32 = 46;
+const RESIZE_BORDER: i32 = 6;
+
+/// Names the file the window messages are traced to, see [`save_message_trace`]
+const TRACE_ENV_VAR: &str = "GLTEST_TRACE";
+
+/// Writes the traced messages to the file named by [`TRACE_ENV_VAR`], as
+/// JSON lines if it ends in `.jsonl` and as text otherwise
+fn save_message_trace() {
+    let (tracer, path) = match (win32::wrapper::set_message_tracer(None), std::env::var_os(TRACE_ENV_VAR)) {
+        (Some(tracer), Some(path)) => (tracer, std::path::PathBuf::from(path)),
+        _ => return,
+    };
+    let written = std::fs::File::create(&path).and_then(|file| {
+        let mut writer = std::io::BufWriter::new(file);
+        if path.extension().is_some_and(|extension| extension == "jsonl") {
+            tracer.write_json_lines(&mut writer)?;
+        } else {
+            tracer.write_text(&mut writer)?;
+        }
+        return std::io::Write::flush(&mut writer);
+    });
+    match written {
+        Ok(()) => println!("Traced {} messages to {}", tracer.len(), path.display()),
+        Err(e) => println!("Couldn't write the message trace to {}: {}", path.display(), e),
+    }
+}
+
+/// Segoe UI from the system font folder, falling back to Segoe UI Symbol
+fn load_ui_font() -> Option<(FontStack, GlyphCache)> {
+    let fonts = std::path::Path::new(&std::env::var_os("WINDIR")?).join("Fonts");
+    let load = |name: &str| -> Option<Font> {
+        let data = std::fs::read(fonts.join(name)).ok()?;
+        return Font::from_bytes(data).map_err(|e| println!("Couldn't read {}: {}", name, e)).ok();
+    };
+    let mut stack = FontStack::new(load("segoeui.ttf")?);
+    if let Some(symbols) = load("seguisym.ttf") {
+        stack = stack.fallback(symbols);
+    }
+    return Some((stack, GlyphCache::new()));
+}
+
+/// Where the window placement is kept between runs, next to the executable
+fn placement_path() -> Option<std::path::PathBuf> {
+    return std::env::current_exe().ok().map(|exe| exe.with_extension("placement"));
+}
+
+/// The placement saved by the last run, if there is a readable one
+fn load_placement() -> Option<WindowPlacement> {
+    let text = std::fs::read_to_string(placement_path()?).ok()?;
+    match text.parse::<WindowPlacement>() {
+        Ok(mut placement) => {
+            // Don't start hidden in the task bar
+            placement.minimized = false;
+            return Some(placement);
+        }
+        Err(e) => {
+            println!("Ignoring the saved window placement: {}", e);
+            return None;
+        }
+    }
+}
+
+fn sample_menu() -> Menu {
+    return Menu::new()
+        .submenu("&File", Menu::new()
+            .item(ID_FILE_OPEN, "&Open...").accelerator("Ctrl+O")
+            .item(ID_FILE_CAPTURE, "Save &screenshot").accelerator("F12")
+            .separator()
+            .item(ID_FILE_EXIT, "E&xit").accelerator("Ctrl+Q"))
+        .submenu("&View", Menu::new()
+            .check_item(ID_VIEW_WIREFRAME, "&Wireframe", false).accelerator("Ctrl+W")
+            .separator()
+            .radio_item(ID_VIEW_SHADED, "&Shaded", 1, true)
+            .radio_item(ID_VIEW_TEXTURED, "&Textured", 1, false)
+            .separator()
+            .check_item(ID_VIEW_CUSTOM_FRAME, "Custom &title bar", false).accelerator("Ctrl+T")
+            .check_item(ID_VIEW_FULLSCREEN, "&Fullscreen", false).accelerator("F11"))
+        .submenu("&Help", Menu::new()
+            .item(ID_HELP_ABOUT, "&About").accelerator("F1"));
+}
+
 #[allow(non_snake_case)]
 fn main() {
     let hInstance = win32::wrapper::get_process_handle();
     let sample_window_class = "Sample Window Class";
     let sample_window_class_wn = win32::utils::wide_null(sample_window_class);
 
-    let mut win: window::WNDCLASSW = window::WNDCLASSW::default();
-    win.lpfnWndProc = Some(window_procedure);
-    win.hInstance = hInstance;
-    win.lpszClassName = sample_window_class_wn.as_ptr();
-    win.hCursor = win32::wrapper::load_predefined_cursor(window::IDCursor::Arrow).unwrap();
+    // GLTEST_TRACE=messages.jsonl records the window messages of this run
+    if std::env::var_os(TRACE_ENV_VAR).is_some() {
+        let tracer = MessageTracer::new().filter(TraceFilter::new().exclude_noisy());
+        win32::wrapper::set_message_tracer(Some(tracer));
+    }
+
+    let app_icon = sample_icon();
+    let win = window::WNDCLASSW {
+        lpfnWndProc: Some(window_procedure),
+        hInstance,
+        lpszClassName: sample_window_class_wn.as_ptr(),
+        // The custom title bar buttons move with the right edge
+        style: window::CS_HREDRAW | window::CS_VREDRAW,
+        hCursor: win32::wrapper::load_predefined_cursor(window::IDCursor::Arrow).unwrap(),
+        hIcon: win32::wrapper::create_icon(&app_icon, window::IconSize::Big).unwrap_or(std::ptr::null_mut()),
+        ..Default::default()
+    };
 
     let _atom = unsafe { win32::wrapper::register_class(&win)}.unwrap();
 
-    let lparam: *mut i32 = Box::leak(Box::new(5_i32)); // make this leak so that we can clean it up on window destruction rather than scope
-    let hwnd = unsafe { win32::wrapper::create_app_window(
-        sample_window_class, 
-        "Test Window", 
-        None,
-        [800, 600], 
-        lparam.cast(),
-    )}.unwrap();
+    let mut builder = WindowBuilder::new("Test Window").size(Size::new(800, 600));
+    let mut menu = sample_menu();
+    if let Some(placement) = load_placement() {
+        menu.set_checked(ID_VIEW_FULLSCREEN, placement.fullscreen);
+        builder = builder.placement(placement);
+    }
+
+    let lparam: *mut AppState = Box::leak(Box::new(AppState { // make this leak so that we can clean it up on window destruction rather than scope
+        paint_count: 5,
+        text_input: TextInput::new(DeadKeys::System),
+        events: EventQueue::new(),
+        menu,
+        menu_bar: std::ptr::null_mut(),
+        tray: None,
+        system_events: None,
+        // The empty part of the tool panel drags the window
+        hit_regions: HitRegions::new().region(Rect::new(0, 276, 200, i32::MAX), HitTest::Caption),
+        custom_frame: false,
+        theme: win32::wrapper::current_theme(),
+        ui_font: load_ui_font(),
+        icons: [win.hIcon].into_iter().filter(|icon| !icon.is_null()).collect(),
+    }));
+    let hwnd = unsafe { win32::wrapper::create_window(sample_window_class, &builder, lparam.cast()) }.unwrap();
+
+    match unsafe { win32::wrapper::set_window_icons(hwnd, &app_icon) } {
+        Ok((small, big)) => unsafe { (*lparam).icons.extend([small, big]) },
+        Err(e) => println!("Couldn't set the window icons: {}", e.0),
+    }
+
+    if let Err(e) = unsafe { win32::wrapper::add_clipboard_listener(hwnd) } {
+        println!("Couldn't listen for clipboard changes: {}", e.0);
+    }
+
+    match win32::wrapper::ole_initialize() {
+        Ok(()) => {
+            let drop_result = unsafe { win32::wrapper::register_drop_target(hwnd, move |event| {
+                if let Ok(state) = win32::wrapper::get_window_userdata::<AppState>(hwnd) {
+                    if !state.is_null() {
+                        (*state).events.push(WindowEvent::DragDrop(event));
+                    }
+                }
+            })};
+            if let Err(e) = drop_result {
+                println!("Couldn't register the drop target: {}", e.0);
+            }
+        }
+        Err(e) => println!("Couldn't initialize OLE, drag and drop is disabled: {}", e.0),
+    }
+
+    match win32::wrapper::create_menu(unsafe { &(*lparam).menu }, true) {
+        Ok(menu_bar) => {
+            unsafe { (*lparam).menu_bar = menu_bar };
+            if let Err(e) = unsafe { win32::wrapper::set_window_menu(hwnd, menu_bar) } {
+                println!("Couldn't attach the menu bar: {}", e.0);
+            }
+        }
+        Err(e) => println!("Couldn't create the menu bar: {}", e.0),
+    }
+
+    if let Err(e) = unsafe { create_sample_controls(hwnd) } {
+        println!("Couldn't create the controls: {}", e.0);
+    }
+
+    let tray = TrayIcon::new(app_icon.clone())
+        .tooltip("gltest sample window")
+        .menu(Menu::new()
+            .item(ID_TRAY_SHOW, "&Show window")
+            .separator()
+            .item(ID_FILE_EXIT, "E&xit"));
+    match unsafe { win32::wrapper::Win32TrayIcon::add(hwnd, TRAY_ID, &tray) } {
+        Ok(mut tray_icon) => {
+            let hello = Notification::new("gltest", "Running in the notification area")
+                .icon(NotificationIcon::App)
+                .silent();
+            if let Err(e) = tray_icon.notify(&hello) {
+                println!("Couldn't show the notification: {}", e.0);
+            }
+            unsafe { (*lparam).tray = Some(tray_icon) };
+        }
+        Err(e) => println!("Couldn't add the tray icon: {}", e.0),
+    }
+
+    if let Err(e) = unsafe { win32::wrapper::set_dark_title_bar(hwnd, (*lparam).theme.is_dark()) } {
+        println!("Couldn't switch the title bar to the {:?} scheme: {}", unsafe { &(*lparam).theme.scheme }, e.0);
+    }
+
+    match unsafe { win32::wrapper::Win32SystemEvents::register(hwnd) } {
+        Ok(system_events) => unsafe { (*lparam).system_events = Some(system_events) },
+        Err(e) => println!("Couldn't subscribe to system events: {}", e.0),
+    }
+
+    // The shortcuts shown in the menu
+    let mut accelerators = std::ptr::null_mut();
+    match AcceleratorTable::from_menu(unsafe { &(*lparam).menu }) {
+        Ok(table) => {
+            for conflict in table.conflicts() {
+                println!("Shortcut conflict: {}", conflict);
+            }
+            match win32::wrapper::create_accelerator_table(&table) {
+                Ok((haccel, skipped)) => {
+                    accelerators = haccel;
+                    for (accelerator, command) in skipped {
+                        println!("Shortcut {} (command {:?}) can't be an accelerator", accelerator, command);
+                    }
+                }
+                Err(e) => println!("Couldn't create the accelerator table: {}", e.0),
+            }
+        }
+        Err(e) => println!("Bad shortcut in the menu: {}", e),
+    }
 
-    let _previously_visible = unsafe { window::ShowWindow(hwnd, window::SW_SHOW) };
+    let show_shortcut = "Ctrl+Alt+Shift+G".parse().expect("the shortcut is well formed");
+    if let Err(e) = unsafe { win32::wrapper::register_global_shortcut(hwnd, ID_GLOBAL_SHOW, &show_shortcut) } {
+        println!("Couldn't register the global shortcut {}: {}", show_shortcut, e.0);
+    }
 
     // let mut msg = window::MSG::default();
     loop {
         match win32::wrapper::get_any_message() {
             Ok(msg) => {
                 if msg.message == window::WM_QUIT {
+                    unsafe { win32::wrapper::destroy_accelerator_table(accelerators) };
+                    save_message_trace();
                     std::process::exit(msg.wParam as i32);
                 }
-                win32::wrapper::translate_message(&msg);
-                unsafe {
-                    // window::TranslateMessage(&msg);
-                    window::DispatchMessageW(&msg);
+                // Shortcuts become WM_COMMAND and must not also be typed as text
+                if !unsafe { win32::wrapper::translate_accelerator(hwnd, accelerators, &msg) } {
+                    win32::wrapper::translate_message(&msg);
+                    unsafe {
+                        // window::TranslateMessage(&msg);
+                        window::DispatchMessageW(&msg);
+                    }
+                }
+
+                // The window procedure only queues events, we handle them here
+                match unsafe { win32::wrapper::get_window_userdata::<AppState>(hwnd) } {
+                    Ok(state) if !state.is_null() => {
+                        // Handling an event can queue more, e.g. a context menu choice
+                        while let Some(event) = unsafe { (*state).events.pop() } {
+                            handle_event(hwnd, unsafe { &mut *state }, event);
+                        }
+                    }
+                    _ => {}
                 }
             }
             Err(e) => panic!("Error when getting msg from message queue: {}", e),
@@ -61,6 +334,231 @@ fn main() {
     }
 }
 
+/// A small tool panel on the left of the window
+unsafe fn create_sample_controls(hwnd: win32::types::HWND) -> Result<(), win32::wrapper::Win32Error> {
+    // Below where the custom title bar goes
+    win32::wrapper::create_control(hwnd, ID_NAME_EDIT, ControlKind::Edit, "Untitled", Rect::new(10, 40, 190, 64))?;
+    let scenes = win32::wrapper::create_control(hwnd, ID_SCENE_LIST, ControlKind::ListBox, "", Rect::new(10, 74, 190, 194))?;
+    for scene in ["Cube", "Teapot", "Sponza"] {
+        win32::wrapper::list_box_add(scenes, scene)?;
+    }
+    win32::wrapper::list_box_select(scenes, Some(0));
+    let vsync = win32::wrapper::create_control(hwnd, ID_VSYNC_CHECK, ControlKind::CheckBox, "&Vsync", Rect::new(10, 204, 190, 228))?;
+    win32::wrapper::set_checked(vsync, true);
+    win32::wrapper::create_control(hwnd, ID_RENDER_BUTTON, ControlKind::Button, "&Render", Rect::new(10, 238, 190, 266))?;
+    return Ok(());
+}
+
+/// Where the parts of the custom title bar and frame are
+fn custom_frame_hit_test(point: Point, size: Size, maximized: bool) -> HitTest {
+    // Maximized windows can't be resized
+    if !maximized {
+        if let Some(edge) = frame_edge(point, size, RESIZE_BORDER) {
+            return HitTest::Border(edge);
+        }
+    }
+    let Point { x, y } = point;
+    if y >= TITLE_BAR_HEIGHT {
+        return HitTest::Default;
+    }
+    match (size.width - x - 1) / TITLE_BUTTON_WIDTH {
+        0 => return HitTest::CloseButton,
+        1 => return HitTest::MaximizeButton,
+        2 => return HitTest::MinimizeButton,
+        _ if x < TITLE_BAR_HEIGHT => return HitTest::SystemMenu,
+        _ => return HitTest::Caption,
+    }
+}
+
+/// A dark title bar with the icon and the three buttons as plain boxes
+unsafe fn paint_custom_title_bar(
+    hdc: win32::types::HDC, width: i32, theme: &Theme,
+) -> Result<(), win32::wrapper::Win32Error> {
+    win32::wrapper::fill_rect_with_color(hdc, Rect::new(0, 0, width, TITLE_BAR_HEIGHT), theme.color(SysColor::ActiveCaption))?;
+    win32::wrapper::fill_rect_with_color(hdc, Rect::new(8, 8, 24, 24), theme.accent_or_highlight())?;
+    let button = theme.color(SysColor::ButtonFace);
+    let buttons = [button, button, [196, 43, 28]];
+    for (i, color) in buttons.into_iter().enumerate() {
+        let x = width - TITLE_BUTTON_WIDTH * (3 - i as i32);
+        win32::wrapper::fill_rect_with_color(hdc, Rect::new(x + 1, 0, x + TITLE_BUTTON_WIDTH, TITLE_BAR_HEIGHT), color)?;
+    }
+    return Ok(());
+}
+
+/// Where the canvas preview is drawn in the client area
+const PREVIEW_RECT: Rect = Rect { left: 220, top: 40, right: 460, bottom: 224 };
+
+/// A few shapes drawn in software, shown next to the controls
+fn sample_canvas(theme: &Theme, ui_font: Option<&mut (FontStack, GlyphCache)>) -> Canvas {
+    let mut canvas = Canvas::new(PREVIEW_RECT.width() as u32, PREVIEW_RECT.height() as u32);
+    canvas.clear(Color::from(theme.color(SysColor::Window)));
+    let text = Color::from(theme.color(SysColor::WindowText));
+    let accent = Color::from(theme.accent_or_highlight());
+
+    canvas.stroke_rect(RectF::new(0.5, 0.5, 239.5, 183.5), 1.0, text.with_alpha(96));
+    canvas.fill_rounded_rect(RectF::new(12.0, 12.0, 108.0, 68.0), 10.0, accent);
+    let font = BitmapFont::builtin();
+    let label = Color::from(theme.color(SysColor::HighlightText));
+    font.draw_wrapped(&mut canvas, PointF::new(20.0, 18.0), "Built-in font: àéîõü ÇÑß ¿¡ 1/2 ½", 80, label);
+    canvas.fill_circle(PointF::new(170.0, 44.0), 30.0, Color::rgba(220, 60, 60, 200));
+    canvas.stroke_ellipse(PointF::new(170.0, 44.0), SizeF::new(44.0, 18.0), 3.0, text);
+    let points = [[12.0, 140.0], [60.0, 96.0], [108.0, 128.0], [156.0, 88.0]].map(PointF::from);
+    canvas.polyline(&points, 4.0, accent.with_alpha(160));
+
+    // Anti-aliased curves: a dashed wave and a star with an even-odd hole
+    let wave = Path::new().move_to([12.0, 84.0]).cubic_to([52.0, 60.0], [72.0, 112.0], [120.0, 84.0]);
+    let dashed = Stroke::new(2.5).cap(LineCap::Round).join(LineJoin::Round).dashes(&[8.0, 5.0], 0.0);
+    canvas.stroke_path(&wave, &dashed, text);
+    let star = (0..5).fold(Path::new(), |star, i| {
+        let angle = std::f32::consts::PI * (0.8 * i as f32 - 0.5);
+        let point = [136.0 + 16.0 * angle.cos(), 118.0 + 16.0 * angle.sin()];
+        if i == 0 { star.move_to(point) } else { star.line_to(point) }
+    }).close();
+    canvas.fill_path(&star, FillRule::EvenOdd, accent);
+
+    // A rotated square, cut by a clip rectangle
+    canvas.save();
+    canvas.clip(Rect::new(160, 90, 224, 146));
+    canvas.translate(196.0, 118.0);
+    canvas.rotate(std::f32::consts::PI / 6.0);
+    canvas.fill_rect(RectF::new(-24.0, -24.0, 24.0, 24.0), Color::rgb(60, 160, 90));
+    canvas.restore();
+
+    if let Some((fonts, cache)) = ui_font {
+        cache.draw(&mut canvas, fonts, "AV Wave — kerning, ünïcödé ✓", PointF::new(8.0, 158.0), 15.0, text);
+    }
+    return canvas;
+}
+
+/// A simple generated icon: a diagonal gradient inside a rounded square
+fn sample_icon() -> Icon {
+    let size = 32_u32;
+    let mut rgba = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let corner = |c: u32| if c < 4 { 4 - c } else if c >= size - 4 { c - (size - 5) } else { 0 };
+            let inside = corner(x) * corner(x) + corner(y) * corner(y) <= 16;
+            let t = (x + y) * 255 / (2 * size - 2);
+            rgba.extend_from_slice(&[t as u8, 96, 255 - t as u8, if inside { 255 } else { 0 }]);
+        }
+    }
+    return Icon::from_rgba(size, size, rgba).expect("the sample icon is well formed");
+}
+
+fn handle_event(hwnd: win32::types::HWND, state: &mut AppState, event: WindowEvent) {
+    match event {
+        WindowEvent::Text(c) => println!("Text input: {:?}", c),
+        WindowEvent::DeadKey(c) => println!("Dead key: {:?}", c),
+        WindowEvent::Ime(ime) => println!("IME: {:?}", ime),
+        WindowEvent::DragDrop(drag) => println!("Drag and drop: {:?}", drag),
+        WindowEvent::Menu(id) | WindowEvent::Accelerator(id) => {
+            for changed in state.menu.activate(id) {
+                let _ = unsafe { win32::wrapper::sync_menu_item(hwnd, state.menu_bar, &state.menu, changed) };
+            }
+            match id {
+                ID_FILE_OPEN => {
+                    let dialog = FileDialog::new()
+                        .title("Open a model")
+                        .filter("Models", &["*.obj", "*.gltf", "*.glb"])
+                        .filter("All files", &["*.*"]);
+                    match unsafe { win32::wrapper::open_file_dialog(hwnd, &dialog) } {
+                        Ok(Some(path)) => println!("Open {}", path.display()),
+                        Ok(None) => println!("Open cancelled"),
+                        Err(e) => println!("Couldn't show the open dialog: {}", e.0),
+                    }
+                }
+                ID_FILE_CAPTURE => {
+                    let path = std::env::temp_dir().join("gltest-capture.png");
+                    match unsafe { win32::wrapper::capture_window(hwnd) } {
+                        Ok(image) => match image.save(&path) {
+                            Ok(()) => println!("Saved a capture to {}", path.display()),
+                            Err(e) => println!("Couldn't save the capture: {}", e),
+                        },
+                        Err(e) => println!("Couldn't capture the window: {}", e.0),
+                    }
+                }
+                ID_VIEW_CUSTOM_FRAME => unsafe {
+                    state.custom_frame = state.menu.find(id).is_some_and(|item| item.is_checked());
+                    // The menu bar is part of the frame that goes away
+                    let menu_bar = if state.custom_frame { std::ptr::null_mut() } else { state.menu_bar };
+                    let _ = win32::wrapper::set_window_menu(hwnd, menu_bar);
+                    if let Err(e) = win32::wrapper::refresh_window_frame(hwnd) {
+                        println!("Couldn't switch the title bar: {}", e.0);
+                    }
+                },
+                ID_VIEW_FULLSCREEN => unsafe {
+                    let fullscreen = state.menu.find(id).is_some_and(|item| item.is_checked());
+                    if let Err(e) = win32::wrapper::set_fullscreen(hwnd, fullscreen) {
+                        println!("Couldn't switch fullscreen: {}", e.0);
+                    }
+                },
+                ID_TRAY_SHOW => unsafe {
+                    window::ShowWindow(hwnd, window::SW_SHOW);
+                    window::SetForegroundWindow(hwnd);
+                },
+                ID_FILE_EXIT => unsafe {
+                    window::PostMessageW(hwnd, window::WM_CLOSE, 0, 0);
+                },
+                ID_HELP_ABOUT => {
+                    let about = MessageBox::new("About", "gltest sample window")
+                        .icon(MessageIcon::Information);
+                    let _ = unsafe { win32::wrapper::message_box(hwnd, &about) };
+                }
+                _ => println!("Menu item {:?}, checked: {:?}", id, state.menu.find(id).map(|item| item.is_checked())),
+            }
+        }
+        WindowEvent::Tray { event: TrayEvent::LeftClick { .. } | TrayEvent::DoubleClick { .. }, .. }
+        | WindowEvent::GlobalShortcut(ID_GLOBAL_SHOW) => unsafe {
+            window::ShowWindow(hwnd, window::SW_SHOW);
+            window::SetForegroundWindow(hwnd);
+        },
+        WindowEvent::GlobalShortcut(id) => println!("Global shortcut {:?}", id),
+        WindowEvent::Tray { id, event } => println!("Tray icon {:?}: {:?}", id, event),
+        WindowEvent::Control { id: ID_RENDER_BUTTON, event: ControlEvent::Clicked } => unsafe {
+            let name = win32::wrapper::get_control(hwnd, ID_NAME_EDIT)
+                .map(|edit| win32::wrapper::get_window_text(edit))
+                .unwrap_or_default();
+            let scene = win32::wrapper::get_control(hwnd, ID_SCENE_LIST)
+                .and_then(|list| win32::wrapper::list_box_selection(list));
+            println!("Render {:?} with scene {:?}", name, scene);
+        },
+        WindowEvent::Control { id, event } => println!("Control {:?}: {:?}", id, event),
+        WindowEvent::ContextMenu { position } => {
+            let context_menu = Menu::new()
+                .item(ID_EDIT_COPY, "&Copy")
+                .item(ID_EDIT_PASTE, "&Paste");
+            match unsafe { win32::wrapper::show_context_menu(hwnd, &context_menu, position.unwrap_or_default()) } {
+                // Chosen items are handled like the menu bar's
+                Ok(Some(id)) => state.events.push(WindowEvent::Menu(id)),
+                Ok(None) => {}
+                Err(e) => println!("Couldn't show the context menu: {}", e.0),
+            }
+        }
+        WindowEvent::System(SystemEvent::Suspend | SystemEvent::LowMemory) => {
+            println!("Dropping cached GPU resources");
+        }
+        WindowEvent::System(SystemEvent::ThemeChanged) => {
+            state.theme = win32::wrapper::current_theme();
+            println!("Theme changed to {:?}, accent {:?}", state.theme.scheme, state.theme.accent);
+            if let Err(e) = unsafe { win32::wrapper::set_dark_title_bar(hwnd, state.theme.is_dark()) } {
+                println!("Couldn't switch the title bar: {}", e.0);
+            }
+            // Every colour may have changed
+            if let Err(e) = unsafe { win32::wrapper::request_redraw(hwnd, None) } {
+                println!("Couldn't repaint the window: {}", e.0);
+            }
+        }
+        WindowEvent::System(event) => println!("System event: {:?}", event),
+        WindowEvent::ClipboardChanged => {
+            match win32::wrapper::Win32Clipboard::new(hwnd).get_text() {
+                Ok(Some(text)) => println!("Clipboard text: {:?}", text),
+                Ok(None) => println!("Clipboard changed (no text)"),
+                Err(e) => println!("Couldn't read the clipboard: {}", e),
+            }
+        }
+    }
+}
+
 #[allow(non_snake_case)]
 pub unsafe extern "system" fn window_procedure(
     hWnd: win32::types::HWND,
@@ -68,6 +566,66 @@ pub unsafe extern "system" fn window_procedure(
     wParam: win32::types::WPARAM,
     lParam: win32::types::LPARAM,
 ) -> win32::types::LRESULT {
+    return win32::wrapper::trace_message(hWnd, Msg, wParam, lParam, || handle_window_message(hWnd, Msg, wParam, lParam));
+}
+
+#[allow(non_snake_case)]
+unsafe fn handle_window_message(
+    hWnd: win32::types::HWND,
+    Msg: win32::types::UINT,
+    wParam: win32::types::WPARAM,
+    lParam: win32::types::LPARAM,
+) -> win32::types::LRESULT {
+    if let Ok(state) = win32::wrapper::get_window_userdata::<AppState>(hWnd) {
+        if !state.is_null() {
+            let state = &mut *state;
+            if state.custom_frame {
+                if let Some(result) = win32::wrapper::handle_custom_frame_message(hWnd, Msg, wParam, lParam) {
+                    return result;
+                }
+            }
+            let hit_regions = &state.hit_regions;
+            let custom_frame = state.custom_frame;
+            let hit_test = |point| {
+                if custom_frame {
+                    let size = win32::wrapper::get_client_size(hWnd).unwrap_or_default();
+                    let hit = custom_frame_hit_test(point, size, win32::wrapper::is_maximized(hWnd));
+                    if hit != HitTest::Default {
+                        return hit;
+                    }
+                }
+                return hit_regions.test(point);
+            };
+            if let Some(result) = win32::wrapper::handle_hit_test_message(hWnd, Msg, lParam, hit_test) {
+                return result;
+            }
+            if let Some(tray) = &mut state.tray {
+                if let Some(result) = tray.handle_message(Msg, wParam, lParam, &mut state.events) {
+                    return result;
+                }
+            }
+            if let Some(system_events) = &mut state.system_events {
+                if let Some(result) = system_events.handle_message(Msg, wParam, lParam, &mut state.events) {
+                    return result;
+                }
+            }
+            if let Some(result) = win32::wrapper::handle_text_input_message(
+                hWnd, Msg, wParam, lParam, &mut state.text_input, &mut state.events,
+            ) {
+                return result;
+            }
+            if let Some(result) = win32::wrapper::handle_menu_message(hWnd, Msg, wParam, lParam, &mut state.events) {
+                return result;
+            }
+            if let Some(result) = win32::wrapper::handle_shortcut_message(Msg, wParam, lParam, &mut state.events) {
+                return result;
+            }
+            if let Some(result) = win32::wrapper::handle_control_message(Msg, wParam, lParam, &mut state.events) {
+                return result;
+            }
+        }
+    }
+
     match Msg {
         // TODO: Set the title of the window in one of the creation events
         window::WM_NCCREATE => {
@@ -76,15 +634,58 @@ pub unsafe extern "system" fn window_procedure(
             if createstruct.is_null() {
                 return 0;
             }
-            let ptr = (*createstruct).lpCreateParams as *mut i32;
+            let ptr = (*createstruct).lpCreateParams as *mut AppState;
             return win32::wrapper::set_window_userdata(hWnd, ptr).is_ok() as win32::types::LRESULT;
         }
         window::WM_CREATE => println!("CREATE"),
-        window::WM_CLOSE => drop(window::DestroyWindow(hWnd)),
+        window::WM_CLIPBOARDUPDATE => {
+            if let Ok(state) = win32::wrapper::get_window_userdata::<AppState>(hWnd) {
+                if !state.is_null() {
+                    (*state).events.push(WindowEvent::ClipboardChanged);
+                }
+            }
+        }
+        window::WM_CLOSE => {
+            let confirm = MessageBox::new("Test Window", "Really quit?")
+                .buttons(MessageButtons::OkCancel)
+                .icon(MessageIcon::Question);
+            match win32::wrapper::message_box(hWnd, &confirm) {
+                Ok(MessageResult::Ok) => {
+                    window::DestroyWindow(hWnd);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    println!("Couldn't ask before closing, closing anyway: {}", e.0);
+                    window::DestroyWindow(hWnd);
+                }
+            }
+        }
         window::WM_DESTROY => {
-            match win32::wrapper::get_window_userdata::<i32>(hWnd) {
+            match (win32::wrapper::get_window_placement(hWnd), placement_path()) {
+                (Ok(placement), Some(path)) => {
+                    if let Err(e) = std::fs::write(&path, placement.to_string()) {
+                        println!("Couldn't save the window placement to {}: {}", path.display(), e);
+                    }
+                }
+                (Err(e), _) => println!("Couldn't read the window placement: {}", e.0),
+                (Ok(_), None) => {}
+            }
+            // After saving the placement, which remembers fullscreen
+            win32::wrapper::handle_fullscreen_message(hWnd, Msg);
+            let _ = win32::wrapper::revoke_drop_target(hWnd);
+            let _ = win32::wrapper::unregister_global_shortcut(hWnd, ID_GLOBAL_SHOW);
+            match win32::wrapper::get_window_userdata::<AppState>(hWnd) {
                 Ok(ptr) if !ptr.is_null() => {
-                    let _dropped = Box::from_raw(ptr);
+                    // Clear the pointer first so nothing can use it after the free
+                    let _ = win32::wrapper::set_window_userdata::<AppState>(hWnd, std::ptr::null_mut());
+                    let state = Box::from_raw(ptr);
+                    // Nothing may use the icons once they are destroyed
+                    let _ = win32::wrapper::set_class_icon(hWnd, window::IconSize::Big, std::ptr::null_mut());
+                    win32::wrapper::set_window_icon(hWnd, window::IconSize::Small, std::ptr::null_mut());
+                    win32::wrapper::set_window_icon(hWnd, window::IconSize::Big, std::ptr::null_mut());
+                    for &icon in &state.icons {
+                        let _ = win32::wrapper::destroy_icon(icon);
+                    }
                     println!("Cleaned up the box");
                 }
                 Ok(_) => {
@@ -101,10 +702,10 @@ pub unsafe extern "system" fn window_procedure(
             // window::PostQuitMessage(0_i32);
         }
         window::WM_PAINT => {
-            match win32::wrapper::get_window_userdata::<i32>(hWnd) {
+            match win32::wrapper::get_window_userdata::<AppState>(hWnd) {
                 Ok(ptr) if !ptr.is_null()  => {
-                    println!("Current ptr: {}", *ptr);
-                    *ptr += 1;
+                    println!("Current paint count: {}", (*ptr).paint_count);
+                    (*ptr).paint_count += 1;
                 }
                 Ok(_) => {
                     println!("userdata pointer is null");
@@ -113,13 +714,31 @@ pub unsafe extern "system" fn window_procedure(
                     println!("Error while getting userdata pointer: {}", e);
                 }
             }
-            win32::wrapper::paint_window(hWnd, |hdc, _erase_bg, target_rect| {
-                let _ = win32::wrapper::fill_rect_with_sys_color(hdc, &target_rect, window::SysColor::Window);
+            let (custom_frame, theme, mut ui_font) = match win32::wrapper::get_window_userdata::<AppState>(hWnd) {
+                Ok(ptr) if !ptr.is_null() => ((*ptr).custom_frame, (*ptr).theme.clone(), (*ptr).ui_font.take()),
+                _ => (false, Theme::default(), None),
+            };
+            win32::wrapper::paint_window(hWnd, |hdc, _erase_bg, target_rect, damaged| {
+                // The theme rather than the system colour, which stays light in dark mode
+                let _ = win32::wrapper::fill_rect_with_color(hdc, target_rect, theme.color(SysColor::Window));
+                let width = win32::wrapper::get_client_size(hWnd)?.width;
+                // Only what intersects the damage needs drawing again
+                if custom_frame && damaged.intersects(&Rect::new(0, 0, width, TITLE_BAR_HEIGHT)) {
+                    paint_custom_title_bar(hdc, width, &theme)?;
+                }
+                if damaged.intersects(&PREVIEW_RECT) {
+                    win32::wrapper::draw_canvas(hdc, PREVIEW_RECT.origin(), &sample_canvas(&theme, ui_font.as_mut()))?;
+                }
                 return Ok(());
             }).unwrap_or_else(|e| println!("error during painting {}", e));
+            if let Ok(ptr) = win32::wrapper::get_window_userdata::<AppState>(hWnd) {
+                if !ptr.is_null() {
+                    (*ptr).ui_font = ui_font;
+                }
+            }
             // match win32::wrapper::begin_paint(hWnd) {
             //     Ok((hdc, ps)) => {
-            //         let _ = win32::wrapper::fill_rect_with_sys_color(hdc, &ps.rcPaint, window::SysColor::Window);
+            //         let _ = win32::wrapper::fill_rect_with_sys_color(hdc, &ps.rcPaint, SysColor::Window);
             //         win32::wrapper::end_paint(hWnd, &ps);
             //     }
             //     Err(e) => {
@@ -0,0 +1,276 @@
+//! Menu bars and context menus
+//!
+//! A [`Menu`] is a plain description of the items. Backends turn it into
+//! native menus and report the [`MenuId`] of the chosen item as a
+//! [`crate::event::WindowEvent::Menu`] event. Check and radio state lives
+//! in the `Menu`; after [`Menu::activate`] changes it the backend syncs the
+//! native menu.
+
+/// Application assigned identifier of a menu item
+///
+/// Win32 reports selections with 16 bits, so that is the range we support.
+/// Avoid `0`, which some controls use to mean "no item".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MenuId(pub u16);
+
+/// What kind of item a [`MenuItem`] is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MenuItemKind {
+    /// A plain command
+    Action,
+
+    /// An item that toggles a check mark when chosen
+    Check { checked: bool },
+
+    /// One of a group of mutually exclusive items. Items with the same
+    /// `group` anywhere in the menu belong together.
+    Radio { group: u32, checked: bool },
+
+    /// Opens another menu
+    Submenu(Menu),
+
+    /// A dividing line
+    Separator,
+}
+
+/// An entry of a [`Menu`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MenuItem {
+    /// Identifier reported when the item is chosen. Unused by separators and submenus.
+    pub id: MenuId,
+    /// Text of the item. `&` marks the next letter as the mnemonic (`"&File"`).
+    pub label: String,
+    /// Shortcut text shown on the right, e.g. `"Ctrl+S"`. Only a hint: the
+    /// shortcut itself has to be registered as an accelerator, see
+    /// [`crate::accelerator::AcceleratorTable::from_menu`].
+    pub accelerator: Option<String>,
+    pub enabled: bool,
+    pub kind: MenuItemKind,
+}
+
+impl MenuItem {
+    /// `true` if the item shows a check mark or radio dot
+    pub fn is_checked(&self) -> bool {
+        match self.kind {
+            MenuItemKind::Check { checked } | MenuItemKind::Radio { checked, .. } => return checked,
+            _ => return false,
+        }
+    }
+}
+
+/// A menu bar, drop down menu or context menu
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Menu {
+    items: Vec<MenuItem>,
+}
+
+impl Menu {
+    pub fn new() -> Self {
+        return Menu::default();
+    }
+
+    pub fn items(&self) -> &[MenuItem] {
+        return &self.items;
+    }
+
+    fn push(mut self, id: MenuId, label: &str, kind: MenuItemKind) -> Self {
+        self.items.push(MenuItem {
+            id,
+            label: label.to_owned(),
+            accelerator: None,
+            enabled: true,
+            kind,
+        });
+        return self;
+    }
+
+    /// Adds a plain command
+    pub fn item(self, id: MenuId, label: &str) -> Self {
+        return self.push(id, label, MenuItemKind::Action);
+    }
+
+    /// Adds an item with a check mark
+    pub fn check_item(self, id: MenuId, label: &str, checked: bool) -> Self {
+        return self.push(id, label, MenuItemKind::Check { checked });
+    }
+
+    /// Adds a radio item to `group`
+    pub fn radio_item(self, id: MenuId, label: &str, group: u32, checked: bool) -> Self {
+        return self.push(id, label, MenuItemKind::Radio { group, checked });
+    }
+
+    pub fn separator(self) -> Self {
+        return self.push(MenuId(0), "", MenuItemKind::Separator);
+    }
+
+    pub fn submenu(self, label: &str, menu: Menu) -> Self {
+        return self.push(MenuId(0), label, MenuItemKind::Submenu(menu));
+    }
+
+    /// Sets the shortcut text of the item added last
+    pub fn accelerator(mut self, accelerator: &str) -> Self {
+        if let Some(item) = self.items.last_mut() {
+            item.accelerator = Some(accelerator.to_owned());
+        }
+        return self;
+    }
+
+    /// Disables the item added last
+    pub fn disabled(mut self) -> Self {
+        if let Some(item) = self.items.last_mut() {
+            item.enabled = false;
+        }
+        return self;
+    }
+
+    /// Finds an item by id, searching submenus too
+    pub fn find(&self, id: MenuId) -> Option<&MenuItem> {
+        for item in &self.items {
+            if let MenuItemKind::Submenu(menu) = &item.kind {
+                if let Some(found) = menu.find(id) {
+                    return Some(found);
+                }
+            } else if item.id == id && item.kind != MenuItemKind::Separator {
+                return Some(item);
+            }
+        }
+        return None;
+    }
+
+    fn for_each_mut(&mut self, f: &mut impl FnMut(&mut MenuItem)) {
+        for item in &mut self.items {
+            if let MenuItemKind::Submenu(menu) = &mut item.kind {
+                menu.for_each_mut(f);
+            }
+            f(item);
+        }
+    }
+
+    /// Enables or disables an item
+    ///
+    /// **Returns:** `false` if there is no such item
+    pub fn set_enabled(&mut self, id: MenuId, enabled: bool) -> bool {
+        let mut found = false;
+        self.for_each_mut(&mut |item| {
+            if item.id == id && !matches!(item.kind, MenuItemKind::Separator | MenuItemKind::Submenu(_)) {
+                item.enabled = enabled;
+                found = true;
+            }
+        });
+        return found;
+    }
+
+    /// Checks or unchecks an item. Checking a radio item unchecks the
+    /// rest of its group; radio items can't be unchecked directly.
+    ///
+    /// **Returns:** the ids whose check state changed
+    pub fn set_checked(&mut self, id: MenuId, checked: bool) -> Vec<MenuId> {
+        let group = match self.find(id).map(|item| &item.kind) {
+            Some(MenuItemKind::Radio { group, .. }) if checked => Some(*group),
+            Some(MenuItemKind::Radio { .. }) => return Vec::new(),
+            Some(MenuItemKind::Check { .. }) => None,
+            _ => return Vec::new(),
+        };
+
+        let mut changed = Vec::new();
+        self.for_each_mut(&mut |item| {
+            match (&mut item.kind, group) {
+                (MenuItemKind::Check { checked: current }, None) if item.id == id && *current != checked => {
+                    *current = checked;
+                    changed.push(item.id);
+                }
+                (MenuItemKind::Radio { group: g, checked: current }, Some(group)) if *g == group => {
+                    let wanted = item.id == id;
+                    if *current != wanted {
+                        *current = wanted;
+                        changed.push(item.id);
+                    }
+                }
+                _ => {}
+            }
+        });
+        return changed;
+    }
+
+    /// Applies the effect of the user choosing an item: check items
+    /// toggle and radio items become the selected one of their group
+    ///
+    /// **Returns:** the ids whose check state changed
+    pub fn activate(&mut self, id: MenuId) -> Vec<MenuId> {
+        match self.find(id) {
+            Some(item) if !item.enabled => return Vec::new(),
+            Some(MenuItem { kind: MenuItemKind::Check { checked }, .. }) => {
+                let checked = !*checked;
+                return self.set_checked(id, checked);
+            }
+            Some(MenuItem { kind: MenuItemKind::Radio { .. }, .. }) => return self.set_checked(id, true),
+            _ => return Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A view menu with a radio group split over the top level and a submenu
+    fn menu() -> Menu {
+        let zoom = Menu::new()
+            .radio_item(MenuId(3), "&Large", 1, false)
+            .radio_item(MenuId(4), "&Huge", 1, false)
+            .disabled()
+            .separator()
+            .item(MenuId(5), "&Reset");
+        return Menu::new()
+            .check_item(MenuId(1), "&Grid", false)
+            .accelerator("Ctrl+G")
+            .radio_item(MenuId(2), "&Small", 1, true)
+            .radio_item(MenuId(6), "&Lit", 2, true)
+            .submenu("&Zoom", zoom);
+    }
+
+    fn checked(menu: &Menu) -> Vec<u16> {
+        return (1..=6).filter(|&id| menu.find(MenuId(id)).is_some_and(MenuItem::is_checked)).collect();
+    }
+
+    #[test]
+    fn items_are_found_in_submenus() {
+        let menu = menu();
+        assert_eq!(menu.find(MenuId(1)).and_then(|item| item.accelerator.as_deref()), Some("Ctrl+G"));
+        assert_eq!(menu.find(MenuId(5)).map(|item| item.label.as_str()), Some("&Reset"));
+        assert!(!menu.find(MenuId(4)).unwrap().enabled);
+        // Separators and submenus share id 0 but aren't items to find
+        assert_eq!(menu.find(MenuId(0)), None);
+        assert_eq!(menu.find(MenuId(7)), None);
+    }
+
+    #[test]
+    fn checking_a_radio_item_unchecks_its_group() {
+        let mut menu = menu();
+        assert_eq!(checked(&menu), vec![2, 6]);
+        assert_eq!(menu.set_checked(MenuId(3), true), vec![MenuId(2), MenuId(3)]);
+        assert_eq!(checked(&menu), vec![3, 6]);
+        // Checking the checked item changes nothing, and unchecking is refused
+        assert_eq!(menu.set_checked(MenuId(3), true), Vec::<MenuId>::new());
+        assert_eq!(menu.set_checked(MenuId(3), false), Vec::<MenuId>::new());
+        assert_eq!(menu.set_checked(MenuId(5), true), Vec::<MenuId>::new());
+        assert_eq!(checked(&menu), vec![3, 6]);
+    }
+
+    #[test]
+    fn activating_toggles_checks_and_selects_radios() {
+        let mut menu = menu();
+        assert_eq!(menu.activate(MenuId(1)), vec![MenuId(1)]);
+        assert_eq!(menu.activate(MenuId(3)), vec![MenuId(2), MenuId(3)]);
+        assert_eq!(checked(&menu), vec![1, 3, 6]);
+        assert_eq!(menu.activate(MenuId(1)), vec![MenuId(1)]);
+        // Disabled items and plain commands keep their state
+        assert_eq!(menu.activate(MenuId(4)), Vec::<MenuId>::new());
+        assert_eq!(menu.activate(MenuId(5)), Vec::<MenuId>::new());
+        assert_eq!(checked(&menu), vec![3, 6]);
+
+        assert!(menu.set_enabled(MenuId(4), true));
+        assert_eq!(menu.activate(MenuId(4)), vec![MenuId(3), MenuId(4)]);
+        assert!(!menu.set_enabled(MenuId(0), false));
+    }
+}
@@ -0,0 +1,717 @@
+//! Vector paths and stroking
+//!
+//! A [`Path`] is a list of contours made of straight lines, quadratic and
+//! cubic Bézier curves and circular arcs. Paths are filled with a
+//! [`FillRule`](crate::raster::FillRule) or stroked with a [`Stroke`]: a
+//! width, how corners are joined, how open ends are capped and an optional
+//! dash pattern. [`crate::canvas::Canvas::fill_path`] and
+//! [`crate::canvas::Canvas::stroke_path`] draw them anti-aliased.
+//!
+//! Curves are approximated by straight segments ([`Path::flatten`]), and
+//! a stroke is turned into polygons that cover it ([`Stroke::outline`]);
+//! both happen before rasterizing.
+
+use std::f32::consts::PI;
+
+use crate::canvas::Transform;
+
+/// Shortest dash or gap, in pixels; shorter non-zero lengths are raised
+/// to it. Zero length dashes stay, they are dots.
+const MIN_DASH: f32 = 0.01;
+/// Most pieces a dash pattern splits the polylines of a stroke into.
+/// Beyond that the pattern is far below a pixel or the path far off the
+/// canvas, and the stroke is drawn solid.
+const MAX_DASHES: f64 = 1_000_000.0;
+
+/// One drawing command of a [`Path`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathSegment {
+    /// Starts a new contour
+    MoveTo([f32; 2]),
+    LineTo([f32; 2]),
+    /// A quadratic Bézier curve with a control point
+    QuadTo([f32; 2], [f32; 2]),
+    /// A cubic Bézier curve with two control points
+    CubicTo([f32; 2], [f32; 2], [f32; 2]),
+    /// Connects the contour back to its start
+    Close,
+}
+
+/// A contour flattened to straight segments
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<[f32; 2]>,
+    pub closed: bool,
+}
+
+/// Contours of lines and curves
+///
+/// Drawing commands without a preceding [`Path::move_to`] start at the
+/// end of the previous contour, or at the origin.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Path {
+    segments: Vec<PathSegment>,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        return Path::default();
+    }
+
+    /// A closed rectangle
+    pub fn rect([x, y]: [f32; 2], [width, height]: [f32; 2]) -> Self {
+        return Path::new()
+            .move_to([x, y])
+            .line_to([x + width, y])
+            .line_to([x + width, y + height])
+            .line_to([x, y + height])
+            .close();
+    }
+
+    /// A closed circle
+    pub fn circle(center: [f32; 2], radius: f32) -> Self {
+        return Path::new()
+            .move_to([center[0] + radius, center[1]])
+            .arc(center, radius, 0.0, 2.0 * PI)
+            .close();
+    }
+
+    /// A closed rectangle with corners rounded by `radius`
+    pub fn rounded_rect([x, y]: [f32; 2], [width, height]: [f32; 2], radius: f32) -> Self {
+        let r = radius.min(width / 2.0).min(height / 2.0).max(0.0);
+        return Path::new()
+            .move_to([x + r, y])
+            .line_to([x + width - r, y])
+            .arc([x + width - r, y + r], r, -PI / 2.0, 0.0)
+            .line_to([x + width, y + height - r])
+            .arc([x + width - r, y + height - r], r, 0.0, PI / 2.0)
+            .line_to([x + r, y + height])
+            .arc([x + r, y + height - r], r, PI / 2.0, PI)
+            .line_to([x, y + r])
+            .arc([x + r, y + r], r, PI, 1.5 * PI)
+            .close();
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        return &self.segments;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.segments.is_empty();
+    }
+
+    pub fn move_to(mut self, point: [f32; 2]) -> Self {
+        self.segments.push(PathSegment::MoveTo(point));
+        return self;
+    }
+
+    pub fn line_to(mut self, point: [f32; 2]) -> Self {
+        self.segments.push(PathSegment::LineTo(point));
+        return self;
+    }
+
+    pub fn quad_to(mut self, control: [f32; 2], point: [f32; 2]) -> Self {
+        self.segments.push(PathSegment::QuadTo(control, point));
+        return self;
+    }
+
+    pub fn cubic_to(mut self, control1: [f32; 2], control2: [f32; 2], point: [f32; 2]) -> Self {
+        self.segments.push(PathSegment::CubicTo(control1, control2, point));
+        return self;
+    }
+
+    /// A circular arc around `center` from `start` to `end` angle, in
+    /// radians clockwise on screen; counterclockwise when `end < start`.
+    /// Like the HTML canvas, a straight line first connects the current
+    /// point to the start of the arc. The arc is stored as cubic curves.
+    pub fn arc(mut self, center: [f32; 2], radius: f32, start: f32, end: f32) -> Self {
+        let point_at = |angle: f32| [center[0] + radius * angle.cos(), center[1] + radius * angle.sin()];
+        let first = point_at(start);
+        match self.current_point() {
+            Some(current) if current != first => self.segments.push(PathSegment::LineTo(first)),
+            Some(_) => {}
+            None => self.segments.push(PathSegment::MoveTo(first)),
+        }
+
+        let sweep = (end - start).clamp(-2.0 * PI, 2.0 * PI);
+        // Pieces of at most a quarter turn stay close to the circle
+        let pieces = (sweep.abs() / (PI / 2.0)).ceil().max(1.0) as usize;
+        let step = sweep / pieces as f32;
+        let handle = 4.0 / 3.0 * (step / 4.0).tan() * radius;
+        for i in 0..pieces {
+            let a0 = start + step * i as f32;
+            let a1 = a0 + step;
+            let (p0, p3) = (point_at(a0), point_at(a1));
+            let control1 = [p0[0] - handle * a0.sin(), p0[1] + handle * a0.cos()];
+            let control2 = [p3[0] + handle * a1.sin(), p3[1] - handle * a1.cos()];
+            self.segments.push(PathSegment::CubicTo(control1, control2, p3));
+        }
+        return self;
+    }
+
+    pub fn close(mut self) -> Self {
+        self.segments.push(PathSegment::Close);
+        return self;
+    }
+
+    /// Appends the contours of another path
+    pub fn extend(mut self, other: &Path) -> Self {
+        self.segments.extend_from_slice(&other.segments);
+        return self;
+    }
+
+    /// Where the next segment would start
+    fn current_point(&self) -> Option<[f32; 2]> {
+        let mut start = None;
+        let mut current = None;
+        for segment in &self.segments {
+            match *segment {
+                PathSegment::MoveTo(p) => {
+                    start = Some(p);
+                    current = Some(p);
+                }
+                PathSegment::LineTo(p) | PathSegment::QuadTo(_, p) | PathSegment::CubicTo(_, _, p) => current = Some(p),
+                PathSegment::Close => current = start,
+            }
+        }
+        return current;
+    }
+
+    /// The path with every point transformed
+    pub fn transformed(&self, transform: &Transform) -> Path {
+        let t = |p| transform.apply(p);
+        let segments = self
+            .segments
+            .iter()
+            .map(|segment| match *segment {
+                PathSegment::MoveTo(p) => PathSegment::MoveTo(t(p)),
+                PathSegment::LineTo(p) => PathSegment::LineTo(t(p)),
+                PathSegment::QuadTo(c, p) => PathSegment::QuadTo(t(c), t(p)),
+                PathSegment::CubicTo(c1, c2, p) => PathSegment::CubicTo(t(c1), t(c2), t(p)),
+                PathSegment::Close => PathSegment::Close,
+            })
+            .collect();
+        return Path { segments };
+    }
+
+    /// Approximates the curves with straight segments that stay within
+    /// `tolerance` of them
+    pub fn flatten(&self, tolerance: f32) -> Vec<Polyline> {
+        let tolerance = tolerance.max(1e-4);
+        let mut polylines = Vec::new();
+        let mut points: Vec<[f32; 2]> = Vec::new();
+        let mut current = [0.0, 0.0];
+        let finish = |points: &mut Vec<[f32; 2]>, closed: bool, polylines: &mut Vec<Polyline>| {
+            if points.len() > 1 {
+                polylines.push(Polyline { points: std::mem::take(points), closed });
+            } else {
+                points.clear();
+            }
+        };
+
+        for segment in &self.segments {
+            match *segment {
+                PathSegment::MoveTo(p) => {
+                    finish(&mut points, false, &mut polylines);
+                    points.push(p);
+                    current = p;
+                }
+                PathSegment::LineTo(p) => {
+                    if points.is_empty() {
+                        points.push(current);
+                    }
+                    points.push(p);
+                    current = p;
+                }
+                PathSegment::QuadTo(c, p) => {
+                    if points.is_empty() {
+                        points.push(current);
+                    }
+                    let deviation = length(sub(add(current, p), scale(c, 2.0)));
+                    let steps = ((deviation / (4.0 * tolerance)).sqrt().ceil() as usize).clamp(1, 1000);
+                    for i in 1..=steps {
+                        let t = i as f32 / steps as f32;
+                        let u = 1.0 - t;
+                        points.push(add(add(scale(current, u * u), scale(c, 2.0 * u * t)), scale(p, t * t)));
+                    }
+                    current = p;
+                }
+                PathSegment::CubicTo(c1, c2, p) => {
+                    if points.is_empty() {
+                        points.push(current);
+                    }
+                    let deviation = length(sub(add(current, c2), scale(c1, 2.0)))
+                        .max(length(sub(add(c1, p), scale(c2, 2.0))));
+                    let steps = ((3.0 * deviation / (4.0 * tolerance)).sqrt().ceil() as usize).clamp(1, 1000);
+                    for i in 1..=steps {
+                        let t = i as f32 / steps as f32;
+                        let u = 1.0 - t;
+                        let point = add(
+                            add(scale(current, u * u * u), scale(c1, 3.0 * u * u * t)),
+                            add(scale(c2, 3.0 * u * t * t), scale(p, t * t * t)),
+                        );
+                        points.push(point);
+                    }
+                    current = p;
+                }
+                PathSegment::Close => {
+                    if let Some(&start) = points.first() {
+                        // The closing edge is implied
+                        if points.len() > 1 && points.last() == Some(&start) {
+                            points.pop();
+                        }
+                        current = start;
+                    }
+                    finish(&mut points, true, &mut polylines);
+                }
+            }
+        }
+        finish(&mut points, false, &mut polylines);
+        return polylines;
+    }
+}
+
+/// How two segments of a stroke meet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineJoin {
+    /// The outer edges are extended until they meet, unless that is
+    /// further than the miter limit, then the corner is beveled
+    #[default]
+    Miter,
+    Round,
+    /// The corner is cut off straight
+    Bevel,
+}
+
+/// How open ends of a stroke look
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineCap {
+    /// The stroke ends exactly at the end point
+    #[default]
+    Butt,
+    Round,
+    /// The stroke goes on half its width past the end point
+    Square,
+}
+
+/// How to outline a path
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stroke {
+    pub width: f32,
+    pub join: LineJoin,
+    pub cap: LineCap,
+    /// How far a miter join may reach, in multiples of half the width
+    pub miter_limit: f32,
+    /// Alternating lengths of dashes and gaps; empty for a solid line
+    pub dashes: Vec<f32>,
+    /// How far into the dash pattern the stroke starts
+    pub dash_offset: f32,
+}
+
+impl Default for Stroke {
+    fn default() -> Self {
+        return Stroke::new(1.0);
+    }
+}
+
+impl Stroke {
+    /// A solid stroke with miter joins and butt caps
+    pub fn new(width: f32) -> Self {
+        return Stroke {
+            width,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            miter_limit: 4.0,
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+        };
+    }
+
+    pub fn join(mut self, join: LineJoin) -> Self {
+        self.join = join;
+        return self;
+    }
+
+    pub fn cap(mut self, cap: LineCap) -> Self {
+        self.cap = cap;
+        return self;
+    }
+
+    pub fn miter_limit(mut self, miter_limit: f32) -> Self {
+        self.miter_limit = miter_limit;
+        return self;
+    }
+
+    /// Alternating dash and gap lengths. An odd number of lengths is
+    /// repeated to make it even, as in SVG.
+    pub fn dashes(mut self, dashes: &[f32], offset: f32) -> Self {
+        self.dashes = dashes.to_vec();
+        if dashes.len() % 2 == 1 {
+            self.dashes.extend_from_slice(dashes);
+        }
+        self.dash_offset = offset;
+        return self;
+    }
+
+    /// Polygons that together cover the stroke of the given polylines.
+    /// They all turn the same way, so filling them with the non-zero rule
+    /// gives their union.
+    pub fn outline(&self, polylines: &[Polyline], tolerance: f32) -> Vec<Vec<[f32; 2]>> {
+        let half = self.width / 2.0;
+        let mut polygons = Vec::new();
+        if half <= 0.0 {
+            return polygons;
+        }
+        let round_segments = round_segments(half, tolerance);
+        for polyline in self.dashed(polylines) {
+            let points = dedup_points(&polyline.points);
+            if points.len() == 1 {
+                // A zero length piece still shows its caps
+                self.caps_of_dot(points[0], half, round_segments, &mut polygons);
+                continue;
+            }
+            let segment_count = if polyline.closed { points.len() } else { points.len() - 1 };
+            for i in 0..segment_count {
+                let (from, to) = (points[i], points[(i + 1) % points.len()]);
+                let normal = scale(unit_normal(from, to), half);
+                polygons.push(orient_positive(vec![add(from, normal), add(to, normal), sub(to, normal), sub(from, normal)]));
+            }
+
+            let corners = if polyline.closed { 0..points.len() } else { 1..points.len() - 1 };
+            for i in corners {
+                let previous = points[(i + points.len() - 1) % points.len()];
+                let next = points[(i + 1) % points.len()];
+                if let Some(join) = self.join_polygon(previous, points[i], next, half, round_segments) {
+                    polygons.push(join);
+                }
+            }
+
+            if !polyline.closed {
+                let last = points.len() - 1;
+                self.cap_polygon(points[1], points[0], half, round_segments, &mut polygons);
+                self.cap_polygon(points[last - 1], points[last], half, round_segments, &mut polygons);
+            }
+        }
+        return polygons;
+    }
+
+    /// Splits polylines into the dashes of the pattern
+    fn dashed(&self, polylines: &[Polyline]) -> Vec<Polyline> {
+        if self.dashes.is_empty() || self.dashes.iter().any(|d| !(*d >= 0.0 && d.is_finite())) {
+            return polylines.to_vec();
+        }
+        // Distances are f64 so that subtracting a short dash from a long
+        // segment always makes progress
+        let pattern: Vec<f64> = self.dashes.iter().map(|&d| if d > 0.0 { d.max(MIN_DASH) as f64 } else { 0.0 }).collect();
+        let pattern_length: f64 = pattern.iter().sum();
+        let total_length: f64 = polylines
+            .iter()
+            .flat_map(|polyline| polyline.points.windows(2).map(|pair| length(sub(pair[1], pair[0])) as f64))
+            .sum();
+        if pattern_length <= 0.0 || total_length / pattern_length * pattern.len() as f64 > MAX_DASHES {
+            return polylines.to_vec();
+        }
+
+        let mut dashes = Vec::new();
+        for polyline in polylines {
+            // Where in the pattern we are: index of the dash or gap, and how much of it is left
+            let mut index = 0;
+            let mut left = pattern[0];
+            let mut skip = (self.dash_offset as f64).rem_euclid(pattern_length);
+            while skip > 0.0 {
+                if skip >= left {
+                    skip -= left;
+                    index = (index + 1) % pattern.len();
+                    left = pattern[index];
+                } else {
+                    left -= skip;
+                    skip = 0.0;
+                }
+            }
+
+            let mut points = polyline.points.clone();
+            if polyline.closed {
+                points.push(points[0]);
+            }
+            let mut dash: Vec<[f32; 2]> = if index % 2 == 0 { vec![points[0]] } else { Vec::new() };
+            for pair in points.windows(2) {
+                let (mut from, to) = (pair[0], pair[1]);
+                let mut remaining = length(sub(to, from)) as f64;
+                while remaining > 0.0 {
+                    if left >= remaining {
+                        left -= remaining;
+                        remaining = 0.0;
+                        if index % 2 == 0 {
+                            dash.push(to);
+                        }
+                    } else {
+                        let point = add(from, scale(sub(to, from), (left / remaining) as f32));
+                        remaining -= left;
+                        from = point;
+                        if index % 2 == 0 {
+                            dash.push(point);
+                            dashes.push(Polyline { points: std::mem::take(&mut dash), closed: false });
+                        } else {
+                            dash.push(point);
+                        }
+                        index = (index + 1) % pattern.len();
+                        left = pattern[index];
+                    }
+                }
+            }
+            // A gap ending right at the end, give or take rounding, starts a
+            // dash there: a dot at least, e.g. the last one of `[0, gap]`
+            if index % 2 == 1 && left < 1e-4 {
+                dash.extend(points.last().copied());
+                index = (index + 1) % pattern.len();
+            }
+            if index % 2 == 0 && !dash.is_empty() {
+                dashes.push(Polyline { points: dash, closed: false });
+            }
+        }
+        return dashes;
+    }
+
+    fn join_polygon(
+        &self, previous: [f32; 2], corner: [f32; 2], next: [f32; 2], half: f32, round_segments: usize,
+    ) -> Option<Vec<[f32; 2]>> {
+        let in_normal = unit_normal(previous, corner);
+        let out_normal = unit_normal(corner, next);
+        let turn = cross(sub(corner, previous), sub(next, corner));
+        if turn.abs() < 1e-9 && dot(in_normal, out_normal) > 0.0 {
+            return None;
+        }
+        // The outer side of the corner is opposite to the turn
+        let side = if turn > 0.0 { -1.0 } else { 1.0 };
+        let from = add(corner, scale(in_normal, half * side));
+        let to = add(corner, scale(out_normal, half * side));
+
+        match self.join {
+            LineJoin::Round => return Some(circle_polygon(corner, half, round_segments)),
+            LineJoin::Bevel => return Some(orient_positive(vec![corner, from, to])),
+            LineJoin::Miter => {
+                // Distance from the corner to the miter tip, in half widths
+                let cos_half_angle = ((1.0 + dot(in_normal, out_normal)) / 2.0).max(0.0).sqrt();
+                if cos_half_angle < 1e-6 || 1.0 / cos_half_angle > self.miter_limit {
+                    return Some(orient_positive(vec![corner, from, to]));
+                }
+                let bisector = normalize(add(in_normal, out_normal));
+                let tip = add(corner, scale(bisector, half * side / cos_half_angle));
+                return Some(orient_positive(vec![corner, from, tip, to]));
+            }
+        }
+    }
+
+    /// The cap at `end` of a stroke coming from `before`
+    fn cap_polygon(&self, before: [f32; 2], end: [f32; 2], half: f32, round_segments: usize, polygons: &mut Vec<Vec<[f32; 2]>>) {
+        match self.cap {
+            LineCap::Butt => {}
+            LineCap::Round => polygons.push(circle_polygon(end, half, round_segments)),
+            LineCap::Square => {
+                let direction = scale(normalize(sub(end, before)), half);
+                let normal = scale(unit_normal(before, end), half);
+                let beyond = add(end, direction);
+                polygons.push(orient_positive(vec![
+                    add(end, normal), add(beyond, normal), sub(beyond, normal), sub(end, normal),
+                ]));
+            }
+        }
+    }
+
+    /// Caps of a piece without length: a dot for round caps, a square for square caps
+    fn caps_of_dot(&self, point: [f32; 2], half: f32, round_segments: usize, polygons: &mut Vec<Vec<[f32; 2]>>) {
+        match self.cap {
+            LineCap::Butt => {}
+            LineCap::Round => polygons.push(circle_polygon(point, half, round_segments)),
+            LineCap::Square => polygons.push(orient_positive(vec![
+                [point[0] - half, point[1] - half],
+                [point[0] + half, point[1] - half],
+                [point[0] + half, point[1] + half],
+                [point[0] - half, point[1] + half],
+            ])),
+        }
+    }
+}
+
+/// How many segments approximate a full circle of `radius` within `tolerance`
+fn round_segments(radius: f32, tolerance: f32) -> usize {
+    if radius <= tolerance {
+        return 4;
+    }
+    let step = (1.0 - tolerance / radius).clamp(-1.0, 1.0).acos() * 2.0;
+    return ((2.0 * PI / step).ceil() as usize).clamp(8, 512);
+}
+
+fn circle_polygon(center: [f32; 2], radius: f32, segments: usize) -> Vec<[f32; 2]> {
+    return (0..segments)
+        .map(|i| {
+            let angle = i as f32 / segments as f32 * 2.0 * PI;
+            [center[0] + radius * angle.cos(), center[1] + radius * angle.sin()]
+        })
+        .collect();
+}
+
+/// Drops consecutive repeated points, which have no direction
+fn dedup_points(points: &[[f32; 2]]) -> Vec<[f32; 2]> {
+    let mut unique: Vec<[f32; 2]> = Vec::with_capacity(points.len());
+    for &point in points {
+        if unique.last().is_none_or(|&last| length(sub(point, last)) > 1e-6) {
+            unique.push(point);
+        }
+    }
+    return unique;
+}
+
+/// Reverses a polygon if needed so that it turns clockwise on screen
+pub(crate) fn orient_positive(mut points: Vec<[f32; 2]>) -> Vec<[f32; 2]> {
+    let mut area = 0.0;
+    for i in 0..points.len() {
+        area += cross(points[i], points[(i + 1) % points.len()]);
+    }
+    if area < 0.0 {
+        points.reverse();
+    }
+    return points;
+}
+
+fn add(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    return [a[0] + b[0], a[1] + b[1]];
+}
+
+fn sub(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    return [a[0] - b[0], a[1] - b[1]];
+}
+
+fn scale(a: [f32; 2], factor: f32) -> [f32; 2] {
+    return [a[0] * factor, a[1] * factor];
+}
+
+fn dot(a: [f32; 2], b: [f32; 2]) -> f32 {
+    return a[0] * b[0] + a[1] * b[1];
+}
+
+fn cross(a: [f32; 2], b: [f32; 2]) -> f32 {
+    return a[0] * b[1] - a[1] * b[0];
+}
+
+fn length(a: [f32; 2]) -> f32 {
+    return dot(a, a).sqrt();
+}
+
+fn normalize(a: [f32; 2]) -> [f32; 2] {
+    let len = length(a);
+    if len == 0.0 {
+        return [0.0, 0.0];
+    }
+    return scale(a, 1.0 / len);
+}
+
+/// The unit vector at a right angle to the segment
+fn unit_normal(from: [f32; 2], to: [f32; 2]) -> [f32; 2] {
+    let [dx, dy] = normalize(sub(to, from));
+    return [-dy, dx];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(from: [f32; 2], to: [f32; 2]) -> Vec<Polyline> {
+        return Path::new().move_to(from).line_to(to).flatten(0.1);
+    }
+
+    fn dashes(stroke: &Stroke, polylines: &[Polyline]) -> Vec<Vec<[f32; 2]>> {
+        return stroke.dashed(polylines).into_iter().map(|polyline| polyline.points).collect();
+    }
+
+    #[test]
+    fn flattening_splits_contours_and_closes_them() {
+        let path = Path::rect([0.0, 0.0], [2.0, 1.0]).move_to([5.0, 5.0]).line_to([6.0, 5.0]).line_to([6.0, 5.0]);
+        let polylines = path.flatten(0.1);
+        assert_eq!(polylines.len(), 2);
+        assert_eq!(polylines[0], Polyline { points: vec![[0.0, 0.0], [2.0, 0.0], [2.0, 1.0], [0.0, 1.0]], closed: true });
+        assert!(!polylines[1].closed);
+        // A lone move_to is no contour
+        assert!(Path::new().move_to([1.0, 1.0]).flatten(0.1).is_empty());
+    }
+
+    #[test]
+    fn flattened_circles_stay_within_the_tolerance() {
+        for tolerance in [1.0, 0.1, 0.01] {
+            let polylines = Path::circle([10.0, 10.0], 8.0).flatten(tolerance);
+            assert_eq!(polylines.len(), 1);
+            let points = &polylines[0].points;
+            for (i, &point) in points.iter().enumerate() {
+                assert!((length(sub(point, [10.0, 10.0])) - 8.0).abs() < 0.01);
+                // The middle of every chord is within the tolerance too
+                let middle = scale(add(point, points[(i + 1) % points.len()]), 0.5);
+                assert!(8.0 - length(sub(middle, [10.0, 10.0])) <= tolerance * 1.01);
+            }
+        }
+    }
+
+    #[test]
+    fn arcs_connect_to_the_current_point() {
+        let path = Path::new().move_to([0.0, 0.0]).arc([10.0, 0.0], 5.0, 0.0, PI);
+        assert_eq!(path.segments()[1], PathSegment::LineTo([15.0, 0.0]));
+        assert!(matches!(path.segments()[2..], [PathSegment::CubicTo(..), PathSegment::CubicTo(..)]));
+    }
+
+    #[test]
+    fn dashes_follow_the_pattern_and_offset() {
+        let stroke = Stroke::new(1.0).dashes(&[2.0, 1.0], 0.0);
+        assert_eq!(
+            dashes(&stroke, &line([0.0, 0.0], [7.0, 0.0])),
+            vec![vec![[0.0, 0.0], [2.0, 0.0]], vec![[3.0, 0.0], [5.0, 0.0]], vec![[6.0, 0.0], [7.0, 0.0]]],
+        );
+        let offset = Stroke::new(1.0).dashes(&[2.0, 1.0], 1.5);
+        assert_eq!(dashes(&offset, &line([0.0, 0.0], [4.0, 0.0])), vec![vec![[0.0, 0.0], [0.5, 0.0]], vec![[1.5, 0.0], [3.5, 0.0]]]);
+        // An odd pattern is repeated: dash 1, gap 2, dash 3, gap 1
+        assert_eq!(Stroke::new(1.0).dashes(&[1.0, 2.0, 3.0], 0.0).dashes, vec![1.0, 2.0, 3.0, 1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn dots_of_zero_length_dashes_include_the_end() {
+        let stroke = Stroke::new(2.0).cap(LineCap::Round).dashes(&[0.0, 4.0], 0.0);
+        let dots = dashes(&stroke, &line([0.0, 0.0], [8.0, 0.0]));
+        assert_eq!(dots.len(), 3);
+        assert_eq!(dots[2], vec![[8.0, 0.0]]);
+        // Each dot is drawn as a round cap
+        assert_eq!(stroke.outline(&line([0.0, 0.0], [8.0, 0.0]), 0.1).len(), 3);
+    }
+
+    #[test]
+    fn degenerate_dash_patterns_terminate() {
+        let polylines = line([0.0, 0.0], [100_000.0, 0.0]);
+        // Too many dashes: drawn solid
+        assert_eq!(dashes(&Stroke::new(1.0).dashes(&[1e-9, 1e-9], 0.0), &polylines).len(), 1);
+        // Negative or non-finite lengths: drawn solid
+        assert_eq!(dashes(&Stroke::new(1.0).dashes(&[-1.0, 2.0], 0.0), &polylines).len(), 1);
+        assert_eq!(dashes(&Stroke::new(1.0).dashes(&[f32::NAN, 2.0], 0.0), &polylines).len(), 1);
+        // All zero: solid as well
+        assert_eq!(dashes(&Stroke::new(1.0).dashes(&[0.0, 0.0], 0.0), &polylines).len(), 1);
+        // Tiny dashes are made long enough to make progress; the last
+        // gap ends at the end, which starts one more
+        let short = line([0.0, 0.0], [1.0, 0.0]);
+        assert_eq!(dashes(&Stroke::new(1.0).dashes(&[1e-6, 0.09], 0.0), &short).len(), 11);
+    }
+
+    #[test]
+    fn joins_and_caps_add_polygons() {
+        let corner = Path::new().move_to([0.0, 0.0]).line_to([10.0, 0.0]).line_to([10.0, 10.0]).flatten(0.1);
+        let butt = Stroke::new(2.0).outline(&corner, 0.1);
+        assert_eq!(butt.len(), 3);
+        assert_eq!(butt[2].len(), 4, "miter join");
+        let bevel = Stroke::new(2.0).join(LineJoin::Bevel).cap(LineCap::Square).outline(&corner, 0.1);
+        assert_eq!(bevel.len(), 5);
+        assert_eq!(bevel[2].len(), 3, "bevel join");
+        // A sharp turn past the miter limit is beveled
+        let spike = Path::new().move_to([0.0, 0.0]).line_to([10.0, 0.0]).line_to([0.0, 0.5]).flatten(0.1);
+        assert_eq!(Stroke::new(2.0).outline(&spike, 0.1)[2].len(), 3);
+        // Every polygon turns the same way
+        for polygon in Stroke::new(3.0).join(LineJoin::Round).cap(LineCap::Round).outline(&corner, 0.1) {
+            assert_eq!(orient_positive(polygon.clone()), polygon);
+        }
+        assert!(Stroke::new(0.0).outline(&corner, 0.1).is_empty());
+    }
+}
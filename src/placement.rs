@@ -0,0 +1,218 @@
+//! Window placement that survives restarts
+//!
+//! A [`WindowPlacement`] records where a window was: its monitor, its
+//! normal (not maximized) rectangle and whether it was maximized,
+//! minimized or fullscreen. It converts to and from a small `key=value`
+//! text format to save between runs. Before restoring it is clamped to
+//! the monitors that exist now, since displays may have been unplugged
+//! or rearranged in the meantime.
+
+use crate::geometry::{Point, Rect, Size};
+
+/// A display, in virtual screen coordinates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Monitor {
+    /// Name of the monitor, e.g. `\\.\DISPLAY1` on Win32
+    pub name: String,
+    /// The whole display
+    pub rect: Rect,
+    /// The part not covered by task bars and docks
+    pub work_rect: Rect,
+    pub primary: bool,
+}
+
+/// Abstraction to represent an error while reading a saved placement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlacementError {
+    /// A line isn't `key=value` or a value doesn't parse
+    InvalidLine(String),
+    /// A required key is missing
+    MissingKey(&'static str),
+}
+
+impl std::fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PlacementError::InvalidLine(line) => write!(f, "invalid placement line {:?}", line),
+            PlacementError::MissingKey(key) => write!(f, "the placement has no {:?}", key),
+        }
+    }
+}
+
+impl std::error::Error for PlacementError {}
+
+/// Where a window is and how it is shown
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowPlacement {
+    /// Name of the monitor the window was on, see [`Monitor::name`]
+    pub monitor: Option<String>,
+    /// The outer window in its normal state, in virtual screen
+    /// coordinates. Kept while the window is maximized or fullscreen, so it
+    /// can go back there.
+    pub rect: Rect,
+    pub maximized: bool,
+    pub minimized: bool,
+    pub fullscreen: bool,
+}
+
+impl WindowPlacement {
+    /// A normal window with the given outer rectangle
+    pub fn new(rect: Rect) -> Self {
+        return WindowPlacement {
+            monitor: None,
+            rect,
+            maximized: false,
+            minimized: false,
+            fullscreen: false,
+        };
+    }
+
+    /// Moves the normal rectangle onto a monitor that exists: the saved one
+    /// if it is still there, else the one the rectangle overlaps most, else
+    /// the primary one. The rectangle is shrunk to the work area if needed
+    /// and moved until it is completely inside.
+    ///
+    /// With no monitors at all the placement is returned unchanged.
+    pub fn clamped(&self, monitors: &[Monitor]) -> WindowPlacement {
+        let by_name = self.monitor
+            .as_ref()
+            .and_then(|name| monitors.iter().find(|m| &m.name == name));
+        let by_overlap = monitors
+            .iter()
+            .map(|m| (self.rect.intersection(&m.work_rect).map_or(0, |rect| rect.area()), m))
+            .filter(|(area, _)| *area > 0)
+            .max_by_key(|(area, _)| *area)
+            .map(|(_, m)| m);
+        let monitor = match by_name.or(by_overlap).or(monitors.iter().find(|m| m.primary)).or(monitors.first()) {
+            Some(monitor) => monitor,
+            None => return self.clone(),
+        };
+
+        let mut placement = self.clone();
+        placement.monitor = Some(monitor.name.clone());
+        let work = &monitor.work_rect;
+        let [left, right] = clamp_span(self.rect.left, self.rect.right, work.left, work.right);
+        let [top, bottom] = clamp_span(self.rect.top, self.rect.bottom, work.top, work.bottom);
+        placement.rect = Rect::new(left, top, right, bottom);
+        return placement;
+    }
+}
+
+/// The span from `start` to `end` shrunk to the work area's if needed and
+/// moved inside it, at least 1 long
+fn clamp_span(start: i32, end: i32, work_start: i32, work_end: i32) -> [i32; 2] {
+    let work_length = (work_end as i64 - work_start as i64).clamp(1, i32::MAX as i64);
+    let length = (end as i64 - start as i64).clamp(1, work_length);
+    let start = (start as i64).clamp(work_start as i64, work_start as i64 + work_length - length);
+    return [start as i32, (start + length).min(i32::MAX as i64) as i32];
+}
+
+impl std::fmt::Display for WindowPlacement {
+    /// One `key=value` per line, the format [`WindowPlacement::from_str`] reads
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(monitor) = &self.monitor {
+            writeln!(f, "monitor={}", monitor)?;
+        }
+        let Size { width, height } = self.rect.size();
+        writeln!(f, "x={}", self.rect.left)?;
+        writeln!(f, "y={}", self.rect.top)?;
+        writeln!(f, "width={}", width)?;
+        writeln!(f, "height={}", height)?;
+        writeln!(f, "maximized={}", self.maximized)?;
+        writeln!(f, "minimized={}", self.minimized)?;
+        return writeln!(f, "fullscreen={}", self.fullscreen);
+    }
+}
+
+impl std::str::FromStr for WindowPlacement {
+    type Err = PlacementError;
+
+    /// Reads the output of `Display`. Blank lines, `#` comments and
+    /// unknown keys are skipped; the flags default to `false`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut monitor = None;
+        let mut numbers: [Option<i32>; 4] = [None; 4];
+        let mut flags = [false; 3];
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = || PlacementError::InvalidLine(line.to_owned());
+            let (key, value) = line.split_once('=').ok_or_else(invalid)?;
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "monitor" => monitor = Some(value.to_owned()).filter(|name| !name.is_empty()),
+                "x" | "y" | "width" | "height" => {
+                    let index = ["x", "y", "width", "height"].iter().position(|k| *k == key).unwrap_or(0);
+                    numbers[index] = Some(value.parse().map_err(|_| invalid())?);
+                }
+                "maximized" | "minimized" | "fullscreen" => {
+                    let index = ["maximized", "minimized", "fullscreen"].iter().position(|k| *k == key).unwrap_or(0);
+                    flags[index] = value.parse().map_err(|_| invalid())?;
+                }
+                _ => {}
+            }
+        }
+
+        let [x, y, width, height] = numbers;
+        return Ok(WindowPlacement {
+            monitor,
+            rect: Rect::from_origin_size(
+                Point::new(x.ok_or(PlacementError::MissingKey("x"))?, y.ok_or(PlacementError::MissingKey("y"))?),
+                Size::new(
+                    width.ok_or(PlacementError::MissingKey("width"))?,
+                    height.ok_or(PlacementError::MissingKey("height"))?,
+                ),
+            ),
+            maximized: flags[0],
+            minimized: flags[1],
+            fullscreen: flags[2],
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, rect: Rect, primary: bool) -> Monitor {
+        return Monitor { name: name.to_owned(), rect, work_rect: Rect::new(rect.left, rect.top, rect.right, rect.bottom - 40), primary };
+    }
+
+    #[test]
+    fn placement_text_round_trips() {
+        let mut placement = WindowPlacement::new(Rect::new(-100, 20, 700, 620));
+        placement.monitor = Some("\\\\.\\DISPLAY2".to_owned());
+        placement.maximized = true;
+        let text = placement.to_string();
+        assert!(text.contains("x=-100\ny=20\nwidth=800\nheight=600\n"));
+        assert_eq!(text.parse::<WindowPlacement>(), Ok(placement));
+        assert_eq!("x=1\ny=2\nwidth=3".parse::<WindowPlacement>(), Err(PlacementError::MissingKey("height")));
+    }
+
+    #[test]
+    fn placement_moves_into_the_work_area() {
+        let monitors = [
+            monitor("primary", Rect::new(0, 0, 1920, 1080), true),
+            monitor("right", Rect::new(1920, 0, 3200, 1024), false),
+        ];
+        // Mostly on the right monitor and hanging off its bottom edge
+        let placement = WindowPlacement::new(Rect::new(1800, 700, 2600, 1300)).clamped(&monitors);
+        assert_eq!(placement.monitor.as_deref(), Some("right"));
+        assert_eq!(placement.rect, Rect::new(1920, 384, 2720, 984));
+    }
+
+    #[test]
+    fn placement_larger_than_the_work_area_is_shrunk() {
+        let monitors = [monitor("primary", Rect::new(0, 0, 1280, 720), true)];
+        let mut placement = WindowPlacement::new(Rect::new(-50, -50, 2000, 2000));
+        placement.monitor = Some("unplugged".to_owned());
+        assert_eq!(placement.clamped(&monitors).rect, Rect::new(0, 0, 1280, 680));
+    }
+
+    #[test]
+    fn placement_without_monitors_is_unchanged() {
+        let placement = WindowPlacement::new(Rect::new(i32::MIN, 0, i32::MAX, 10));
+        assert_eq!(placement.clamped(&[]), placement);
+    }
+}
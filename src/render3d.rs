@@ -0,0 +1,787 @@
+//! Software 3D rendering
+//!
+//! A CPU version of the fixed stages of a GPU pipeline, so 3D rendering
+//! logic can run and be tested on machines without a graphics card. A
+//! [`Pipeline::draw`] runs a vertex shader closure on every vertex, clips
+//! the triangles against the view frustum in clip space, divides by `w`,
+//! culls by facing and rasterizes into a [`Canvas`], the pixel buffer
+//! windows present. Every covered pixel becomes a [`Fragment`] with the
+//! vertex outputs interpolated with perspective correction; a fragment
+//! shader closure turns it into a colour, which is depth tested against a
+//! [`DepthBuffer`] and blended over the canvas.
+//!
+//! The conventions are OpenGL's: clip space `z` goes from `-w` to `w`,
+//! normalized device `y` points up, counter-clockwise triangles face the
+//! viewer and depth goes from 0 at the near plane to 1 at the far one.
+//! A pixel is covered when its centre is inside the triangle, with the
+//! top-left rule for centres exactly on an edge, so triangles sharing an
+//! edge never both draw a pixel.
+
+use crate::canvas::{Canvas, Color};
+
+/// Abstraction to represent an error while drawing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderError {
+    /// The depth buffer isn't the size of the canvas
+    DepthBufferSize { expected: [u32; 2], actual: [u32; 2] },
+
+    /// A triangle uses a vertex that doesn't exist
+    IndexOutOfRange { index: u32, vertex_count: usize },
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RenderError::DepthBufferSize { expected, actual } => write!(
+                f,
+                "depth buffer is {}x{}, the canvas is {}x{}",
+                actual[0], actual[1], expected[0], expected[1],
+            ),
+            RenderError::IndexOutOfRange { index, vertex_count } => {
+                write!(f, "vertex index {} out of range for {} vertices", index, vertex_count)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+/// A 4x4 matrix for 3D transforms, applied to column vectors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    /// Column-major like OpenGL, `columns[3]` holds the translation
+    pub columns: [[f32; 4]; 4],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        return Mat4::IDENTITY;
+    }
+}
+
+impl Mat4 {
+    pub const IDENTITY: Mat4 =
+        Mat4 { columns: [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]] };
+
+    pub fn translate([x, y, z]: [f32; 3]) -> Self {
+        let mut matrix = Mat4::IDENTITY;
+        matrix.columns[3] = [x, y, z, 1.0];
+        return matrix;
+    }
+
+    pub fn scale([x, y, z]: [f32; 3]) -> Self {
+        let mut matrix = Mat4::IDENTITY;
+        (matrix.columns[0][0], matrix.columns[1][1], matrix.columns[2][2]) = (x, y, z);
+        return matrix;
+    }
+
+    /// Rotation by `angle` radians around `axis`, counter-clockwise when
+    /// the axis points at the viewer
+    pub fn rotate(axis: [f32; 3], angle: f32) -> Self {
+        let [x, y, z] = normalize(axis);
+        let (s, c) = angle.sin_cos();
+        let t = 1.0 - c;
+        return Mat4 {
+            columns: [
+                [t * x * x + c, t * x * y + s * z, t * x * z - s * y, 0.0],
+                [t * x * y - s * z, t * y * y + c, t * y * z + s * x, 0.0],
+                [t * x * z + s * y, t * y * z - s * x, t * z * z + c, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+        };
+    }
+
+    /// Perspective projection looking down `-z`, with the vertical field of
+    /// view `fov_y` in radians and `aspect` as width over height
+    pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let f = 1.0 / (fov_y / 2.0).tan();
+        return Mat4 {
+            columns: [
+                [f / aspect, 0.0, 0.0, 0.0],
+                [0.0, f, 0.0, 0.0],
+                [0.0, 0.0, (far + near) / (near - far), -1.0],
+                [0.0, 0.0, 2.0 * far * near / (near - far), 0.0],
+            ],
+        };
+    }
+
+    /// Parallel projection of a box looking down `-z`
+    pub fn orthographic(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Self {
+        return Mat4 {
+            columns: [
+                [2.0 / (right - left), 0.0, 0.0, 0.0],
+                [0.0, 2.0 / (top - bottom), 0.0, 0.0],
+                [0.0, 0.0, -2.0 / (far - near), 0.0],
+                [
+                    -(right + left) / (right - left),
+                    -(top + bottom) / (top - bottom),
+                    -(far + near) / (far - near),
+                    1.0,
+                ],
+            ],
+        };
+    }
+
+    /// View transform of a camera at `eye` looking at `target`
+    pub fn look_at(eye: [f32; 3], target: [f32; 3], up: [f32; 3]) -> Self {
+        let forward = normalize(sub(target, eye));
+        let side = normalize(cross(forward, up));
+        let up = cross(side, forward);
+        let mut matrix = Mat4::IDENTITY;
+        for i in 0..3 {
+            matrix.columns[i] = [side[i], up[i], -forward[i], 0.0];
+        }
+        matrix.columns[3] = [-dot(side, eye), -dot(up, eye), dot(forward, eye), 1.0];
+        return matrix;
+    }
+
+    /// `self` applied after `first`
+    pub fn after(&self, first: &Mat4) -> Self {
+        return Mat4 { columns: first.columns.map(|column| self.apply(column)) };
+    }
+
+    pub fn apply(&self, vector: [f32; 4]) -> [f32; 4] {
+        let mut output = [0.0; 4];
+        for (column, &value) in self.columns.iter().zip(&vector) {
+            for (output, &entry) in output.iter_mut().zip(column) {
+                *output += entry * value;
+            }
+        }
+        return output;
+    }
+
+    /// Transforms a point, with `w` 1
+    pub fn apply_point(&self, [x, y, z]: [f32; 3]) -> [f32; 4] {
+        return self.apply([x, y, z, 1.0]);
+    }
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    return [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    return a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    return [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]];
+}
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let length = dot(v, v).sqrt();
+    if length == 0.0 {
+        return v;
+    }
+    return v.map(|value| value / length);
+}
+
+/// What a vertex shader returns: the clip space position and `N` values
+/// to interpolate across the triangle, like texture coordinates, normals
+/// or colours
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipVertex<const N: usize> {
+    pub position: [f32; 4],
+    pub varyings: [f32; N],
+}
+
+/// A pixel covered by a triangle, what a fragment shader sees
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fragment<const N: usize> {
+    /// The pixel in the canvas
+    pub position: [u32; 2],
+    /// Between 0 at the near plane and 1 at the far plane
+    pub depth: f32,
+    pub front_facing: bool,
+    /// The vertex shader outputs, interpolated with perspective correction
+    pub varyings: [f32; N],
+}
+
+/// Which triangles are skipped by facing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CullMode {
+    None,
+    #[default]
+    Back,
+    Front,
+}
+
+/// The winding of front facing triangles in normalized device coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrontFace {
+    #[default]
+    CounterClockwise,
+    Clockwise,
+}
+
+/// How the depth of a fragment compares to the buffer for it to be drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DepthTest {
+    Always,
+    #[default]
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl DepthTest {
+    fn passes(self, depth: f32, stored: f32) -> bool {
+        match self {
+            DepthTest::Always => return true,
+            DepthTest::Less => return depth < stored,
+            DepthTest::LessEqual => return depth <= stored,
+            DepthTest::Greater => return depth > stored,
+            DepthTest::GreaterEqual => return depth >= stored,
+        }
+    }
+}
+
+/// One depth per pixel of a canvas
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthBuffer {
+    width: u32,
+    height: u32,
+    depths: Vec<f32>,
+}
+
+impl DepthBuffer {
+    /// A buffer cleared to the far plane
+    pub fn new(width: u32, height: u32) -> Self {
+        return DepthBuffer { width, height, depths: vec![1.0; width as usize * height as usize] };
+    }
+
+    pub fn width(&self) -> u32 {
+        return self.width;
+    }
+
+    pub fn height(&self) -> u32 {
+        return self.height;
+    }
+
+    pub fn clear(&mut self, depth: f32) {
+        self.depths.fill(depth);
+    }
+
+    /// The depth of a pixel, or `None` outside the buffer
+    pub fn depth(&self, x: u32, y: u32) -> Option<f32> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        return Some(self.depths[y as usize * self.width as usize + x as usize]);
+    }
+
+    /// The depths row by row
+    pub fn depths(&self) -> &[f32] {
+        return &self.depths;
+    }
+}
+
+/// What a draw did, for tests and profiling
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DrawStats {
+    /// Triangles submitted
+    pub triangles: u64,
+    /// Triangles entirely outside the view frustum
+    pub clipped: u64,
+    /// Triangles skipped by facing
+    pub culled: u64,
+    /// Pixels that passed the depth test and ran the fragment shader
+    pub fragments: u64,
+    /// Fragments the shader didn't discard, which were drawn
+    pub written: u64,
+}
+
+/// Clip space planes as a normal and an offset: a position is inside when
+/// its dot product with the normal is at least the offset. The last plane
+/// keeps `w` away from 0 so the perspective divide is safe.
+const CLIP_PLANES: [([f32; 4], f32); 7] = [
+    ([1.0, 0.0, 0.0, 1.0], 0.0),
+    ([-1.0, 0.0, 0.0, 1.0], 0.0),
+    ([0.0, 1.0, 0.0, 1.0], 0.0),
+    ([0.0, -1.0, 0.0, 1.0], 0.0),
+    ([0.0, 0.0, 1.0, 1.0], 0.0),
+    ([0.0, 0.0, -1.0, 1.0], 0.0),
+    ([0.0, 0.0, 0.0, 1.0], 1e-5),
+];
+
+fn plane_distance((normal, offset): ([f32; 4], f32), position: [f32; 4]) -> f32 {
+    return normal.iter().zip(&position).map(|(n, p)| n * p).sum::<f32>() - offset;
+}
+
+fn lerp_vertex<const N: usize>(a: &ClipVertex<N>, b: &ClipVertex<N>, t: f32) -> ClipVertex<N> {
+    let mut vertex = *a;
+    for (value, &target) in vertex.position.iter_mut().zip(&b.position) {
+        *value += (target - *value) * t;
+    }
+    for (value, &target) in vertex.varyings.iter_mut().zip(&b.varyings) {
+        *value += (target - *value) * t;
+    }
+    return vertex;
+}
+
+/// Clips a convex polygon against every plane of [`CLIP_PLANES`]
+/// (Sutherland-Hodgman). Clip space is linear, so the varyings are too.
+fn clip_polygon<const N: usize>(mut polygon: Vec<ClipVertex<N>>) -> Vec<ClipVertex<N>> {
+    for plane in CLIP_PLANES {
+        if polygon.is_empty() {
+            break;
+        }
+        let mut clipped = Vec::with_capacity(polygon.len() + 2);
+        for (i, current) in polygon.iter().enumerate() {
+            let next = &polygon[(i + 1) % polygon.len()];
+            let (current_distance, next_distance) =
+                (plane_distance(plane, current.position), plane_distance(plane, next.position));
+            if current_distance >= 0.0 {
+                clipped.push(*current);
+            }
+            if (current_distance >= 0.0) != (next_distance >= 0.0) {
+                let t = current_distance / (current_distance - next_distance);
+                clipped.push(lerp_vertex(current, next, t));
+            }
+        }
+        polygon = clipped;
+    }
+    return polygon;
+}
+
+/// A vertex after the perspective divide and the viewport transform, with
+/// the varyings divided by `w` so they interpolate linearly on screen
+#[derive(Debug, Clone, Copy)]
+struct ScreenVertex<const N: usize> {
+    x: f32,
+    y: f32,
+    depth: f32,
+    inverse_w: f32,
+    varyings: [f32; N],
+}
+
+/// Whether an edge is the top or a left edge of a triangle that is
+/// clockwise on screen, which owns the pixel centres exactly on it
+fn is_top_left<const N: usize>(from: &ScreenVertex<N>, to: &ScreenVertex<N>) -> bool {
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    return (dy == 0.0 && dx > 0.0) || dy < 0.0;
+}
+
+fn edge<const N: usize>(from: &ScreenVertex<N>, to: &ScreenVertex<N>, [x, y]: [f32; 2]) -> f32 {
+    return (to.x - from.x) * (y - from.y) - (to.y - from.y) * (x - from.x);
+}
+
+/// How triangles are culled and depth tested, see the module documentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pipeline {
+    cull: CullMode,
+    front_face: FrontFace,
+    depth_test: DepthTest,
+    depth_write: bool,
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        return Pipeline::new();
+    }
+}
+
+impl Pipeline {
+    /// Back faces culled, counter-clockwise front faces, depth tested with
+    /// [`DepthTest::Less`] and written
+    pub fn new() -> Self {
+        return Pipeline {
+            cull: CullMode::Back,
+            front_face: FrontFace::CounterClockwise,
+            depth_test: DepthTest::Less,
+            depth_write: true,
+        };
+    }
+
+    pub fn cull(mut self, cull: CullMode) -> Self {
+        self.cull = cull;
+        return self;
+    }
+
+    pub fn front_face(mut self, front_face: FrontFace) -> Self {
+        self.front_face = front_face;
+        return self;
+    }
+
+    pub fn depth_test(mut self, depth_test: DepthTest) -> Self {
+        self.depth_test = depth_test;
+        return self;
+    }
+
+    /// Whether drawn fragments store their depth, off for transparent
+    /// geometry drawn after the opaque one
+    pub fn depth_write(mut self, depth_write: bool) -> Self {
+        self.depth_write = depth_write;
+        return self;
+    }
+
+    /// Draws the `triangles`, indices into `vertices`, into `canvas`
+    ///
+    /// `vertex_shader` runs once per vertex. `fragment_shader` runs for
+    /// every covered pixel that passes the depth test and returns the colour
+    /// to blend over the canvas, or `None` to discard the pixel. Without a
+    /// depth buffer every pixel passes and nothing is written. The clip of
+    /// the canvas applies, its transform doesn't.
+    pub fn draw<V, const N: usize>(
+        &self,
+        canvas: &mut Canvas,
+        mut depth: Option<&mut DepthBuffer>,
+        vertices: &[V],
+        triangles: &[[u32; 3]],
+        vertex_shader: impl Fn(&V) -> ClipVertex<N>,
+        mut fragment_shader: impl FnMut(&Fragment<N>) -> Option<Color>,
+    ) -> Result<DrawStats, RenderError> {
+        if let Some(depth) = depth.as_deref() {
+            if [depth.width, depth.height] != [canvas.width(), canvas.height()] {
+                return Err(RenderError::DepthBufferSize {
+                    expected: [canvas.width(), canvas.height()],
+                    actual: [depth.width, depth.height],
+                });
+            }
+        }
+        if let Some(&index) = triangles.iter().flatten().find(|&&index| index as usize >= vertices.len()) {
+            return Err(RenderError::IndexOutOfRange { index, vertex_count: vertices.len() });
+        }
+
+        let transformed: Vec<ClipVertex<N>> = vertices.iter().map(vertex_shader).collect();
+        let (width, height) = (canvas.width() as f32, canvas.height() as f32);
+        let mut stats = DrawStats::default();
+        for triangle in triangles {
+            stats.triangles += 1;
+            let corners = triangle.map(|index| transformed[index as usize]);
+            let inside = corners.iter().all(|corner| CLIP_PLANES.iter().all(|&plane| plane_distance(plane, corner.position) >= 0.0));
+            let polygon = if inside { corners.to_vec() } else { clip_polygon(corners.to_vec()) };
+            if polygon.len() < 3 {
+                stats.clipped += 1;
+                continue;
+            }
+
+            let screen: Vec<ScreenVertex<N>> = polygon
+                .iter()
+                .map(|vertex| {
+                    let [x, y, z, w] = vertex.position;
+                    let inverse_w = 1.0 / w;
+                    return ScreenVertex {
+                        x: (x * inverse_w + 1.0) * 0.5 * width,
+                        y: (1.0 - y * inverse_w) * 0.5 * height,
+                        depth: (z * inverse_w + 1.0) * 0.5,
+                        inverse_w,
+                        varyings: vertex.varyings.map(|value| value * inverse_w),
+                    };
+                })
+                .collect();
+
+            // Twice the signed area on screen, where y points down, so
+            // counter-clockwise in device coordinates is negative
+            let area: f32 = (0..screen.len())
+                .map(|i| {
+                    let (a, b) = (&screen[i], &screen[(i + 1) % screen.len()]);
+                    return a.x * b.y - b.x * a.y;
+                })
+                .sum();
+            if area == 0.0 {
+                continue;
+            }
+            let front_facing = (area < 0.0) == (self.front_face == FrontFace::CounterClockwise);
+            let culled = match self.cull {
+                CullMode::None => false,
+                CullMode::Back => !front_facing,
+                CullMode::Front => front_facing,
+            };
+            if culled {
+                stats.culled += 1;
+                continue;
+            }
+            for i in 1..screen.len() - 1 {
+                let corners = [&screen[0], &screen[i], &screen[i + 1]];
+                self.rasterize(canvas, depth.as_deref_mut(), corners, front_facing, &mut fragment_shader, &mut stats);
+            }
+        }
+        return Ok(stats);
+    }
+
+    fn rasterize<const N: usize>(
+        &self,
+        canvas: &mut Canvas,
+        mut depth: Option<&mut DepthBuffer>,
+        [a, mut b, mut c]: [&ScreenVertex<N>; 3],
+        front_facing: bool,
+        fragment_shader: &mut impl FnMut(&Fragment<N>) -> Option<Color>,
+        stats: &mut DrawStats,
+    ) {
+        let mut area = edge(a, b, [c.x, c.y]);
+        if area == 0.0 {
+            return;
+        }
+        // The edge functions below are positive inside clockwise triangles
+        if area < 0.0 {
+            (b, c) = (c, b);
+            area = -area;
+        }
+        let clip = canvas.clip_rect();
+        let left = (a.x.min(b.x).min(c.x) - 0.5).ceil().max(clip.left as f32) as i32;
+        let right = (a.x.max(b.x).max(c.x) - 0.5).floor().min((clip.right - 1) as f32) as i32;
+        let top = (a.y.min(b.y).min(c.y) - 0.5).ceil().max(clip.top as f32) as i32;
+        let bottom = (a.y.max(b.y).max(c.y) - 0.5).floor().min((clip.bottom - 1) as f32) as i32;
+        let owns = [is_top_left(b, c), is_top_left(c, a), is_top_left(a, b)];
+
+        for y in top..=bottom {
+            for x in left..=right {
+                let center = [x as f32 + 0.5, y as f32 + 0.5];
+                let weights = [edge(b, c, center), edge(c, a, center), edge(a, b, center)];
+                if weights.iter().zip(&owns).any(|(&weight, &owns)| weight < 0.0 || (weight == 0.0 && !owns)) {
+                    continue;
+                }
+                let [la, lb, lc] = weights.map(|weight| weight / area);
+                let fragment_depth = la * a.depth + lb * b.depth + lc * c.depth;
+                let index = y as usize * canvas.width() as usize + x as usize;
+                if let Some(depth) = depth.as_deref() {
+                    if !self.depth_test.passes(fragment_depth, depth.depths[index]) {
+                        continue;
+                    }
+                }
+                let inverse_w = la * a.inverse_w + lb * b.inverse_w + lc * c.inverse_w;
+                let mut varyings = [0.0; N];
+                for (i, value) in varyings.iter_mut().enumerate() {
+                    *value = (la * a.varyings[i] + lb * b.varyings[i] + lc * c.varyings[i]) / inverse_w;
+                }
+                let fragment = Fragment { position: [x as u32, y as u32], depth: fragment_depth, front_facing, varyings };
+                stats.fragments += 1;
+                let Some(color) = fragment_shader(&fragment) else {
+                    continue;
+                };
+                stats.written += 1;
+                if self.depth_write {
+                    if let Some(depth) = depth.as_deref_mut() {
+                        depth.depths[index] = fragment_depth;
+                    }
+                }
+                canvas.blend_pixel(x, y, color, 1.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Rect;
+
+    fn vertex(x: f32, y: f32, z: f32) -> ClipVertex<1> {
+        return ClipVertex { position: [x, y, z, 1.0], varyings: [0.0] };
+    }
+
+    /// A counter-clockwise triangle over the whole viewport, clipped to it
+    fn full_screen(z: f32) -> [ClipVertex<1>; 3] {
+        return [vertex(-1.0, -1.0, z), vertex(3.0, -1.0, z), vertex(-1.0, 3.0, z)];
+    }
+
+    /// How many times the fragment shader ran for every pixel of an 8x8 canvas
+    fn coverage(pipeline: Pipeline, vertices: &[ClipVertex<1>], triangles: &[[u32; 3]]) -> (Vec<u32>, DrawStats) {
+        let mut canvas = Canvas::new(8, 8);
+        let mut counts = vec![0; 64];
+        let shade = |fragment: &Fragment<1>| {
+            counts[(fragment.position[1] * 8 + fragment.position[0]) as usize] += 1;
+            return Some(Color::BLACK);
+        };
+        let stats = pipeline.draw(&mut canvas, None, vertices, triangles, |v| *v, shade).unwrap();
+        return (counts, stats);
+    }
+
+    fn assert_close(actual: [f32; 4], expected: [f32; 4]) {
+        assert!(actual.iter().zip(&expected).all(|(a, e)| (a - e).abs() < 1e-5), "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn matrices_transform_points() {
+        assert_close(Mat4::translate([1.0, 2.0, 3.0]).apply_point([1.0, 1.0, 1.0]), [2.0, 3.0, 4.0, 1.0]);
+        assert_close(Mat4::scale([2.0, 3.0, 4.0]).apply([1.0, 1.0, 1.0, 0.0]), [2.0, 3.0, 4.0, 0.0]);
+        let quarter = Mat4::rotate([0.0, 0.0, 2.0], std::f32::consts::FRAC_PI_2);
+        assert_close(quarter.apply_point([1.0, 0.0, 0.0]), [0.0, 1.0, 0.0, 1.0]);
+        // Scaled then moved, not the other way around
+        let combined = Mat4::translate([1.0, 0.0, 0.0]).after(&Mat4::scale([2.0, 2.0, 2.0]));
+        assert_close(combined.apply_point([1.0, 1.0, 0.0]), [3.0, 2.0, 0.0, 1.0]);
+        assert_eq!(Mat4::default().after(&quarter), quarter);
+
+        let camera = Mat4::look_at([0.0, 0.0, 5.0], [0.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+        assert_close(camera.apply_point([0.0, 0.0, 0.0]), [0.0, 0.0, -5.0, 1.0]);
+        assert_close(camera.apply_point([0.0, 1.0, 5.0]), [0.0, 1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn projections_map_the_view_volume_to_clip_space() {
+        let ndc = |[x, y, z, w]: [f32; 4]| [x / w, y / w, z / w, 1.0];
+        let perspective = Mat4::perspective(std::f32::consts::FRAC_PI_2, 2.0, 1.0, 10.0);
+        assert_close(ndc(perspective.apply_point([0.0, 0.0, -1.0])), [0.0, 0.0, -1.0, 1.0]);
+        assert_close(ndc(perspective.apply_point([0.0, 0.0, -10.0])), [0.0, 0.0, 1.0, 1.0]);
+        // A 90 degree field of view reaches the top at the distance, twice that sideways
+        assert_close(ndc(perspective.apply_point([4.0, 2.0, -2.0])), [1.0, 1.0, 1.0 / 9.0, 1.0]);
+
+        let orthographic = Mat4::orthographic(0.0, 8.0, 0.0, 4.0, 1.0, 3.0);
+        assert_close(orthographic.apply_point([0.0, 0.0, -1.0]), [-1.0, -1.0, -1.0, 1.0]);
+        assert_close(orthographic.apply_point([8.0, 4.0, -3.0]), [1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn shared_edges_draw_every_pixel_once() {
+        // Four triangles meeting at a pixel centre, with edges through a column and a row of centres
+        let corners = [[-1.0, -1.0], [1.0, -1.0], [1.0, 1.0], [-1.0, 1.0], [0.125, -0.125]];
+        let vertices = corners.map(|[x, y]| vertex(x, y, 0.0));
+        let triangles = [[0, 1, 4], [1, 2, 4], [2, 3, 4], [3, 0, 4]];
+        let (counts, stats) = coverage(Pipeline::new(), &vertices, &triangles);
+        assert_eq!(counts, vec![1; 64]);
+        assert_eq!((stats.triangles, stats.fragments, stats.written), (4, 64, 64));
+        let (counts, _) = coverage(Pipeline::new(), &full_screen(0.0), &[[0, 1, 2]]);
+        assert_eq!(counts, vec![1; 64]);
+    }
+
+    #[test]
+    fn triangles_are_culled_by_facing() {
+        let clockwise = [vertex(-1.0, -1.0, 0.0), vertex(-1.0, 1.0, 0.0), vertex(1.0, -1.0, 0.0)];
+        let (_, stats) = coverage(Pipeline::new(), &clockwise, &[[0, 1, 2]]);
+        assert_eq!((stats.culled, stats.fragments), (1, 0));
+        let (_, stats) = coverage(Pipeline::new().front_face(FrontFace::Clockwise), &clockwise, &[[0, 1, 2]]);
+        assert_eq!((stats.culled, stats.fragments), (0, 28));
+        let (_, stats) = coverage(Pipeline::new().cull(CullMode::Front), &full_screen(0.0), &[[0, 1, 2]]);
+        assert_eq!((stats.culled, stats.fragments), (1, 0));
+
+        let mut canvas = Canvas::new(8, 8);
+        let mut facing = Vec::new();
+        let pipeline = Pipeline::new().cull(CullMode::None);
+        pipeline
+            .draw(&mut canvas, None, &clockwise, &[[0, 1, 2], [0, 2, 1]], |v| *v, |fragment| {
+                facing.push(fragment.front_facing);
+                return None;
+            })
+            .unwrap();
+        assert_eq!(facing.iter().filter(|&&front| front).count(), 28);
+        assert_eq!(facing.len(), 56);
+    }
+
+    #[test]
+    fn triangles_are_clipped_to_the_frustum() {
+        let outside = [vertex(1.5, 0.0, 0.0), vertex(3.0, 0.0, 0.0), vertex(2.0, 1.0, 0.0)];
+        let (_, stats) = coverage(Pipeline::new(), &outside, &[[0, 1, 2]]);
+        assert_eq!((stats.clipped, stats.fragments), (1, 0));
+        let beyond_far = full_screen(1.5);
+        assert_eq!(coverage(Pipeline::new(), &beyond_far, &[[0, 1, 2]]).1.clipped, 1);
+
+        // One corner behind the camera, with w below 0
+        let mut behind = [vertex(-0.5, -0.5, 0.0), vertex(0.5, -0.5, 0.0), vertex(0.0, 1.0, 0.0)];
+        behind[2].position[3] = -1.0;
+        let (counts, stats) = coverage(Pipeline::new().cull(CullMode::None), &behind, &[[0, 1, 2]]);
+        assert_eq!(stats.clipped, 0);
+        assert!(counts.iter().all(|&count| count <= 1) && stats.fragments > 0);
+
+        // Clipping keeps the varyings of the parts left, here the normalized x
+        let mut canvas = Canvas::new(8, 8);
+        let mut vertices = full_screen(0.0);
+        for vertex in &mut vertices {
+            vertex.varyings = [vertex.position[0]];
+        }
+        let stats = Pipeline::new()
+            .draw(&mut canvas, None, &vertices, &[[0, 1, 2]], |v| *v, |fragment| {
+                let x = (fragment.position[0] as f32 + 0.5) / 4.0 - 1.0;
+                assert!((fragment.varyings[0] - x).abs() < 1e-4, "{:?}", fragment);
+                return None;
+            })
+            .unwrap();
+        assert_eq!((stats.fragments, stats.written), (64, 0));
+    }
+
+    #[test]
+    fn varyings_are_perspective_correct() {
+        // Clip x and w interpolate linearly in 3D, so their ratio is the normalized x of the pixel
+        let corners = [[-1.0, -1.0, 1.0], [4.0, -4.0, 4.0], [-2.0, 2.0, 2.0]];
+        let vertices = corners.map(|[x, y, w]| ClipVertex { position: [x, y, 0.0, w], varyings: [x, w] });
+        let mut canvas = Canvas::new(8, 8);
+        let stats = Pipeline::new()
+            .draw(&mut canvas, None, &vertices, &[[0, 1, 2]], |v| *v, |fragment| {
+                let x = (fragment.position[0] as f32 + 0.5) / 4.0 - 1.0;
+                assert!((fragment.varyings[0] / fragment.varyings[1] - x).abs() < 1e-4, "{:?}", fragment);
+                return None;
+            })
+            .unwrap();
+        // The lower left half, without the centres on the diagonal
+        assert_eq!(stats.fragments, 28);
+    }
+
+    #[test]
+    fn nearer_fragments_win_the_depth_test() {
+        let mut vertices = full_screen(0.5).to_vec();
+        vertices.extend(full_screen(-0.5));
+        let (far, near) = ([0, 1, 2], [3, 4, 5]);
+        let draw = |pipeline: Pipeline, depth: &mut DepthBuffer, triangles: &[[u32; 3]]| {
+            let mut canvas = Canvas::new(8, 8);
+            let stats = pipeline
+                .draw(&mut canvas, Some(depth), &vertices, triangles, |v| *v, |fragment| {
+                    return Some(if fragment.depth < 0.5 { Color::rgb(0, 255, 0) } else { Color::rgb(255, 0, 0) });
+                })
+                .unwrap();
+            return (canvas.pixel(3, 3), stats.written);
+        };
+
+        let green = Some(Color::rgb(0, 255, 0));
+        let mut depth = DepthBuffer::new(8, 8);
+        assert_eq!(draw(Pipeline::new(), &mut depth, &[far, near]), (green, 128));
+        assert_eq!(depth.depth(3, 3), Some(0.25));
+        depth.clear(1.0);
+        assert_eq!(draw(Pipeline::new(), &mut depth, &[near, far]), (green, 64));
+        // Equal depths only pass with LessEqual
+        assert_eq!(draw(Pipeline::new(), &mut depth, &[near]).1, 0);
+        assert_eq!(draw(Pipeline::new().depth_test(DepthTest::LessEqual), &mut depth, &[near]).1, 64);
+        assert_eq!(draw(Pipeline::new().depth_test(DepthTest::Greater), &mut depth, &[far]).1, 64);
+        assert_eq!(depth.depth(0, 0), Some(0.75));
+        assert_eq!(draw(Pipeline::new().depth_test(DepthTest::GreaterEqual), &mut depth, &[far]).1, 64);
+
+        // Without writes the buffer keeps the far depth, and Always ignores it
+        let mut depth = DepthBuffer::new(8, 8);
+        assert_eq!(draw(Pipeline::new().depth_write(false), &mut depth, &[far, near]).1, 128);
+        assert!(depth.depths().iter().all(|&value| value == 1.0));
+        depth.clear(0.0);
+        assert_eq!(draw(Pipeline::new().depth_test(DepthTest::Always), &mut depth, &[far]).1, 64);
+        assert_eq!(depth.depth(8, 0), None);
+    }
+
+    #[test]
+    fn discarded_fragments_leave_no_trace() {
+        let mut canvas = Canvas::new(8, 8);
+        let mut depth = DepthBuffer::new(8, 8);
+        let stats = Pipeline::new()
+            .draw(&mut canvas, Some(&mut depth), &full_screen(0.0), &[[0, 1, 2]], |v| *v, |fragment| {
+                return (fragment.position[0] < 2).then_some(Color::BLACK);
+            })
+            .unwrap();
+        assert_eq!((stats.fragments, stats.written), (64, 16));
+        assert_eq!((depth.depth(1, 0), depth.depth(2, 0)), (Some(0.5), Some(1.0)));
+        assert_eq!((canvas.pixel(1, 0), canvas.pixel(2, 0)), (Some(Color::BLACK), Some(Color::TRANSPARENT)));
+    }
+
+    #[test]
+    fn the_canvas_clip_limits_drawing() {
+        let mut canvas = Canvas::new(8, 8);
+        canvas.clip(Rect::new(2, 2, 5, 4));
+        let stats = Pipeline::new()
+            .draw(&mut canvas, None, &full_screen(0.0), &[[0, 1, 2]], |v| *v, |_| Some(Color::BLACK))
+            .unwrap();
+        assert_eq!(stats.written, 6);
+        assert_eq!((canvas.pixel(2, 2), canvas.pixel(5, 2)), (Some(Color::BLACK), Some(Color::TRANSPARENT)));
+    }
+
+    #[test]
+    fn bad_buffers_and_indices_are_rejected() {
+        let mut canvas = Canvas::new(8, 8);
+        let mut depth = DepthBuffer::new(8, 4);
+        let vertices = full_screen(0.0);
+        let never = |_: &Fragment<1>| -> Option<Color> { panic!("nothing should be drawn") };
+        assert_eq!(
+            Pipeline::new().draw(&mut canvas, Some(&mut depth), &vertices, &[[0, 1, 2]], |v| *v, never),
+            Err(RenderError::DepthBufferSize { expected: [8, 8], actual: [8, 4] }),
+        );
+        assert_eq!(
+            Pipeline::new().draw(&mut canvas, None, &vertices, &[[0, 1, 2], [2, 3, 0]], |v| *v, never),
+            Err(RenderError::IndexOutOfRange { index: 3, vertex_count: 3 }),
+        );
+    }
+}
@@ -0,0 +1,487 @@
+//! Text input state shared by every backend
+//!
+//! Win32 delivers typed text as UTF-16 code units, one `WM_CHAR` per unit,
+//! so characters outside the Basic Multilingual Plane arrive as two
+//! messages. [`TextInput`] reassembles them, tracks dead keys and turns IME
+//! compositions into [`Ime`] events.
+
+use crate::event::{EventQueue, Ime, WindowEvent};
+
+/// Reassembles a stream of UTF-16 code units into `char`s
+///
+/// Unpaired surrogates are replaced with `U+FFFD REPLACEMENT CHARACTER`
+/// instead of being dropped, so the user can see that something was lost.
+#[derive(Debug, Default, Clone)]
+pub struct Utf16Decoder {
+    high_surrogate: Option<u16>,
+}
+
+fn is_high_surrogate(unit: u16) -> bool {
+    return (0xD800..0xDC00).contains(&unit);
+}
+
+fn is_low_surrogate(unit: u16) -> bool {
+    return (0xDC00..0xE000).contains(&unit);
+}
+
+impl Utf16Decoder {
+    pub fn new() -> Self {
+        return Utf16Decoder::default();
+    }
+
+    /// Feeds one code unit, calling `emit` for every completed character.
+    /// That is zero, one, or two characters (when a dangling high surrogate
+    /// is flushed as a replacement character).
+    pub fn push(&mut self, unit: u16, mut emit: impl FnMut(char)) {
+        if let Some(high) = self.high_surrogate.take() {
+            if is_low_surrogate(unit) {
+                let c = 0x10000 + (((high as u32) - 0xD800) << 10) + ((unit as u32) - 0xDC00);
+                emit(char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER));
+                return;
+            }
+            emit(char::REPLACEMENT_CHARACTER);
+        }
+
+        if is_high_surrogate(unit) {
+            self.high_surrogate = Some(unit);
+        } else if is_low_surrogate(unit) {
+            emit(char::REPLACEMENT_CHARACTER);
+        } else {
+            emit(char::from_u32(unit as u32).unwrap_or(char::REPLACEMENT_CHARACTER));
+        }
+    }
+
+    /// `true` if half of a surrogate pair is waiting for its other half
+    pub fn is_pending(&self) -> bool {
+        return self.high_surrogate.is_some();
+    }
+
+    /// Drops a pending high surrogate, emitting a replacement character for it
+    pub fn flush(&mut self, mut emit: impl FnMut(char)) {
+        if self.high_surrogate.take().is_some() {
+            emit(char::REPLACEMENT_CHARACTER);
+        }
+    }
+}
+
+/// Decodes a complete UTF-16 string, replacing unpaired surrogates
+pub fn string_from_utf16_lossy(units: &[u16]) -> String {
+    return std::char::decode_utf16(units.iter().copied())
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect();
+}
+
+/// Converts an offset in UTF-16 code units into a byte offset into the
+/// same text encoded as UTF-8. Offsets past the end are clamped and an
+/// offset between the halves of a surrogate pair is moved to its start.
+pub fn utf16_offset_to_utf8(units: &[u16], offset: usize) -> usize {
+    let mut offset = offset.min(units.len());
+    if offset > 0 && offset < units.len() && is_high_surrogate(units[offset - 1]) && is_low_surrogate(units[offset]) {
+        offset -= 1;
+    }
+    return std::char::decode_utf16(units[..offset].iter().copied())
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER).len_utf8())
+        .sum();
+}
+
+/// Who combines dead keys with the character that follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadKeys {
+    /// The platform already sends the composed character (Win32 does
+    /// this in `TranslateMessage`), we only report the pending accent
+    System,
+
+    /// The backend only reports raw characters and we compose them
+    /// with [`compose_dead_key`]
+    Compose,
+}
+
+/// Per-window text input state
+#[derive(Debug, Clone)]
+pub struct TextInput {
+    decoder: Utf16Decoder,
+    dead_keys: DeadKeys,
+    dead_key: Option<char>,
+    composing: bool,
+}
+
+impl TextInput {
+    pub fn new(dead_keys: DeadKeys) -> Self {
+        return TextInput {
+            decoder: Utf16Decoder::new(),
+            dead_keys,
+            dead_key: None,
+            composing: false,
+        };
+    }
+
+    /// The dead key waiting for the next character, if any
+    pub fn pending_dead_key(&self) -> Option<char> {
+        return self.dead_key;
+    }
+
+    /// `true` while an IME composition is in progress
+    pub fn is_composing(&self) -> bool {
+        return self.composing;
+    }
+
+    /// A UTF-16 code unit of typed text (`WM_CHAR`)
+    pub fn utf16_unit(&mut self, unit: u16, events: &mut EventQueue) {
+        let mut decoded = [None; 2];
+        let mut count = 0;
+        self.decoder.push(unit, |c| {
+            decoded[count] = Some(c);
+            count += 1;
+        });
+        for c in decoded.into_iter().flatten() {
+            self.character(c, events);
+        }
+    }
+
+    /// A complete character of typed text (`WM_UNICHAR`, or a backend
+    /// that already decodes its input)
+    pub fn character(&mut self, c: char, events: &mut EventQueue) {
+        let c = match (self.dead_key.take(), self.dead_keys) {
+            (Some(dead), DeadKeys::Compose) => match compose_dead_key(dead, c) {
+                Some(composed) => composed,
+                None => {
+                    // No combination exists, so both are typed as-is,
+                    // like every desktop platform does
+                    events.push(WindowEvent::Text(dead));
+                    if c == ' ' {
+                        // dead key + space is the way to type the accent itself
+                        return;
+                    }
+                    c
+                }
+            },
+            _ => c,
+        };
+        events.push(WindowEvent::Text(c));
+    }
+
+    /// A dead key was pressed (`WM_DEADCHAR`). `unit` is the UTF-16 code
+    /// unit of the accent itself.
+    pub fn dead_key_unit(&mut self, unit: u16, events: &mut EventQueue) {
+        let dead = char::from_u32(unit as u32).unwrap_or(char::REPLACEMENT_CHARACTER);
+        self.dead_key(dead, events);
+    }
+
+    /// A dead key was pressed
+    pub fn dead_key(&mut self, dead: char, events: &mut EventQueue) {
+        if let Some(previous) = self.dead_key.take() {
+            // Two dead keys in a row type the first one
+            if self.dead_keys == DeadKeys::Compose {
+                events.push(WindowEvent::Text(previous));
+            }
+        }
+        self.dead_key = Some(dead);
+        events.push(WindowEvent::DeadKey(dead));
+    }
+
+    /// The IME started a composition
+    pub fn ime_start(&mut self, events: &mut EventQueue) {
+        if !self.composing {
+            self.composing = true;
+            events.push(WindowEvent::Ime(Ime::Start));
+        }
+    }
+
+    /// The IME composition string changed. `cursor` is in UTF-16 code units.
+    pub fn ime_preedit(&mut self, text: &[u16], cursor: Option<usize>, events: &mut EventQueue) {
+        self.ime_start(events);
+        let cursor = cursor.map(|cursor| utf16_offset_to_utf8(text, cursor));
+        events.push(WindowEvent::Ime(Ime::Preedit {
+            text: string_from_utf16_lossy(text),
+            cursor,
+        }));
+    }
+
+    /// The IME produced final text
+    pub fn ime_commit(&mut self, text: &[u16], events: &mut EventQueue) {
+        if text.is_empty() {
+            return;
+        }
+        self.ime_start(events);
+        events.push(WindowEvent::Ime(Ime::Commit(string_from_utf16_lossy(text))));
+    }
+
+    /// The IME composition ended, whether committed or cancelled
+    pub fn ime_end(&mut self, events: &mut EventQueue) {
+        if self.composing {
+            self.composing = false;
+            events.push(WindowEvent::Ime(Ime::Preedit { text: String::new(), cursor: None }));
+            events.push(WindowEvent::Ime(Ime::End));
+        }
+    }
+
+    /// Forget any half-typed input, e.g. when the window loses focus
+    pub fn reset(&mut self) {
+        self.decoder = Utf16Decoder::new();
+        self.dead_key = None;
+    }
+}
+
+/// Combining marks for the usual dead keys. Both the spacing accent
+/// characters and the combining characters are accepted as dead keys.
+const DEAD_KEY_MARKS: &[(char, char)] = &[
+    ('`', '\u{300}'),
+    ('\u{B4}', '\u{301}'),
+    ('\'', '\u{301}'),
+    ('^', '\u{302}'),
+    ('~', '\u{303}'),
+    ('\u{AF}', '\u{304}'),
+    ('\u{2D8}', '\u{306}'),
+    ('\u{2D9}', '\u{307}'),
+    ('\u{A8}', '\u{308}'),
+    ('"', '\u{308}'),
+    ('\u{2DA}', '\u{30A}'),
+    ('\u{2DD}', '\u{30B}'),
+    ('\u{2C7}', '\u{30C}'),
+    ('\u{B8}', '\u{327}'),
+    ('\u{2DB}', '\u{328}'),
+];
+
+/// Precomposed Latin characters as `(base, combining mark, result)`
+const COMPOSITIONS: &[(char, char, char)] = &[
+    ('A', '\u{300}', 'À'), ('A', '\u{301}', 'Á'), ('A', '\u{302}', 'Â'), ('A', '\u{303}', 'Ã'),
+    ('A', '\u{308}', 'Ä'), ('A', '\u{30A}', 'Å'), ('A', '\u{304}', 'Ā'), ('A', '\u{306}', 'Ă'),
+    ('A', '\u{328}', 'Ą'),
+    ('a', '\u{300}', 'à'), ('a', '\u{301}', 'á'), ('a', '\u{302}', 'â'), ('a', '\u{303}', 'ã'),
+    ('a', '\u{308}', 'ä'), ('a', '\u{30A}', 'å'), ('a', '\u{304}', 'ā'), ('a', '\u{306}', 'ă'),
+    ('a', '\u{328}', 'ą'),
+    ('C', '\u{301}', 'Ć'), ('C', '\u{302}', 'Ĉ'), ('C', '\u{307}', 'Ċ'), ('C', '\u{30C}', 'Č'),
+    ('C', '\u{327}', 'Ç'),
+    ('c', '\u{301}', 'ć'), ('c', '\u{302}', 'ĉ'), ('c', '\u{307}', 'ċ'), ('c', '\u{30C}', 'č'),
+    ('c', '\u{327}', 'ç'),
+    ('D', '\u{30C}', 'Ď'), ('d', '\u{30C}', 'ď'),
+    ('E', '\u{300}', 'È'), ('E', '\u{301}', 'É'), ('E', '\u{302}', 'Ê'), ('E', '\u{308}', 'Ë'),
+    ('E', '\u{304}', 'Ē'), ('E', '\u{306}', 'Ĕ'), ('E', '\u{307}', 'Ė'), ('E', '\u{328}', 'Ę'),
+    ('E', '\u{30C}', 'Ě'), ('E', '\u{303}', 'Ẽ'),
+    ('e', '\u{300}', 'è'), ('e', '\u{301}', 'é'), ('e', '\u{302}', 'ê'), ('e', '\u{308}', 'ë'),
+    ('e', '\u{304}', 'ē'), ('e', '\u{306}', 'ĕ'), ('e', '\u{307}', 'ė'), ('e', '\u{328}', 'ę'),
+    ('e', '\u{30C}', 'ě'), ('e', '\u{303}', 'ẽ'),
+    ('G', '\u{302}', 'Ĝ'), ('G', '\u{306}', 'Ğ'), ('G', '\u{307}', 'Ġ'), ('G', '\u{327}', 'Ģ'),
+    ('g', '\u{302}', 'ĝ'), ('g', '\u{306}', 'ğ'), ('g', '\u{307}', 'ġ'), ('g', '\u{327}', 'ģ'),
+    ('H', '\u{302}', 'Ĥ'), ('h', '\u{302}', 'ĥ'),
+    ('I', '\u{300}', 'Ì'), ('I', '\u{301}', 'Í'), ('I', '\u{302}', 'Î'), ('I', '\u{308}', 'Ï'),
+    ('I', '\u{303}', 'Ĩ'), ('I', '\u{304}', 'Ī'), ('I', '\u{306}', 'Ĭ'), ('I', '\u{328}', 'Į'),
+    ('I', '\u{307}', 'İ'),
+    ('i', '\u{300}', 'ì'), ('i', '\u{301}', 'í'), ('i', '\u{302}', 'î'), ('i', '\u{308}', 'ï'),
+    ('i', '\u{303}', 'ĩ'), ('i', '\u{304}', 'ī'), ('i', '\u{306}', 'ĭ'), ('i', '\u{328}', 'į'),
+    ('J', '\u{302}', 'Ĵ'), ('j', '\u{302}', 'ĵ'),
+    ('K', '\u{327}', 'Ķ'), ('k', '\u{327}', 'ķ'),
+    ('L', '\u{301}', 'Ĺ'), ('L', '\u{327}', 'Ļ'), ('L', '\u{30C}', 'Ľ'),
+    ('l', '\u{301}', 'ĺ'), ('l', '\u{327}', 'ļ'), ('l', '\u{30C}', 'ľ'),
+    ('N', '\u{303}', 'Ñ'), ('N', '\u{301}', 'Ń'), ('N', '\u{327}', 'Ņ'), ('N', '\u{30C}', 'Ň'),
+    ('n', '\u{303}', 'ñ'), ('n', '\u{301}', 'ń'), ('n', '\u{327}', 'ņ'), ('n', '\u{30C}', 'ň'),
+    ('O', '\u{300}', 'Ò'), ('O', '\u{301}', 'Ó'), ('O', '\u{302}', 'Ô'), ('O', '\u{303}', 'Õ'),
+    ('O', '\u{308}', 'Ö'), ('O', '\u{304}', 'Ō'), ('O', '\u{306}', 'Ŏ'), ('O', '\u{30B}', 'Ő'),
+    ('o', '\u{300}', 'ò'), ('o', '\u{301}', 'ó'), ('o', '\u{302}', 'ô'), ('o', '\u{303}', 'õ'),
+    ('o', '\u{308}', 'ö'), ('o', '\u{304}', 'ō'), ('o', '\u{306}', 'ŏ'), ('o', '\u{30B}', 'ő'),
+    ('R', '\u{301}', 'Ŕ'), ('R', '\u{327}', 'Ŗ'), ('R', '\u{30C}', 'Ř'),
+    ('r', '\u{301}', 'ŕ'), ('r', '\u{327}', 'ŗ'), ('r', '\u{30C}', 'ř'),
+    ('S', '\u{301}', 'Ś'), ('S', '\u{302}', 'Ŝ'), ('S', '\u{327}', 'Ş'), ('S', '\u{30C}', 'Š'),
+    ('s', '\u{301}', 'ś'), ('s', '\u{302}', 'ŝ'), ('s', '\u{327}', 'ş'), ('s', '\u{30C}', 'š'),
+    ('T', '\u{327}', 'Ţ'), ('T', '\u{30C}', 'Ť'), ('t', '\u{327}', 'ţ'), ('t', '\u{30C}', 'ť'),
+    ('U', '\u{300}', 'Ù'), ('U', '\u{301}', 'Ú'), ('U', '\u{302}', 'Û'), ('U', '\u{308}', 'Ü'),
+    ('U', '\u{303}', 'Ũ'), ('U', '\u{304}', 'Ū'), ('U', '\u{306}', 'Ŭ'), ('U', '\u{30A}', 'Ů'),
+    ('U', '\u{30B}', 'Ű'), ('U', '\u{328}', 'Ų'),
+    ('u', '\u{300}', 'ù'), ('u', '\u{301}', 'ú'), ('u', '\u{302}', 'û'), ('u', '\u{308}', 'ü'),
+    ('u', '\u{303}', 'ũ'), ('u', '\u{304}', 'ū'), ('u', '\u{306}', 'ŭ'), ('u', '\u{30A}', 'ů'),
+    ('u', '\u{30B}', 'ű'), ('u', '\u{328}', 'ų'),
+    ('W', '\u{302}', 'Ŵ'), ('w', '\u{302}', 'ŵ'),
+    ('Y', '\u{301}', 'Ý'), ('Y', '\u{302}', 'Ŷ'), ('Y', '\u{308}', 'Ÿ'),
+    ('y', '\u{301}', 'ý'), ('y', '\u{302}', 'ŷ'), ('y', '\u{308}', 'ÿ'),
+    ('Z', '\u{301}', 'Ź'), ('Z', '\u{307}', 'Ż'), ('Z', '\u{30C}', 'Ž'),
+    ('z', '\u{301}', 'ź'), ('z', '\u{307}', 'ż'), ('z', '\u{30C}', 'ž'),
+];
+
+/// Combines a dead key with the character typed after it
+///
+/// **Returns:** The composed character, or `None` when the pair has no
+/// precomposed form. Dead key followed by space gives the spacing accent.
+pub fn compose_dead_key(dead: char, base: char) -> Option<char> {
+    let mark = DEAD_KEY_MARKS
+        .iter()
+        .find(|(accent, mark)| *accent == dead || *mark == dead)
+        .map(|(_, mark)| *mark)?;
+
+    if base == ' ' {
+        if !('\u{300}'..='\u{36F}').contains(&dead) {
+            return Some(dead);
+        }
+        // the spacing version of a combining mark
+        return DEAD_KEY_MARKS
+            .iter()
+            .find(|(_, m)| *m == mark)
+            .map(|(accent, _)| *accent);
+    }
+
+    return COMPOSITIONS
+        .iter()
+        .find(|(b, m, _)| *b == base && *m == mark)
+        .map(|(_, _, composed)| *composed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// "a😀é" as UTF-16
+    const TEXT: [u16; 4] = [0x61, 0xD83D, 0xDE00, 0xE9];
+
+    fn decode(units: &[u16]) -> (String, bool) {
+        let mut decoder = Utf16Decoder::new();
+        let mut text = String::new();
+        for &unit in units {
+            decoder.push(unit, |c| text.push(c));
+        }
+        return (text, decoder.is_pending());
+    }
+
+    fn drain(events: &mut EventQueue) -> Vec<WindowEvent> {
+        return events.drain().collect();
+    }
+
+    #[test]
+    fn surrogate_pairs_are_reassembled() {
+        assert_eq!(decode(&TEXT), ("a😀é".to_owned(), false));
+        assert_eq!(decode(&TEXT[..2]), ("a".to_owned(), true));
+        // Unpaired halves become replacement characters
+        assert_eq!(decode(&[0xD83D, 0x62]), ("\u{FFFD}b".to_owned(), false));
+        assert_eq!(decode(&[0xDE00, 0x62]), ("\u{FFFD}b".to_owned(), false));
+        assert_eq!(decode(&[0xD83D, 0xD83D, 0xDE00]), ("\u{FFFD}😀".to_owned(), false));
+
+        let mut decoder = Utf16Decoder::new();
+        decoder.push(0xD83D, |_| panic!("half a pair is not a character"));
+        let mut flushed = Vec::new();
+        decoder.flush(|c| flushed.push(c));
+        decoder.flush(|c| flushed.push(c));
+        assert_eq!((flushed, decoder.is_pending()), (vec![char::REPLACEMENT_CHARACTER], false));
+
+        assert_eq!(string_from_utf16_lossy(&[0x61, 0xDE00, 0xD83D]), "a\u{FFFD}\u{FFFD}");
+    }
+
+    #[test]
+    fn utf16_offsets_become_utf8_offsets() {
+        let offsets: Vec<usize> = (0..6).map(|offset| utf16_offset_to_utf8(&TEXT, offset)).collect();
+        // Between the halves of the emoji is the start of the emoji
+        assert_eq!(offsets, vec![0, 1, 1, 5, 7, 7]);
+        assert_eq!(utf16_offset_to_utf8(&[0xD83D, 0x62], 1), 3);
+        assert_eq!(utf16_offset_to_utf8(&[], 3), 0);
+    }
+
+    #[test]
+    fn typed_units_become_text_events() {
+        let mut input = TextInput::new(DeadKeys::System);
+        let mut events = EventQueue::new();
+        for unit in TEXT {
+            input.utf16_unit(unit, &mut events);
+        }
+        input.utf16_unit(0xD83D, &mut events);
+        input.utf16_unit(0x62, &mut events);
+        let text: String = drain(&mut events)
+            .into_iter()
+            .map(|event| match event {
+                WindowEvent::Text(c) => c,
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(text, "a😀é\u{FFFD}b");
+
+        // Focus loss forgets half a pair
+        input.utf16_unit(0xD83D, &mut events);
+        input.reset();
+        input.utf16_unit(0x63, &mut events);
+        assert_eq!(drain(&mut events), vec![WindowEvent::Text('c')]);
+    }
+
+    #[test]
+    fn dead_keys_compose_when_asked_to() {
+        let mut input = TextInput::new(DeadKeys::Compose);
+        let mut events = EventQueue::new();
+        input.dead_key_unit('^' as u16, &mut events);
+        assert_eq!(input.pending_dead_key(), Some('^'));
+        input.character('e', &mut events);
+        assert_eq!(input.pending_dead_key(), None);
+        assert_eq!(drain(&mut events), vec![WindowEvent::DeadKey('^'), WindowEvent::Text('ê')]);
+
+        // Pairs without a precomposed form type both characters
+        input.dead_key('\u{B4}', &mut events);
+        input.character('q', &mut events);
+        let expected = vec![WindowEvent::DeadKey('\u{B4}'), WindowEvent::Text('\u{B4}'), WindowEvent::Text('q')];
+        assert_eq!(drain(&mut events), expected);
+
+        // Space types the accent, and a second dead key types the first
+        input.dead_key('~', &mut events);
+        input.character(' ', &mut events);
+        input.dead_key('`', &mut events);
+        input.dead_key('\u{A8}', &mut events);
+        input.character('u', &mut events);
+        let expected = vec![
+            WindowEvent::DeadKey('~'),
+            WindowEvent::Text('~'),
+            WindowEvent::DeadKey('`'),
+            WindowEvent::Text('`'),
+            WindowEvent::DeadKey('\u{A8}'),
+            WindowEvent::Text('ü'),
+        ];
+        assert_eq!(drain(&mut events), expected);
+    }
+
+    #[test]
+    fn system_dead_keys_are_only_reported() {
+        let mut input = TextInput::new(DeadKeys::System);
+        let mut events = EventQueue::new();
+        input.dead_key('^', &mut events);
+        input.dead_key('`', &mut events);
+        // The platform sends the composed character itself
+        input.character('è', &mut events);
+        let expected = vec![WindowEvent::DeadKey('^'), WindowEvent::DeadKey('`'), WindowEvent::Text('è')];
+        assert_eq!(drain(&mut events), expected);
+        assert_eq!(input.pending_dead_key(), None);
+    }
+
+    #[test]
+    fn dead_keys_combine_with_letters() {
+        assert_eq!(compose_dead_key('\'', 'E'), Some('É'));
+        assert_eq!(compose_dead_key('\u{30C}', 'z'), Some('ž'));
+        assert_eq!(compose_dead_key('\u{B8}', 'c'), Some('ç'));
+        assert_eq!(compose_dead_key('^', 'q'), None);
+        assert_eq!(compose_dead_key('x', 'e'), None);
+        assert_eq!(compose_dead_key('"', ' '), Some('"'));
+        // A combining mark followed by space gives its spacing accent
+        assert_eq!(compose_dead_key('\u{301}', ' '), Some('\u{B4}'));
+    }
+
+    #[test]
+    fn ime_compositions_become_events() {
+        let mut input = TextInput::new(DeadKeys::System);
+        let mut events = EventQueue::new();
+        input.ime_commit(&[], &mut events);
+        input.ime_end(&mut events);
+        assert!(events.is_empty() && !input.is_composing());
+
+        input.ime_preedit(&TEXT, Some(3), &mut events);
+        input.ime_preedit(&TEXT[..1], None, &mut events);
+        assert!(input.is_composing());
+        input.ime_commit(&TEXT, &mut events);
+        input.ime_end(&mut events);
+        input.ime_end(&mut events);
+        let expected = vec![
+            WindowEvent::Ime(Ime::Start),
+            WindowEvent::Ime(Ime::Preedit { text: "a😀é".to_owned(), cursor: Some(5) }),
+            WindowEvent::Ime(Ime::Preedit { text: "a".to_owned(), cursor: None }),
+            WindowEvent::Ime(Ime::Commit("a😀é".to_owned())),
+            WindowEvent::Ime(Ime::Preedit { text: String::new(), cursor: None }),
+            WindowEvent::Ime(Ime::End),
+        ];
+        assert_eq!(drain(&mut events), expected);
+
+        // Committing without a preedit still starts a composition
+        input.ime_commit(&[0x61], &mut events);
+        let expected = vec![WindowEvent::Ime(Ime::Start), WindowEvent::Ime(Ime::Commit("a".to_owned()))];
+        assert_eq!(drain(&mut events), expected);
+    }
+}
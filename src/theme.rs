@@ -0,0 +1,227 @@
+//! System colours, light/dark preference and accent colour
+//!
+//! Win32 resolves every [`SysColor`] with `GetSysColor` and reads the
+//! preference and accent colour from the user's settings. Platforms
+//! without system colours use a [`Palette`] chosen by the application,
+//! starting from [`Palette::light`] or [`Palette::dark`].
+
+/// The classic system colours. The value is the Win32 `COLOR_*` index.
+///
+/// See [`GetSysColor`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getsyscolor)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SysColor {
+    _3dDarkShadow = 21,
+    _3dLight = 22,
+    ActiveBorder = 10,
+    ActiveCaption = 2,
+    AppWorkspace = 12,
+    /// Button face, also "3D face" color.
+    ButtonFace = 15,
+    /// Button highlight, also "3D highlight" color.
+    ButtonHighlight = 20,
+    /// Button shadow, also "3D shadow" color.
+    ButtonShadow = 16,
+    ButtonText = 18,
+    CaptionText = 9,
+    /// Desktop background color
+    Desktop = 1,
+    GradientActiveCaption = 27,
+    GradientInactiveCaption = 28,
+    GrayText = 17,
+    Highlight = 13,
+    HighlightText = 14,
+    HotLight = 26,
+    InactiveBorder = 11,
+    InactiveCaption = 3,
+    InactiveCaptionText = 19,
+    InfoBackground = 24,
+    InfoText = 23,
+    Menu = 4,
+    MenuHighlight = 29,
+    MenuBar = 30,
+    MenuText = 7,
+    ScrollBar = 0,
+    Window = 5,
+    WindowFrame = 6,
+    WindowText = 8,
+}
+
+impl SysColor {
+    /// Every system colour, in `COLOR_*` order
+    pub const ALL: [SysColor; 30] = [
+        SysColor::ScrollBar, SysColor::Desktop, SysColor::ActiveCaption, SysColor::InactiveCaption,
+        SysColor::Menu, SysColor::Window, SysColor::WindowFrame, SysColor::MenuText,
+        SysColor::WindowText, SysColor::CaptionText, SysColor::ActiveBorder, SysColor::InactiveBorder,
+        SysColor::AppWorkspace, SysColor::Highlight, SysColor::HighlightText, SysColor::ButtonFace,
+        SysColor::ButtonShadow, SysColor::GrayText, SysColor::ButtonText, SysColor::InactiveCaptionText,
+        SysColor::ButtonHighlight, SysColor::_3dDarkShadow, SysColor::_3dLight, SysColor::InfoText,
+        SysColor::InfoBackground, SysColor::HotLight, SysColor::GradientActiveCaption,
+        SysColor::GradientInactiveCaption, SysColor::MenuHighlight, SysColor::MenuBar,
+    ];
+}
+
+/// Whether the user prefers light or dark applications
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorScheme {
+    #[default]
+    Light,
+    Dark,
+}
+
+/// An RGB value for every [`SysColor`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    /// Indexed by the `COLOR_*` value; index 25 is unused
+    colors: [[u8; 3]; 31],
+}
+
+impl Palette {
+    /// The default colours of Windows 10 in light mode
+    pub fn light() -> Self {
+        return Palette::from_pairs(&[
+            (SysColor::ScrollBar, [200, 200, 200]),
+            (SysColor::Desktop, [0, 0, 0]),
+            (SysColor::ActiveCaption, [153, 180, 209]),
+            (SysColor::InactiveCaption, [191, 205, 219]),
+            (SysColor::Menu, [240, 240, 240]),
+            (SysColor::Window, [255, 255, 255]),
+            (SysColor::WindowFrame, [100, 100, 100]),
+            (SysColor::MenuText, [0, 0, 0]),
+            (SysColor::WindowText, [0, 0, 0]),
+            (SysColor::CaptionText, [0, 0, 0]),
+            (SysColor::ActiveBorder, [180, 180, 180]),
+            (SysColor::InactiveBorder, [244, 247, 252]),
+            (SysColor::AppWorkspace, [171, 171, 171]),
+            (SysColor::Highlight, [0, 120, 215]),
+            (SysColor::HighlightText, [255, 255, 255]),
+            (SysColor::ButtonFace, [240, 240, 240]),
+            (SysColor::ButtonShadow, [160, 160, 160]),
+            (SysColor::GrayText, [109, 109, 109]),
+            (SysColor::ButtonText, [0, 0, 0]),
+            (SysColor::InactiveCaptionText, [0, 0, 0]),
+            (SysColor::ButtonHighlight, [255, 255, 255]),
+            (SysColor::_3dDarkShadow, [105, 105, 105]),
+            (SysColor::_3dLight, [227, 227, 227]),
+            (SysColor::InfoText, [0, 0, 0]),
+            (SysColor::InfoBackground, [255, 255, 225]),
+            (SysColor::HotLight, [0, 102, 204]),
+            (SysColor::GradientActiveCaption, [185, 209, 234]),
+            (SysColor::GradientInactiveCaption, [215, 228, 242]),
+            (SysColor::MenuHighlight, [51, 153, 255]),
+            (SysColor::MenuBar, [240, 240, 240]),
+        ]);
+    }
+
+    /// Dark counterparts of [`Palette::light`], close to what dark mode
+    /// applications on Windows 11 use
+    pub fn dark() -> Self {
+        return Palette::from_pairs(&[
+            (SysColor::ScrollBar, [77, 77, 77]),
+            (SysColor::Desktop, [0, 0, 0]),
+            (SysColor::ActiveCaption, [32, 32, 32]),
+            (SysColor::InactiveCaption, [43, 43, 43]),
+            (SysColor::Menu, [43, 43, 43]),
+            (SysColor::Window, [32, 32, 32]),
+            (SysColor::WindowFrame, [90, 90, 90]),
+            (SysColor::MenuText, [255, 255, 255]),
+            (SysColor::WindowText, [255, 255, 255]),
+            (SysColor::CaptionText, [255, 255, 255]),
+            (SysColor::ActiveBorder, [60, 60, 60]),
+            (SysColor::InactiveBorder, [43, 43, 43]),
+            (SysColor::AppWorkspace, [25, 25, 25]),
+            (SysColor::Highlight, [0, 120, 215]),
+            (SysColor::HighlightText, [255, 255, 255]),
+            (SysColor::ButtonFace, [51, 51, 51]),
+            (SysColor::ButtonShadow, [25, 25, 25]),
+            (SysColor::GrayText, [128, 128, 128]),
+            (SysColor::ButtonText, [255, 255, 255]),
+            (SysColor::InactiveCaptionText, [160, 160, 160]),
+            (SysColor::ButtonHighlight, [77, 77, 77]),
+            (SysColor::_3dDarkShadow, [0, 0, 0]),
+            (SysColor::_3dLight, [64, 64, 64]),
+            (SysColor::InfoText, [255, 255, 255]),
+            (SysColor::InfoBackground, [43, 43, 43]),
+            (SysColor::HotLight, [96, 205, 255]),
+            (SysColor::GradientActiveCaption, [32, 32, 32]),
+            (SysColor::GradientInactiveCaption, [43, 43, 43]),
+            (SysColor::MenuHighlight, [65, 65, 65]),
+            (SysColor::MenuBar, [43, 43, 43]),
+        ]);
+    }
+
+    /// The default palette of a colour scheme
+    pub fn for_scheme(scheme: ColorScheme) -> Self {
+        match scheme {
+            ColorScheme::Light => return Palette::light(),
+            ColorScheme::Dark => return Palette::dark(),
+        }
+    }
+
+    fn from_pairs(pairs: &[(SysColor, [u8; 3])]) -> Self {
+        let mut palette = Palette { colors: [[0, 0, 0]; 31] };
+        for &(color, rgb) in pairs {
+            palette.set(color, rgb);
+        }
+        return palette;
+    }
+
+    /// Replaces one colour
+    pub fn with(mut self, color: SysColor, rgb: [u8; 3]) -> Self {
+        self.set(color, rgb);
+        return self;
+    }
+
+    pub fn set(&mut self, color: SysColor, rgb: [u8; 3]) {
+        self.colors[color as usize] = rgb;
+    }
+
+    pub fn get(&self, color: SysColor) -> [u8; 3] {
+        return self.colors[color as usize];
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        return Palette::light();
+    }
+}
+
+/// The user's colour preferences
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Theme {
+    pub scheme: ColorScheme,
+    /// The colour the user picked for highlights, if the platform has one
+    pub accent: Option<[u8; 3]>,
+    pub palette: Palette,
+}
+
+impl Theme {
+    /// A theme with the default palette of `scheme` and no accent colour
+    pub fn new(scheme: ColorScheme) -> Self {
+        return Theme { scheme, accent: None, palette: Palette::for_scheme(scheme) };
+    }
+
+    pub fn accent(mut self, accent: [u8; 3]) -> Self {
+        self.accent = Some(accent);
+        return self;
+    }
+
+    pub fn palette(mut self, palette: Palette) -> Self {
+        self.palette = palette;
+        return self;
+    }
+
+    pub fn is_dark(&self) -> bool {
+        return self.scheme == ColorScheme::Dark;
+    }
+
+    /// The RGB value of a system colour
+    pub fn color(&self, color: SysColor) -> [u8; 3] {
+        return self.palette.get(color);
+    }
+
+    /// The accent colour, or the highlight colour when there is none
+    pub fn accent_or_highlight(&self) -> [u8; 3] {
+        return self.accent.unwrap_or_else(|| self.color(SysColor::Highlight));
+    }
+}
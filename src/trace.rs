@@ -0,0 +1,877 @@
+//! Recording of window messages for debugging
+//!
+//! A [`MessageTracer`] records every message a window procedure receives:
+//! its symbolic name, decoded parameters, window, time and what the
+//! procedure returned. Records can be filtered by window and message,
+//! printed as text or written as JSON lines to look at later.
+//!
+//! Messages are the Win32 numbers; [`message_name`] knows every `WM_*`
+//! message of `WinUser.h`. Windows are recorded as plain numbers so the
+//! records don't keep handles alive.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// `WM_*` names by message number, sorted by number
+const MESSAGE_NAMES: &[(u32, &str)] = &[
+    (0x0000, "WM_NULL"),
+    (0x0001, "WM_CREATE"),
+    (0x0002, "WM_DESTROY"),
+    (0x0003, "WM_MOVE"),
+    (0x0005, "WM_SIZE"),
+    (0x0006, "WM_ACTIVATE"),
+    (0x0007, "WM_SETFOCUS"),
+    (0x0008, "WM_KILLFOCUS"),
+    (0x000A, "WM_ENABLE"),
+    (0x000B, "WM_SETREDRAW"),
+    (0x000C, "WM_SETTEXT"),
+    (0x000D, "WM_GETTEXT"),
+    (0x000E, "WM_GETTEXTLENGTH"),
+    (0x000F, "WM_PAINT"),
+    (0x0010, "WM_CLOSE"),
+    (0x0011, "WM_QUERYENDSESSION"),
+    (0x0012, "WM_QUIT"),
+    (0x0013, "WM_QUERYOPEN"),
+    (0x0014, "WM_ERASEBKGND"),
+    (0x0015, "WM_SYSCOLORCHANGE"),
+    (0x0016, "WM_ENDSESSION"),
+    (0x0018, "WM_SHOWWINDOW"),
+    (0x001A, "WM_SETTINGCHANGE"),
+    (0x001B, "WM_DEVMODECHANGE"),
+    (0x001C, "WM_ACTIVATEAPP"),
+    (0x001D, "WM_FONTCHANGE"),
+    (0x001E, "WM_TIMECHANGE"),
+    (0x001F, "WM_CANCELMODE"),
+    (0x0020, "WM_SETCURSOR"),
+    (0x0021, "WM_MOUSEACTIVATE"),
+    (0x0022, "WM_CHILDACTIVATE"),
+    (0x0023, "WM_QUEUESYNC"),
+    (0x0024, "WM_GETMINMAXINFO"),
+    (0x0026, "WM_PAINTICON"),
+    (0x0027, "WM_ICONERASEBKGND"),
+    (0x0028, "WM_NEXTDLGCTL"),
+    (0x002A, "WM_SPOOLERSTATUS"),
+    (0x002B, "WM_DRAWITEM"),
+    (0x002C, "WM_MEASUREITEM"),
+    (0x002D, "WM_DELETEITEM"),
+    (0x002E, "WM_VKEYTOITEM"),
+    (0x002F, "WM_CHARTOITEM"),
+    (0x0030, "WM_SETFONT"),
+    (0x0031, "WM_GETFONT"),
+    (0x0032, "WM_SETHOTKEY"),
+    (0x0033, "WM_GETHOTKEY"),
+    (0x0037, "WM_QUERYDRAGICON"),
+    (0x0039, "WM_COMPAREITEM"),
+    (0x003D, "WM_GETOBJECT"),
+    (0x0041, "WM_COMPACTING"),
+    (0x0044, "WM_COMMNOTIFY"),
+    (0x0046, "WM_WINDOWPOSCHANGING"),
+    (0x0047, "WM_WINDOWPOSCHANGED"),
+    (0x0048, "WM_POWER"),
+    (0x004A, "WM_COPYDATA"),
+    (0x004B, "WM_CANCELJOURNAL"),
+    (0x004E, "WM_NOTIFY"),
+    (0x0050, "WM_INPUTLANGCHANGEREQUEST"),
+    (0x0051, "WM_INPUTLANGCHANGE"),
+    (0x0052, "WM_TCARD"),
+    (0x0053, "WM_HELP"),
+    (0x0054, "WM_USERCHANGED"),
+    (0x0055, "WM_NOTIFYFORMAT"),
+    (0x007B, "WM_CONTEXTMENU"),
+    (0x007C, "WM_STYLECHANGING"),
+    (0x007D, "WM_STYLECHANGED"),
+    (0x007E, "WM_DISPLAYCHANGE"),
+    (0x007F, "WM_GETICON"),
+    (0x0080, "WM_SETICON"),
+    (0x0081, "WM_NCCREATE"),
+    (0x0082, "WM_NCDESTROY"),
+    (0x0083, "WM_NCCALCSIZE"),
+    (0x0084, "WM_NCHITTEST"),
+    (0x0085, "WM_NCPAINT"),
+    (0x0086, "WM_NCACTIVATE"),
+    (0x0087, "WM_GETDLGCODE"),
+    (0x0088, "WM_SYNCPAINT"),
+    (0x00A0, "WM_NCMOUSEMOVE"),
+    (0x00A1, "WM_NCLBUTTONDOWN"),
+    (0x00A2, "WM_NCLBUTTONUP"),
+    (0x00A3, "WM_NCLBUTTONDBLCLK"),
+    (0x00A4, "WM_NCRBUTTONDOWN"),
+    (0x00A5, "WM_NCRBUTTONUP"),
+    (0x00A6, "WM_NCRBUTTONDBLCLK"),
+    (0x00A7, "WM_NCMBUTTONDOWN"),
+    (0x00A8, "WM_NCMBUTTONUP"),
+    (0x00A9, "WM_NCMBUTTONDBLCLK"),
+    (0x00AB, "WM_NCXBUTTONDOWN"),
+    (0x00AC, "WM_NCXBUTTONUP"),
+    (0x00AD, "WM_NCXBUTTONDBLCLK"),
+    (0x00FE, "WM_INPUT_DEVICE_CHANGE"),
+    (0x00FF, "WM_INPUT"),
+    (0x0100, "WM_KEYDOWN"),
+    (0x0101, "WM_KEYUP"),
+    (0x0102, "WM_CHAR"),
+    (0x0103, "WM_DEADCHAR"),
+    (0x0104, "WM_SYSKEYDOWN"),
+    (0x0105, "WM_SYSKEYUP"),
+    (0x0106, "WM_SYSCHAR"),
+    (0x0107, "WM_SYSDEADCHAR"),
+    (0x0109, "WM_UNICHAR"),
+    (0x010D, "WM_IME_STARTCOMPOSITION"),
+    (0x010E, "WM_IME_ENDCOMPOSITION"),
+    (0x010F, "WM_IME_COMPOSITION"),
+    (0x0110, "WM_INITDIALOG"),
+    (0x0111, "WM_COMMAND"),
+    (0x0112, "WM_SYSCOMMAND"),
+    (0x0113, "WM_TIMER"),
+    (0x0114, "WM_HSCROLL"),
+    (0x0115, "WM_VSCROLL"),
+    (0x0116, "WM_INITMENU"),
+    (0x0117, "WM_INITMENUPOPUP"),
+    (0x0119, "WM_GESTURE"),
+    (0x011A, "WM_GESTURENOTIFY"),
+    (0x011F, "WM_MENUSELECT"),
+    (0x0120, "WM_MENUCHAR"),
+    (0x0121, "WM_ENTERIDLE"),
+    (0x0122, "WM_MENURBUTTONUP"),
+    (0x0123, "WM_MENUDRAG"),
+    (0x0124, "WM_MENUGETOBJECT"),
+    (0x0125, "WM_UNINITMENUPOPUP"),
+    (0x0126, "WM_MENUCOMMAND"),
+    (0x0127, "WM_CHANGEUISTATE"),
+    (0x0128, "WM_UPDATEUISTATE"),
+    (0x0129, "WM_QUERYUISTATE"),
+    (0x0132, "WM_CTLCOLORMSGBOX"),
+    (0x0133, "WM_CTLCOLOREDIT"),
+    (0x0134, "WM_CTLCOLORLISTBOX"),
+    (0x0135, "WM_CTLCOLORBTN"),
+    (0x0136, "WM_CTLCOLORDLG"),
+    (0x0137, "WM_CTLCOLORSCROLLBAR"),
+    (0x0138, "WM_CTLCOLORSTATIC"),
+    (0x01E1, "MN_GETHMENU"),
+    (0x0200, "WM_MOUSEMOVE"),
+    (0x0201, "WM_LBUTTONDOWN"),
+    (0x0202, "WM_LBUTTONUP"),
+    (0x0203, "WM_LBUTTONDBLCLK"),
+    (0x0204, "WM_RBUTTONDOWN"),
+    (0x0205, "WM_RBUTTONUP"),
+    (0x0206, "WM_RBUTTONDBLCLK"),
+    (0x0207, "WM_MBUTTONDOWN"),
+    (0x0208, "WM_MBUTTONUP"),
+    (0x0209, "WM_MBUTTONDBLCLK"),
+    (0x020A, "WM_MOUSEWHEEL"),
+    (0x020B, "WM_XBUTTONDOWN"),
+    (0x020C, "WM_XBUTTONUP"),
+    (0x020D, "WM_XBUTTONDBLCLK"),
+    (0x020E, "WM_MOUSEHWHEEL"),
+    (0x0210, "WM_PARENTNOTIFY"),
+    (0x0211, "WM_ENTERMENULOOP"),
+    (0x0212, "WM_EXITMENULOOP"),
+    (0x0213, "WM_NEXTMENU"),
+    (0x0214, "WM_SIZING"),
+    (0x0215, "WM_CAPTURECHANGED"),
+    (0x0216, "WM_MOVING"),
+    (0x0218, "WM_POWERBROADCAST"),
+    (0x0219, "WM_DEVICECHANGE"),
+    (0x0220, "WM_MDICREATE"),
+    (0x0221, "WM_MDIDESTROY"),
+    (0x0222, "WM_MDIACTIVATE"),
+    (0x0223, "WM_MDIRESTORE"),
+    (0x0224, "WM_MDINEXT"),
+    (0x0225, "WM_MDIMAXIMIZE"),
+    (0x0226, "WM_MDITILE"),
+    (0x0227, "WM_MDICASCADE"),
+    (0x0228, "WM_MDIICONARRANGE"),
+    (0x0229, "WM_MDIGETACTIVE"),
+    (0x0230, "WM_MDISETMENU"),
+    (0x0231, "WM_ENTERSIZEMOVE"),
+    (0x0232, "WM_EXITSIZEMOVE"),
+    (0x0233, "WM_DROPFILES"),
+    (0x0234, "WM_MDIREFRESHMENU"),
+    (0x0238, "WM_POINTERDEVICECHANGE"),
+    (0x0239, "WM_POINTERDEVICEINRANGE"),
+    (0x023A, "WM_POINTERDEVICEOUTOFRANGE"),
+    (0x0240, "WM_TOUCH"),
+    (0x0241, "WM_NCPOINTERUPDATE"),
+    (0x0242, "WM_NCPOINTERDOWN"),
+    (0x0243, "WM_NCPOINTERUP"),
+    (0x0245, "WM_POINTERUPDATE"),
+    (0x0246, "WM_POINTERDOWN"),
+    (0x0247, "WM_POINTERUP"),
+    (0x0249, "WM_POINTERENTER"),
+    (0x024A, "WM_POINTERLEAVE"),
+    (0x024B, "WM_POINTERACTIVATE"),
+    (0x024C, "WM_POINTERCAPTURECHANGED"),
+    (0x024D, "WM_TOUCHHITTESTING"),
+    (0x024E, "WM_POINTERWHEEL"),
+    (0x024F, "WM_POINTERHWHEEL"),
+    (0x0251, "WM_POINTERROUTEDTO"),
+    (0x0252, "WM_POINTERROUTEDAWAY"),
+    (0x0253, "WM_POINTERROUTEDRELEASED"),
+    (0x0281, "WM_IME_SETCONTEXT"),
+    (0x0282, "WM_IME_NOTIFY"),
+    (0x0283, "WM_IME_CONTROL"),
+    (0x0284, "WM_IME_COMPOSITIONFULL"),
+    (0x0285, "WM_IME_SELECT"),
+    (0x0286, "WM_IME_CHAR"),
+    (0x0288, "WM_IME_REQUEST"),
+    (0x0290, "WM_IME_KEYDOWN"),
+    (0x0291, "WM_IME_KEYUP"),
+    (0x02A0, "WM_NCMOUSEHOVER"),
+    (0x02A1, "WM_MOUSEHOVER"),
+    (0x02A2, "WM_NCMOUSELEAVE"),
+    (0x02A3, "WM_MOUSELEAVE"),
+    (0x02B1, "WM_WTSSESSION_CHANGE"),
+    (0x02C0, "WM_TABLET_FIRST"),
+    (0x02DF, "WM_TABLET_LAST"),
+    (0x02E0, "WM_DPICHANGED"),
+    (0x02E2, "WM_DPICHANGED_BEFOREPARENT"),
+    (0x02E3, "WM_DPICHANGED_AFTERPARENT"),
+    (0x02E4, "WM_GETDPISCALEDSIZE"),
+    (0x0300, "WM_CUT"),
+    (0x0301, "WM_COPY"),
+    (0x0302, "WM_PASTE"),
+    (0x0303, "WM_CLEAR"),
+    (0x0304, "WM_UNDO"),
+    (0x0305, "WM_RENDERFORMAT"),
+    (0x0306, "WM_RENDERALLFORMATS"),
+    (0x0307, "WM_DESTROYCLIPBOARD"),
+    (0x0308, "WM_DRAWCLIPBOARD"),
+    (0x0309, "WM_PAINTCLIPBOARD"),
+    (0x030A, "WM_VSCROLLCLIPBOARD"),
+    (0x030B, "WM_SIZECLIPBOARD"),
+    (0x030C, "WM_ASKCBFORMATNAME"),
+    (0x030D, "WM_CHANGECBCHAIN"),
+    (0x030E, "WM_HSCROLLCLIPBOARD"),
+    (0x030F, "WM_QUERYNEWPALETTE"),
+    (0x0310, "WM_PALETTEISCHANGING"),
+    (0x0311, "WM_PALETTECHANGED"),
+    (0x0312, "WM_HOTKEY"),
+    (0x0317, "WM_PRINT"),
+    (0x0318, "WM_PRINTCLIENT"),
+    (0x0319, "WM_APPCOMMAND"),
+    (0x031A, "WM_THEMECHANGED"),
+    (0x031D, "WM_CLIPBOARDUPDATE"),
+    (0x031E, "WM_DWMCOMPOSITIONCHANGED"),
+    (0x031F, "WM_DWMNCRENDERINGCHANGED"),
+    (0x0320, "WM_DWMCOLORIZATIONCOLORCHANGED"),
+    (0x0321, "WM_DWMWINDOWMAXIMIZEDCHANGE"),
+    (0x0323, "WM_DWMSENDICONICTHUMBNAIL"),
+    (0x0326, "WM_DWMSENDICONICLIVEPREVIEWBITMAP"),
+    (0x033F, "WM_GETTITLEBARINFOEX"),
+    (0x0358, "WM_HANDHELDFIRST"),
+    (0x035F, "WM_HANDHELDLAST"),
+    (0x0360, "WM_AFXFIRST"),
+    (0x037F, "WM_AFXLAST"),
+    (0x0380, "WM_PENWINFIRST"),
+    (0x038F, "WM_PENWINLAST"),
+];
+
+const WM_USER: u32 = 0x0400;
+const WM_APP: u32 = 0x8000;
+const FIRST_REGISTERED_MESSAGE: u32 = 0xC000;
+
+/// The `WM_*` name of a system message
+pub fn message_name(message: u32) -> Option<&'static str> {
+    return MESSAGE_NAMES
+        .binary_search_by_key(&message, |(number, _)| *number)
+        .ok()
+        .map(|index| MESSAGE_NAMES[index].1);
+}
+
+/// A readable name for any message: the `WM_*` name of system messages,
+/// `WM_USER+n` or `WM_APP+n` for private ones, and the number otherwise
+pub fn describe_message(message: u32) -> String {
+    if let Some(name) = message_name(message) {
+        return name.to_owned();
+    } else if (WM_USER..WM_APP).contains(&message) {
+        return format!("WM_USER+{}", message - WM_USER);
+    } else if (WM_APP..FIRST_REGISTERED_MESSAGE).contains(&message) {
+        return format!("WM_APP+{}", message - WM_APP);
+    } else if (FIRST_REGISTERED_MESSAGE..=0xFFFF).contains(&message) {
+        return format!("registered 0x{:04X}", message);
+    } else {
+        return format!("0x{:04X}", message);
+    }
+}
+
+fn low_word(value: usize) -> u16 {
+    return (value & 0xFFFF) as u16;
+}
+
+fn high_word(value: usize) -> u16 {
+    return ((value >> 16) & 0xFFFF) as u16;
+}
+
+/// The signed coordinates packed in an `LPARAM`
+fn point(lparam: isize) -> (i16, i16) {
+    return (low_word(lparam as usize) as i16, high_word(lparam as usize) as i16);
+}
+
+/// The parameters of a message in words, for the messages whose layout is
+/// known; the raw values otherwise. Pointers are shown, not followed.
+pub fn decode_params(message: u32, wparam: usize, lparam: isize) -> String {
+    let raw = || format!("wparam=0x{:X} lparam=0x{:X}", wparam, lparam);
+    match message_name(message).unwrap_or("") {
+        "WM_MOVE" => {
+            let (x, y) = point(lparam);
+            return format!("x={} y={}", x, y);
+        }
+        "WM_SIZE" => {
+            let kind = match wparam {
+                0 => "restored",
+                1 => "minimized",
+                2 => "maximized",
+                3 => "max-show",
+                4 => "max-hide",
+                _ => "?",
+            };
+            return format!("{} width={} height={}", kind, low_word(lparam as usize), high_word(lparam as usize));
+        }
+        "WM_ACTIVATE" => {
+            let state = match low_word(wparam) {
+                0 => "inactive",
+                1 => "active",
+                2 => "click-active",
+                _ => "?",
+            };
+            return format!("{} minimized={} other=0x{:X}", state, high_word(wparam) != 0, lparam);
+        }
+        "WM_SETFOCUS" | "WM_KILLFOCUS" => return format!("other=0x{:X}", wparam),
+        "WM_ENABLE" | "WM_SHOWWINDOW" | "WM_NCACTIVATE" | "WM_ACTIVATEAPP" | "WM_SETREDRAW" => {
+            return format!("{} lparam=0x{:X}", wparam != 0, lparam);
+        }
+        "WM_SETCURSOR" => {
+            return format!("window=0x{:X} hit={} mouse={}", wparam, low_word(lparam as usize) as i16, high_word(lparam as usize));
+        }
+        "WM_NCHITTEST" | "WM_CONTEXTMENU" | "WM_NCMOUSEMOVE" | "WM_NCLBUTTONDOWN" | "WM_NCLBUTTONUP"
+        | "WM_NCLBUTTONDBLCLK" | "WM_NCRBUTTONDOWN" | "WM_NCRBUTTONUP" | "WM_NCMBUTTONDOWN" | "WM_NCMBUTTONUP" => {
+            let (x, y) = point(lparam);
+            return format!("screen x={} y={} wparam=0x{:X}", x, y, wparam);
+        }
+        "WM_MOUSEMOVE" | "WM_LBUTTONDOWN" | "WM_LBUTTONUP" | "WM_LBUTTONDBLCLK" | "WM_RBUTTONDOWN"
+        | "WM_RBUTTONUP" | "WM_RBUTTONDBLCLK" | "WM_MBUTTONDOWN" | "WM_MBUTTONUP" | "WM_MBUTTONDBLCLK"
+        | "WM_XBUTTONDOWN" | "WM_XBUTTONUP" | "WM_XBUTTONDBLCLK" | "WM_MOUSEHOVER" => {
+            let (x, y) = point(lparam);
+            return format!("x={} y={} keys=0x{:X}", x, y, low_word(wparam));
+        }
+        "WM_MOUSEWHEEL" | "WM_MOUSEHWHEEL" => {
+            let (x, y) = point(lparam);
+            return format!("delta={} keys=0x{:X} screen x={} y={}", high_word(wparam) as i16, low_word(wparam), x, y);
+        }
+        "WM_KEYDOWN" | "WM_KEYUP" | "WM_SYSKEYDOWN" | "WM_SYSKEYUP" => {
+            let flags = lparam as usize;
+            return format!(
+                "vk=0x{:02X} repeat={} scan=0x{:02X} extended={} alt={} was_down={}",
+                wparam,
+                low_word(flags),
+                (flags >> 16) & 0xFF,
+                flags & (1 << 24) != 0,
+                flags & (1 << 29) != 0,
+                flags & (1 << 30) != 0,
+            );
+        }
+        "WM_CHAR" | "WM_DEADCHAR" | "WM_SYSCHAR" | "WM_SYSDEADCHAR" | "WM_UNICHAR" | "WM_IME_CHAR" => {
+            let unit = wparam as u32;
+            return match char::from_u32(unit) {
+                Some(c) => format!("char={:?} (U+{:04X}) repeat={}", c, unit, low_word(lparam as usize)),
+                None => format!("code unit=0x{:04X} repeat={}", unit, low_word(lparam as usize)),
+            };
+        }
+        "WM_COMMAND" => {
+            if lparam != 0 {
+                return format!("id={} control=0x{:X} code={}", low_word(wparam), lparam, high_word(wparam));
+            } else if high_word(wparam) == 1 {
+                return format!("id={} accelerator", low_word(wparam));
+            } else {
+                return format!("id={} menu", low_word(wparam));
+            }
+        }
+        "WM_SYSCOMMAND" => {
+            let command = match wparam & 0xFFF0 {
+                0xF000 => "SC_SIZE",
+                0xF010 => "SC_MOVE",
+                0xF020 => "SC_MINIMIZE",
+                0xF030 => "SC_MAXIMIZE",
+                0xF060 => "SC_CLOSE",
+                0xF090 => "SC_KEYMENU",
+                0xF100 => "SC_MOUSEMENU",
+                0xF120 => "SC_RESTORE",
+                0xF140 => "SC_SCREENSAVE",
+                0xF170 => "SC_MONITORPOWER",
+                _ => "",
+            };
+            let (x, y) = point(lparam);
+            if command.is_empty() {
+                return format!("command=0x{:X} x={} y={}", wparam, x, y);
+            } else {
+                return format!("{} x={} y={}", command, x, y);
+            }
+        }
+        "WM_TIMER" => return format!("id={} callback=0x{:X}", wparam, lparam),
+        "WM_HOTKEY" => {
+            return format!("id={} modifiers=0x{:X} vk=0x{:02X}", wparam, low_word(lparam as usize), high_word(lparam as usize));
+        }
+        "WM_SETICON" | "WM_GETICON" => {
+            let size = match wparam {
+                0 => "small",
+                1 => "big",
+                2 => "small2",
+                _ => "?",
+            };
+            return format!("{} icon=0x{:X}", size, lparam);
+        }
+        "WM_DISPLAYCHANGE" => {
+            return format!("bpp={} width={} height={}", wparam, low_word(lparam as usize), high_word(lparam as usize));
+        }
+        "WM_POWERBROADCAST" => {
+            let event = match wparam {
+                0x4 => "PBT_APMSUSPEND".to_owned(),
+                0x7 => "PBT_APMRESUMESUSPEND".to_owned(),
+                0xA => "PBT_APMPOWERSTATUSCHANGE".to_owned(),
+                0x12 => "PBT_APMRESUMEAUTOMATIC".to_owned(),
+                0x8013 => "PBT_POWERSETTINGCHANGE".to_owned(),
+                other => format!("event=0x{:X}", other),
+            };
+            return format!("{} lparam=0x{:X}", event, lparam);
+        }
+        "WM_WTSSESSION_CHANGE" => {
+            let event = match wparam {
+                0x1 => "console connect".to_owned(),
+                0x2 => "console disconnect".to_owned(),
+                0x5 => "logon".to_owned(),
+                0x6 => "logoff".to_owned(),
+                0x7 => "lock".to_owned(),
+                0x8 => "unlock".to_owned(),
+                other => format!("event=0x{:X}", other),
+            };
+            return format!("{} session={}", event, lparam);
+        }
+        "WM_DPICHANGED" => {
+            return format!("dpi x={} y={} suggested rect=0x{:X}", low_word(wparam), high_word(wparam), lparam);
+        }
+        "WM_NCCALCSIZE" => return format!("calc_valid_rects={} params=0x{:X}", wparam != 0, lparam),
+        "WM_STYLECHANGING" | "WM_STYLECHANGED" => {
+            let which = match wparam as i32 {
+                -16 => "GWL_STYLE",
+                -20 => "GWL_EXSTYLE",
+                _ => "?",
+            };
+            return format!("{} styles=0x{:X}", which, lparam);
+        }
+        "WM_ERASEBKGND" | "WM_PRINTCLIENT" => return format!("hdc=0x{:X}", wparam),
+        "WM_SETFONT" => return format!("font=0x{:X} redraw={}", wparam, lparam != 0),
+        "WM_SETTEXT" | "WM_SETTINGCHANGE" | "WM_NCCREATE" | "WM_CREATE" | "WM_NOTIFY"
+        | "WM_GETMINMAXINFO" | "WM_WINDOWPOSCHANGING" | "WM_WINDOWPOSCHANGED" => {
+            return format!("wparam=0x{:X} data=0x{:X}", wparam, lparam);
+        }
+        _ => return raw(),
+    }
+}
+
+/// One message received by a window procedure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TracedMessage {
+    /// Order of arrival, starting at 0 for each tracer
+    pub sequence: u64,
+    /// Time since the tracer was created
+    pub time: Duration,
+    /// The window handle as a number
+    pub window: usize,
+    pub message: u32,
+    pub wparam: usize,
+    pub lparam: isize,
+    /// How many messages were being handled when this one arrived, e.g.
+    /// 1 for a message sent from inside another handler
+    pub depth: u32,
+    /// What the window procedure returned; `None` while it is still running
+    pub result: Option<isize>,
+}
+
+impl TracedMessage {
+    pub fn name(&self) -> String {
+        return describe_message(self.message);
+    }
+
+    pub fn params(&self) -> String {
+        return decode_params(self.message, self.wparam, self.lparam);
+    }
+
+    /// One JSON object, without a trailing newline
+    pub fn to_json(&self) -> String {
+        let result = match self.result {
+            Some(result) => result.to_string(),
+            None => "null".to_owned(),
+        };
+        return format!(
+            "{{\"seq\":{},\"time_us\":{},\"window\":{},\"message\":{},\"name\":{},\"wparam\":{},\"lparam\":{},\"params\":{},\"depth\":{},\"result\":{}}}",
+            self.sequence,
+            self.time.as_micros(),
+            self.window,
+            self.message,
+            json_string(&self.name()),
+            self.wparam,
+            self.lparam,
+            json_string(&self.params()),
+            self.depth,
+            result,
+        );
+    }
+}
+
+impl std::fmt::Display for TracedMessage {
+    /// `[  12.345 ms] 0x1A2B3C   WM_SIZE restored width=800 height=600 -> 0`,
+    /// indented by the nesting depth
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "[{:>10.3} ms] 0x{:<8X} {}{} {}",
+            self.time.as_secs_f64() * 1000.0,
+            self.window,
+            "  ".repeat(self.depth as usize),
+            self.name(),
+            self.params(),
+        )?;
+        match self.result {
+            Some(result) => return write!(f, " -> {}", result),
+            None => return write!(f, " -> (running)"),
+        }
+    }
+}
+
+/// Quotes and escapes a string for JSON
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    return quoted;
+}
+
+/// Which messages a [`MessageTracer`] records
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceFilter {
+    windows: Vec<usize>,
+    messages: Vec<u32>,
+    excluded: Vec<u32>,
+}
+
+impl TraceFilter {
+    /// Records everything
+    pub fn new() -> Self {
+        return TraceFilter::default();
+    }
+
+    /// Only records messages of the given windows. Can be called several times.
+    pub fn window(mut self, window: usize) -> Self {
+        self.windows.push(window);
+        return self;
+    }
+
+    /// Only records the given messages. Can be called several times.
+    pub fn message(mut self, message: u32) -> Self {
+        self.messages.push(message);
+        return self;
+    }
+
+    /// Never records the given message
+    pub fn exclude(mut self, message: u32) -> Self {
+        self.excluded.push(message);
+        return self;
+    }
+
+    /// Leaves out the messages that arrive constantly while the mouse
+    /// moves or a timer runs, which drown out everything else
+    pub fn exclude_noisy(self) -> Self {
+        return self
+            .exclude(0x0020) // WM_SETCURSOR
+            .exclude(0x0084) // WM_NCHITTEST
+            .exclude(0x00A0) // WM_NCMOUSEMOVE
+            .exclude(0x0113) // WM_TIMER
+            .exclude(0x0200); // WM_MOUSEMOVE
+    }
+
+    pub fn matches(&self, window: usize, message: u32) -> bool {
+        return (self.windows.is_empty() || self.windows.contains(&window))
+            && (self.messages.is_empty() || self.messages.contains(&message))
+            && !self.excluded.contains(&message);
+    }
+}
+
+/// Identifies a message being handled, to record its result with
+/// [`MessageTracer::finish`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceToken {
+    sequence: u64,
+}
+
+/// Records messages as a window procedure receives them
+///
+/// Call [`MessageTracer::begin`] when the procedure is entered and
+/// [`MessageTracer::finish`] with its result. The tracer keeps the last
+/// `capacity` records and drops older ones.
+#[derive(Debug)]
+pub struct MessageTracer {
+    start: Instant,
+    filter: TraceFilter,
+    records: VecDeque<TracedMessage>,
+    capacity: usize,
+    next_sequence: u64,
+    depth: u32,
+}
+
+impl Default for MessageTracer {
+    fn default() -> Self {
+        return MessageTracer::new();
+    }
+}
+
+impl MessageTracer {
+    /// A tracer that records every message and keeps the last 10 000
+    pub fn new() -> Self {
+        return MessageTracer {
+            start: Instant::now(),
+            filter: TraceFilter::new(),
+            records: VecDeque::new(),
+            capacity: 10_000,
+            next_sequence: 0,
+            depth: 0,
+        };
+    }
+
+    pub fn filter(mut self, filter: TraceFilter) -> Self {
+        self.filter = filter;
+        return self;
+    }
+
+    /// How many records to keep, at least one
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        return self;
+    }
+
+    pub fn set_filter(&mut self, filter: TraceFilter) {
+        self.filter = filter;
+    }
+
+    /// Starts recording a message. Must be paired with [`MessageTracer::finish`],
+    /// also for messages the filter leaves out, so nesting is tracked.
+    pub fn begin(&mut self, window: usize, message: u32, wparam: usize, lparam: isize) -> Option<TraceToken> {
+        let depth = self.depth;
+        self.depth += 1;
+        if !self.filter.matches(window, message) {
+            return None;
+        }
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(TracedMessage {
+            sequence,
+            time: self.start.elapsed(),
+            window,
+            message,
+            wparam,
+            lparam,
+            depth,
+            result: None,
+        });
+        return Some(TraceToken { sequence });
+    }
+
+    /// Records what the window procedure returned for a message started
+    /// with [`MessageTracer::begin`]
+    pub fn finish(&mut self, token: Option<TraceToken>, result: isize) {
+        self.depth = self.depth.saturating_sub(1);
+        let token = match token {
+            Some(token) => token,
+            None => return,
+        };
+        // Usually the newest record, unless nested messages came in between
+        if let Some(record) = self.records.iter_mut().rev().find(|record| record.sequence == token.sequence) {
+            record.result = Some(result);
+        }
+    }
+
+    /// The kept records, oldest first
+    pub fn records(&self) -> impl Iterator<Item = &TracedMessage> + '_ {
+        return self.records.iter();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.records.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.records.is_empty();
+    }
+
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+
+    /// Every record on its own line, see [`TracedMessage`]'s `Display`
+    pub fn write_text(&self, mut writer: impl std::io::Write) -> std::io::Result<()> {
+        for record in &self.records {
+            writeln!(writer, "{}", record)?;
+        }
+        return Ok(());
+    }
+
+    /// Every record as a JSON object on its own line
+    pub fn write_json_lines(&self, mut writer: impl std::io::Write) -> std::io::Result<()> {
+        for record in &self.records {
+            writeln!(writer, "{}", record.to_json())?;
+        }
+        return Ok(());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Packs two signed 16-bit coordinates the way an `LPARAM` holds them
+    fn lparam(low: i16, high: i16) -> isize {
+        return ((high as u16 as u32) << 16 | low as u16 as u32) as isize;
+    }
+
+    fn record(message: u32, wparam: usize, lparam: isize, depth: u32, result: Option<isize>) -> TracedMessage {
+        let time = Duration::from_micros(1500);
+        return TracedMessage { sequence: 3, time, window: 0x1A2B, message, wparam, lparam, depth, result };
+    }
+
+    #[test]
+    fn message_names_are_sorted_for_the_binary_search() {
+        assert_eq!(MESSAGE_NAMES.len(), 249);
+        for pair in MESSAGE_NAMES.windows(2) {
+            assert!(pair[0].0 < pair[1].0, "{} is not before {}", pair[0].1, pair[1].1);
+        }
+        for &(number, name) in MESSAGE_NAMES {
+            assert_eq!(message_name(number), Some(name));
+        }
+        assert_eq!(message_name(0x0004), None);
+    }
+
+    #[test]
+    fn messages_are_described_by_range() {
+        assert_eq!(describe_message(0x0005), "WM_SIZE");
+        assert_eq!(describe_message(0x0004), "0x0004");
+        assert_eq!(describe_message(WM_USER + 1), "WM_USER+1");
+        assert_eq!(describe_message(WM_APP + 2), "WM_APP+2");
+        assert_eq!(describe_message(0xC123), "registered 0xC123");
+        assert_eq!(describe_message(0x1_0000), "0x10000");
+    }
+
+    #[test]
+    fn known_parameters_are_decoded() {
+        assert_eq!(decode_params(0x0005, 2, lparam(800, 600)), "maximized width=800 height=600");
+        assert_eq!(decode_params(0x0200, 0x1, lparam(-3, -5)), "x=-3 y=-5 keys=0x1");
+        assert_eq!(decode_params(0x020A, 0xFF88_0008, lparam(10, 20)), "delta=-120 keys=0x8 screen x=10 y=20");
+        assert_eq!(decode_params(0x0102, 0xE9, 1), "char='é' (U+00E9) repeat=1");
+        assert_eq!(decode_params(0x0102, 0xD83D, 1), "code unit=0xD83D repeat=1");
+        assert_eq!(decode_params(0x0111, 0x1_0007, 0), "id=7 accelerator");
+        assert_eq!(decode_params(0x0111, 0x0002_0007, 0x42), "id=7 control=0x42 code=2");
+        assert_eq!(decode_params(0x0112, 0xF062, lparam(1, 2)), "SC_CLOSE x=1 y=2");
+        assert_eq!(decode_params(0x0001, 1, 2), "wparam=0x1 data=0x2");
+        assert_eq!(decode_params(WM_USER, 0xAB, -1), "wparam=0xAB lparam=0xFFFFFFFFFFFFFFFF");
+    }
+
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(json_string(""), "\"\"");
+        assert_eq!(json_string("say \"hi\" \\ bye"), r#""say \"hi\" \\ bye""#);
+        assert_eq!(json_string("a\nb\rc\td"), r#""a\nb\rc\td""#);
+        assert_eq!(json_string("\u{0}\u{1B}\u{1F} \u{7F}"), "\"\\u0000\\u001b\\u001f \u{7F}\"");
+        // Everything else is valid JSON as it is
+        assert_eq!(json_string("é 😀 \u{2028}"), "\"é 😀 \u{2028}\"");
+    }
+
+    #[test]
+    fn records_print_as_json_and_text() {
+        let json = record(0x0102, 0x22, 1, 0, None).to_json();
+        let expected = concat!(
+            r#"{"seq":3,"time_us":1500,"window":6699,"message":258,"name":"WM_CHAR","wparam":34,"lparam":1,"#,
+            r#""params":"char='\"' (U+0022) repeat=1","depth":0,"result":null}"#,
+        );
+        assert_eq!(json, expected);
+        assert!(record(0x0010, 0, 0, 0, Some(-2)).to_json().ends_with(r#""depth":0,"result":-2}"#));
+
+        let text = record(0x0010, 0, 0, 2, Some(0)).to_string();
+        assert_eq!(text, "[     1.500 ms] 0x1A2B         WM_CLOSE wparam=0x0 lparam=0x0 -> 0");
+        assert!(record(0x0010, 0, 0, 0, None).to_string().ends_with("-> (running)"));
+    }
+
+    #[test]
+    fn filters_match_windows_and_messages() {
+        assert!(TraceFilter::new().matches(1, 0x0010));
+        let filter = TraceFilter::new().window(1).window(2).message(0x0010).message(0x0005);
+        assert!(filter.matches(2, 0x0005));
+        assert!(!filter.matches(3, 0x0005));
+        assert!(!filter.matches(1, 0x0006));
+        // Exclusions win over everything else
+        assert!(!filter.clone().exclude(0x0005).matches(1, 0x0005));
+
+        let quiet = TraceFilter::new().exclude_noisy();
+        assert!(!quiet.matches(1, 0x0200) && !quiet.matches(1, 0x0084) && !quiet.matches(1, 0x0113));
+        assert!(quiet.matches(1, 0x0201));
+    }
+
+    #[test]
+    fn nested_messages_get_their_depth_and_result() {
+        let mut tracer = MessageTracer::new().filter(TraceFilter::new().exclude(0x0020));
+        let outer = tracer.begin(1, 0x0005, 0, 0);
+        let hidden = tracer.begin(1, 0x0020, 0, 0);
+        assert_eq!(hidden, None);
+        // A message sent while both are running is two levels deep
+        let inner = tracer.begin(1, 0x0003, 0, 0);
+        tracer.finish(inner, 7);
+        tracer.finish(hidden, 1);
+        let running: Vec<Option<isize>> = tracer.records().map(|record| record.result).collect();
+        assert_eq!(running, vec![None, Some(7)]);
+        tracer.finish(outer, 9);
+
+        let records: Vec<(u64, u32, u32, Option<isize>)> =
+            tracer.records().map(|record| (record.sequence, record.message, record.depth, record.result)).collect();
+        assert_eq!(records, vec![(0, 0x0005, 0, Some(9)), (1, 0x0003, 2, Some(7))]);
+        // Unbalanced finishes don't underflow
+        tracer.finish(None, 0);
+        assert_eq!(tracer.begin(1, 0x0010, 0, 0).map(|_| tracer.records().last().unwrap().depth), Some(0));
+    }
+
+    #[test]
+    fn old_records_are_dropped_at_capacity() {
+        let mut tracer = MessageTracer::new().capacity(2);
+        let first = tracer.begin(1, 0x0001, 0, 0);
+        tracer.finish(first, 0);
+        for message in [0x0002, 0x0003] {
+            let token = tracer.begin(1, message, 0, 0);
+            tracer.finish(token, 0);
+        }
+        let kept: Vec<u64> = tracer.records().map(|record| record.sequence).collect();
+        assert_eq!((kept, tracer.len()), (vec![1, 2], 2));
+        // Finishing a dropped record changes nothing
+        tracer.finish(first, 5);
+        assert!(tracer.records().all(|record| record.result == Some(0)));
+
+        let mut lines = Vec::new();
+        tracer.write_json_lines(&mut lines).unwrap();
+        assert_eq!(String::from_utf8(lines).unwrap().lines().count(), 2);
+        tracer.clear();
+        assert!(tracer.is_empty());
+
+        let mut single = MessageTracer::new().capacity(0);
+        single.begin(1, 0x0001, 0, 0);
+        single.begin(1, 0x0002, 0, 0);
+        assert_eq!(single.records().map(|record| record.message).collect::<Vec<_>>(), vec![0x0002]);
+    }
+}
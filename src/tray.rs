@@ -0,0 +1,122 @@
+//! System tray (notification area) icons
+//!
+//! A [`TrayIcon`] describes the icon, its tooltip and an optional context
+//! menu. Backends add it to the tray and report clicks as
+//! [`crate::event::WindowEvent::Tray`] events through the normal event loop.
+//! Items chosen from the attached menu arrive as ordinary
+//! [`crate::event::WindowEvent::Menu`] events.
+//!
+//! On Linux the tray is the StatusNotifierItem D-Bus service;
+//! [`sni_icon_pixmap`] converts icons into its `IconPixmap` format.
+
+use crate::geometry::Point;
+use crate::icon::Icon;
+use crate::menu::Menu;
+
+/// Application assigned identifier of a tray icon, to tell several apart
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TrayId(pub u16);
+
+/// Description of a tray icon
+#[derive(Debug, Clone)]
+pub struct TrayIcon {
+    pub icon: Icon,
+    /// Text shown when hovering the icon. Win32 keeps 127 UTF-16 units.
+    pub tooltip: String,
+    /// Menu shown when the icon is right clicked
+    pub menu: Option<Menu>,
+}
+
+impl TrayIcon {
+    pub fn new(icon: Icon) -> Self {
+        return TrayIcon { icon, tooltip: String::new(), menu: None };
+    }
+
+    pub fn tooltip(mut self, tooltip: &str) -> Self {
+        self.tooltip = tooltip.to_owned();
+        return self;
+    }
+
+    pub fn menu(mut self, menu: Menu) -> Self {
+        self.menu = Some(menu);
+        return self;
+    }
+}
+
+/// Icon of a [`Notification`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationIcon {
+    None,
+    Information,
+    Warning,
+    Error,
+    /// The tray icon itself
+    App,
+}
+
+/// A balloon or toast notification shown next to a tray icon
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// Win32 keeps 63 UTF-16 units
+    pub title: String,
+    /// Win32 keeps 255 UTF-16 units
+    pub text: String,
+    pub icon: NotificationIcon,
+    /// Don't play the notification sound
+    pub silent: bool,
+}
+
+impl Notification {
+    pub fn new(title: &str, text: &str) -> Self {
+        return Notification {
+            title: title.to_owned(),
+            text: text.to_owned(),
+            icon: NotificationIcon::None,
+            silent: false,
+        };
+    }
+
+    pub fn icon(mut self, icon: NotificationIcon) -> Self {
+        self.icon = icon;
+        return self;
+    }
+
+    pub fn silent(mut self) -> Self {
+        self.silent = true;
+        return self;
+    }
+}
+
+/// What happened to a tray icon. Positions are in screen coordinates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrayEvent {
+    /// Left click, or Space/Enter when the icon has the keyboard focus
+    LeftClick { position: Point },
+
+    /// Right click or the menu key. The attached menu, if any, has
+    /// already been shown when this arrives.
+    RightClick { position: Point },
+
+    DoubleClick { position: Point },
+
+    /// The user clicked the notification
+    NotificationClicked,
+
+    /// The notification timed out or was closed without clicking it
+    NotificationDismissed,
+}
+
+/// Converts an icon to the StatusNotifierItem `IconPixmap` property, an
+/// array of `(width, height, pixels)` with pixels as ARGB32 in network
+/// byte order
+pub fn sni_icon_pixmap(icon: &Icon) -> Vec<(i32, i32, Vec<u8>)> {
+    let mut pixmaps = Vec::with_capacity(icon.images().len());
+    for image in icon.images() {
+        let mut argb = Vec::with_capacity(image.rgba().len());
+        for px in image.rgba().chunks_exact(4) {
+            argb.extend_from_slice(&[px[3], px[0], px[1], px[2]]);
+        }
+        pixmaps.push((image.width() as i32, image.height() as i32, argb));
+    }
+    return pixmaps;
+}
@@ -0,0 +1,1172 @@
+//! TrueType and OpenType fonts
+//!
+//! A [`Font`] reads the tables needed to draw text from a `.ttf`, `.otf`
+//! or `.ttc` file: `cmap` maps characters to glyphs, `glyf` and `loca`
+//! hold the quadratic outlines, `hmtx` the advances and `kern` the pair
+//! adjustments. OpenType fonts with CFF outlines are not supported, and
+//! kerning only stored in `GPOS` is ignored.
+//!
+//! A [`FontStack`] lays out UTF-8 text with a primary font and fallbacks
+//! for the characters it lacks. Glyphs are rasterized with anti-aliasing
+//! by the coverage [`Rasterizer`] at any pixel size and kept in a
+//! [`GlyphCache`], which draws into a [`Canvas`]. A [`GlyphAtlas`] packs
+//! the same glyphs into one coverage texture and produces the quads to
+//! draw text with a GPU, like [`BitmapFont::overlay_vertices`](crate::bitmap_font::BitmapFont::overlay_vertices).
+
+use std::collections::HashMap;
+
+use crate::bitmap_font::OverlayVertex;
+use crate::canvas::{Canvas, Color, Transform};
+use crate::geometry::PointF;
+use crate::path::Path;
+use crate::raster::{FillRule, Rasterizer};
+
+/// Abstraction to represent an error while reading a font
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrueTypeError {
+    /// The data doesn't start like a TrueType, OpenType or collection file
+    NotAFont,
+
+    /// The collection has no font at this index
+    FontIndex(u32),
+
+    /// A required table is missing
+    MissingTable(&'static str),
+
+    /// A table is shorter than its content needs
+    Truncated(&'static str),
+
+    /// The font has CFF outlines instead of TrueType ones
+    UnsupportedOutlines,
+
+    /// The outline of a glyph is malformed
+    InvalidGlyph(u16),
+
+    /// The outline of a glyph, with its components, has too many parts
+    OutlineTooComplex(u16),
+
+    /// A glyph rasterized at the requested size would need too many pixels
+    GlyphTooLarge(u16),
+
+    /// A [`GlyphAtlas`] has no room left for a glyph
+    AtlasFull,
+}
+
+impl std::fmt::Display for TrueTypeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TrueTypeError::NotAFont => write!(f, "not a TrueType or OpenType font"),
+            TrueTypeError::FontIndex(index) => write!(f, "the font collection has no font {}", index),
+            TrueTypeError::MissingTable(tag) => write!(f, "the font has no '{}' table", tag),
+            TrueTypeError::Truncated(tag) => write!(f, "the '{}' table is truncated", tag),
+            TrueTypeError::UnsupportedOutlines => write!(f, "CFF outlines are not supported"),
+            TrueTypeError::InvalidGlyph(glyph) => write!(f, "glyph {} has an invalid outline", glyph),
+            TrueTypeError::OutlineTooComplex(glyph) => write!(f, "glyph {} has too many components or points", glyph),
+            TrueTypeError::GlyphTooLarge(glyph) => write!(f, "glyph {} is too large to rasterize at this size", glyph),
+            TrueTypeError::AtlasFull => write!(f, "the glyph atlas is full"),
+        }
+    }
+}
+
+impl std::error::Error for TrueTypeError {}
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    return data.get(at..at + 2).map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]));
+}
+
+fn read_i16(data: &[u8], at: usize) -> Option<i16> {
+    return read_u16(data, at).map(|value| value as i16);
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    return data.get(at..at + 4).map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]));
+}
+
+/// A 2.14 fixed point number, used by the transforms of composite glyphs
+fn read_f2dot14(data: &[u8], at: usize) -> Option<f32> {
+    return read_i16(data, at).map(|value| value as f32 / 16384.0);
+}
+
+/// Composite glyphs can nest; real fonts stay well under this
+const MAX_COMPONENT_DEPTH: u32 = 8;
+
+/// Components can be shared, so a shallow tree can still place a glyph
+/// many times. These bound the work for one outline.
+const MAX_OUTLINE_COMPONENTS: u32 = 1024;
+const MAX_OUTLINE_POINTS: u32 = 1 << 17;
+
+/// The largest bitmap [`Font::rasterize`] makes, a 4096 pixel square
+const MAX_GLYPH_PIXELS: u32 = 1 << 24;
+
+/// What an outline may still use of [`MAX_OUTLINE_COMPONENTS`] and [`MAX_OUTLINE_POINTS`]
+struct OutlineBudget {
+    components: u32,
+    points: u32,
+}
+
+/// Ascent, descent and gap between lines
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineMetrics {
+    /// Distance from the baseline up to the top of the line
+    pub ascent: f32,
+    /// Distance from the baseline down to the bottom of the line, usually negative
+    pub descent: f32,
+    pub line_gap: f32,
+}
+
+impl LineMetrics {
+    /// Distance between the baselines of two lines
+    pub fn line_height(&self) -> f32 {
+        return self.ascent - self.descent + self.line_gap;
+    }
+}
+
+/// A parsed font, see the module documentation
+#[derive(Clone, PartialEq, Eq)]
+pub struct Font {
+    data: Vec<u8>,
+    units_per_em: u16,
+    glyph_count: u16,
+    ascender: i16,
+    descender: i16,
+    line_gap: i16,
+    long_loca: bool,
+    loca: usize,
+    glyf: usize,
+    glyf_length: usize,
+    hmtx: usize,
+    h_metric_count: u16,
+    /// Offset and format of the character map subtable
+    cmap: Option<(usize, u16)>,
+    kerning: HashMap<(u16, u16), i16>,
+}
+
+impl std::fmt::Debug for Font {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        return f
+            .debug_struct("Font")
+            .field("units_per_em", &self.units_per_em)
+            .field("glyph_count", &self.glyph_count)
+            .field("kerning_pairs", &self.kerning.len())
+            .finish();
+    }
+}
+
+impl Font {
+    /// Reads a `.ttf` or `.otf` file, or the first font of a `.ttc` file
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self, TrueTypeError> {
+        return Font::from_collection(data, 0);
+    }
+
+    /// Reads font `index` of a `.ttc` collection. Single font files only
+    /// have index 0.
+    pub fn from_collection(data: Vec<u8>, index: u32) -> Result<Self, TrueTypeError> {
+        let mut start = 0;
+        if data.starts_with(b"ttcf") {
+            let count = read_u32(&data, 8).ok_or(TrueTypeError::NotAFont)?;
+            if index >= count {
+                return Err(TrueTypeError::FontIndex(index));
+            }
+            start = read_u32(&data, 12 + 4 * index as usize).ok_or(TrueTypeError::NotAFont)? as usize;
+        } else if index != 0 {
+            return Err(TrueTypeError::FontIndex(index));
+        }
+        match read_u32(&data, start) {
+            Some(0x0001_0000) | Some(0x7472_7565) => {}
+            Some(0x4F54_544F) => return Err(TrueTypeError::UnsupportedOutlines),
+            _ => return Err(TrueTypeError::NotAFont),
+        }
+
+        let table_count = read_u16(&data, start + 4).ok_or(TrueTypeError::NotAFont)? as usize;
+        let mut tables = HashMap::new();
+        for i in 0..table_count {
+            let record = start + 12 + 16 * i;
+            let tag = data.get(record..record + 4).ok_or(TrueTypeError::NotAFont)?;
+            let offset = read_u32(&data, record + 8).ok_or(TrueTypeError::NotAFont)? as usize;
+            let length = read_u32(&data, record + 12).ok_or(TrueTypeError::NotAFont)? as usize;
+            if offset.checked_add(length).is_none_or(|end| end > data.len()) {
+                return Err(TrueTypeError::NotAFont);
+            }
+            tables.insert([tag[0], tag[1], tag[2], tag[3]], (offset, length));
+        }
+        let table = |tag: &'static str| -> Result<(usize, usize), TrueTypeError> {
+            let bytes = tag.as_bytes();
+            return tables
+                .get(&[bytes[0], bytes[1], bytes[2], bytes[3]])
+                .copied()
+                .ok_or(TrueTypeError::MissingTable(tag));
+        };
+
+        let (head, _) = table("head")?;
+        let units_per_em = read_u16(&data, head + 18).ok_or(TrueTypeError::Truncated("head"))?.max(1);
+        let long_loca = read_i16(&data, head + 50).ok_or(TrueTypeError::Truncated("head"))? == 1;
+        let (maxp, _) = table("maxp")?;
+        let glyph_count = read_u16(&data, maxp + 4).ok_or(TrueTypeError::Truncated("maxp"))?;
+        let (hhea, _) = table("hhea")?;
+        let ascender = read_i16(&data, hhea + 4).ok_or(TrueTypeError::Truncated("hhea"))?;
+        let descender = read_i16(&data, hhea + 6).ok_or(TrueTypeError::Truncated("hhea"))?;
+        let line_gap = read_i16(&data, hhea + 8).ok_or(TrueTypeError::Truncated("hhea"))?;
+        let h_metric_count = read_u16(&data, hhea + 34).ok_or(TrueTypeError::Truncated("hhea"))?;
+        let (hmtx, hmtx_length) = table("hmtx")?;
+        if hmtx_length < 4 * h_metric_count as usize {
+            return Err(TrueTypeError::Truncated("hmtx"));
+        }
+        let (loca, loca_length) = table("loca")?;
+        let entry_size = if long_loca { 4 } else { 2 };
+        if loca_length < entry_size * (glyph_count as usize + 1) {
+            return Err(TrueTypeError::Truncated("loca"));
+        }
+        let (glyf, glyf_length) = table("glyf")?;
+        let (cmap, _) = table("cmap")?;
+        let cmap = Font::find_cmap_subtable(&data, cmap).ok_or(TrueTypeError::Truncated("cmap"))?;
+        let kerning = match table("kern") {
+            Ok((kern, length)) => Font::read_kerning(&data[kern..kern + length]),
+            Err(_) => HashMap::new(),
+        };
+
+        return Ok(Font {
+            data,
+            units_per_em,
+            glyph_count,
+            ascender,
+            descender,
+            line_gap,
+            long_loca,
+            loca,
+            glyf,
+            glyf_length,
+            hmtx,
+            h_metric_count,
+            cmap,
+            kerning,
+        });
+    }
+
+    /// Picks the best Unicode subtable: format 12 covers every plane,
+    /// format 4 only the basic multilingual plane
+    fn find_cmap_subtable(data: &[u8], cmap: usize) -> Option<Option<(usize, u16)>> {
+        let count = read_u16(data, cmap + 2)? as usize;
+        let mut best: Option<(usize, u16)> = None;
+        for i in 0..count {
+            let record = cmap + 4 + 8 * i;
+            let platform = read_u16(data, record)?;
+            let encoding = read_u16(data, record + 2)?;
+            let offset = cmap + read_u32(data, record + 4)? as usize;
+            let unicode = platform == 0 || (platform == 3 && (encoding == 1 || encoding == 10));
+            let format = read_u16(data, offset)?;
+            if !unicode || (format != 4 && format != 12) {
+                continue;
+            }
+            if best.is_none_or(|(_, best_format)| format > best_format) {
+                best = Some((offset, format));
+            }
+        }
+        return Some(best);
+    }
+
+    /// Reads the pairs of the horizontal format 0 subtables of a `kern` table
+    fn read_kerning(kern: &[u8]) -> HashMap<(u16, u16), i16> {
+        let mut pairs = HashMap::new();
+        let count = read_u16(kern, 2).unwrap_or(0) as usize;
+        let mut offset = 4;
+        for _ in 0..count {
+            let (Some(length), Some(coverage)) = (read_u16(kern, offset + 2), read_u16(kern, offset + 4)) else {
+                break;
+            };
+            let horizontal = coverage & 1 != 0;
+            let format = coverage >> 8;
+            if horizontal && format == 0 {
+                let pair_count = read_u16(kern, offset + 6).unwrap_or(0) as usize;
+                for pair in 0..pair_count {
+                    let at = offset + 14 + 6 * pair;
+                    if let (Some(left), Some(right), Some(value)) =
+                        (read_u16(kern, at), read_u16(kern, at + 2), read_i16(kern, at + 4))
+                    {
+                        pairs.insert((left, right), value);
+                    }
+                }
+            }
+            offset += length.max(6) as usize;
+        }
+        return pairs;
+    }
+
+    pub fn units_per_em(&self) -> u16 {
+        return self.units_per_em;
+    }
+
+    pub fn glyph_count(&self) -> u16 {
+        return self.glyph_count;
+    }
+
+    /// Font units per pixel at `size` pixels per em
+    pub fn scale(&self, size: f32) -> f32 {
+        return size / self.units_per_em as f32;
+    }
+
+    /// Line metrics at `size` pixels per em
+    pub fn line_metrics(&self, size: f32) -> LineMetrics {
+        let scale = self.scale(size);
+        return LineMetrics {
+            ascent: self.ascender as f32 * scale,
+            descent: self.descender as f32 * scale,
+            line_gap: self.line_gap as f32 * scale,
+        };
+    }
+
+    /// The glyph of a character, or 0 (the "missing" glyph) if the font has none
+    pub fn glyph_index(&self, c: char) -> u16 {
+        let c = c as u32;
+        let data = &self.data;
+        let lookup = || -> Option<u16> {
+            match self.cmap? {
+                (table, 4) => {
+                    if c > 0xFFFF {
+                        return Some(0);
+                    }
+                    let segments = read_u16(data, table + 6)? as usize / 2;
+                    let ends = table + 14;
+                    let starts = ends + 2 * segments + 2;
+                    let deltas = starts + 2 * segments;
+                    let range_offsets = deltas + 2 * segments;
+                    for segment in 0..segments {
+                        if read_u16(data, ends + 2 * segment)? as u32 >= c {
+                            let start = read_u16(data, starts + 2 * segment)? as u32;
+                            if start > c {
+                                return Some(0);
+                            }
+                            let delta = read_u16(data, deltas + 2 * segment)?;
+                            let range_offset = read_u16(data, range_offsets + 2 * segment)? as usize;
+                            if range_offset == 0 {
+                                return Some((c as u16).wrapping_add(delta));
+                            }
+                            let at = range_offsets + 2 * segment + range_offset + 2 * (c - start) as usize;
+                            let glyph = read_u16(data, at)?;
+                            return Some(if glyph == 0 { 0 } else { glyph.wrapping_add(delta) });
+                        }
+                    }
+                    return Some(0);
+                }
+                (table, _) => {
+                    let groups = read_u32(data, table + 12)? as usize;
+                    for group in 0..groups {
+                        let at = table + 16 + 12 * group;
+                        let start = read_u32(data, at)?;
+                        let end = read_u32(data, at + 4)?;
+                        if (start..=end).contains(&c) {
+                            return Some((read_u32(data, at + 8)? + c - start) as u16);
+                        }
+                    }
+                    return Some(0);
+                }
+            }
+        };
+        let glyph = lookup().unwrap_or(0);
+        return if glyph < self.glyph_count { glyph } else { 0 };
+    }
+
+    pub fn has_glyph(&self, c: char) -> bool {
+        return self.glyph_index(c) != 0;
+    }
+
+    /// Advance of a glyph in font units
+    pub fn advance(&self, glyph: u16) -> u16 {
+        let metric = glyph.min(self.h_metric_count.saturating_sub(1));
+        return read_u16(&self.data, self.hmtx + 4 * metric as usize).unwrap_or(0);
+    }
+
+    /// Pen adjustment between two glyphs in font units, usually negative
+    pub fn kerning(&self, left: u16, right: u16) -> i16 {
+        return self.kerning.get(&(left, right)).copied().unwrap_or(0);
+    }
+
+    /// The bytes of a glyph in the `glyf` table, empty for glyphs without outline
+    fn glyph_data(&self, glyph: u16) -> Result<&[u8], TrueTypeError> {
+        if glyph >= self.glyph_count {
+            return Err(TrueTypeError::InvalidGlyph(glyph));
+        }
+        let (start, end) = if self.long_loca {
+            let at = self.loca + 4 * glyph as usize;
+            (read_u32(&self.data, at), read_u32(&self.data, at + 4))
+        } else {
+            let at = self.loca + 2 * glyph as usize;
+            (read_u16(&self.data, at).map(|v| 2 * v as u32), read_u16(&self.data, at + 2).map(|v| 2 * v as u32))
+        };
+        let (start, end) = (start.unwrap_or(0) as usize, end.unwrap_or(0) as usize);
+        if start >= end {
+            return Ok(&[]);
+        }
+        if end > self.glyf_length {
+            return Err(TrueTypeError::InvalidGlyph(glyph));
+        }
+        return Ok(&self.data[self.glyf + start..self.glyf + end]);
+    }
+
+    /// The outline of a glyph in font units, with y going up
+    pub fn outline(&self, glyph: u16) -> Result<Path, TrueTypeError> {
+        let mut budget = OutlineBudget { components: MAX_OUTLINE_COMPONENTS, points: MAX_OUTLINE_POINTS };
+        return self.outline_at_depth(glyph, 0, &mut budget);
+    }
+
+    fn outline_at_depth(&self, glyph: u16, depth: u32, budget: &mut OutlineBudget) -> Result<Path, TrueTypeError> {
+        let data = self.glyph_data(glyph)?;
+        if data.is_empty() {
+            return Ok(Path::new());
+        }
+        let invalid = TrueTypeError::InvalidGlyph(glyph);
+        let contour_count = read_i16(data, 0).ok_or(invalid.clone())?;
+        if contour_count >= 0 {
+            // The last end point is the number of points minus one
+            let point_count = match contour_count {
+                0 => 0,
+                _ => read_u16(data, 8 + 2 * contour_count as usize).ok_or(invalid.clone())? as u32 + 1,
+            };
+            budget.points = budget.points.checked_sub(point_count).ok_or(TrueTypeError::OutlineTooComplex(glyph))?;
+            return simple_outline(data, contour_count as usize).ok_or(invalid);
+        }
+        if depth >= MAX_COMPONENT_DEPTH {
+            return Err(invalid);
+        }
+
+        // Composite glyph: other glyphs placed with an offset and a transform
+        const ARGS_ARE_WORDS: u16 = 0x0001;
+        const ARGS_ARE_OFFSETS: u16 = 0x0002;
+        const HAS_SCALE: u16 = 0x0008;
+        const MORE_COMPONENTS: u16 = 0x0020;
+        const HAS_XY_SCALE: u16 = 0x0040;
+        const HAS_2X2: u16 = 0x0080;
+        let mut path = Path::new();
+        let mut at = 10;
+        loop {
+            let flags = read_u16(data, at).ok_or(invalid.clone())?;
+            let component = read_u16(data, at + 2).ok_or(invalid.clone())?;
+            at += 4;
+            budget.components = budget.components.checked_sub(1).ok_or(TrueTypeError::OutlineTooComplex(glyph))?;
+            let (dx, dy) = if flags & ARGS_ARE_WORDS != 0 {
+                at += 4;
+                (read_i16(data, at - 4).ok_or(invalid.clone())? as f32, read_i16(data, at - 2).ok_or(invalid.clone())? as f32)
+            } else {
+                at += 2;
+                let bytes = data.get(at - 2..at).ok_or(invalid.clone())?;
+                (bytes[0] as i8 as f32, bytes[1] as i8 as f32)
+            };
+            // Components aligned by matching points are placed without offset
+            let (dx, dy) = if flags & ARGS_ARE_OFFSETS != 0 { (dx, dy) } else { (0.0, 0.0) };
+            let mut transform = Transform { a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: dx, f: dy };
+            if flags & HAS_SCALE != 0 {
+                let scale = read_f2dot14(data, at).ok_or(invalid.clone())?;
+                (transform.a, transform.d) = (scale, scale);
+                at += 2;
+            } else if flags & HAS_XY_SCALE != 0 {
+                transform.a = read_f2dot14(data, at).ok_or(invalid.clone())?;
+                transform.d = read_f2dot14(data, at + 2).ok_or(invalid.clone())?;
+                at += 4;
+            } else if flags & HAS_2X2 != 0 {
+                transform.a = read_f2dot14(data, at).ok_or(invalid.clone())?;
+                transform.b = read_f2dot14(data, at + 2).ok_or(invalid.clone())?;
+                transform.c = read_f2dot14(data, at + 4).ok_or(invalid.clone())?;
+                transform.d = read_f2dot14(data, at + 6).ok_or(invalid.clone())?;
+                at += 8;
+            }
+            path = path.extend(&self.outline_at_depth(component, depth + 1, budget)?.transformed(&transform));
+            if flags & MORE_COMPONENTS == 0 {
+                return Ok(path);
+            }
+        }
+    }
+
+    /// Rasterizes a glyph at `size` pixels per em
+    pub fn rasterize(&self, glyph: u16, size: f32) -> Result<RasterizedGlyph, TrueTypeError> {
+        let scale = self.scale(size);
+        let outline = self.outline(glyph)?.transformed(&Transform::scale(scale, -scale));
+        let polylines = outline.flatten(GLYPH_TOLERANCE);
+        let points = polylines.iter().flat_map(|polyline| polyline.points.iter());
+        let (mut min, mut max) = ([f32::MAX; 2], [f32::MIN; 2]);
+        for point in points {
+            for axis in 0..2 {
+                min[axis] = min[axis].min(point[axis]);
+                max[axis] = max[axis].max(point[axis]);
+            }
+        }
+        if min[0] > max[0] {
+            return Ok(RasterizedGlyph { size: [0, 0], offset: [0, 0], coverage: Vec::new() });
+        }
+        let offset = [min[0].floor() as i32, min[1].floor() as i32];
+        // Huge sizes saturate the casts, so the subtractions can overflow
+        let too_large = TrueTypeError::GlyphTooLarge(glyph);
+        let width = (max[0].ceil() as i32).checked_sub(offset[0]).ok_or(too_large.clone())?.max(1) as u32;
+        let height = (max[1].ceil() as i32).checked_sub(offset[1]).ok_or(too_large.clone())?.max(1) as u32;
+        if width.checked_mul(height).is_none_or(|pixels| pixels > MAX_GLYPH_PIXELS) {
+            return Err(too_large);
+        }
+        let mut rasterizer = Rasterizer::new(width, height);
+        for polyline in &polylines {
+            let shifted: Vec<[f32; 2]> =
+                polyline.points.iter().map(|&[x, y]| [x - offset[0] as f32, y - offset[1] as f32]).collect();
+            rasterizer.polygon(&shifted);
+        }
+        return Ok(RasterizedGlyph { size: [width, height], offset, coverage: rasterizer.to_mask(FillRule::NonZero) });
+    }
+}
+
+/// How far flattened glyph outlines may be from the curves, in pixels
+const GLYPH_TOLERANCE: f32 = 0.1;
+
+/// Turns the contours of a simple glyph into a path of lines and quadratic curves
+fn simple_outline(data: &[u8], contour_count: usize) -> Option<Path> {
+    const ON_CURVE: u8 = 0x01;
+    const X_SHORT: u8 = 0x02;
+    const Y_SHORT: u8 = 0x04;
+    const REPEAT: u8 = 0x08;
+    const X_SAME_OR_POSITIVE: u8 = 0x10;
+    const Y_SAME_OR_POSITIVE: u8 = 0x20;
+
+    let mut ends = Vec::with_capacity(contour_count);
+    for i in 0..contour_count {
+        ends.push(read_u16(data, 10 + 2 * i)? as usize);
+    }
+    let point_count = ends.last().map_or(0, |&end| end + 1);
+    let instructions = read_u16(data, 10 + 2 * contour_count)? as usize;
+    let mut at = 12 + 2 * contour_count + instructions;
+
+    let mut flags = Vec::with_capacity(point_count);
+    while flags.len() < point_count {
+        let flag = *data.get(at)?;
+        at += 1;
+        flags.push(flag);
+        if flag & REPEAT != 0 {
+            let count = *data.get(at)?;
+            at += 1;
+            flags.extend(std::iter::repeat_n(flag, count as usize));
+        }
+    }
+    flags.truncate(point_count);
+
+    let mut read_coordinates = |short: u8, same_or_positive: u8| -> Option<Vec<f32>> {
+        let mut value = 0i32;
+        let mut values = Vec::with_capacity(point_count);
+        for &flag in &flags {
+            if flag & short != 0 {
+                let delta = *data.get(at)? as i32;
+                at += 1;
+                value += if flag & same_or_positive != 0 { delta } else { -delta };
+            } else if flag & same_or_positive == 0 {
+                value += read_i16(data, at)? as i32;
+                at += 2;
+            }
+            values.push(value as f32);
+        }
+        return Some(values);
+    };
+    let xs = read_coordinates(X_SHORT, X_SAME_OR_POSITIVE)?;
+    let ys = read_coordinates(Y_SHORT, Y_SAME_OR_POSITIVE)?;
+
+    let mut path = Path::new();
+    let mut start = 0;
+    for &end in &ends {
+        if end < start || end >= point_count {
+            return None;
+        }
+        let points: Vec<([f32; 2], bool)> =
+            (start..=end).map(|i| ([xs[i], ys[i]], flags[i] & ON_CURVE != 0)).collect();
+        path = contour_path(path, &points);
+        start = end + 1;
+    }
+    return Some(path);
+}
+
+/// Adds one closed contour. Two off-curve points in a row have an
+/// implied on-curve point halfway between them.
+fn contour_path(path: Path, points: &[([f32; 2], bool)]) -> Path {
+    let midpoint = |a: [f32; 2], b: [f32; 2]| [(a[0] + b[0]) / 2.0, (a[1] + b[1]) / 2.0];
+    let (Some(&(first, first_on)), Some(&(last, last_on))) = (points.first(), points.last()) else {
+        return path;
+    };
+    let (start, rest) = if first_on {
+        (first, &points[1..])
+    } else if last_on {
+        (last, &points[..points.len() - 1])
+    } else {
+        (midpoint(first, last), points)
+    };
+    let mut path = path.move_to(start);
+    let mut control: Option<[f32; 2]> = None;
+    for &(point, on_curve) in rest {
+        match (on_curve, control) {
+            (true, Some(c)) => path = path.quad_to(c, point),
+            (true, None) => path = path.line_to(point),
+            (false, Some(c)) => path = path.quad_to(c, midpoint(c, point)),
+            (false, None) => {}
+        }
+        control = if on_curve { None } else { Some(point) };
+    }
+    path = match control {
+        Some(c) => path.quad_to(c, start),
+        None => path.line_to(start),
+    };
+    return path.close();
+}
+
+/// Coverage of a glyph at one size, one byte per pixel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RasterizedGlyph {
+    pub size: [u32; 2],
+    /// From the pen on the baseline to the top left corner
+    pub offset: [i32; 2],
+    pub coverage: Vec<u8>,
+}
+
+/// A glyph of one of the fonts of a [`FontStack`], placed by [`FontStack::layout`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionedGlyph {
+    /// Index of the font in the stack
+    pub font: usize,
+    pub glyph: u16,
+    /// The pen on the baseline, from the top left corner of the text
+    pub position: [f32; 2],
+}
+
+/// A primary font and the fonts used for characters it doesn't have
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FontStack {
+    fonts: Vec<Font>,
+}
+
+impl FontStack {
+    pub fn new(font: Font) -> Self {
+        return FontStack { fonts: vec![font] };
+    }
+
+    /// Adds a font to try after the ones already in the stack
+    pub fn fallback(mut self, font: Font) -> Self {
+        self.fonts.push(font);
+        return self;
+    }
+
+    pub fn fonts(&self) -> &[Font] {
+        return &self.fonts;
+    }
+
+    /// Line metrics of the primary font
+    pub fn line_metrics(&self, size: f32) -> LineMetrics {
+        return self.fonts[0].line_metrics(size);
+    }
+
+    /// The first font with a glyph for `c`, or the missing glyph of the primary font
+    fn find_glyph(&self, c: char) -> (usize, u16) {
+        for (index, font) in self.fonts.iter().enumerate() {
+            let glyph = font.glyph_index(c);
+            if glyph != 0 {
+                return (index, glyph);
+            }
+        }
+        return (0, 0);
+    }
+
+    /// Places the glyphs of `text` at `size` pixels per em. Lines are
+    /// separated by `\n` and pen positions are rounded to whole pixels.
+    pub fn layout(&self, text: &str, size: f32) -> Vec<PositionedGlyph> {
+        let metrics = self.line_metrics(size);
+        let mut glyphs = Vec::new();
+        for (index, line) in text.split('\n').enumerate() {
+            let baseline = (metrics.ascent + index as f32 * metrics.line_height()).round();
+            let mut pen = 0.0;
+            let mut previous: Option<(usize, u16)> = None;
+            for c in line.chars().filter(|&c| c != '\r') {
+                let (font_index, glyph) = self.find_glyph(c);
+                let font = &self.fonts[font_index];
+                let scale = font.scale(size);
+                if let Some((previous_font, previous_glyph)) = previous {
+                    if previous_font == font_index {
+                        pen += font.kerning(previous_glyph, glyph) as f32 * scale;
+                    }
+                }
+                glyphs.push(PositionedGlyph { font: font_index, glyph, position: [pen.round(), baseline] });
+                pen += font.advance(glyph) as f32 * scale;
+                previous = Some((font_index, glyph));
+            }
+        }
+        return glyphs;
+    }
+
+    /// Width of the longest line and height of all lines of `text`, in pixels
+    pub fn measure(&self, text: &str, size: f32) -> [f32; 2] {
+        let mut width: f32 = 0.0;
+        for line in text.split('\n') {
+            if let Some(last) = self.layout(line, size).last() {
+                let font = &self.fonts[last.font];
+                width = width.max(last.position[0] + font.advance(last.glyph) as f32 * font.scale(size));
+            }
+        }
+        let lines = text.split('\n').count() as f32;
+        return [width, lines * self.line_metrics(size).line_height()];
+    }
+}
+
+/// Identifies a rasterized glyph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font: usize,
+    glyph: u16,
+    /// Bits of the size in pixels, which is an `f32`
+    size: u32,
+}
+
+/// Rasterized glyphs of a [`FontStack`], kept to draw the same text again
+#[derive(Debug, Clone, Default)]
+pub struct GlyphCache {
+    glyphs: HashMap<GlyphKey, RasterizedGlyph>,
+}
+
+impl GlyphCache {
+    pub fn new() -> Self {
+        return GlyphCache::default();
+    }
+
+    pub fn len(&self) -> usize {
+        return self.glyphs.len();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        return self.glyphs.is_empty();
+    }
+
+    /// Forgets every glyph, for instance after changing the fonts of the stack
+    pub fn clear(&mut self) {
+        self.glyphs.clear();
+    }
+
+    /// A glyph rasterized at `size` pixels per em. Glyphs that can't be
+    /// read are empty.
+    pub fn get(&mut self, fonts: &FontStack, font: usize, glyph: u16, size: f32) -> &RasterizedGlyph {
+        let key = GlyphKey { font, glyph, size: size.to_bits() };
+        return self.glyphs.entry(key).or_insert_with(|| {
+            return fonts.fonts[font].rasterize(glyph, size).unwrap_or(RasterizedGlyph {
+                size: [0, 0],
+                offset: [0, 0],
+                coverage: Vec::new(),
+            });
+        });
+    }
+
+    /// Draws `text` with the top left corner of its first line at
+    /// `position`. The canvas transform only moves the text.
+    pub fn draw(&mut self, canvas: &mut Canvas, fonts: &FontStack, text: &str, position: PointF, size: f32, color: Color) {
+        let [x, y] = canvas.transform().apply(position.into());
+        let origin = [x.round() as i32, y.round() as i32];
+        for placed in fonts.layout(text, size) {
+            let glyph = self.get(fonts, placed.font, placed.glyph, size);
+            let left = origin[0] + placed.position[0] as i32 + glyph.offset[0];
+            let top = origin[1] + placed.position[1] as i32 + glyph.offset[1];
+            for row in 0..glyph.size[1] {
+                for column in 0..glyph.size[0] {
+                    let coverage = glyph.coverage[(row * glyph.size[0] + column) as usize];
+                    if coverage > 0 {
+                        canvas.blend_pixel(left + column as i32, top + row as i32, color, coverage as f32 / 255.0);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Largest height a [`GlyphAtlas`] grows to
+const MAX_ATLAS_SIZE: u32 = 4096;
+
+/// Free pixels kept around glyphs so texture filtering doesn't bleed
+const ATLAS_PADDING: u32 = 1;
+
+/// Glyphs packed in rows on one coverage texture, for drawing text with a GPU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlyphAtlas {
+    width: u32,
+    height: u32,
+    coverage: Vec<u8>,
+    /// Top left corner of every glyph in the atlas
+    entries: HashMap<GlyphKey, [u32; 2]>,
+    /// Where the next glyph goes and the height of the current row
+    cursor: [u32; 2],
+    row_height: u32,
+    dirty: bool,
+}
+
+impl GlyphAtlas {
+    /// An empty atlas. It doubles in height when full, up to 4096 pixels or
+    /// its starting height if that is larger. A zero height is taken as 1.
+    pub fn new(width: u32, height: u32) -> Self {
+        let height = height.max(1);
+        return GlyphAtlas {
+            width,
+            height,
+            coverage: vec![0; width as usize * height as usize],
+            entries: HashMap::new(),
+            cursor: [0, 0],
+            row_height: 0,
+            dirty: true,
+        };
+    }
+
+    pub fn width(&self) -> u32 {
+        return self.width;
+    }
+
+    pub fn height(&self) -> u32 {
+        return self.height;
+    }
+
+    /// One byte per pixel, rows from top to bottom
+    pub fn coverage(&self) -> &[u8] {
+        return &self.coverage;
+    }
+
+    /// Whether glyphs were added or the atlas grew since the last call,
+    /// meaning the texture must be uploaded again
+    pub fn take_dirty(&mut self) -> bool {
+        return std::mem::replace(&mut self.dirty, false);
+    }
+
+    /// Finds room for a glyph and copies it in
+    fn insert(&mut self, key: GlyphKey, glyph: &RasterizedGlyph) -> Result<[u32; 2], TrueTypeError> {
+        if let Some(&position) = self.entries.get(&key) {
+            return Ok(position);
+        }
+        let [width, height] = [glyph.size[0] + ATLAS_PADDING, glyph.size[1] + ATLAS_PADDING];
+        if width > self.width {
+            return Err(TrueTypeError::AtlasFull);
+        }
+        if self.cursor[0] + width > self.width {
+            self.cursor = [0, self.cursor[1] + self.row_height];
+            self.row_height = 0;
+        }
+        while self.cursor[1] + height > self.height {
+            let height = self.height.saturating_mul(2).min(MAX_ATLAS_SIZE);
+            if height <= self.height {
+                return Err(TrueTypeError::AtlasFull);
+            }
+            self.height = height;
+            self.coverage.resize(self.width as usize * self.height as usize, 0);
+        }
+        let position = self.cursor;
+        for row in 0..glyph.size[1] {
+            let source = (row * glyph.size[0]) as usize;
+            let target = ((position[1] + row) * self.width + position[0]) as usize;
+            self.coverage[target..target + glyph.size[0] as usize]
+                .copy_from_slice(&glyph.coverage[source..source + glyph.size[0] as usize]);
+        }
+        self.cursor[0] += width;
+        self.row_height = self.row_height.max(height);
+        self.entries.insert(key, position);
+        self.dirty = true;
+        return Ok(position);
+    }
+
+    /// Two triangles per glyph of `text`, with the top left corner of the
+    /// first line at `position`. Glyphs missing from the atlas are added,
+    /// and texture coordinates are only valid for the current atlas size,
+    /// so vertices should be made again after [`GlyphAtlas::take_dirty`].
+    pub fn overlay_vertices(
+        &mut self,
+        cache: &mut GlyphCache,
+        fonts: &FontStack,
+        text: &str,
+        position: PointF,
+        size: f32,
+        color: Color,
+    ) -> Result<Vec<OverlayVertex>, TrueTypeError> {
+        let color = [color.r, color.g, color.b, color.a].map(|channel| channel as f32 / 255.0);
+        let origin = [position.x.round(), position.y.round()];
+        let mut placed_glyphs = Vec::new();
+        for placed in fonts.layout(text, size) {
+            let key = GlyphKey { font: placed.font, glyph: placed.glyph, size: size.to_bits() };
+            let glyph = cache.get(fonts, placed.font, placed.glyph, size);
+            if glyph.size[0] == 0 || glyph.size[1] == 0 {
+                continue;
+            }
+            let atlas_position = self.insert(key, glyph)?;
+            placed_glyphs.push((placed.position, glyph.size, glyph.offset, atlas_position));
+        }
+
+        let mut vertices = Vec::with_capacity(placed_glyphs.len() * 6);
+        let [atlas_width, atlas_height] = [self.width as f32, self.height as f32];
+        for (pen, [width, height], offset, [u, v]) in placed_glyphs {
+            let x0 = origin[0] + pen[0] + offset[0] as f32;
+            let y0 = origin[1] + pen[1] + offset[1] as f32;
+            let [x1, y1] = [x0 + width as f32, y0 + height as f32];
+            let [u0, v0] = [u as f32 / atlas_width, v as f32 / atlas_height];
+            let [u1, v1] = [(u + width) as f32 / atlas_width, (v + height) as f32 / atlas_height];
+            let vertex = |position, uv| OverlayVertex { position, uv, color };
+            vertices.extend_from_slice(&[
+                vertex([x0, y0], [u0, v0]),
+                vertex([x1, y0], [u1, v0]),
+                vertex([x1, y1], [u1, v1]),
+                vertex([x0, y0], [u0, v0]),
+                vertex([x1, y1], [u1, v1]),
+                vertex([x0, y1], [u0, v1]),
+            ]);
+        }
+        return Ok(vertices);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(width: u32, height: u32) -> RasterizedGlyph {
+        return RasterizedGlyph { size: [width, height], offset: [0, 0], coverage: vec![255; (width * height) as usize] };
+    }
+
+    fn key(glyph: u16) -> GlyphKey {
+        return GlyphKey { font: 0, glyph, size: 16f32.to_bits() };
+    }
+
+    fn be16(values: &[i32]) -> Vec<u8> {
+        return values.iter().flat_map(|&value| (value as u16).to_be_bytes()).collect();
+    }
+
+    /// The tables of a font with 1000 units per em and three glyphs: 0 is
+    /// empty, 1 is a 500 unit square and 2 is the square moved right by
+    /// 250 units. `first` and the character after it map to glyphs 1 and 2.
+    fn font_tables(first: char) -> Vec<(&'static [u8; 4], Vec<u8>)> {
+        let mut head = vec![0; 54];
+        head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+        let maxp = be16(&[0, 0x5000, 3]);
+        let mut hhea = be16(&[1, 0, 800, -200, 100]);
+        hhea.resize(34, 0);
+        hhea.extend(be16(&[3]));
+        let hmtx = be16(&[500, 0, 600, 0, 850, 0]);
+        let loca = be16(&[0, 0, 17, 26]);
+        // A simple glyph with four on-curve points, then a composite one
+        let mut glyf = be16(&[1, 0, 0, 500, 500, 3, 0]);
+        glyf.extend_from_slice(&[1, 1, 1, 1]);
+        glyf.extend(be16(&[0, 500, 0, -500, 0, 0, 500, 0]));
+        glyf.extend(be16(&[-1, 250, 0, 750, 500, 0x0003, 1, 250, 0]));
+        let first = first as i32;
+        let mut cmap = be16(&[0, 1, 3, 1, 0, 12]);
+        cmap.extend(be16(&[4, 32, 0, 4, 0, 0, 0]));
+        cmap.extend(be16(&[first + 1, 0xFFFF, 0, first, 0xFFFF, 1 - first, 1, 0, 0]));
+        let kern = be16(&[0, 1, 0, 20, 0x0001, 1, 0, 0, 0, 1, 2, -100]);
+        return vec![
+            (b"head", head),
+            (b"maxp", maxp),
+            (b"hhea", hhea),
+            (b"hmtx", hmtx),
+            (b"loca", loca),
+            (b"glyf", glyf),
+            (b"cmap", cmap),
+            (b"kern", kern),
+        ];
+    }
+
+    /// A font file with its table directory at `start`
+    fn font_file(start: usize, tables: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut data = 0x0001_0000u32.to_be_bytes().to_vec();
+        data.extend(be16(&[tables.len() as i32, 0, 0, 0]));
+        let mut offset = start + 12 + 16 * tables.len();
+        for (tag, table) in tables {
+            data.extend_from_slice(&tag[..]);
+            data.extend_from_slice(&[0; 4]);
+            data.extend_from_slice(&(offset as u32).to_be_bytes());
+            data.extend_from_slice(&(table.len() as u32).to_be_bytes());
+            offset += table.len().next_multiple_of(4);
+        }
+        for (_, table) in tables {
+            data.extend_from_slice(table);
+            data.resize(data.len().next_multiple_of(4), 0);
+        }
+        return data;
+    }
+
+    fn test_font() -> Font {
+        return Font::from_bytes(font_file(0, &font_tables('A'))).unwrap();
+    }
+
+    #[test]
+    fn font_tables_are_read() {
+        let font = test_font();
+        assert_eq!(font.units_per_em(), 1000);
+        assert_eq!(font.glyph_count(), 3);
+        assert_eq!([font.glyph_index('A'), font.glyph_index('B'), font.glyph_index('C')], [1, 2, 0]);
+        assert!(!font.has_glyph('\u{1F600}'));
+        assert_eq!([font.advance(0), font.advance(1), font.advance(2), font.advance(7)], [500, 600, 850, 850]);
+        assert_eq!([font.kerning(1, 2), font.kerning(2, 1)], [-100, 0]);
+        let metrics = font.line_metrics(10.0);
+        assert_eq!([metrics.ascent, metrics.descent, metrics.line_gap], [8.0, -2.0, 1.0]);
+        assert_eq!(metrics.line_height(), 11.0);
+    }
+
+    #[test]
+    fn fonts_in_collections_are_read() {
+        let mut data = b"ttcf\x00\x01\x00\x00\x00\x00\x00\x01\x00\x00\x00\x10".to_vec();
+        data.extend(font_file(16, &font_tables('A')));
+        let font = Font::from_collection(data.clone(), 0).unwrap();
+        assert_eq!(font.glyph_index('B'), 2);
+        assert_eq!(font.rasterize(2, 10.0), test_font().rasterize(2, 10.0));
+        assert_eq!(Font::from_collection(data, 1), Err(TrueTypeError::FontIndex(1)));
+        assert_eq!(Font::from_collection(font_file(0, &font_tables('A')), 1), Err(TrueTypeError::FontIndex(1)));
+    }
+
+    #[test]
+    fn malformed_fonts_are_rejected() {
+        let data = font_file(0, &font_tables('A'));
+        assert_eq!(Font::from_bytes(b"not a font".to_vec()), Err(TrueTypeError::NotAFont));
+        assert_eq!(Font::from_bytes(b"OTTO".to_vec()), Err(TrueTypeError::UnsupportedOutlines));
+        assert_eq!(Font::from_bytes(b"ttcf\x00\x01\x00\x00\x00\x00\x00\x01".to_vec()), Err(TrueTypeError::NotAFont));
+        assert_eq!(Font::from_bytes(data[..40].to_vec()), Err(TrueTypeError::NotAFont));
+        assert_eq!(Font::from_bytes(data[..data.len() - 8].to_vec()), Err(TrueTypeError::NotAFont));
+
+        let mut tables = font_tables('A');
+        tables.retain(|(tag, _)| *tag != b"cmap");
+        assert_eq!(Font::from_bytes(font_file(0, &tables)), Err(TrueTypeError::MissingTable("cmap")));
+        let mut tables = font_tables('A');
+        tables[1].1 = be16(&[0, 0x5000, 200]);
+        assert_eq!(Font::from_bytes(font_file(0, &tables)), Err(TrueTypeError::Truncated("loca")));
+        // Tables are only cut short by the end of the file
+        let mut tables = font_tables('A');
+        let mut hhea = tables.remove(2);
+        hhea.1.truncate(20);
+        tables.push(hhea);
+        assert_eq!(Font::from_bytes(font_file(0, &tables)), Err(TrueTypeError::Truncated("hhea")));
+        // A missing kern table only means no kerning
+        let mut tables = font_tables('A');
+        tables.pop();
+        assert_eq!(Font::from_bytes(font_file(0, &tables)).map(|font| font.kerning(1, 2)), Ok(0));
+    }
+
+    #[test]
+    fn glyphs_are_outlined_and_rasterized() {
+        let font = test_font();
+        assert_eq!(font.outline(0), Ok(Path::new()));
+        assert_eq!(font.rasterize(0, 10.0).map(|glyph| glyph.size), Ok([0, 0]));
+        assert_eq!(font.outline(3), Err(TrueTypeError::InvalidGlyph(3)));
+
+        let square = font.rasterize(1, 10.0).unwrap();
+        assert_eq!((square.size, square.offset), ([5, 5], [0, -5]));
+        assert!(square.coverage.iter().all(|&coverage| coverage == 255));
+        // The component lands half a pixel into the first column
+        let moved = font.rasterize(2, 10.0).unwrap();
+        assert_eq!((moved.size, moved.offset), ([6, 5], [2, -5]));
+        assert!((120..=136).contains(&moved.coverage[0]) && (120..=136).contains(&moved.coverage[5]));
+        assert_eq!(moved.coverage[1], 255);
+    }
+
+    #[test]
+    fn corrupt_glyphs_are_rejected() {
+        // A composite glyph made of itself
+        let mut tables = font_tables('A');
+        tables[5].1[47] = 2;
+        let font = Font::from_bytes(font_file(0, &tables)).unwrap();
+        assert_eq!(font.outline(2), Err(TrueTypeError::InvalidGlyph(2)));
+        // Glyph data past the end of the glyf table
+        let mut tables = font_tables('A');
+        tables[4].1 = be16(&[0, 0, 17, 40]);
+        let font = Font::from_bytes(font_file(0, &tables)).unwrap();
+        assert_eq!(font.outline(2), Err(TrueTypeError::InvalidGlyph(2)));
+        // A simple glyph cut short in its coordinates
+        let mut tables = font_tables('A');
+        tables[4].1 = be16(&[0, 0, 12, 26]);
+        let font = Font::from_bytes(font_file(0, &tables)).unwrap();
+        assert_eq!(font.outline(1), Err(TrueTypeError::InvalidGlyph(1)));
+    }
+
+    #[test]
+    fn huge_glyphs_are_not_rasterized() {
+        let font = test_font();
+        assert_eq!(font.rasterize(1, 1e6), Err(TrueTypeError::GlyphTooLarge(1)));
+        // Big enough to saturate the pixel coordinates
+        assert_eq!(font.rasterize(2, 1e30), Err(TrueTypeError::GlyphTooLarge(2)));
+        assert_eq!(font.rasterize(1, 8000.0).map(|glyph| glyph.size), Ok([4000, 4000]));
+    }
+
+    /// The test font with glyph 1 and 2 replaced
+    fn font_with_glyphs(simple: &[u8], composite: &[u8]) -> Font {
+        let mut tables = font_tables('A');
+        let (simple_end, composite_end) = (simple.len() / 2, (simple.len() + composite.len()) / 2);
+        tables[4].1 = be16(&[0, 0, simple_end as i32, composite_end as i32]);
+        tables[5].1 = [simple, composite].concat();
+        return Font::from_bytes(font_file(0, &tables)).unwrap();
+    }
+
+    /// A composite glyph placing glyph 1 `count` times
+    fn repeated_component(count: usize) -> Vec<u8> {
+        let mut glyph = be16(&[-1, 0, 0, 0, 0]);
+        for i in 0..count {
+            let more = if i + 1 < count { 0x0020 } else { 0 };
+            glyph.extend(be16(&[0x0002 | more, 1, 0]));
+        }
+        return glyph;
+    }
+
+    #[test]
+    fn outlines_have_a_component_budget() {
+        let square = font_tables('A')[5].1[..34].to_vec();
+        let font = font_with_glyphs(&square, &repeated_component(MAX_OUTLINE_COMPONENTS as usize));
+        assert!(font.outline(2).is_ok());
+        let font = font_with_glyphs(&square, &repeated_component(MAX_OUTLINE_COMPONENTS as usize + 1));
+        assert_eq!(font.outline(2), Err(TrueTypeError::OutlineTooComplex(2)));
+        // The budget is per outline
+        assert!(font.outline(1).is_ok());
+    }
+
+    #[test]
+    fn outlines_have_a_point_budget() {
+        // One contour of 65536 points at the origin, in repeated flags without coordinates
+        let mut dots = be16(&[1, 0, 0, 0, 0, 0xFFFF, 0]);
+        for _ in 0..256 {
+            dots.extend_from_slice(&[0x39, 255]);
+        }
+        let font = font_with_glyphs(&dots, &repeated_component(2));
+        assert!(font.outline(2).is_ok());
+        let font = font_with_glyphs(&dots, &repeated_component(3));
+        assert_eq!(font.outline(2), Err(TrueTypeError::OutlineTooComplex(1)));
+    }
+
+    #[test]
+    fn layout_kerns_and_falls_back() {
+        let fallback = Font::from_bytes(font_file(0, &font_tables('B'))).unwrap();
+        let fonts = FontStack::new(test_font()).fallback(fallback);
+        let positions: Vec<_> =
+            fonts.layout("AB\nAC", 10.0).iter().map(|placed| (placed.font, placed.glyph, placed.position)).collect();
+        assert_eq!(positions, vec![(0, 1, [0.0, 8.0]), (0, 2, [5.0, 8.0]), (0, 1, [0.0, 19.0]), (1, 2, [6.0, 19.0])]);
+        assert_eq!(fonts.measure("AB\nA", 10.0), [13.5, 22.0]);
+        assert_eq!(fonts.layout("D", 10.0)[0].glyph, 0);
+
+        let mut cache = GlyphCache::new();
+        let mut canvas = Canvas::new(16, 16);
+        cache.draw(&mut canvas, &fonts, "A", PointF::new(1.0, 0.0), 10.0, Color::BLACK);
+        assert_eq!(cache.len(), 1);
+        assert_eq!([canvas.pixel(1, 3), canvas.pixel(5, 7)], [Some(Color::BLACK); 2]);
+        assert_eq!([canvas.pixel(0, 3), canvas.pixel(1, 2), canvas.pixel(6, 7)], [Some(Color::TRANSPARENT); 3]);
+    }
+
+    #[test]
+    fn zero_height_atlas_grows() {
+        let mut atlas = GlyphAtlas::new(64, 0);
+        assert_eq!(atlas.insert(key(1), &glyph(8, 8)), Ok([0, 0]));
+        assert_eq!(atlas.height(), 16);
+        assert_eq!(atlas.coverage().len(), 64 * 16);
+    }
+
+    #[test]
+    fn atlas_grows_up_to_the_maximum() {
+        let mut atlas = GlyphAtlas::new(4, 3000);
+        assert_eq!(atlas.insert(key(1), &glyph(3, 2999)), Ok([0, 0]));
+        assert_eq!(atlas.insert(key(2), &glyph(3, 1000)), Ok([0, 3000]));
+        assert_eq!(atlas.height(), MAX_ATLAS_SIZE);
+        assert_eq!(atlas.insert(key(3), &glyph(3, 100)), Err(TrueTypeError::AtlasFull));
+    }
+
+    #[test]
+    fn atlas_taller_than_the_maximum_stays_as_is() {
+        let mut atlas = GlyphAtlas::new(4, 5000);
+        assert_eq!(atlas.insert(key(1), &glyph(3, 4999)), Ok([0, 0]));
+        assert_eq!(atlas.insert(key(2), &glyph(3, 10)), Err(TrueTypeError::AtlasFull));
+        assert_eq!(atlas.height(), 5000);
+    }
+
+    #[test]
+    fn glyphs_wider_than_the_atlas_are_rejected() {
+        let mut atlas = GlyphAtlas::new(8, 8);
+        assert_eq!(atlas.insert(key(1), &glyph(8, 1)), Err(TrueTypeError::AtlasFull));
+    }
+}
@@ -0,0 +1,59 @@
+use crate::win32::types::*;
+
+// Common dialog structures //
+
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct OPENFILENAMEW {
+    pub lStructSize: DWORD,
+    pub hwndOwner: HWND,
+    pub hInstance: HINSTANCE,
+    pub lpstrFilter: LPCWSTR,
+    pub lpstrCustomFilter: LPWSTR,
+    pub nMaxCustFilter: DWORD,
+    pub nFilterIndex: DWORD,
+    pub lpstrFile: LPWSTR,
+    pub nMaxFile: DWORD,
+    pub lpstrFileTitle: LPWSTR,
+    pub nMaxFileTitle: DWORD,
+    pub lpstrInitialDir: LPCWSTR,
+    pub lpstrTitle: LPCWSTR,
+    pub Flags: DWORD,
+    pub nFileOffset: WORD,
+    pub nFileExtension: WORD,
+    pub lpstrDefExt: LPCWSTR,
+    pub lCustData: LPARAM,
+    pub lpfnHook: LPVOID,
+    pub lpTemplateName: LPCWSTR,
+    pub pvReserved: LPVOID,
+    pub dwReserved: DWORD,
+    pub FlagsEx: DWORD,
+}
+
+impl Default for OPENFILENAMEW {
+    fn default() -> Self {
+        let mut ofn: OPENFILENAMEW = unsafe { core::mem::zeroed() };
+        ofn.lStructSize = core::mem::size_of::<OPENFILENAMEW>() as DWORD;
+        return ofn;
+    }
+}
+
+// CONSTANTS //
+
+pub const OFN_OVERWRITEPROMPT: DWORD = 0x0000_0002;
+pub const OFN_NOCHANGEDIR: DWORD = 0x0000_0008;
+pub const OFN_ALLOWMULTISELECT: DWORD = 0x0000_0200;
+pub const OFN_PATHMUSTEXIST: DWORD = 0x0000_0800;
+pub const OFN_FILEMUSTEXIST: DWORD = 0x0000_1000;
+pub const OFN_EXPLORER: DWORD = 0x0008_0000;
+
+/// `CommDlgExtendedError` value when the file name buffer was too small
+pub const FNERR_BUFFERTOOSMALL: DWORD = 0x3003;
+
+// COMMON DIALOG API //
+#[link(name = "Comdlg32")]
+extern "system" {
+    pub fn GetOpenFileNameW(lpofn: *mut OPENFILENAMEW) -> BOOL;
+    pub fn GetSaveFileNameW(lpofn: *mut OPENFILENAMEW) -> BOOL;
+    pub fn CommDlgExtendedError() -> DWORD;
+}
@@ -26,9 +26,52 @@ extern "system" {
     pub fn LocalFree(hMem: HLOCAL) -> HLOCAL;
 
     pub fn SetLastError(dwErrCode: DWORD);
+
+    pub fn Sleep(dwMilliseconds: DWORD);
+
+    // Global memory, needed to hand data to the clipboard
+    pub fn GlobalAlloc(uFlags: UINT, dwBytes: SIZE_T) -> HGLOBAL;
+    pub fn GlobalLock(hMem: HGLOBAL) -> LPVOID;
+    pub fn GlobalUnlock(hMem: HGLOBAL) -> BOOL;
+    pub fn GlobalSize(hMem: HGLOBAL) -> SIZE_T;
+    pub fn GlobalFree(hMem: HGLOBAL) -> HGLOBAL;
+
+    pub fn CloseHandle(hObject: HANDLE) -> BOOL;
+
+    // Low memory notifications
+    pub fn CreateMemoryResourceNotification(NotificationType: c_int) -> HANDLE;
+    pub fn QueryMemoryResourceNotification(ResourceNotificationHandle: HANDLE, ResourceState: *mut BOOL) -> BOOL;
 }
 
+pub const GMEM_MOVEABLE: UINT = 0x0002;
+pub const LOW_MEMORY_RESOURCE_NOTIFICATION: c_int = 0;
+
+// System error codes
+pub const ERROR_INVALID_PARAMETER: DWORD = 87;
+pub const ERROR_NOT_ENOUGH_MEMORY: DWORD = 8;
+
 #[allow(non_snake_case)]
 pub const fn MAKEINTRESOURCEW(i: WORD) -> LPWSTR {
     return i as ULONG_PTR as LPWSTR;
-}
\ No newline at end of file
+}
+#[allow(non_snake_case)]
+pub const fn LOWORD(l: usize) -> WORD {
+    return (l & 0xFFFF) as WORD;
+}
+
+#[allow(non_snake_case)]
+pub const fn HIWORD(l: usize) -> WORD {
+    return ((l >> 16) & 0xFFFF) as WORD;
+}
+
+/// Signed x coordinate packed in an `LPARAM`
+#[allow(non_snake_case)]
+pub const fn GET_X_LPARAM(lp: LPARAM) -> c_int {
+    return (lp & 0xFFFF) as i16 as c_int;
+}
+
+/// Signed y coordinate packed in an `LPARAM`
+#[allow(non_snake_case)]
+pub const fn GET_Y_LPARAM(lp: LPARAM) -> c_int {
+    return ((lp >> 16) & 0xFFFF) as i16 as c_int;
+}
@@ -0,0 +1,17 @@
+use crate::win32::types::*;
+
+// Window attributes for DwmSetWindowAttribute
+/// Dark title bar, Windows 10 20H1 and later
+pub const DWMWA_USE_IMMERSIVE_DARK_MODE: DWORD = 20;
+/// The undocumented value of the same attribute on earlier Windows 10 builds
+pub const DWMWA_USE_IMMERSIVE_DARK_MODE_BEFORE_20H1: DWORD = 19;
+
+// Desktop Window Manager
+#[link(name = "Dwmapi")]
+extern "system" {
+    /// [`DwmSetWindowAttribute`](https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/nf-dwmapi-dwmsetwindowattribute)
+    pub fn DwmSetWindowAttribute(hwnd: HWND, dwAttribute: DWORD, pvAttribute: LPCVOID, cbAttribute: DWORD) -> HRESULT;
+
+    /// [`DwmGetColorizationColor`](https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/nf-dwmapi-dwmgetcolorizationcolor)
+    pub fn DwmGetColorizationColor(pcrColorization: *mut DWORD, pfOpaqueBlend: *mut BOOL) -> HRESULT;
+}
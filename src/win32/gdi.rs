@@ -0,0 +1,120 @@
+use crate::win32::types::*;
+
+// GDI STRUCTURES //
+
+#[allow(non_snake_case)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BITMAPINFOHEADER {
+    pub biSize: DWORD,
+    pub biWidth: LONG,
+    /// Negative for top-down bitmaps
+    pub biHeight: LONG,
+    pub biPlanes: WORD,
+    pub biBitCount: WORD,
+    pub biCompression: DWORD,
+    pub biSizeImage: DWORD,
+    pub biXPelsPerMeter: LONG,
+    pub biYPelsPerMeter: LONG,
+    pub biClrUsed: DWORD,
+    pub biClrImportant: DWORD,
+}
+
+/// Narrowed to true colour bitmaps, which have no colour table
+#[allow(non_snake_case)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BITMAPINFO {
+    pub bmiHeader: BITMAPINFOHEADER,
+    pub bmiColors: [u32; 1],
+}
+
+#[allow(non_snake_case)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BLENDFUNCTION {
+    pub BlendOp: BYTE,
+    pub BlendFlags: BYTE,
+    pub SourceConstantAlpha: BYTE,
+    pub AlphaFormat: BYTE,
+}
+
+/// Followed by `nCount` rectangles in an `RGNDATA`
+#[allow(non_snake_case)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RGNDATAHEADER {
+    pub dwSize: DWORD,
+    pub iType: DWORD,
+    pub nCount: DWORD,
+    pub nRgnSize: DWORD,
+    pub rcBound: crate::win32::window::RECT,
+}
+
+// CONSTANTS //
+
+pub const BI_RGB: DWORD = 0;
+pub const DIB_RGB_COLORS: UINT = 0;
+pub const AC_SRC_OVER: BYTE = 0x00;
+pub const AC_SRC_ALPHA: BYTE = 0x01;
+pub const DEFAULT_GUI_FONT: c_int = 17;
+pub const SRCCOPY: DWORD = 0x00CC0020;
+/// Includes layered windows on top of the source
+pub const CAPTUREBLT: DWORD = 0x40000000;
+
+// Region types
+pub const ERROR: c_int = 0;
+pub const NULLREGION: c_int = 1;
+
+/// Packs a colour the way GDI wants it, `0x00BBGGRR`
+#[allow(non_snake_case)]
+pub const fn RGB(r: u8, g: u8, b: u8) -> COLORREF {
+    return r as COLORREF | (g as COLORREF) << 8 | (b as COLORREF) << 16;
+}
+
+// GDI API //
+#[link(name = "Gdi32")]
+extern "system" {
+    pub fn GetStockObject(i: c_int) -> HGDIOBJ;
+    pub fn CreateSolidBrush(color: COLORREF) -> HBRUSH;
+    pub fn CreateCompatibleDC(hdc: HDC) -> HDC;
+    pub fn DeleteDC(hdc: HDC) -> BOOL;
+    pub fn SelectObject(hdc: HDC, h: HGDIOBJ) -> HGDIOBJ;
+    pub fn DeleteObject(ho: HGDIOBJ) -> BOOL;
+    pub fn CreateDIBSection(
+        hdc: HDC,
+        pbmi: *const BITMAPINFO,
+        usage: UINT,
+        ppvBits: *mut *mut core::ffi::c_void,
+        hSection: HANDLE,
+        offset: DWORD,
+    ) -> HBITMAP;
+    pub fn SetDIBitsToDevice(
+        hdc: HDC,
+        xDest: c_int,
+        yDest: c_int,
+        w: DWORD,
+        h: DWORD,
+        xSrc: c_int,
+        ySrc: c_int,
+        StartScan: UINT,
+        cLines: UINT,
+        lpvBits: *const core::ffi::c_void,
+        lpbmi: *const BITMAPINFO,
+        ColorUse: UINT,
+    ) -> c_int;
+    pub fn BitBlt(
+        hdc: HDC,
+        x: c_int,
+        y: c_int,
+        cx: c_int,
+        cy: c_int,
+        hdcSrc: HDC,
+        x1: c_int,
+        y1: c_int,
+        rop: DWORD,
+    ) -> BOOL;
+    pub fn GdiFlush() -> BOOL;
+    pub fn CreateRectRgn(x1: c_int, y1: c_int, x2: c_int, y2: c_int) -> HRGN;
+    pub fn GetRegionData(hrgn: HRGN, nCount: DWORD, lpRgnData: *mut core::ffi::c_void) -> DWORD;
+}
@@ -0,0 +1,62 @@
+use crate::win32::types::*;
+use crate::win32::window::{POINT, RECT};
+
+// Input Method Manager (IME) structures //
+
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct COMPOSITIONFORM {
+    pub dwStyle: DWORD,
+    pub ptCurrentPos: POINT,
+    pub rcArea: RECT,
+}
+
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct CANDIDATEFORM {
+    pub dwIndex: DWORD,
+    pub dwStyle: DWORD,
+    pub ptCurrentPos: POINT,
+    pub rcArea: RECT,
+}
+
+// CONSTANTS //
+
+// ImmGetCompositionStringW indices, also the lParam flags of WM_IME_COMPOSITION
+pub const GCS_COMPSTR: DWORD = 0x0008;
+pub const GCS_CURSORPOS: DWORD = 0x0080;
+pub const GCS_RESULTSTR: DWORD = 0x0800;
+
+// COMPOSITIONFORM / CANDIDATEFORM styles
+pub const CFS_DEFAULT: DWORD = 0x0000;
+pub const CFS_RECT: DWORD = 0x0001;
+pub const CFS_POINT: DWORD = 0x0002;
+pub const CFS_CANDIDATEPOS: DWORD = 0x0040;
+pub const CFS_EXCLUDE: DWORD = 0x0080;
+
+// ImmAssociateContextEx flags
+pub const IACE_CHILDREN: DWORD = 0x0001;
+pub const IACE_DEFAULT: DWORD = 0x0010;
+pub const IACE_IGNORENOCONTEXT: DWORD = 0x0020;
+
+// Error values returned by ImmGetCompositionStringW
+pub const IMM_ERROR_NODATA: LONG = -1;
+pub const IMM_ERROR_GENERAL: LONG = -2;
+
+// IMM API //
+#[link(name = "Imm32")]
+extern "system" {
+    pub fn ImmGetContext(hWnd: HWND) -> HIMC;
+    pub fn ImmReleaseContext(hWnd: HWND, hIMC: HIMC) -> BOOL;
+    pub fn ImmAssociateContextEx(hWnd: HWND, hIMC: HIMC, dwFlags: DWORD) -> BOOL;
+
+    pub fn ImmGetCompositionStringW(
+        hIMC: HIMC,
+        dwIndex: DWORD,
+        lpBuf: LPVOID,
+        dwBufLen: DWORD,
+    ) -> LONG;
+
+    pub fn ImmSetCompositionWindow(hIMC: HIMC, lpCompForm: *const COMPOSITIONFORM) -> BOOL;
+    pub fn ImmSetCandidateWindow(hIMC: HIMC, lpCandidate: *const CANDIDATEFORM) -> BOOL;
+}
@@ -2,4 +2,11 @@ pub mod window;
 pub mod types;
 pub mod core;
 pub mod utils;
-pub mod wrapper;
\ No newline at end of file
+pub mod wrapper;
+pub mod imm;
+pub mod ole;
+pub mod shell;
+pub mod comdlg;
+pub mod gdi;
+pub mod registry;
+pub mod dwm;
\ No newline at end of file
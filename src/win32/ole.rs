@@ -0,0 +1,113 @@
+use crate::win32::types::*;
+
+// COM and OLE drag and drop types //
+
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct GUID {
+    pub Data1: u32,
+    pub Data2: u16,
+    pub Data3: u16,
+    pub Data4: [u8; 8],
+}
+
+pub const IID_IUNKNOWN: GUID = GUID {
+    Data1: 0x0000_0000,
+    Data2: 0x0000,
+    Data3: 0x0000,
+    Data4: [0xC0, 0, 0, 0, 0, 0, 0, 0x46],
+};
+
+pub const IID_IDROPTARGET: GUID = GUID {
+    Data1: 0x0000_0122,
+    Data2: 0x0000,
+    Data3: 0x0000,
+    Data4: [0xC0, 0, 0, 0, 0, 0, 0, 0x46],
+};
+
+#[allow(non_snake_case)]
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct POINTL {
+    pub x: LONG,
+    pub y: LONG,
+}
+
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct FORMATETC {
+    pub cfFormat: CLIPFORMAT,
+    pub ptd: LPVOID,
+    pub dwAspect: DWORD,
+    pub lindex: LONG,
+    pub tymed: DWORD,
+}
+
+/// `STGMEDIUM` with its union narrowed to the `hGlobal` member,
+/// the only one we ask for
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct STGMEDIUM {
+    pub tymed: DWORD,
+    pub hGlobal: HGLOBAL,
+    pub pUnkForRelease: LPVOID,
+}
+
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct IDataObject {
+    pub lpVtbl: *const IDataObjectVtbl,
+}
+
+/// Only the methods up to `GetData`; we never call past it
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct IDataObjectVtbl {
+    pub QueryInterface: unsafe extern "system" fn(*mut IDataObject, *const GUID, *mut LPVOID) -> HRESULT,
+    pub AddRef: unsafe extern "system" fn(*mut IDataObject) -> ULONG,
+    pub Release: unsafe extern "system" fn(*mut IDataObject) -> ULONG,
+    pub GetData: unsafe extern "system" fn(*mut IDataObject, *const FORMATETC, *mut STGMEDIUM) -> HRESULT,
+}
+
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct IDropTarget {
+    pub lpVtbl: *const IDropTargetVtbl,
+}
+
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct IDropTargetVtbl {
+    pub QueryInterface: unsafe extern "system" fn(*mut IDropTarget, *const GUID, *mut LPVOID) -> HRESULT,
+    pub AddRef: unsafe extern "system" fn(*mut IDropTarget) -> ULONG,
+    pub Release: unsafe extern "system" fn(*mut IDropTarget) -> ULONG,
+    pub DragEnter: unsafe extern "system" fn(*mut IDropTarget, *mut IDataObject, DWORD, POINTL, *mut DWORD) -> HRESULT,
+    pub DragOver: unsafe extern "system" fn(*mut IDropTarget, DWORD, POINTL, *mut DWORD) -> HRESULT,
+    pub DragLeave: unsafe extern "system" fn(*mut IDropTarget) -> HRESULT,
+    pub Drop: unsafe extern "system" fn(*mut IDropTarget, *mut IDataObject, DWORD, POINTL, *mut DWORD) -> HRESULT,
+}
+
+// CONSTANTS //
+
+pub const S_OK: HRESULT = 0;
+pub const S_FALSE: HRESULT = 1;
+pub const E_NOINTERFACE: HRESULT = 0x8000_4002_u32 as HRESULT;
+pub const E_UNEXPECTED: HRESULT = 0x8000_FFFF_u32 as HRESULT;
+
+pub const DROPEFFECT_NONE: DWORD = 0;
+pub const DROPEFFECT_COPY: DWORD = 1;
+
+pub const DVASPECT_CONTENT: DWORD = 1;
+pub const TYMED_HGLOBAL: DWORD = 1;
+
+// OLE API //
+#[link(name = "Ole32")]
+extern "system" {
+    pub fn OleInitialize(pvReserved: LPVOID) -> HRESULT;
+    pub fn OleUninitialize();
+    pub fn RegisterDragDrop(hwnd: HWND, pDropTarget: *mut IDropTarget) -> HRESULT;
+    pub fn RevokeDragDrop(hwnd: HWND) -> HRESULT;
+    pub fn ReleaseStgMedium(pMedium: *mut STGMEDIUM);
+    pub fn CoTaskMemFree(pv: LPVOID);
+}
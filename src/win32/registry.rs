@@ -0,0 +1,22 @@
+use crate::win32::types::*;
+
+pub const HKEY_CURRENT_USER: HKEY = 0x8000_0001_usize as HKEY;
+pub const ERROR_SUCCESS: LSTATUS = 0;
+
+// Types a value may have, for `dwFlags` of RegGetValueW
+pub const RRF_RT_REG_DWORD: DWORD = 0x0000_0010;
+
+// Registry
+#[link(name = "Advapi32")]
+extern "system" {
+    /// [`RegGetValueW`](https://docs.microsoft.com/en-us/windows/win32/api/winreg/nf-winreg-reggetvaluew)
+    pub fn RegGetValueW(
+        hkey: HKEY,
+        lpSubKey: LPCWSTR,
+        lpValue: LPCWSTR,
+        dwFlags: DWORD,
+        pdwType: *mut DWORD,
+        pvData: PVOID,
+        pcbData: *mut DWORD,
+    ) -> LSTATUS;
+}
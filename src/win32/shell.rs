@@ -0,0 +1,116 @@
+use crate::win32::types::*;
+use crate::win32::ole::GUID;
+use crate::win32::window::POINT;
+
+/// Receives the events of a folder picker, `BFFM_*` messages
+#[allow(non_snake_case)]
+pub type BFFCALLBACK = Option<
+    unsafe extern "system" fn(hwnd: HWND, uMsg: UINT, lParam: LPARAM, lpData: LPARAM) -> c_int,
+>;
+
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct BROWSEINFOW {
+    pub hwndOwner: HWND,
+    pub pidlRoot: LPVOID,
+    pub pszDisplayName: LPWSTR,
+    pub lpszTitle: LPCWSTR,
+    pub ulFlags: UINT,
+    pub lpfn: BFFCALLBACK,
+    pub lParam: LPARAM,
+    pub iImage: c_int,
+}
+
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct NOTIFYICONDATAW {
+    pub cbSize: DWORD,
+    pub hWnd: HWND,
+    pub uID: UINT,
+    pub uFlags: UINT,
+    pub uCallbackMessage: UINT,
+    pub hIcon: HICON,
+    pub szTip: [WCHAR; 128],
+    pub dwState: DWORD,
+    pub dwStateMask: DWORD,
+    pub szInfo: [WCHAR; 256],
+    /// Union with `uTimeout`, which is ignored since Vista
+    pub uVersion: UINT,
+    pub szInfoTitle: [WCHAR; 64],
+    pub dwInfoFlags: DWORD,
+    pub guidItem: GUID,
+    pub hBalloonIcon: HICON,
+}
+
+impl Default for NOTIFYICONDATAW {
+    fn default() -> Self {
+        let mut data: NOTIFYICONDATAW = unsafe { core::mem::zeroed() };
+        data.cbSize = core::mem::size_of::<NOTIFYICONDATAW>() as DWORD;
+        return data;
+    }
+}
+
+// CONSTANTS //
+
+/// Clipboard format of a list of dropped files
+pub const CF_HDROP: CLIPFORMAT = 15;
+
+pub const MAX_PATH: usize = 260;
+
+// SHBrowseForFolderW flags
+pub const BIF_RETURNONLYFSDIRS: UINT = 0x0001;
+pub const BIF_EDITBOX: UINT = 0x0010;
+pub const BIF_NEWDIALOGSTYLE: UINT = 0x0040;
+
+// SHBrowseForFolderW callback messages
+pub const BFFM_INITIALIZED: UINT = 1;
+/// `WM_USER + 103`, selects the folder whose path is in `lParam`
+pub const BFFM_SETSELECTIONW: UINT = 0x0467;
+
+// Shell_NotifyIconW messages
+pub const NIM_ADD: DWORD = 0x0000;
+pub const NIM_MODIFY: DWORD = 0x0001;
+pub const NIM_DELETE: DWORD = 0x0002;
+pub const NIM_SETVERSION: DWORD = 0x0004;
+
+// NOTIFYICONDATAW flags
+pub const NIF_MESSAGE: UINT = 0x0001;
+pub const NIF_ICON: UINT = 0x0002;
+pub const NIF_TIP: UINT = 0x0004;
+pub const NIF_INFO: UINT = 0x0010;
+pub const NIF_SHOWTIP: UINT = 0x0080;
+pub const NIIF_NONE: DWORD = 0x0000;
+pub const NIIF_INFO: DWORD = 0x0001;
+pub const NIIF_WARNING: DWORD = 0x0002;
+pub const NIIF_ERROR: DWORD = 0x0003;
+pub const NIIF_USER: DWORD = 0x0004;
+pub const NIIF_NOSOUND: DWORD = 0x0010;
+pub const NOTIFYICON_VERSION_4: UINT = 4;
+
+// Notifications sent to the tray icon's callback message
+pub const NIN_SELECT: UINT = 0x0400;
+pub const NIN_KEYSELECT: UINT = 0x0401;
+pub const NIN_BALLOONHIDE: UINT = 0x0403;
+pub const NIN_BALLOONTIMEOUT: UINT = 0x0404;
+pub const NIN_BALLOONUSERCLICK: UINT = 0x0405;
+
+// SHELL API //
+#[link(name = "Shell32")]
+extern "system" {
+    pub fn DragAcceptFiles(hWnd: HWND, fAccept: BOOL);
+    pub fn DragQueryFileW(hDrop: HDROP, iFile: UINT, lpszFile: LPWSTR, cch: UINT) -> UINT;
+    pub fn DragQueryPoint(hDrop: HDROP, ppt: *mut POINT) -> BOOL;
+    pub fn DragFinish(hDrop: HDROP);
+}
+
+#[link(name = "Shell32")]
+extern "system" {
+    /// Returns a PIDL that must be freed with `CoTaskMemFree`
+    pub fn SHBrowseForFolderW(lpbi: *mut BROWSEINFOW) -> LPVOID;
+    pub fn SHGetPathFromIDListW(pidl: LPCVOID, pszPath: LPWSTR) -> BOOL;
+}
+
+#[link(name = "Shell32")]
+extern "system" {
+    pub fn Shell_NotifyIconW(dwMessage: DWORD, lpData: *const NOTIFYICONDATAW) -> BOOL;
+}
@@ -34,6 +34,27 @@ pub type HANDLE = PVOID;
 #[allow(non_camel_case_types)]
 pub type HLOCAL = HANDLE;
 
+#[allow(non_camel_case_types)]
+pub type HGLOBAL = HANDLE;
+
+#[allow(non_camel_case_types)]
+pub type SIZE_T = usize;
+
+#[allow(non_camel_case_types)]
+pub type HDROP = HANDLE;
+
+#[allow(non_camel_case_types)]
+pub type HBITMAP = HANDLE;
+
+#[allow(non_camel_case_types)]
+pub type HRESULT = LONG;
+
+#[allow(non_camel_case_types)]
+pub type ULONG = c_ulong;
+
+#[allow(non_camel_case_types)]
+pub type CLIPFORMAT = WORD;
+
 #[allow(non_camel_case_types)]
 pub type HMODULE = HINSTANCE;
 
@@ -43,6 +64,9 @@ pub type HICON = HANDLE;
 #[allow(non_camel_case_types)]
 pub type HCURSOR = HICON;
 
+#[allow(non_camel_case_types)]
+pub type HIMC = HANDLE;
+
 #[allow(non_camel_case_types)]
 pub type LPCWSTR = *const WCHAR;
 
@@ -76,6 +100,21 @@ pub type c_ulong = u32;
 #[allow(non_camel_case_types)]
 pub type HMENU = HANDLE;
 
+#[allow(non_camel_case_types)]
+pub type HACCEL = HANDLE;
+
+#[allow(non_camel_case_types)]
+pub type HGDIOBJ = HANDLE;
+
+#[allow(non_camel_case_types)]
+pub type HRGN = HANDLE;
+
+#[allow(non_camel_case_types)]
+pub type COLORREF = DWORD;
+
+#[allow(non_camel_case_types)]
+pub type HMONITOR = HANDLE;
+
 #[allow(non_camel_case_types)]
 pub type LPVOID = *mut std::ffi::c_void;
 
@@ -108,3 +147,9 @@ pub type va_list = *mut c_char;
 
 #[allow(non_camel_case_types)]
 pub type c_char = i8;
+
+#[allow(non_camel_case_types)]
+pub type HKEY = HANDLE;
+
+#[allow(non_camel_case_types)]
+pub type LSTATUS = LONG;
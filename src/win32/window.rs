@@ -6,6 +6,7 @@
 // };
 use crate::win32::types::*;
 use crate::win32::core::*;
+use crate::geometry::{Point, Rect, Size};
 
 /// Define a function to zero out a struct's fields
 macro_rules! unsafe_impl_default_zeroed {
@@ -29,6 +30,20 @@ pub struct POINT {
 }
 unsafe_impl_default_zeroed!(POINT);
 
+impl POINT {
+    pub(crate) const fn new(x: LONG, y: LONG) -> Self {
+        return POINT { x, y };
+    }
+
+    pub(crate) const fn x(&self) -> LONG {
+        return self.x;
+    }
+
+    pub(crate) const fn y(&self) -> LONG {
+        return self.y;
+    }
+}
+
 pub type WNDPROC = Option<
     unsafe extern "system" fn(
         hwnd: HWND,
@@ -92,6 +107,122 @@ pub struct RECT {
 }
 unsafe_impl_default_zeroed!(RECT);
 
+impl RECT {
+    pub(crate) const fn new(left: LONG, top: LONG, right: LONG, bottom: LONG) -> Self {
+        return RECT { left, top, right, bottom };
+    }
+
+    pub(crate) const fn left(&self) -> LONG {
+        return self.left;
+    }
+
+    pub(crate) const fn top(&self) -> LONG {
+        return self.top;
+    }
+
+    pub(crate) const fn right(&self) -> LONG {
+        return self.right;
+    }
+
+    pub(crate) const fn bottom(&self) -> LONG {
+        return self.bottom;
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SIZE {
+    pub cx: LONG,
+    pub cy: LONG,
+}
+
+// Conversions from and to the public geometry types
+impl From<POINT> for Point {
+    fn from(point: POINT) -> Self {
+        return Point::new(point.x, point.y);
+    }
+}
+
+impl From<Point> for POINT {
+    fn from(point: Point) -> Self {
+        return POINT::new(point.x, point.y);
+    }
+}
+
+impl From<RECT> for Rect {
+    fn from(rect: RECT) -> Self {
+        return Rect::new(rect.left, rect.top, rect.right, rect.bottom);
+    }
+}
+
+impl From<Rect> for RECT {
+    fn from(rect: Rect) -> Self {
+        return RECT::new(rect.left, rect.top, rect.right, rect.bottom);
+    }
+}
+
+impl From<SIZE> for Size {
+    fn from(size: SIZE) -> Self {
+        return Size::new(size.cx, size.cy);
+    }
+}
+
+impl From<Size> for SIZE {
+    fn from(size: Size) -> Self {
+        return SIZE { cx: size.width, cy: size.height };
+    }
+}
+
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct WINDOWPLACEMENT {
+    pub length: UINT,
+    pub flags: UINT,
+    pub showCmd: UINT,
+    pub ptMinPosition: POINT,
+    pub ptMaxPosition: POINT,
+    /// In workspace coordinates: relative to the work area of the monitor
+    pub rcNormalPosition: RECT,
+}
+
+impl Default for WINDOWPLACEMENT {
+    fn default() -> Self {
+        let mut placement: WINDOWPLACEMENT = unsafe { core::mem::zeroed() };
+        placement.length = core::mem::size_of::<WINDOWPLACEMENT>() as UINT;
+        return placement;
+    }
+}
+
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct MONITORINFOEXW {
+    pub cbSize: DWORD,
+    pub rcMonitor: RECT,
+    pub rcWork: RECT,
+    pub dwFlags: DWORD,
+    pub szDevice: [WCHAR; 32],
+}
+
+impl Default for MONITORINFOEXW {
+    fn default() -> Self {
+        let mut info: MONITORINFOEXW = unsafe { core::mem::zeroed() };
+        info.cbSize = core::mem::size_of::<MONITORINFOEXW>() as DWORD;
+        return info;
+    }
+}
+
+pub type TIMERPROC = Option<unsafe extern "system" fn(HWND, UINT, UINT_PTR, DWORD)>;
+pub type MONITORENUMPROC = Option<unsafe extern "system" fn(HMONITOR, HDC, *mut RECT, LPARAM) -> BOOL>;
+
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct NCCALCSIZE_PARAMS {
+    /// The first rectangle is the proposed window rectangle on input
+    /// and the client rectangle on output
+    pub rgrc: [RECT; 3],
+    pub lppos: PVOID,
+}
+
 #[allow(non_snake_case)]
 #[repr(C)]
 pub struct CREATESTRUCTW {
@@ -110,6 +241,41 @@ pub struct CREATESTRUCTW {
 }
 unsafe_impl_default_zeroed!(CREATESTRUCTW);
 
+#[allow(non_snake_case)]
+#[repr(C)]
+pub struct MENUITEMINFOW {
+    pub cbSize: UINT,
+    pub fMask: UINT,
+    pub fType: UINT,
+    pub fState: UINT,
+    pub wID: UINT,
+    pub hSubMenu: HMENU,
+    pub hbmpChecked: HBITMAP,
+    pub hbmpUnchecked: HBITMAP,
+    pub dwItemData: ULONG_PTR,
+    pub dwTypeData: LPWSTR,
+    pub cch: UINT,
+    pub hbmpItem: HBITMAP,
+}
+
+impl Default for MENUITEMINFOW {
+    fn default() -> Self {
+        let mut info: MENUITEMINFOW = unsafe { core::mem::zeroed() };
+        info.cbSize = core::mem::size_of::<MENUITEMINFOW>() as UINT;
+        return info;
+    }
+}
+
+/// An entry of an accelerator table
+#[allow(non_snake_case)]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ACCEL {
+    pub fVirt: BYTE,
+    pub key: WORD,
+    pub cmd: WORD,
+}
+
 /// Enumeration of predefined cursors styles
 pub enum IDCursor {
     /// Standard arrow and small hourglass
@@ -155,46 +321,23 @@ pub enum IDCursor {
     Wait = 32514,
 }
 
-/// See [`GetSysColor`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getsyscolor)
-pub enum SysColor {
-  _3dDarkShadow = 21,
-  _3dLight = 22,
-  ActiveBorder = 10,
-  ActiveCaption = 2,
-  AppWorkspace = 12,
-  /// Button face, also "3D face" color.
-  ButtonFace = 15,
-  /// Button highlight, also "3D highlight" color.
-  ButtonHighlight = 20,
-  /// Button shadow, also "3D shadow" color.
-  ButtonShadow = 16,
-  ButtonText = 18,
-  CaptionText = 9,
-  /// Desktop background color
-  Desktop = 1,
-  GradientActiveCaption = 27,
-  GradientInactiveCaption = 28,
-  GrayText = 17,
-  Highlight = 13,
-  HighlightText = 14,
-  HotLight = 26,
-  InactiveBorder = 11,
-  InactiveCaption = 3,
-  InactiveCaptionText = 19,
-  InfoBackground = 24,
-  InfoText = 23,
-  Menu = 4,
-  MenuHighlight = 29,
-  MenuBar = 30,
-  MenuText = 7,
-  ScrollBar = 0,
-  Window = 5,
-  WindowFrame = 6,
-  WindowText = 8,
+/// Which of the two window icons to change. See [`WM_SETICON`]
+pub enum IconSize {
+  /// Shown in the title bar, usually 16x16 at 100% scale
+  Small = 0,
+  /// Shown in the taskbar and alt-tab switcher, usually 32x32 at 100% scale
+  Big = 1,
 }
 
+/// Moved to the portable theme module, see [`crate::theme::SysColor`]
+pub use crate::theme::SysColor;
+
 // CONSTANTS //
 
+// Window class styles
+pub const CS_VREDRAW: UINT = 0x0001;
+pub const CS_HREDRAW: UINT = 0x0002;
+
 // WS values to specify parameters for Window's window
 // TODO: There are more, but this is enough for now
 pub const WS_OVERLAPPED: u32 = 0x0000_0000;
@@ -211,11 +354,20 @@ pub const WS_OVERLAPPEDWINDOW: u32 = WS_OVERLAPPED
     | WS_THICKFRAME
     | WS_MINIMIZEBOX
     | WS_MAXIMIZEBOX;
+pub const WS_CHILD: u32 = 0x4000_0000;
+pub const WS_VISIBLE: u32 = 0x1000_0000;
+pub const WS_BORDER: u32 = 0x0080_0000;
+pub const WS_VSCROLL: u32 = 0x0020_0000;
+pub const WS_TABSTOP: u32 = 0x0001_0000;
 pub const WS_EX_APPWINDOW: DWORD = 0x00040000;
 pub const WS_EX_WINDOWEDGE: DWORD = 0x00000100;
 pub const WS_EX_CLIENTEDGE: DWORD = 0x00000200;
 pub const WS_EX_OVERLAPPEDWINDOW: DWORD = WS_EX_WINDOWEDGE | WS_EX_CLIENTEDGE;
 pub const CW_USEDEFAULT: c_int = 0x8000_0000_u32 as c_int;
+pub const SW_HIDE: c_int = 0;
+pub const SW_SHOWNORMAL: c_int = 1;
+pub const SW_SHOWMINIMIZED: c_int = 2;
+pub const SW_SHOWMAXIMIZED: c_int = 3;
 pub const SW_SHOW: c_int = 5;
 pub const WM_CLOSE: u32 = 0x0010;
 pub const WM_QUIT: u32 = 0x0012;
@@ -226,14 +378,234 @@ pub const IDC_ARROW: LPCWSTR = MAKEINTRESOURCEW(32512);
 pub const WM_PAINT: u32 = 0x000F;
 
 pub const COLOR_WINDOW: u32 = 5;
-pub const MB_OKCANCEL: u32 = 1;
+
+// MessageBoxW buttons
+pub const MB_OK: u32 = 0x0000_0000;
+pub const MB_OKCANCEL: u32 = 0x0000_0001;
+pub const MB_ABORTRETRYIGNORE: u32 = 0x0000_0002;
+pub const MB_YESNOCANCEL: u32 = 0x0000_0003;
+pub const MB_YESNO: u32 = 0x0000_0004;
+pub const MB_RETRYCANCEL: u32 = 0x0000_0005;
+pub const MB_CANCELTRYCONTINUE: u32 = 0x0000_0006;
+// MessageBoxW icons
+pub const MB_ICONERROR: u32 = 0x0000_0010;
+pub const MB_ICONQUESTION: u32 = 0x0000_0020;
+pub const MB_ICONWARNING: u32 = 0x0000_0030;
+pub const MB_ICONINFORMATION: u32 = 0x0000_0040;
+// MessageBoxW default button and modality
+pub const MB_DEFBUTTON1: u32 = 0x0000_0000;
+pub const MB_DEFBUTTON2: u32 = 0x0000_0100;
+pub const MB_DEFBUTTON3: u32 = 0x0000_0200;
+pub const MB_DEFBUTTON4: u32 = 0x0000_0300;
+pub const MB_APPLMODAL: u32 = 0x0000_0000;
+pub const MB_SYSTEMMODAL: u32 = 0x0000_1000;
+pub const MB_TASKMODAL: u32 = 0x0000_2000;
+// MessageBoxW results
 pub const IDOK: c_int = 1;
+pub const IDCANCEL: c_int = 2;
+pub const IDABORT: c_int = 3;
+pub const IDRETRY: c_int = 4;
+pub const IDIGNORE: c_int = 5;
+pub const IDYES: c_int = 6;
+pub const IDNO: c_int = 7;
+pub const IDTRYAGAIN: c_int = 10;
+pub const IDCONTINUE: c_int = 11;
 
 pub const WM_NCCREATE: u32 = 0x0081;
 pub const WM_CREATE: u32 = 0x0001;
 pub const WM_SETCURSOR: u32 = 0x0020;
+pub const WM_KILLFOCUS: u32 = 0x0008;
+
+// Text input
+pub const WM_CHAR: u32 = 0x0102;
+pub const WM_DEADCHAR: u32 = 0x0103;
+pub const WM_SYSCHAR: u32 = 0x0106;
+pub const WM_UNICHAR: u32 = 0x0109;
+pub const UNICODE_NOCHAR: WPARAM = 0xFFFF;
+pub const WM_IME_STARTCOMPOSITION: u32 = 0x010D;
+pub const WM_IME_ENDCOMPOSITION: u32 = 0x010E;
+pub const WM_IME_COMPOSITION: u32 = 0x010F;
+
+pub const WM_DROPFILES: u32 = 0x0233;
+
+// Menus
+pub const WM_COMMAND: u32 = 0x0111;
+pub const WM_CONTEXTMENU: u32 = 0x007B;
+pub const WM_NULL: u32 = 0x0000;
+pub const WM_USER: u32 = 0x0400;
+pub const WM_APP: u32 = 0x8000;
+
+// Mouse
+pub const WM_LBUTTONUP: u32 = 0x0202;
+pub const WM_LBUTTONDBLCLK: u32 = 0x0203;
+pub const WM_RBUTTONUP: u32 = 0x0205;
+pub const MIIM_STATE: UINT = 0x0001;
+pub const MIIM_ID: UINT = 0x0002;
+pub const MIIM_SUBMENU: UINT = 0x0004;
+pub const MIIM_STRING: UINT = 0x0040;
+pub const MIIM_FTYPE: UINT = 0x0100;
+pub const MFT_STRING: UINT = 0x0000;
+pub const MFT_RADIOCHECK: UINT = 0x0200;
+pub const MFT_SEPARATOR: UINT = 0x0800;
+pub const MFS_ENABLED: UINT = 0x0000;
+pub const MFS_DISABLED: UINT = 0x0003;
+pub const MFS_CHECKED: UINT = 0x0008;
+pub const TPM_LEFTALIGN: UINT = 0x0000;
+pub const TPM_TOPALIGN: UINT = 0x0000;
+pub const TPM_RIGHTBUTTON: UINT = 0x0002;
+pub const TPM_RETURNCMD: UINT = 0x0100;
+
+// Accelerators and hot keys
+pub const FVIRTKEY: BYTE = 0x01;
+pub const FSHIFT: BYTE = 0x04;
+pub const FCONTROL: BYTE = 0x08;
+pub const FALT: BYTE = 0x10;
+pub const WM_HOTKEY: u32 = 0x0312;
+pub const MOD_ALT: UINT = 0x0001;
+pub const MOD_CONTROL: UINT = 0x0002;
+pub const MOD_SHIFT: UINT = 0x0004;
+pub const MOD_WIN: UINT = 0x0008;
+pub const MOD_NOREPEAT: UINT = 0x4000;
+
+// Virtual key codes. Letters and digits use their ASCII code.
+pub const VK_BACK: UINT = 0x08;
+pub const VK_TAB: UINT = 0x09;
+pub const VK_RETURN: UINT = 0x0D;
+pub const VK_ESCAPE: UINT = 0x1B;
+pub const VK_SPACE: UINT = 0x20;
+pub const VK_PRIOR: UINT = 0x21;
+pub const VK_NEXT: UINT = 0x22;
+pub const VK_END: UINT = 0x23;
+pub const VK_HOME: UINT = 0x24;
+pub const VK_LEFT: UINT = 0x25;
+pub const VK_UP: UINT = 0x26;
+pub const VK_RIGHT: UINT = 0x27;
+pub const VK_DOWN: UINT = 0x28;
+pub const VK_INSERT: UINT = 0x2D;
+pub const VK_DELETE: UINT = 0x2E;
+pub const VK_F1: UINT = 0x70;
+pub const VK_ADD: UINT = 0x6B;
+pub const VK_SUBTRACT: UINT = 0x6D;
+
+// Clipboard
+pub const WM_CLIPBOARDUPDATE: u32 = 0x031D;
+pub const CF_DIB: UINT = 8;
+pub const CF_UNICODETEXT: UINT = 13;
+pub const CF_DIBV5: UINT = 17;
 
 pub const GWLP_USERDATA: c_int = -21;
+pub const GWLP_ID: c_int = -12;
+pub const GWL_STYLE: c_int = -16;
+pub const GWL_EXSTYLE: c_int = -20;
+
+// Layered windows and hit testing
+pub const WS_EX_LAYERED: DWORD = 0x0008_0000;
+pub const WS_EX_TRANSPARENT: DWORD = 0x0000_0020;
+pub const LWA_ALPHA: DWORD = 0x0000_0002;
+pub const ULW_ALPHA: DWORD = 0x0000_0002;
+pub const WM_NCHITTEST: u32 = 0x0084;
+pub const HTTRANSPARENT: LRESULT = -1;
+pub const HTCLIENT: LRESULT = 1;
+pub const HTCAPTION: LRESULT = 2;
+pub const HTSYSMENU: LRESULT = 3;
+pub const HTMINBUTTON: LRESULT = 8;
+pub const HTMAXBUTTON: LRESULT = 9;
+pub const HTLEFT: LRESULT = 10;
+pub const HTRIGHT: LRESULT = 11;
+pub const HTTOP: LRESULT = 12;
+pub const HTTOPLEFT: LRESULT = 13;
+pub const HTTOPRIGHT: LRESULT = 14;
+pub const HTBOTTOM: LRESULT = 15;
+pub const HTBOTTOMLEFT: LRESULT = 16;
+pub const HTBOTTOMRIGHT: LRESULT = 17;
+pub const HTCLOSE: LRESULT = 20;
+
+// Non-client area
+pub const WM_NCCALCSIZE: u32 = 0x0083;
+pub const WM_NCLBUTTONDOWN: u32 = 0x00A1;
+pub const WM_NCLBUTTONUP: u32 = 0x00A2;
+pub const WM_SYSCOMMAND: u32 = 0x0112;
+pub const SC_MINIMIZE: WPARAM = 0xF020;
+pub const SC_MAXIMIZE: WPARAM = 0xF030;
+pub const SC_CLOSE: WPARAM = 0xF060;
+pub const SC_RESTORE: WPARAM = 0xF120;
+pub const SM_CXFRAME: c_int = 32;
+pub const SM_CYFRAME: c_int = 33;
+pub const SM_CXPADDEDBORDER: c_int = 92;
+pub const SWP_NOSIZE: UINT = 0x0001;
+pub const SWP_NOMOVE: UINT = 0x0002;
+pub const SWP_NOZORDER: UINT = 0x0004;
+pub const SWP_NOACTIVATE: UINT = 0x0010;
+pub const SWP_FRAMECHANGED: UINT = 0x0020;
+pub const SWP_NOOWNERZORDER: UINT = 0x0200;
+
+// Placement and monitors
+pub const WPF_RESTORETOMAXIMIZED: UINT = 0x0002;
+pub const MONITOR_DEFAULTTONEAREST: DWORD = 0x0002;
+pub const MONITOR_DEFAULTTOPRIMARY: DWORD = 0x0001;
+pub const MONITORINFOF_PRIMARY: DWORD = 0x0001;
+
+// System events
+pub const WM_SYSCOLORCHANGE: u32 = 0x0015;
+pub const WM_SETTINGCHANGE: u32 = 0x001A;
+pub const WM_DISPLAYCHANGE: u32 = 0x007E;
+pub const WM_TIMER: u32 = 0x0113;
+pub const WM_POWERBROADCAST: u32 = 0x0218;
+pub const WM_WTSSESSION_CHANGE: u32 = 0x02B1;
+pub const WM_THEMECHANGED: u32 = 0x031A;
+pub const PBT_APMSUSPEND: WPARAM = 0x0004;
+pub const PBT_APMRESUMEAUTOMATIC: WPARAM = 0x0012;
+pub const WTS_SESSION_LOCK: WPARAM = 0x7;
+pub const WTS_SESSION_UNLOCK: WPARAM = 0x8;
+pub const NOTIFY_FOR_THIS_SESSION: DWORD = 0;
+
+// Standard controls
+pub const BS_PUSHBUTTON: u32 = 0x0000;
+pub const BS_CHECKBOX: u32 = 0x0002;
+pub const BS_AUTOCHECKBOX: u32 = 0x0003;
+pub const BS_TYPEMASK: u32 = 0x000F;
+pub const BS_NOTIFY: u32 = 0x4000;
+pub const ES_LEFT: u32 = 0x0000;
+pub const ES_MULTILINE: u32 = 0x0004;
+pub const ES_AUTOVSCROLL: u32 = 0x0040;
+pub const ES_AUTOHSCROLL: u32 = 0x0080;
+pub const ES_WANTRETURN: u32 = 0x1000;
+pub const LBS_NOTIFY: u32 = 0x0001;
+pub const LBS_NOINTEGRALHEIGHT: u32 = 0x0100;
+pub const BN_CLICKED: WORD = 0;
+pub const BN_SETFOCUS: WORD = 6;
+pub const BN_KILLFOCUS: WORD = 7;
+pub const EN_SETFOCUS: WORD = 0x0100;
+pub const EN_KILLFOCUS: WORD = 0x0200;
+pub const EN_CHANGE: WORD = 0x0300;
+pub const LBN_SELCHANGE: WORD = 1;
+pub const LBN_DBLCLK: WORD = 2;
+pub const LBN_SETFOCUS: WORD = 4;
+pub const LBN_KILLFOCUS: WORD = 5;
+pub const BM_GETCHECK: u32 = 0x00F0;
+pub const BM_SETCHECK: u32 = 0x00F1;
+pub const BST_UNCHECKED: WPARAM = 0x0000;
+pub const BST_CHECKED: WPARAM = 0x0001;
+pub const LB_ADDSTRING: u32 = 0x0180;
+pub const LB_RESETCONTENT: u32 = 0x0184;
+pub const LB_SETCURSEL: u32 = 0x0186;
+pub const LB_GETCURSEL: u32 = 0x0188;
+pub const LB_GETCOUNT: u32 = 0x018B;
+pub const LB_ERR: LRESULT = -1;
+pub const LB_ERRSPACE: LRESULT = -2;
+pub const WM_SETFONT: u32 = 0x0030;
+
+pub const WM_SETICON: u32 = 0x0080;
+pub const ICON_SMALL: WPARAM = 0;
+pub const ICON_BIG: WPARAM = 1;
+pub const GCLP_HICON: c_int = -14;
+pub const GCLP_HICONSM: c_int = -34;
+
+// System metrics for the icon sizes the shell expects
+pub const SM_CXICON: c_int = 11;
+pub const SM_CYICON: c_int = 12;
+pub const SM_CXSMICON: c_int = 49;
+pub const SM_CYSMICON: c_int = 50;
 
 // WINDOWS API //
 
@@ -315,11 +687,159 @@ extern "system" {
     // Painting the window
     pub fn BeginPaint(hWnd: HWND, lpPaint: LPPAINTSTRUCT) -> HDC;
     pub fn FillRect(hDC: HDC, lprc: *const RECT, hbr: HBRUSH) -> c_int;
+    pub fn GetSysColor(nIndex: c_int) -> DWORD;
     pub fn EndPaint(hWnd: HWND, lpPaint: *const PAINTSTRUCT) -> BOOL;
+    pub fn InvalidateRect(hWnd: HWND, lpRect: *const RECT, bErase: BOOL) -> BOOL;
+    pub fn UpdateWindow(hWnd: HWND) -> BOOL;
+    pub fn GetUpdateRgn(hWnd: HWND, hRgn: HRGN, bErase: BOOL) -> c_int;
 
     // Closing the window
-    pub fn MessageBoxW(hWnd: HWND, lpText: LPCWSTR, lpCaption: LPCWSTR, uType: UINT);
+    pub fn MessageBoxW(hWnd: HWND, lpText: LPCWSTR, lpCaption: LPCWSTR, uType: UINT) -> c_int;
 
     pub fn SetWindowLongPtrW(hWnd: HWND, nIndex: c_int, dwNewLong: LONG_PTR) -> LONG_PTR;
     pub fn GetWindowLongPtrW(hWnd: HWND, nIndex: c_int) -> LONG_PTR;
-}
\ No newline at end of file
+}
+
+// Icons
+#[link(name = "User32")]
+extern "system" {
+    pub fn CreateIcon(
+        hInstance: HINSTANCE,
+        nWidth: c_int,
+        nHeight: c_int,
+        cPlanes: BYTE,
+        cBitsPixel: BYTE,
+        lpbANDbits: *const BYTE,
+        lpbXORbits: *const BYTE,
+    ) -> HICON;
+    pub fn DestroyIcon(hIcon: HICON) -> BOOL;
+
+    pub fn SendMessageW(hWnd: HWND, Msg: UINT, wParam: WPARAM, lParam: LPARAM) -> LRESULT;
+    pub fn SetClassLongPtrW(hWnd: HWND, nIndex: c_int, dwNewLong: LONG_PTR) -> ULONG_PTR;
+    pub fn GetSystemMetrics(nIndex: c_int) -> c_int;
+}
+
+#[link(name = "User32")]
+extern "system" {
+    pub fn ScreenToClient(hWnd: HWND, lpPoint: *mut POINT) -> BOOL;
+    pub fn ClientToScreen(hWnd: HWND, lpPoint: *mut POINT) -> BOOL;
+}
+
+// Timers
+#[link(name = "User32")]
+extern "system" {
+    pub fn SetTimer(hWnd: HWND, nIDEvent: UINT_PTR, uElapse: UINT, lpTimerFunc: TIMERPROC) -> UINT_PTR;
+    pub fn KillTimer(hWnd: HWND, uIDEvent: UINT_PTR) -> BOOL;
+}
+
+// Session notifications
+#[link(name = "Wtsapi32")]
+extern "system" {
+    pub fn WTSRegisterSessionNotification(hWnd: HWND, dwFlags: DWORD) -> BOOL;
+    pub fn WTSUnRegisterSessionNotification(hWnd: HWND) -> BOOL;
+}
+
+// Clipboard
+#[link(name = "User32")]
+extern "system" {
+    pub fn OpenClipboard(hWndNewOwner: HWND) -> BOOL;
+    pub fn CloseClipboard() -> BOOL;
+    pub fn EmptyClipboard() -> BOOL;
+    pub fn GetClipboardData(uFormat: UINT) -> HANDLE;
+    pub fn SetClipboardData(uFormat: UINT, hMem: HANDLE) -> HANDLE;
+    pub fn IsClipboardFormatAvailable(format: UINT) -> BOOL;
+    pub fn RegisterClipboardFormatW(lpszFormat: LPCWSTR) -> UINT;
+    pub fn GetClipboardSequenceNumber() -> DWORD;
+    pub fn AddClipboardFormatListener(hwnd: HWND) -> BOOL;
+    pub fn RemoveClipboardFormatListener(hwnd: HWND) -> BOOL;
+}
+
+// Menus
+#[link(name = "User32")]
+extern "system" {
+    pub fn CreateMenu() -> HMENU;
+    pub fn CreatePopupMenu() -> HMENU;
+    pub fn DestroyMenu(hMenu: HMENU) -> BOOL;
+    pub fn InsertMenuItemW(hmenu: HMENU, item: UINT, fByPosition: BOOL, lpmi: *const MENUITEMINFOW) -> BOOL;
+    pub fn SetMenuItemInfoW(hmenu: HMENU, item: UINT, fByPosition: BOOL, lpmii: *const MENUITEMINFOW) -> BOOL;
+    pub fn SetMenu(hWnd: HWND, hMenu: HMENU) -> BOOL;
+    pub fn GetMenu(hWnd: HWND) -> HMENU;
+    pub fn DrawMenuBar(hWnd: HWND) -> BOOL;
+    pub fn TrackPopupMenu(
+        hMenu: HMENU,
+        uFlags: UINT,
+        x: c_int,
+        y: c_int,
+        nReserved: c_int,
+        hWnd: HWND,
+        prcRect: *const RECT,
+    ) -> BOOL;
+    pub fn SetForegroundWindow(hWnd: HWND) -> BOOL;
+    pub fn PostMessageW(hWnd: HWND, Msg: UINT, wParam: WPARAM, lParam: LPARAM) -> BOOL;
+    pub fn RegisterWindowMessageW(lpString: LPCWSTR) -> UINT;
+}
+
+// Layered windows
+#[link(name = "User32")]
+extern "system" {
+    pub fn SetLayeredWindowAttributes(hwnd: HWND, crKey: COLORREF, bAlpha: BYTE, dwFlags: DWORD) -> BOOL;
+    pub fn UpdateLayeredWindow(
+        hWnd: HWND,
+        hdcDst: HDC,
+        pptDst: *const POINT,
+        psize: *const SIZE,
+        hdcSrc: HDC,
+        pptSrc: *const POINT,
+        crKey: COLORREF,
+        pblend: *const crate::win32::gdi::BLENDFUNCTION,
+        dwFlags: DWORD,
+    ) -> BOOL;
+    pub fn GetDC(hWnd: HWND) -> HDC;
+    pub fn ReleaseDC(hWnd: HWND, hDC: HDC) -> c_int;
+}
+
+// Window frame
+#[link(name = "User32")]
+extern "system" {
+    pub fn SetWindowPos(hWnd: HWND, hWndInsertAfter: HWND, X: c_int, Y: c_int, cx: c_int, cy: c_int, uFlags: UINT) -> BOOL;
+    pub fn IsZoomed(hWnd: HWND) -> BOOL;
+    pub fn IsIconic(hWnd: HWND) -> BOOL;
+    pub fn GetSystemMenu(hWnd: HWND, bRevert: BOOL) -> HMENU;
+    pub fn GetClientRect(hWnd: HWND, lpRect: *mut RECT) -> BOOL;
+    pub fn GetWindowRect(hWnd: HWND, lpRect: *mut RECT) -> BOOL;
+    pub fn GetWindowPlacement(hWnd: HWND, lpwndpl: *mut WINDOWPLACEMENT) -> BOOL;
+    pub fn SetWindowPlacement(hWnd: HWND, lpwndpl: *const WINDOWPLACEMENT) -> BOOL;
+}
+
+// Monitors
+#[link(name = "User32")]
+extern "system" {
+    pub fn MonitorFromWindow(hwnd: HWND, dwFlags: DWORD) -> HMONITOR;
+    pub fn GetMonitorInfoW(hMonitor: HMONITOR, lpmi: *mut MONITORINFOEXW) -> BOOL;
+    pub fn EnumDisplayMonitors(hdc: HDC, lprcClip: *const RECT, lpfnEnum: MONITORENUMPROC, dwData: LPARAM) -> BOOL;
+    pub fn GetDpiForWindow(hwnd: HWND) -> UINT;
+}
+
+// Child windows and controls
+#[link(name = "User32")]
+extern "system" {
+    pub fn MoveWindow(hWnd: HWND, X: c_int, Y: c_int, nWidth: c_int, nHeight: c_int, bRepaint: BOOL) -> BOOL;
+    pub fn GetDlgItem(hDlg: HWND, nIDDlgItem: c_int) -> HWND;
+    pub fn GetClassNameW(hWnd: HWND, lpClassName: LPWSTR, nMaxCount: c_int) -> c_int;
+    pub fn GetWindowTextLengthW(hWnd: HWND) -> c_int;
+    pub fn GetWindowTextW(hWnd: HWND, lpString: LPWSTR, nMaxCount: c_int) -> c_int;
+    pub fn SetWindowTextW(hWnd: HWND, lpString: LPCWSTR) -> BOOL;
+    pub fn EnableWindow(hWnd: HWND, bEnable: BOOL) -> BOOL;
+    pub fn SetFocus(hWnd: HWND) -> HWND;
+}
+
+// Accelerators and hot keys
+#[link(name = "User32")]
+extern "system" {
+    pub fn CreateAcceleratorTableW(paccel: *const ACCEL, cAccel: c_int) -> HACCEL;
+    pub fn DestroyAcceleratorTable(hAccel: HACCEL) -> BOOL;
+    pub fn TranslateAcceleratorW(hWnd: HWND, hAccTable: HACCEL, lpMsg: *const MSG) -> c_int;
+    pub fn VkKeyScanW(ch: WCHAR) -> i16;
+    pub fn RegisterHotKey(hWnd: HWND, id: c_int, fsModifiers: UINT, vk: UINT) -> BOOL;
+    pub fn UnregisterHotKey(hWnd: HWND, id: c_int) -> BOOL;
+}
@@ -1,6 +1,27 @@
 use crate::win32::*;
 
 use self::{window::{WNDCLASSW, CreateWindowExW, GWLP_USERDATA}, core::GetLastError, types, utils::wide_null};
+use crate::accelerator::{Accelerator, AcceleratorTable, Key};
+use crate::canvas::Canvas;
+use crate::clipboard::{Clipboard, ClipboardError, ClipboardImage};
+use crate::control::{ControlEvent, ControlId, ControlKind};
+use crate::dirty_region::DirtyRegion;
+use crate::geometry::{Point, Rect, Size, DEFAULT_DPI};
+use crate::dialog::{
+    DefaultButton, DialogError, Dialogs, FileDialog, MessageBox, MessageButtons, MessageIcon,
+    MessageResult, Modality,
+};
+use crate::drag_drop::DropTracker;
+use crate::event::{DragDrop, EventQueue, SystemEvent, WindowEvent};
+use crate::hit_test::{HitTest, ResizeEdge};
+use crate::image::{Image, PixelFormat};
+use crate::menu::{Menu, MenuId, MenuItemKind};
+use crate::placement::{Monitor, WindowPlacement};
+use crate::text_input::TextInput;
+use crate::theme::{ColorScheme, SysColor, Theme};
+use crate::trace::MessageTracer;
+use crate::tray::{Notification, NotificationIcon, TrayEvent, TrayIcon, TrayId};
+use crate::window_builder::WindowBuilder;
 
 /// Abstraction to represent an error
 #[derive(Debug)]
@@ -168,15 +189,15 @@ pub unsafe fn create_window_ex_w(
 pub unsafe fn create_app_window(
     class_name: &str,
     window_name: &str,
-    position: Option<[i32; 2]>,
-    [width, height]: [i32; 2],
+    position: Option<Point>,
+    Size { width, height }: Size,
     create_param: types::LPVOID,
 ) -> Result<types::HWND, Win32Error> {
     let class_name_null = wide_null(class_name);
     let window_name_null = wide_null(window_name);
 
     let (x, y) = match position {
-        Some([x, y]) => (x, y),
+        Some(Point { x, y }) => (x, y),
         None => (window::CW_USEDEFAULT, window::CW_USEDEFAULT),
     };
 
@@ -322,20 +343,3351 @@ pub unsafe fn end_paint(hwnd: types::HWND, ps: &window::PAINTSTRUCT) {
 /// regardless of the current mapping mode
 /// [`FillRect`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-fillrect)
 pub unsafe fn fill_rect_with_sys_color(
-    hdc: types::HDC, rect: &window::RECT, color: window::SysColor
+    hdc: types::HDC, rect: &Rect, color: window::SysColor
 ) -> Result<(), ()> {
-    if window::FillRect(hdc, rect, (color as u32 + 1) as types::HBRUSH) != 0 {
+    let rect = window::RECT::from(*rect);
+    if window::FillRect(hdc, &rect, (color as u32 + 1) as types::HBRUSH) != 0 {
         return Ok(());
     } else {
         return Err(());
     }
 }
 
+/// Copies the pixels of a canvas to a device context, e.g. the one
+/// [`paint_window`] hands out. GDI ignores alpha, so clear the canvas to an
+/// opaque colour first.
+///
+/// ## Safety
+///
+/// `hdc` must be a valid device context.
+///
+/// See [`SetDIBitsToDevice`](https://docs.microsoft.com/en-us/windows/win32/api/wingdi/nf-wingdi-setdibitstodevice)
+pub unsafe fn draw_canvas(hdc: types::HDC, Point { x, y }: Point, canvas: &Canvas) -> Result<(), Win32Error> {
+    let (width, height) = (canvas.width(), canvas.height());
+    if width == 0 || height == 0 {
+        return Ok(());
+    }
+    let mut bgra = canvas.pixels().to_vec();
+    for px in bgra.chunks_exact_mut(4) {
+        px.swap(0, 2);
+    }
+
+    let mut info = gdi::BITMAPINFO::default();
+    info.bmiHeader.biSize = std::mem::size_of::<gdi::BITMAPINFOHEADER>() as types::DWORD;
+    info.bmiHeader.biWidth = width as types::LONG;
+    info.bmiHeader.biHeight = -(height as types::LONG);
+    info.bmiHeader.biPlanes = 1;
+    info.bmiHeader.biBitCount = 32;
+    info.bmiHeader.biCompression = gdi::BI_RGB;
+
+    let lines = gdi::SetDIBitsToDevice(
+        hdc, x, y, width, height, 0, 0, 0, height, bgra.as_ptr().cast(), &info, gdi::DIB_RGB_COLORS,
+    );
+    if lines == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(());
+    }
+}
+
+/// Paints the invalid part of a window between `BeginPaint` and `EndPaint`
+///
+/// The callback gets the device context, whether the background needs
+/// erasing, the bounding box of the damage and the damaged rectangles
+/// themselves, which are read before `BeginPaint` validates them.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle, and this must be called while handling
+/// its `WM_PAINT`. `f` must not destroy the window.
 pub unsafe fn paint_window<F, T>(hwnd: types::HWND, f: F) -> Result<T, Win32Error>
-where F: FnOnce(types::HDC, bool, window::RECT) -> Result<T, Win32Error>,
+where F: FnOnce(types::HDC, bool, Rect, &DirtyRegion) -> Result<T, Win32Error>,
 {
+    let region = get_update_region(hwnd).unwrap_or_default();
     let (hdc, ps) = begin_paint(hwnd)?;
-    let output = f(hdc, ps.fErase != 0, ps.rcPaint);
+    // Painting without an update region, e.g. after `GetDC` drawing, uses the paint rectangle
+    let bounds = Rect::from(ps.rcPaint);
+    let region = if region.is_empty() { DirtyRegion::from(bounds) } else { region };
+    let output = f(hdc, ps.fErase != 0, bounds, &region);
     end_paint(hwnd, &ps);
     return output;
-}
\ No newline at end of file
+}
+
+/// The rectangles of the client area waiting to be repainted, empty when
+/// the window is valid. Call before `BeginPaint`, which validates them.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`GetUpdateRgn`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getupdatergn)
+pub unsafe fn get_update_region(hwnd: types::HWND) -> Result<DirtyRegion, Win32Error> {
+    let region = gdi::CreateRectRgn(0, 0, 0, 0);
+    if region.is_null() {
+        return Err(get_last_error());
+    }
+    if window::GetUpdateRgn(hwnd, region, 0) == gdi::ERROR {
+        let e = get_last_error();
+        gdi::DeleteObject(region);
+        return Err(e);
+    }
+    let size = gdi::GetRegionData(region, 0, std::ptr::null_mut()) as usize;
+    // DWORDs keep the header and rectangles aligned
+    let mut buffer = vec![0u32; size.div_ceil(4)];
+    let written = if size == 0 { 0 } else { gdi::GetRegionData(region, size as types::DWORD, buffer.as_mut_ptr().cast()) };
+    let e = get_last_error();
+    gdi::DeleteObject(region);
+    if written == 0 || size < std::mem::size_of::<gdi::RGNDATAHEADER>() {
+        return Err(e);
+    }
+
+    let header = &*(buffer.as_ptr() as *const gdi::RGNDATAHEADER);
+    let (start, count) = (header.dwSize as usize, header.nCount as usize);
+    if start % 4 != 0 || start + count * std::mem::size_of::<window::RECT>() > size {
+        return Err(Win32Error(core::ERROR_INVALID_PARAMETER));
+    }
+    let rects = std::slice::from_raw_parts(buffer.as_ptr().add(start / 4) as *const window::RECT, count);
+    return Ok(rects.iter().map(|&rect| Rect::from(rect)).collect());
+}
+
+/// Marks part of the client area as needing a repaint, or all of it for
+/// `None`. The window gets `WM_PAINT` once its message queue is otherwise
+/// empty, or right away with [`update_window`]. The background isn't
+/// erased: paint callbacks cover the damaged region themselves.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`InvalidateRect`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-invalidaterect)
+pub unsafe fn request_redraw(hwnd: types::HWND, rect: Option<&Rect>) -> Result<(), Win32Error> {
+    let rect = rect.map(|&rect| window::RECT::from(rect));
+    let pointer = rect.as_ref().map_or(std::ptr::null(), |rect| rect as *const window::RECT);
+    if window::InvalidateRect(hwnd, pointer, 0) == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(());
+    }
+}
+
+/// [`request_redraw`] for every rectangle of a region
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+pub unsafe fn request_redraw_region(hwnd: types::HWND, region: &DirtyRegion) -> Result<(), Win32Error> {
+    for rect in region.rects() {
+        request_redraw(hwnd, Some(rect))?;
+    }
+    return Ok(());
+}
+
+/// Sends `WM_PAINT` straight to the window procedure if anything is
+/// waiting to be repainted
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle. Its window procedure runs before this
+/// returns, so the caller must not hold anything the procedure borrows.
+///
+/// See [`UpdateWindow`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-updatewindow)
+pub unsafe fn update_window(hwnd: types::HWND) -> Result<(), Win32Error> {
+    if window::UpdateWindow(hwnd) == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(());
+    }
+}
+
+/// Reads back what the client area of a window shows, as an opaque
+/// [`PixelFormat::Bgra8`] image
+///
+/// The pixels come from the screen, so parts of the window covered by
+/// other windows or outside the monitors come back with whatever is
+/// there instead. A minimized window has no client area and fails with
+/// `ERROR_INVALID_PARAMETER`.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`BitBlt`](https://docs.microsoft.com/en-us/windows/win32/api/wingdi/nf-wingdi-bitblt)
+pub unsafe fn capture_window(hwnd: types::HWND) -> Result<Image, Win32Error> {
+    let Size { width, height } = get_client_size(hwnd)?;
+    if width <= 0 || height <= 0 {
+        return Err(Win32Error(core::ERROR_INVALID_PARAMETER));
+    }
+
+    let mut info = gdi::BITMAPINFO::default();
+    info.bmiHeader.biSize = std::mem::size_of::<gdi::BITMAPINFOHEADER>() as types::DWORD;
+    info.bmiHeader.biWidth = width;
+    info.bmiHeader.biHeight = -height;
+    info.bmiHeader.biPlanes = 1;
+    info.bmiHeader.biBitCount = 32;
+    info.bmiHeader.biCompression = gdi::BI_RGB;
+
+    let window_dc = window::GetDC(hwnd);
+    if window_dc.is_null() {
+        return Err(get_last_error());
+    }
+    let memory_dc = gdi::CreateCompatibleDC(window_dc);
+    let mut bits: *mut std::ffi::c_void = std::ptr::null_mut();
+    let bitmap = gdi::CreateDIBSection(memory_dc, &info, gdi::DIB_RGB_COLORS, &mut bits, std::ptr::null_mut(), 0);
+    if memory_dc.is_null() || bitmap.is_null() || bits.is_null() {
+        let e = get_last_error();
+        if !bitmap.is_null() {
+            gdi::DeleteObject(bitmap);
+        }
+        if !memory_dc.is_null() {
+            gdi::DeleteDC(memory_dc);
+        }
+        window::ReleaseDC(hwnd, window_dc);
+        return Err(e);
+    }
+    let previous = gdi::SelectObject(memory_dc, bitmap);
+    let ok = gdi::BitBlt(memory_dc, 0, 0, width, height, window_dc, 0, 0, gdi::SRCCOPY | gdi::CAPTUREBLT) != 0;
+    let e = get_last_error();
+    gdi::GdiFlush();
+
+    let length = width as usize * height as usize * 4;
+    let mut data = std::slice::from_raw_parts(bits as *const u8, length).to_vec();
+
+    gdi::SelectObject(memory_dc, previous);
+    gdi::DeleteObject(bitmap);
+    gdi::DeleteDC(memory_dc);
+    window::ReleaseDC(hwnd, window_dc);
+
+    if !ok {
+        return Err(e);
+    }
+    // GDI leaves the alpha byte of what it draws undefined
+    for pixel in data.chunks_exact_mut(4) {
+        pixel[3] = 255;
+    }
+    return Image::from_data(width as u32, height as u32, PixelFormat::Bgra8, width as usize * 4, data)
+        .map_err(|_| Win32Error(core::ERROR_NOT_ENOUGH_MEMORY));
+}
+
+/// Creates an `HICON` from a single RGBA icon image
+///
+/// The icon must be destroyed with [`destroy_icon`] once no window
+/// or class uses it anymore.
+///
+/// See [`CreateIcon`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-createicon)
+pub fn create_icon_from_image(image: &crate::icon::IconImage) -> Result<types::HICON, Win32Error> {
+    let width = image.width() as usize;
+    let height = image.height() as usize;
+
+    // The colour bitmap is 32bpp BGRA, top-down. The alpha channel
+    // carries the transparency, but the monochrome AND mask is still
+    // required, so we derive it from alpha for older consumers.
+    let mut xor_bits = Vec::with_capacity(width * height * 4);
+    for px in image.rgba().chunks_exact(4) {
+        xor_bits.extend_from_slice(&[px[2], px[1], px[0], px[3]]);
+    }
+
+    // AND mask rows are padded to a WORD boundary
+    let and_stride = width.div_ceil(16) * 2;
+    let mut and_bits = vec![0_u8; and_stride * height];
+    for y in 0..height {
+        for x in 0..width {
+            if image.rgba()[(y * width + x) * 4 + 3] == 0 {
+                and_bits[y * and_stride + x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+    }
+
+    // Safety: both buffers are sized for the given width and height
+    let hicon = unsafe {
+        window::CreateIcon(
+            get_process_handle(),
+            width as types::c_int,
+            height as types::c_int,
+            1,
+            32,
+            and_bits.as_ptr(),
+            xor_bits.as_ptr(),
+        )
+    };
+
+    if hicon.is_null() {
+        return Err(get_last_error());
+    } else {
+        return Ok(hicon);
+    }
+}
+
+/// Creates an `HICON` at the size the system uses for the
+/// small (title bar) or big (taskbar) icon
+///
+/// See [`GetSystemMetrics`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getsystemmetrics)
+pub fn create_icon(icon: &crate::icon::Icon, size: window::IconSize) -> Result<types::HICON, Win32Error> {
+    let metric = match size {
+        window::IconSize::Small => window::SM_CXSMICON,
+        window::IconSize::Big => window::SM_CXICON,
+    };
+    let pixels = match unsafe { window::GetSystemMetrics(metric) } {
+        0 => match size {
+            window::IconSize::Small => 16,
+            window::IconSize::Big => 32,
+        },
+        pixels => pixels as u32,
+    };
+
+    // The icon data has already been validated, so resizing can only
+    // fail if the system reports an absurd icon size
+    let image = icon
+        .image_for_size(pixels)
+        .map_err(|_| Win32Error(core::ERROR_INVALID_PARAMETER))?;
+    return create_icon_from_image(&image);
+}
+
+/// Destroys an icon created with [`create_icon`] or [`create_icon_from_image`]
+///
+/// ## Safety
+///
+/// `hicon` must be an icon this program created that no window or class uses
+/// any more. It must not be used after this call.
+///
+/// See [`DestroyIcon`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-destroyicon)
+pub unsafe fn destroy_icon(hicon: types::HICON) -> Result<(), Win32Error> {
+    if window::DestroyIcon(hicon) == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(());
+    }
+}
+
+/// Sets the small or big icon of a single window
+///
+/// **Returns:** The previous icon of that size, which may be null
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle and `hicon` a valid icon or null. The
+/// icon must stay alive for as long as the window uses it.
+///
+/// See [`WM_SETICON`](https://docs.microsoft.com/en-us/windows/win32/winmsg/wm-seticon)
+pub unsafe fn set_window_icon(
+    hwnd: types::HWND, size: window::IconSize, hicon: types::HICON,
+) -> types::HICON {
+    let which = match size {
+        window::IconSize::Small => window::ICON_SMALL,
+        window::IconSize::Big => window::ICON_BIG,
+    };
+    return window::SendMessageW(hwnd, window::WM_SETICON, which, hicon as types::LPARAM) as types::HICON;
+}
+
+/// Sets the small or big icon of the window class the window belongs to.
+/// Every window of that class without its own icon will use it.
+///
+/// **Returns:** The previous class icon of that size, which may be null
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle and `hicon` a valid icon or null. The
+/// icon must stay alive for as long as any window of the class uses it.
+///
+/// See [`SetClassLongPtrW`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setclasslongptrw)
+pub unsafe fn set_class_icon(
+    hwnd: types::HWND, size: window::IconSize, hicon: types::HICON,
+) -> Result<types::HICON, Win32Error> {
+    let index = match size {
+        window::IconSize::Small => window::GCLP_HICONSM,
+        window::IconSize::Big => window::GCLP_HICON,
+    };
+    set_last_error(Win32Error(0));
+    let out = window::SetClassLongPtrW(hwnd, index, hicon as types::LONG_PTR);
+    if out == 0 {
+        // A previous value of 0 is only an error if the last error is set
+        let last_error = get_last_error();
+        if last_error.0 != 0 {
+            return Err(last_error);
+        }
+    }
+    return Ok(out as types::HICON);
+}
+
+/// Creates both icon sizes from `icon` and assigns them to the window
+///
+/// **Returns:** The `(small, big)` icons that were created. The caller owns
+/// them and should pass them to [`destroy_icon`] after the window is gone.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+pub unsafe fn set_window_icons(
+    hwnd: types::HWND, icon: &crate::icon::Icon,
+) -> Result<(types::HICON, types::HICON), Win32Error> {
+    let small = create_icon(icon, window::IconSize::Small)?;
+    let big = match create_icon(icon, window::IconSize::Big) {
+        Ok(big) => big,
+        Err(e) => {
+            let _ = destroy_icon(small);
+            return Err(e);
+        }
+    };
+    set_window_icon(hwnd, window::IconSize::Small, small);
+    set_window_icon(hwnd, window::IconSize::Big, big);
+    return Ok((small, big));
+}
+
+/// Input context of a window, released when dropped
+struct ImmContext {
+    hwnd: types::HWND,
+    himc: types::HIMC,
+}
+
+impl ImmContext {
+    /// **Returns:** `None` when the window has no input context (no IME is active)
+    unsafe fn get(hwnd: types::HWND) -> Option<ImmContext> {
+        let himc = imm::ImmGetContext(hwnd);
+        if himc.is_null() {
+            return None;
+        } else {
+            return Some(ImmContext { hwnd, himc });
+        }
+    }
+}
+
+impl Drop for ImmContext {
+    fn drop(&mut self) {
+        unsafe {
+            imm::ImmReleaseContext(self.hwnd, self.himc)
+        };
+    }
+}
+
+/// Reads the composition (`GCS_COMPSTR`) or result (`GCS_RESULTSTR`) string
+/// of the window's input context as UTF-16
+///
+/// **Returns:** `None` when there is no input context or no string
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle belonging to the calling thread.
+///
+/// See [`ImmGetCompositionStringW`](https://docs.microsoft.com/en-us/windows/win32/api/imm/nf-imm-immgetcompositionstringw)
+pub unsafe fn get_ime_composition_string(hwnd: types::HWND, index: types::DWORD) -> Option<Vec<u16>> {
+    let context = ImmContext::get(hwnd)?;
+
+    // The first call gets the size in bytes, the second one fills the buffer
+    let byte_len = imm::ImmGetCompositionStringW(context.himc, index, std::ptr::null_mut(), 0);
+    if byte_len < 0 {
+        return None;
+    }
+    let mut buffer = vec![0_u16; byte_len as usize / 2];
+    let written = imm::ImmGetCompositionStringW(
+        context.himc,
+        index,
+        buffer.as_mut_ptr().cast(),
+        byte_len as types::DWORD,
+    );
+    if written < 0 {
+        return None;
+    }
+    buffer.truncate(written as usize / 2);
+    return Some(buffer);
+}
+
+/// Gets the cursor position in the composition string, in UTF-16 code units
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle belonging to the calling thread.
+///
+/// See [`ImmGetCompositionStringW`](https://docs.microsoft.com/en-us/windows/win32/api/imm/nf-imm-immgetcompositionstringw)
+pub unsafe fn get_ime_cursor_position(hwnd: types::HWND) -> Option<usize> {
+    let context = ImmContext::get(hwnd)?;
+    let position = imm::ImmGetCompositionStringW(context.himc, imm::GCS_CURSORPOS, std::ptr::null_mut(), 0);
+    if position < 0 {
+        return None;
+    } else {
+        return Some(position as usize);
+    }
+}
+
+/// Enables or disables the IME for a window. Windows that don't take text
+/// (such as a 3D viewport) should disable it so keys reach the window directly.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle belonging to the calling thread.
+///
+/// See [`ImmAssociateContextEx`](https://docs.microsoft.com/en-us/windows/win32/api/imm/nf-imm-immassociatecontextex)
+pub unsafe fn set_ime_allowed(hwnd: types::HWND, allowed: bool) -> Result<(), Win32Error> {
+    let flags = if allowed { imm::IACE_DEFAULT } else { 0 };
+    if imm::ImmAssociateContextEx(hwnd, std::ptr::null_mut(), flags) == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(());
+    }
+}
+
+/// Tells the IME where the text cursor is, in client coordinates, so the
+/// composition and candidate windows are placed next to it without
+/// covering the text being typed
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle belonging to the calling thread.
+///
+/// See [`ImmSetCandidateWindow`](https://docs.microsoft.com/en-us/windows/win32/api/imm/nf-imm-immsetcandidatewindow)
+pub unsafe fn set_ime_cursor_area(hwnd: types::HWND, area: Rect) -> Result<(), Win32Error> {
+    let context = match ImmContext::get(hwnd) {
+        Some(context) => context,
+        // Nothing to position when no IME is active
+        None => return Ok(()),
+    };
+
+    let composition = imm::COMPOSITIONFORM {
+        dwStyle: imm::CFS_POINT,
+        ptCurrentPos: area.origin().into(),
+        rcArea: window::RECT::default(),
+    };
+    if imm::ImmSetCompositionWindow(context.himc, &composition) == 0 {
+        return Err(get_last_error());
+    }
+
+    let candidate = imm::CANDIDATEFORM {
+        dwIndex: 0,
+        dwStyle: imm::CFS_EXCLUDE,
+        ptCurrentPos: area.origin().into(),
+        rcArea: area.into(),
+    };
+    if imm::ImmSetCandidateWindow(context.himc, &candidate) == 0 {
+        return Err(get_last_error());
+    }
+    return Ok(());
+}
+
+/// Translates the text input and IME messages of a window procedure into
+/// events
+///
+/// **Returns:** `Some(result)` when the message was consumed and the window
+/// procedure should return `result`, `None` when it should fall through to
+/// `DefWindowProcW`. IME messages are consumed so the system doesn't show its
+/// own composition window or send the committed text a second time as
+/// `WM_CHAR`s; the application is expected to draw the preedit itself.
+///
+/// ## Safety
+///
+/// Must be called from the window procedure of `hwnd`, with the message and
+/// parameters it received.
+pub unsafe fn handle_text_input_message(
+    hwnd: types::HWND,
+    msg: types::UINT,
+    wparam: types::WPARAM,
+    lparam: types::LPARAM,
+    input: &mut TextInput,
+    events: &mut EventQueue,
+) -> Option<types::LRESULT> {
+    match msg {
+        window::WM_CHAR => {
+            input.utf16_unit(wparam as u16, events);
+            return Some(0);
+        }
+        window::WM_DEADCHAR => {
+            input.dead_key_unit(wparam as u16, events);
+            return Some(0);
+        }
+        window::WM_UNICHAR => {
+            // Answering TRUE to UNICODE_NOCHAR tells the sender we accept UTF-32
+            if wparam == window::UNICODE_NOCHAR {
+                return Some(1);
+            }
+            let c = char::from_u32(wparam as u32).unwrap_or(char::REPLACEMENT_CHARACTER);
+            input.character(c, events);
+            return Some(0);
+        }
+        window::WM_IME_STARTCOMPOSITION => {
+            input.ime_start(events);
+            return Some(0);
+        }
+        window::WM_IME_COMPOSITION => {
+            let flags = lparam as types::DWORD;
+            // The result comes first: some IMEs commit part of the text
+            // and keep composing the rest in the same message
+            if flags & imm::GCS_RESULTSTR != 0 {
+                if let Some(result) = get_ime_composition_string(hwnd, imm::GCS_RESULTSTR) {
+                    input.ime_commit(&result, events);
+                }
+            }
+            if flags & imm::GCS_COMPSTR != 0 {
+                if let Some(composition) = get_ime_composition_string(hwnd, imm::GCS_COMPSTR) {
+                    let cursor = if flags & imm::GCS_CURSORPOS != 0 {
+                        get_ime_cursor_position(hwnd)
+                    } else {
+                        None
+                    };
+                    input.ime_preedit(&composition, cursor, events);
+                }
+            }
+            return Some(0);
+        }
+        window::WM_IME_ENDCOMPOSITION => {
+            input.ime_end(events);
+            return Some(0);
+        }
+        window::WM_KILLFOCUS => {
+            // Don't let half a surrogate pair or a dead key
+            // leak into the next window that gets focus
+            input.reset();
+            return None;
+        }
+        _ => return None,
+    }
+}
+
+impl From<Win32Error> for ClipboardError {
+    fn from(e: Win32Error) -> Self {
+        return ClipboardError::Platform(e.0);
+    }
+}
+
+/// Keeps the clipboard open and closes it when dropped
+struct OpenedClipboard;
+
+impl OpenedClipboard {
+    /// Opens the clipboard, retrying for a short while because other
+    /// programs (clipboard managers in particular) open it all the time
+    ///
+    /// See [`OpenClipboard`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-openclipboard)
+    fn open(owner: types::HWND) -> Result<OpenedClipboard, Win32Error> {
+        let mut attempts = 0;
+        loop {
+            if unsafe { window::OpenClipboard(owner) } != 0 {
+                return Ok(OpenedClipboard);
+            }
+            attempts += 1;
+            if attempts == 10 {
+                return Err(get_last_error());
+            }
+            unsafe { core::Sleep(5) };
+        }
+    }
+}
+
+impl Drop for OpenedClipboard {
+    fn drop(&mut self) {
+        unsafe {
+            window::CloseClipboard()
+        };
+    }
+}
+
+/// Copies the contents of a global memory block
+///
+/// ## Safety
+///
+/// `handle` must be a valid `HGLOBAL`, such as one returned by `GetClipboardData`
+unsafe fn read_global(handle: types::HGLOBAL) -> Result<Vec<u8>, Win32Error> {
+    let size = core::GlobalSize(handle);
+    let ptr = core::GlobalLock(handle) as *const u8;
+    if ptr.is_null() {
+        return Err(get_last_error());
+    }
+    let bytes = std::slice::from_raw_parts(ptr, size).to_vec();
+    core::GlobalUnlock(handle);
+    return Ok(bytes);
+}
+
+/// Gets the raw bytes of a clipboard format. The clipboard must be open.
+///
+/// **Returns:** `None` if the clipboard doesn't hold that format
+///
+/// See [`GetClipboardData`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getclipboarddata)
+unsafe fn get_clipboard_bytes(format: types::UINT) -> Result<Option<Vec<u8>>, Win32Error> {
+    if window::IsClipboardFormatAvailable(format) == 0 {
+        return Ok(None);
+    }
+    let handle = window::GetClipboardData(format);
+    if handle.is_null() {
+        return Err(get_last_error());
+    }
+    return read_global(handle).map(Some);
+}
+
+/// Puts raw bytes on the clipboard. The clipboard must be open and emptied
+/// by this program first.
+///
+/// See [`SetClipboardData`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setclipboarddata)
+unsafe fn set_clipboard_bytes(format: types::UINT, bytes: &[u8]) -> Result<(), Win32Error> {
+    let handle = core::GlobalAlloc(core::GMEM_MOVEABLE, bytes.len().max(1));
+    if handle.is_null() {
+        return Err(Win32Error(core::ERROR_NOT_ENOUGH_MEMORY));
+    }
+    let ptr = core::GlobalLock(handle) as *mut u8;
+    if ptr.is_null() {
+        let e = get_last_error();
+        core::GlobalFree(handle);
+        return Err(e);
+    }
+    std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+    core::GlobalUnlock(handle);
+
+    // On success the system owns the memory, on failure we still do
+    if window::SetClipboardData(format, handle).is_null() {
+        let e = get_last_error();
+        core::GlobalFree(handle);
+        return Err(e);
+    }
+    return Ok(());
+}
+
+/// Registers (or looks up) an application defined clipboard format
+///
+/// See [`RegisterClipboardFormatW`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-registerclipboardformatw)
+pub fn register_clipboard_format(name: &str) -> Result<types::UINT, Win32Error> {
+    let name_null = wide_null(name);
+    let format = unsafe { window::RegisterClipboardFormatW(name_null.as_ptr()) };
+    if format == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(format);
+    }
+}
+
+/// Asks the system to send `WM_CLIPBOARDUPDATE` to the window whenever
+/// the clipboard contents change
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`AddClipboardFormatListener`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-addclipboardformatlistener)
+pub unsafe fn add_clipboard_listener(hwnd: types::HWND) -> Result<(), Win32Error> {
+    if window::AddClipboardFormatListener(hwnd) == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(());
+    }
+}
+
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle that was passed to
+/// [`add_clipboard_listener`].
+///
+/// See [`RemoveClipboardFormatListener`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-removeclipboardformatlistener)
+pub unsafe fn remove_clipboard_listener(hwnd: types::HWND) -> Result<(), Win32Error> {
+    if window::RemoveClipboardFormatListener(hwnd) == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(());
+    }
+}
+
+/// The system clipboard
+///
+/// Text is exchanged as `CF_UNICODETEXT` and images as `CF_DIBV5` (Windows
+/// synthesizes `CF_DIB` and `CF_BITMAP` for older programs). Custom formats
+/// are registered by name, so other programs using the same name can read them.
+/// The system rounds clipboard blocks up to its allocation granularity, so
+/// custom data is stored after its length as a little-endian `u64` and other
+/// programs reading it need to skip those 8 bytes.
+pub struct Win32Clipboard {
+    owner: types::HWND,
+}
+
+impl Win32Clipboard {
+    /// `owner` is the window that owns what we put on the clipboard.
+    /// It can be null, but then `set_*` only works from the thread
+    /// that has no window, which is rarely what you want.
+    pub fn new(owner: types::HWND) -> Self {
+        return Win32Clipboard { owner };
+    }
+
+    fn replace_with(&mut self, format: types::UINT, bytes: &[u8]) -> Result<(), ClipboardError> {
+        let _clipboard = OpenedClipboard::open(self.owner)?;
+        unsafe {
+            if window::EmptyClipboard() == 0 {
+                return Err(get_last_error().into());
+            }
+            set_clipboard_bytes(format, bytes)?;
+        }
+        return Ok(());
+    }
+}
+
+impl Clipboard for Win32Clipboard {
+    fn get_text(&mut self) -> Result<Option<String>, ClipboardError> {
+        let _clipboard = OpenedClipboard::open(self.owner)?;
+        let bytes = match unsafe { get_clipboard_bytes(window::CF_UNICODETEXT)? } {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+        let units: Vec<u16> = bytes
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .take_while(|&unit| unit != 0)
+            .collect();
+        return Ok(Some(crate::text_input::string_from_utf16_lossy(&units)));
+    }
+
+    fn set_text(&mut self, text: &str) -> Result<(), ClipboardError> {
+        let bytes: Vec<u8> = wide_null(text)
+            .into_iter()
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect();
+        return self.replace_with(window::CF_UNICODETEXT, &bytes);
+    }
+
+    fn get_image(&mut self) -> Result<Option<ClipboardImage>, ClipboardError> {
+        let _clipboard = OpenedClipboard::open(self.owner)?;
+        // Prefer the V5 header because it's the only one that says
+        // whether the fourth byte is alpha
+        let mut bytes = unsafe { get_clipboard_bytes(window::CF_DIBV5)? };
+        if bytes.is_none() {
+            bytes = unsafe { get_clipboard_bytes(window::CF_DIB)? };
+        }
+        match bytes {
+            Some(bytes) => return crate::clipboard::decode_dib(&bytes).map(Some),
+            None => return Ok(None),
+        }
+    }
+
+    fn set_image(&mut self, image: &ClipboardImage) -> Result<(), ClipboardError> {
+        return self.replace_with(window::CF_DIBV5, &crate::clipboard::encode_dibv5(image));
+    }
+
+    fn get_custom(&mut self, format: &str) -> Result<Option<Vec<u8>>, ClipboardError> {
+        if format.is_empty() {
+            return Err(ClipboardError::InvalidFormatName);
+        }
+        let format = register_clipboard_format(format)?;
+        let _clipboard = OpenedClipboard::open(self.owner)?;
+        let bytes = match unsafe { get_clipboard_bytes(format)? } {
+            Some(bytes) => bytes,
+            None => return Ok(None),
+        };
+        // Drop the length prefix and the padding after the data
+        let length = bytes
+            .get(..8)
+            .map(|prefix| u64::from_le_bytes(prefix.try_into().unwrap()))
+            .ok_or(ClipboardError::InvalidData("custom data without a length"))?;
+        let data = usize::try_from(length)
+            .ok()
+            .and_then(|length| bytes.get(8..8_usize.checked_add(length)?))
+            .ok_or(ClipboardError::InvalidData("custom data shorter than its length"))?;
+        return Ok(Some(data.to_vec()));
+    }
+
+    fn set_custom(&mut self, format: &str, data: &[u8]) -> Result<(), ClipboardError> {
+        if format.is_empty() {
+            return Err(ClipboardError::InvalidFormatName);
+        }
+        let format = register_clipboard_format(format)?;
+        let mut bytes = Vec::with_capacity(8 + data.len());
+        bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(data);
+        return self.replace_with(format, &bytes);
+    }
+
+    fn clear(&mut self) -> Result<(), ClipboardError> {
+        let _clipboard = OpenedClipboard::open(self.owner)?;
+        if unsafe { window::EmptyClipboard() } == 0 {
+            return Err(get_last_error().into());
+        }
+        return Ok(());
+    }
+
+    fn sequence_number(&self) -> u64 {
+        return unsafe { window::GetClipboardSequenceNumber() } as u64;
+    }
+}
+
+/// Initializes COM and OLE for this thread. Required before
+/// [`register_drop_target`], and the thread must stay alive (and keep
+/// pumping messages) for as long as its windows accept drops.
+///
+/// See [`OleInitialize`](https://docs.microsoft.com/en-us/windows/win32/api/ole2/nf-ole2-oleinitialize)
+pub fn ole_initialize() -> Result<(), Win32Error> {
+    let hr = unsafe { ole::OleInitialize(std::ptr::null_mut()) };
+    // S_FALSE means it was already initialized on this thread
+    if hr < 0 {
+        return Err(Win32Error(hr as types::DWORD));
+    } else {
+        return Ok(());
+    }
+}
+
+/// Reads every path from a `CF_HDROP` handle
+///
+/// ## Safety
+///
+/// `hdrop` must be a valid `HDROP`, such as the `wparam` of `WM_DROPFILES`,
+/// that hasn't been released with `DragFinish` yet.
+///
+/// See [`DragQueryFileW`](https://docs.microsoft.com/en-us/windows/win32/api/shellapi/nf-shellapi-dragqueryfilew)
+pub unsafe fn drop_handle_paths(hdrop: types::HDROP) -> Vec<std::path::PathBuf> {
+    use std::os::windows::ffi::OsStringExt;
+
+    // 0xFFFFFFFF asks for the number of files
+    let count = shell::DragQueryFileW(hdrop, u32::MAX, std::ptr::null_mut(), 0);
+    let mut paths = Vec::with_capacity(count as usize);
+    for index in 0..count {
+        // first call for the length without the null, then fill the buffer
+        let len = shell::DragQueryFileW(hdrop, index, std::ptr::null_mut(), 0);
+        let mut buffer = vec![0_u16; len as usize + 1];
+        let copied = shell::DragQueryFileW(hdrop, index, buffer.as_mut_ptr(), buffer.len() as types::UINT);
+        buffer.truncate(copied as usize);
+        paths.push(std::ffi::OsString::from_wide(&buffer).into());
+    }
+    return paths;
+}
+
+/// Gets the dropped paths out of an OLE data object, if it holds files
+unsafe fn data_object_paths(data: *mut ole::IDataObject) -> Vec<std::path::PathBuf> {
+    if data.is_null() {
+        return Vec::new();
+    }
+    let format = ole::FORMATETC {
+        cfFormat: shell::CF_HDROP,
+        ptd: std::ptr::null_mut(),
+        dwAspect: ole::DVASPECT_CONTENT,
+        lindex: -1,
+        tymed: ole::TYMED_HGLOBAL,
+    };
+    let mut medium = ole::STGMEDIUM {
+        tymed: 0,
+        hGlobal: std::ptr::null_mut(),
+        pUnkForRelease: std::ptr::null_mut(),
+    };
+    if ((*(*data).lpVtbl).GetData)(data, &format, &mut medium) < 0 {
+        return Vec::new();
+    }
+    let paths = drop_handle_paths(medium.hGlobal);
+    ole::ReleaseStgMedium(&mut medium);
+    return paths;
+}
+
+/// Converts a drag position from screen to client coordinates
+unsafe fn drag_position(hwnd: types::HWND, pt: ole::POINTL) -> Point {
+    let mut point = window::POINT::new(pt.x, pt.y);
+    window::ScreenToClient(hwnd, &mut point);
+    return Point::from(point);
+}
+
+/// Our `IDropTarget` COM object. `interface` has to be the first field so
+/// a pointer to it is also a pointer to the whole object.
+#[repr(C)]
+struct DropTarget {
+    interface: ole::IDropTarget,
+    ref_count: std::sync::atomic::AtomicU32,
+    hwnd: types::HWND,
+    tracker: DropTracker,
+    handler: Box<dyn FnMut(DragDrop)>,
+}
+
+static DROP_TARGET_VTBL: ole::IDropTargetVtbl = ole::IDropTargetVtbl {
+    QueryInterface: drop_target_query_interface,
+    AddRef: drop_target_add_ref,
+    Release: drop_target_release,
+    DragEnter: drop_target_drag_enter,
+    DragOver: drop_target_drag_over,
+    DragLeave: drop_target_drag_leave,
+    Drop: drop_target_drop,
+};
+
+impl DropTarget {
+    unsafe fn from_interface<'a>(this: *mut ole::IDropTarget) -> &'a mut DropTarget {
+        return &mut *(this as *mut DropTarget);
+    }
+
+    fn emit(&mut self, event: Option<DragDrop>) {
+        if let Some(event) = event {
+            (self.handler)(event);
+        }
+    }
+
+    fn effect(&self) -> types::DWORD {
+        if self.tracker.is_hovering() {
+            return ole::DROPEFFECT_COPY;
+        } else {
+            return ole::DROPEFFECT_NONE;
+        }
+    }
+}
+
+unsafe extern "system" fn drop_target_query_interface(
+    this: *mut ole::IDropTarget, riid: *const ole::GUID, object: *mut types::LPVOID,
+) -> types::HRESULT {
+    if object.is_null() || riid.is_null() {
+        return ole::E_UNEXPECTED;
+    }
+    if *riid == ole::IID_IUNKNOWN || *riid == ole::IID_IDROPTARGET {
+        drop_target_add_ref(this);
+        *object = this.cast();
+        return ole::S_OK;
+    }
+    *object = std::ptr::null_mut();
+    return ole::E_NOINTERFACE;
+}
+
+unsafe extern "system" fn drop_target_add_ref(this: *mut ole::IDropTarget) -> types::ULONG {
+    let target = DropTarget::from_interface(this);
+    return target.ref_count.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+}
+
+unsafe extern "system" fn drop_target_release(this: *mut ole::IDropTarget) -> types::ULONG {
+    let remaining = {
+        let target = DropTarget::from_interface(this);
+        target.ref_count.fetch_sub(1, std::sync::atomic::Ordering::Release) - 1
+    };
+    if remaining == 0 {
+        std::sync::atomic::fence(std::sync::atomic::Ordering::Acquire);
+        drop(Box::from_raw(this as *mut DropTarget));
+    }
+    return remaining;
+}
+
+unsafe extern "system" fn drop_target_drag_enter(
+    this: *mut ole::IDropTarget,
+    data: *mut ole::IDataObject,
+    _key_state: types::DWORD,
+    pt: ole::POINTL,
+    effect: *mut types::DWORD,
+) -> types::HRESULT {
+    let target = DropTarget::from_interface(this);
+    let position = drag_position(target.hwnd, pt);
+    let event = target.tracker.enter(data_object_paths(data), position);
+    target.emit(event);
+    if !effect.is_null() {
+        *effect = target.effect();
+    }
+    return ole::S_OK;
+}
+
+unsafe extern "system" fn drop_target_drag_over(
+    this: *mut ole::IDropTarget,
+    _key_state: types::DWORD,
+    pt: ole::POINTL,
+    effect: *mut types::DWORD,
+) -> types::HRESULT {
+    let target = DropTarget::from_interface(this);
+    let position = drag_position(target.hwnd, pt);
+    let event = target.tracker.moved(position);
+    target.emit(event);
+    if !effect.is_null() {
+        *effect = target.effect();
+    }
+    return ole::S_OK;
+}
+
+unsafe extern "system" fn drop_target_drag_leave(this: *mut ole::IDropTarget) -> types::HRESULT {
+    let target = DropTarget::from_interface(this);
+    let event = target.tracker.leave();
+    target.emit(event);
+    return ole::S_OK;
+}
+
+unsafe extern "system" fn drop_target_drop(
+    this: *mut ole::IDropTarget,
+    data: *mut ole::IDataObject,
+    _key_state: types::DWORD,
+    pt: ole::POINTL,
+    effect: *mut types::DWORD,
+) -> types::HRESULT {
+    let target = DropTarget::from_interface(this);
+    let position = drag_position(target.hwnd, pt);
+    let paths = data_object_paths(data);
+    let accepted = !paths.is_empty();
+    let event = target.tracker.drop(Some(paths), position);
+    target.emit(event);
+    if !effect.is_null() {
+        *effect = if accepted { ole::DROPEFFECT_COPY } else { ole::DROPEFFECT_NONE };
+    }
+    return ole::S_OK;
+}
+
+/// Lets files be dragged onto the window. `handler` is called with every
+/// drag and drop event, on the thread that owns the window.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle belonging to the calling thread, and
+/// [`ole_initialize`] must have been called on this thread first. Call
+/// [`revoke_drop_target`] before the window is destroyed.
+///
+/// See [`RegisterDragDrop`](https://docs.microsoft.com/en-us/windows/win32/api/ole2/nf-ole2-registerdragdrop)
+pub unsafe fn register_drop_target(
+    hwnd: types::HWND,
+    handler: impl FnMut(DragDrop) + 'static,
+) -> Result<(), Win32Error> {
+    let target = Box::into_raw(Box::new(DropTarget {
+        interface: ole::IDropTarget { lpVtbl: &DROP_TARGET_VTBL },
+        ref_count: std::sync::atomic::AtomicU32::new(1),
+        hwnd,
+        tracker: DropTracker::new(),
+        handler: Box::new(handler),
+    }));
+    let interface = target as *mut ole::IDropTarget;
+
+    // The system keeps its own reference, so ours is released either way
+    let hr = ole::RegisterDragDrop(hwnd, interface);
+    drop_target_release(interface);
+    if hr < 0 {
+        return Err(Win32Error(hr as types::DWORD));
+    } else {
+        return Ok(());
+    }
+}
+
+/// Stops accepting drops on the window, releasing the handler
+///
+/// ## Safety
+///
+/// `hwnd` must be a window registered with [`register_drop_target`] on the
+/// calling thread.
+///
+/// See [`RevokeDragDrop`](https://docs.microsoft.com/en-us/windows/win32/api/ole2/nf-ole2-revokedragdrop)
+pub unsafe fn revoke_drop_target(hwnd: types::HWND) -> Result<(), Win32Error> {
+    let hr = ole::RevokeDragDrop(hwnd);
+    if hr < 0 {
+        return Err(Win32Error(hr as types::DWORD));
+    } else {
+        return Ok(());
+    }
+}
+
+/// Simpler alternative to [`register_drop_target`] that doesn't need OLE:
+/// the window gets `WM_DROPFILES`, which only reports the drop itself
+/// (no hover events). Handle it with [`handle_drop_files_message`].
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`DragAcceptFiles`](https://docs.microsoft.com/en-us/windows/win32/api/shellapi/nf-shellapi-dragacceptfiles)
+pub unsafe fn accept_dropped_files(hwnd: types::HWND, accept: bool) {
+    shell::DragAcceptFiles(hwnd, accept as types::BOOL);
+}
+
+/// Turns a `WM_DROPFILES` message into a [`DragDrop::Dropped`] event
+///
+/// **Returns:** `Some(result)` when the message was consumed and the window
+/// procedure should return `result`, `None` for any other message
+///
+/// ## Safety
+///
+/// Must be called from the window procedure of a window that accepts dropped
+/// files, with the message and parameters it received. The drop handle is
+/// released.
+pub unsafe fn handle_drop_files_message(
+    msg: types::UINT,
+    wparam: types::WPARAM,
+    events: &mut EventQueue,
+) -> Option<types::LRESULT> {
+    if msg != window::WM_DROPFILES {
+        return None;
+    }
+    let hdrop = wparam as types::HDROP;
+    let paths = drop_handle_paths(hdrop);
+    let mut point = window::POINT::default();
+    // the point is already in client coordinates
+    shell::DragQueryPoint(hdrop, &mut point);
+    shell::DragFinish(hdrop);
+
+    if !paths.is_empty() {
+        events.push(crate::event::WindowEvent::DragDrop(DragDrop::Dropped {
+            paths,
+            position: Point::from(point),
+        }));
+    }
+    return Some(0);
+}
+
+impl From<Win32Error> for DialogError {
+    fn from(e: Win32Error) -> Self {
+        return DialogError::Platform(e.0);
+    }
+}
+
+/// Shows a message box and waits for the user to close it
+///
+/// `owner` may be null, in which case the box has no owner window.
+///
+/// ## Safety
+///
+/// `owner` must be a valid window handle or null. Messages of this thread keep
+/// being dispatched while the box is shown, so window procedures may run before
+/// this returns.
+///
+/// See [`MessageBoxW`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-messageboxw)
+pub unsafe fn message_box(owner: types::HWND, message: &MessageBox) -> Result<MessageResult, Win32Error> {
+    let buttons = match message.buttons {
+        MessageButtons::Ok => window::MB_OK,
+        MessageButtons::OkCancel => window::MB_OKCANCEL,
+        MessageButtons::AbortRetryIgnore => window::MB_ABORTRETRYIGNORE,
+        MessageButtons::YesNoCancel => window::MB_YESNOCANCEL,
+        MessageButtons::YesNo => window::MB_YESNO,
+        MessageButtons::RetryCancel => window::MB_RETRYCANCEL,
+        MessageButtons::CancelTryContinue => window::MB_CANCELTRYCONTINUE,
+    };
+    let icon = match message.icon {
+        MessageIcon::None => 0,
+        MessageIcon::Error => window::MB_ICONERROR,
+        MessageIcon::Warning => window::MB_ICONWARNING,
+        MessageIcon::Information => window::MB_ICONINFORMATION,
+        MessageIcon::Question => window::MB_ICONQUESTION,
+    };
+    let default_button = match message.default_button {
+        DefaultButton::First => window::MB_DEFBUTTON1,
+        DefaultButton::Second => window::MB_DEFBUTTON2,
+        DefaultButton::Third => window::MB_DEFBUTTON3,
+        DefaultButton::Fourth => window::MB_DEFBUTTON4,
+    };
+    let modality = match message.modality {
+        Modality::Window => window::MB_APPLMODAL,
+        Modality::Task => window::MB_TASKMODAL,
+        Modality::System => window::MB_SYSTEMMODAL,
+    };
+
+    let text_null = wide_null(&message.text);
+    let title_null = wide_null(&message.title);
+    let result = window::MessageBoxW(
+        owner,
+        text_null.as_ptr(),
+        title_null.as_ptr(),
+        buttons | icon | default_button | modality,
+    );
+
+    match result {
+        window::IDOK => return Ok(MessageResult::Ok),
+        window::IDCANCEL => return Ok(MessageResult::Cancel),
+        window::IDABORT => return Ok(MessageResult::Abort),
+        window::IDRETRY => return Ok(MessageResult::Retry),
+        window::IDIGNORE => return Ok(MessageResult::Ignore),
+        window::IDYES => return Ok(MessageResult::Yes),
+        window::IDNO => return Ok(MessageResult::No),
+        window::IDTRYAGAIN => return Ok(MessageResult::TryAgain),
+        window::IDCONTINUE => return Ok(MessageResult::Continue),
+        _ => return Err(get_last_error()),
+    }
+}
+
+/// Builds the double-null terminated `"name\0*.a;*.b\0...\0\0"` list
+/// that `OPENFILENAMEW::lpstrFilter` expects
+fn file_filter_string(dialog: &FileDialog) -> Option<Vec<u16>> {
+    if dialog.filters.is_empty() {
+        return None;
+    }
+    let mut filter = Vec::new();
+    for entry in &dialog.filters {
+        filter.extend(entry.name.encode_utf16());
+        filter.push(0);
+        filter.extend(entry.patterns.join(";").encode_utf16());
+        filter.push(0);
+    }
+    filter.push(0);
+    return Some(filter);
+}
+
+/// Splits the file buffer filled in by a multi-select open dialog. It is
+/// either one full path, or a directory followed by the selected names.
+fn split_multi_select(buffer: &[u16]) -> Vec<std::path::PathBuf> {
+    use std::os::windows::ffi::OsStringExt;
+
+    let parts: Vec<std::path::PathBuf> = buffer
+        .split(|&unit| unit == 0)
+        .take_while(|part| !part.is_empty())
+        .map(|part| std::ffi::OsString::from_wide(part).into())
+        .collect();
+    if parts.len() <= 1 {
+        return parts;
+    }
+    return parts[1..].iter().map(|name| parts[0].join(name)).collect();
+}
+
+/// Shows an open or save dialog
+///
+/// **Returns:** the filled in file buffer, or `None` if the user cancelled
+unsafe fn run_file_dialog(
+    owner: types::HWND, dialog: &FileDialog, save: bool, multiple: bool,
+) -> Result<Option<Vec<u16>>, Win32Error> {
+    let filter = file_filter_string(dialog);
+    let title = dialog.title.as_deref().map(wide_null);
+    let directory = dialog
+        .directory
+        .as_ref()
+        .map(|directory| wide_null(&directory.to_string_lossy()));
+    let extension = dialog.default_extension.as_deref().map(wide_null);
+
+    // The selection is lost once the dialog closes, so there is no second
+    // try with a bigger buffer: multi-selection gets room for thousands of
+    // names up front
+    let capacity = if multiple { MULTI_SELECT_CAPACITY } else { 4 * shell::MAX_PATH };
+    let mut file = vec![0_u16; capacity];
+    if let Some(name) = &dialog.file_name {
+        let name: Vec<u16> = name.encode_utf16().take(capacity - 1).collect();
+        file[..name.len()].copy_from_slice(&name);
+    }
+
+    let mut flags = comdlg::OFN_EXPLORER | comdlg::OFN_NOCHANGEDIR | comdlg::OFN_PATHMUSTEXIST;
+    if save {
+        flags |= comdlg::OFN_OVERWRITEPROMPT;
+    } else {
+        flags |= comdlg::OFN_FILEMUSTEXIST;
+    }
+    if multiple {
+        flags |= comdlg::OFN_ALLOWMULTISELECT;
+    }
+
+    let mut ofn = comdlg::OPENFILENAMEW {
+        hwndOwner: owner,
+        lpstrFilter: filter.as_ref().map_or(std::ptr::null(), |f| f.as_ptr()),
+        nFilterIndex: 1,
+        lpstrFile: file.as_mut_ptr(),
+        nMaxFile: file.len() as types::DWORD,
+        lpstrInitialDir: directory.as_ref().map_or(std::ptr::null(), |d| d.as_ptr()),
+        lpstrTitle: title.as_ref().map_or(std::ptr::null(), |t| t.as_ptr()),
+        lpstrDefExt: extension.as_ref().map_or(std::ptr::null(), |e| e.as_ptr()),
+        Flags: flags,
+        ..Default::default()
+    };
+
+    let ok = if save {
+        comdlg::GetSaveFileNameW(&mut ofn)
+    } else {
+        comdlg::GetOpenFileNameW(&mut ofn)
+    };
+    if ok != 0 {
+        return Ok(Some(file));
+    }
+    match comdlg::CommDlgExtendedError() {
+        // plain cancel
+        0 => return Ok(None),
+        // FNERR_BUFFERTOOSMALL among others
+        code => return Err(Win32Error(code)),
+    }
+}
+
+/// UTF-16 units of the buffer of a multi-select open dialog, 2 MiB
+const MULTI_SELECT_CAPACITY: usize = 1024 * 1024;
+
+/// Shows an open file dialog for one file
+///
+/// ## Safety
+///
+/// `owner` must be a valid window handle or null. Messages of this thread keep
+/// being dispatched while the dialog is shown, so window procedures may run
+/// before this returns.
+///
+/// See [`GetOpenFileNameW`](https://docs.microsoft.com/en-us/windows/win32/api/commdlg/nf-commdlg-getopenfilenamew)
+pub unsafe fn open_file_dialog(
+    owner: types::HWND, dialog: &FileDialog,
+) -> Result<Option<std::path::PathBuf>, Win32Error> {
+    let file = run_file_dialog(owner, dialog, false, false)?;
+    return Ok(file.and_then(|file| split_multi_select(&file).into_iter().next()));
+}
+
+/// Shows an open file dialog that allows selecting several files
+///
+/// ## Safety
+///
+/// `owner` must be a valid window handle or null. Window procedures of this
+/// thread may run while the dialog is shown.
+///
+/// See [`GetOpenFileNameW`](https://docs.microsoft.com/en-us/windows/win32/api/commdlg/nf-commdlg-getopenfilenamew)
+pub unsafe fn open_files_dialog(
+    owner: types::HWND, dialog: &FileDialog,
+) -> Result<Option<Vec<std::path::PathBuf>>, Win32Error> {
+    let file = run_file_dialog(owner, dialog, false, true)?;
+    return Ok(file.map(|file| split_multi_select(&file)));
+}
+
+/// Shows a save file dialog, which asks before overwriting a file
+///
+/// ## Safety
+///
+/// `owner` must be a valid window handle or null. Window procedures of this
+/// thread may run while the dialog is shown.
+///
+/// See [`GetSaveFileNameW`](https://docs.microsoft.com/en-us/windows/win32/api/commdlg/nf-commdlg-getsavefilenamew)
+pub unsafe fn save_file_dialog(
+    owner: types::HWND, dialog: &FileDialog,
+) -> Result<Option<std::path::PathBuf>, Win32Error> {
+    let file = run_file_dialog(owner, dialog, true, false)?;
+    return Ok(file.and_then(|file| split_multi_select(&file).into_iter().next()));
+}
+
+/// Shows a folder picker, starting in `dialog.directory` when it is set.
+/// OLE must be initialized on this thread (see [`ole_initialize`]) for the
+/// resizable dialog with an edit box.
+///
+/// ## Safety
+///
+/// `owner` must be a valid window handle or null. Window procedures of this
+/// thread may run while the dialog is shown.
+///
+/// See [`SHBrowseForFolderW`](https://docs.microsoft.com/en-us/windows/win32/api/shlobj_core/nf-shlobj_core-shbrowseforfolderw)
+pub unsafe fn pick_folder_dialog(
+    owner: types::HWND, dialog: &FileDialog,
+) -> Result<Option<std::path::PathBuf>, Win32Error> {
+    use std::os::windows::ffi::OsStringExt;
+
+    let title = dialog.title.as_deref().map(wide_null);
+    let directory = dialog
+        .directory
+        .as_ref()
+        .map(|directory| wide_null(&directory.to_string_lossy()));
+    let mut display_name = vec![0_u16; shell::MAX_PATH];
+    let mut info = shell::BROWSEINFOW {
+        hwndOwner: owner,
+        pidlRoot: std::ptr::null_mut(),
+        pszDisplayName: display_name.as_mut_ptr(),
+        lpszTitle: title.as_ref().map_or(std::ptr::null(), |t| t.as_ptr()),
+        ulFlags: shell::BIF_RETURNONLYFSDIRS | shell::BIF_NEWDIALOGSTYLE | shell::BIF_EDITBOX,
+        lpfn: Some(folder_dialog_callback),
+        lParam: directory.as_ref().map_or(0, |d| d.as_ptr() as types::LPARAM),
+        iImage: 0,
+    };
+
+    let pidl = shell::SHBrowseForFolderW(&mut info);
+    if pidl.is_null() {
+        return Ok(None);
+    }
+    let mut path = vec![0_u16; 32 * 1024];
+    let ok = shell::SHGetPathFromIDListW(pidl, path.as_mut_ptr());
+    ole::CoTaskMemFree(pidl);
+    if ok == 0 {
+        // The user picked something that isn't a file system folder
+        return Ok(None);
+    }
+    let len = path.iter().position(|&unit| unit == 0).unwrap_or(path.len());
+    return Ok(Some(std::ffi::OsString::from_wide(&path[..len]).into()));
+}
+
+/// Selects the initial folder of [`pick_folder_dialog`], whose path is in
+/// `data`, once the dialog is ready
+unsafe extern "system" fn folder_dialog_callback(
+    hwnd: types::HWND, msg: types::UINT, _lparam: types::LPARAM, data: types::LPARAM,
+) -> types::c_int {
+    if msg == shell::BFFM_INITIALIZED && data != 0 {
+        window::SendMessageW(hwnd, shell::BFFM_SETSELECTIONW, 1, data);
+    }
+    return 0;
+}
+
+/// The native Win32 dialogs, owned by a window
+pub struct Win32Dialogs {
+    owner: types::HWND,
+}
+
+impl Win32Dialogs {
+    /// `owner` is disabled while a dialog is open. It can be null.
+    pub fn new(owner: types::HWND) -> Self {
+        return Win32Dialogs { owner };
+    }
+}
+
+impl Dialogs for Win32Dialogs {
+    fn message_box(&mut self, message: &MessageBox) -> Result<MessageResult, DialogError> {
+        return Ok(unsafe { message_box(self.owner, message)? });
+    }
+
+    fn open_file(&mut self, dialog: &FileDialog) -> Result<Option<std::path::PathBuf>, DialogError> {
+        return Ok(unsafe { open_file_dialog(self.owner, dialog)? });
+    }
+
+    fn open_files(&mut self, dialog: &FileDialog) -> Result<Option<Vec<std::path::PathBuf>>, DialogError> {
+        return Ok(unsafe { open_files_dialog(self.owner, dialog)? });
+    }
+
+    fn save_file(&mut self, dialog: &FileDialog) -> Result<Option<std::path::PathBuf>, DialogError> {
+        return Ok(unsafe { save_file_dialog(self.owner, dialog)? });
+    }
+
+    fn pick_folder(&mut self, dialog: &FileDialog) -> Result<Option<std::path::PathBuf>, DialogError> {
+        return Ok(unsafe { pick_folder_dialog(self.owner, dialog)? });
+    }
+}
+
+/// Fills in the type and state of a menu item
+fn menu_item_info(item: &crate::menu::MenuItem) -> window::MENUITEMINFOW {
+    let mut info = window::MENUITEMINFOW {
+        fMask: window::MIIM_FTYPE | window::MIIM_STATE,
+        fType: match item.kind {
+            MenuItemKind::Separator => window::MFT_SEPARATOR,
+            MenuItemKind::Radio { .. } => window::MFT_STRING | window::MFT_RADIOCHECK,
+            _ => window::MFT_STRING,
+        },
+        fState: if item.enabled { window::MFS_ENABLED } else { window::MFS_DISABLED },
+        ..Default::default()
+    };
+    if item.is_checked() {
+        info.fState |= window::MFS_CHECKED;
+    }
+    return info;
+}
+
+/// Appends the items of `menu` to a native menu, creating submenus as needed
+unsafe fn append_menu_items(hmenu: types::HMENU, menu: &Menu) -> Result<(), Win32Error> {
+    for (position, item) in menu.items().iter().enumerate() {
+        let mut info = menu_item_info(item);
+
+        // The label and shortcut hint are separated by a tab
+        let mut label = item.label.clone();
+        if let Some(accelerator) = &item.accelerator {
+            label.push('\t');
+            label.push_str(accelerator);
+        }
+        let mut label_null = wide_null(&label);
+
+        if item.kind != MenuItemKind::Separator {
+            info.fMask |= window::MIIM_STRING;
+            info.dwTypeData = label_null.as_mut_ptr();
+        }
+        match &item.kind {
+            MenuItemKind::Submenu(submenu) => {
+                let hsubmenu = window::CreatePopupMenu();
+                if hsubmenu.is_null() {
+                    return Err(get_last_error());
+                }
+                if let Err(e) = append_menu_items(hsubmenu, submenu) {
+                    window::DestroyMenu(hsubmenu);
+                    return Err(e);
+                }
+                info.fMask |= window::MIIM_SUBMENU;
+                info.hSubMenu = hsubmenu;
+            }
+            MenuItemKind::Separator => {}
+            _ => {
+                info.fMask |= window::MIIM_ID;
+                info.wID = item.id.0 as types::UINT;
+            }
+        }
+
+        if window::InsertMenuItemW(hmenu, position as types::UINT, 1, &info) == 0 {
+            let e = get_last_error();
+            if !info.hSubMenu.is_null() {
+                window::DestroyMenu(info.hSubMenu);
+            }
+            return Err(e);
+        }
+    }
+    return Ok(());
+}
+
+/// Creates a native menu from a menu description. Use `bar` for a window's
+/// menu bar and not for context menus.
+///
+/// The menu must be freed with [`destroy_menu`] unless it is attached to a
+/// window, which frees it when the window is destroyed.
+///
+/// See [`InsertMenuItemW`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-insertmenuitemw)
+pub fn create_menu(menu: &Menu, bar: bool) -> Result<types::HMENU, Win32Error> {
+    let hmenu = unsafe {
+        if bar { window::CreateMenu() } else { window::CreatePopupMenu() }
+    };
+    if hmenu.is_null() {
+        return Err(get_last_error());
+    }
+    if let Err(e) = unsafe { append_menu_items(hmenu, menu) } {
+        unsafe { window::DestroyMenu(hmenu) };
+        return Err(e);
+    }
+    return Ok(hmenu);
+}
+
+/// Destroys a menu and all of its submenus
+///
+/// ## Safety
+///
+/// `hmenu` must be a menu this program created that no window uses any more. It
+/// must not be used after this call.
+///
+/// See [`DestroyMenu`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-destroymenu)
+pub unsafe fn destroy_menu(hmenu: types::HMENU) -> Result<(), Win32Error> {
+    if window::DestroyMenu(hmenu) == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(());
+    }
+}
+
+/// Attaches a menu bar to the window, replacing the previous one
+///
+/// **Returns:** The previous menu bar, which the caller must destroy. May be null.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid top level window handle and `hmenu` a valid menu or
+/// null. The window destroys the menu when it is destroyed itself, so the menu
+/// must not be destroyed by the caller while it is attached.
+///
+/// See [`SetMenu`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setmenu)
+pub unsafe fn set_window_menu(hwnd: types::HWND, hmenu: types::HMENU) -> Result<types::HMENU, Win32Error> {
+    let previous = window::GetMenu(hwnd);
+    if window::SetMenu(hwnd, hmenu) == 0 {
+        return Err(get_last_error());
+    }
+    return Ok(previous);
+}
+
+/// Updates the enabled and checked state of a native menu item from the
+/// description, e.g. after [`Menu::activate`]. Submenus are searched too.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle and `hmenu` a menu built from `menu`,
+/// such as the one returned by [`create_menu`].
+///
+/// See [`SetMenuItemInfoW`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setmenuiteminfow)
+pub unsafe fn sync_menu_item(
+    hwnd: types::HWND, hmenu: types::HMENU, menu: &Menu, id: MenuId,
+) -> Result<(), Win32Error> {
+    let item = match menu.find(id) {
+        Some(item) => item,
+        None => return Err(Win32Error(core::ERROR_INVALID_PARAMETER)),
+    };
+    let mut info = menu_item_info(item);
+    // only the state, the type can't change
+    info.fMask = window::MIIM_STATE;
+    if window::SetMenuItemInfoW(hmenu, id.0 as types::UINT, 0, &info) == 0 {
+        return Err(get_last_error());
+    }
+    // The menu bar doesn't redraw by itself
+    if !hwnd.is_null() && window::GetMenu(hwnd) == hmenu {
+        window::DrawMenuBar(hwnd);
+    }
+    return Ok(());
+}
+
+/// Shows a context menu at a position in client coordinates and waits
+/// until the user chooses an item or dismisses it
+///
+/// **Returns:** The chosen item, or `None` if the menu was dismissed. The
+/// choice is returned directly and is *not* also sent as a `WM_COMMAND`.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle belonging to the calling thread. Its
+/// window procedure keeps running while the menu is open.
+///
+/// See [`TrackPopupMenu`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-trackpopupmenu)
+pub unsafe fn show_context_menu(
+    hwnd: types::HWND, menu: &Menu, position: Point,
+) -> Result<Option<MenuId>, Win32Error> {
+    let mut point = window::POINT::from(position);
+    window::ClientToScreen(hwnd, &mut point);
+    return track_popup_menu(hwnd, menu, point);
+}
+
+/// [`show_context_menu`] at a position in screen coordinates
+unsafe fn track_popup_menu(
+    hwnd: types::HWND, menu: &Menu, point: window::POINT,
+) -> Result<Option<MenuId>, Win32Error> {
+    let hmenu = create_menu(menu, false)?;
+
+    // Without this the menu doesn't close when clicking elsewhere
+    window::SetForegroundWindow(hwnd);
+    set_last_error(Win32Error(0));
+    let chosen = window::TrackPopupMenu(
+        hmenu,
+        window::TPM_LEFTALIGN | window::TPM_TOPALIGN | window::TPM_RIGHTBUTTON | window::TPM_RETURNCMD,
+        point.x(),
+        point.y(),
+        0,
+        hwnd,
+        std::ptr::null(),
+    );
+    let last_error = get_last_error();
+    // And this makes it work the second time, see the remarks on MSDN
+    window::PostMessageW(hwnd, window::WM_NULL, 0, 0);
+    window::DestroyMenu(hmenu);
+
+    if chosen != 0 {
+        return Ok(Some(MenuId(chosen as u16)));
+    } else if last_error.0 != 0 {
+        return Err(last_error);
+    } else {
+        return Ok(None);
+    }
+}
+
+/// Translates menu messages of a window procedure into events:
+/// `WM_COMMAND` from a menu into [`WindowEvent::Menu`] and
+/// `WM_CONTEXTMENU` into [`WindowEvent::ContextMenu`]
+///
+/// **Returns:** `Some(result)` when the message was consumed and the window
+/// procedure should return `result`, `None` when it should fall through
+///
+/// ## Safety
+///
+/// Must be called from the window procedure of `hwnd`, with the message and
+/// parameters it received.
+pub unsafe fn handle_menu_message(
+    hwnd: types::HWND,
+    msg: types::UINT,
+    wparam: types::WPARAM,
+    lparam: types::LPARAM,
+    events: &mut EventQueue,
+) -> Option<types::LRESULT> {
+    match msg {
+        // The high word is 0 for menus, 1 for accelerators; controls
+        // send their own handle in lparam
+        window::WM_COMMAND if core::HIWORD(wparam) == 0 && lparam == 0 => {
+            events.push(WindowEvent::Menu(MenuId(core::LOWORD(wparam))));
+            return Some(0);
+        }
+        window::WM_CONTEXTMENU => {
+            // Only for our own window, not for child windows that passed it up
+            if wparam as types::HWND != hwnd {
+                return None;
+            }
+            // -1, -1 means the menu key or Shift+F10
+            let position = if lparam as u32 == 0xFFFF_FFFF {
+                None
+            } else {
+                let mut point = window::POINT::new(core::GET_X_LPARAM(lparam), core::GET_Y_LPARAM(lparam));
+                window::ScreenToClient(hwnd, &mut point);
+                Some(Point::from(point))
+            };
+            events.push(WindowEvent::ContextMenu { position });
+            return Some(0);
+        }
+        _ => return None,
+    }
+}
+
+
+/// Virtual key codes of an accelerator key. Characters other than letters
+/// and digits are looked up in the current keyboard layout; `+` and `-`
+/// also get their numeric keypad keys.
+///
+/// See [`VkKeyScanW`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-vkkeyscanw)
+pub fn virtual_keys(key: Key) -> Vec<types::UINT> {
+    let named = match key {
+        Key::Character(c @ ('A'..='Z' | '0'..='9')) => Some(c as types::UINT),
+        Key::Character(_) => None,
+        Key::Function(number @ 1..=24) => Some(window::VK_F1 + number as types::UINT - 1),
+        Key::Function(_) => return Vec::new(),
+        Key::Enter => Some(window::VK_RETURN),
+        Key::Escape => Some(window::VK_ESCAPE),
+        Key::Tab => Some(window::VK_TAB),
+        Key::Space => Some(window::VK_SPACE),
+        Key::Backspace => Some(window::VK_BACK),
+        Key::Delete => Some(window::VK_DELETE),
+        Key::Insert => Some(window::VK_INSERT),
+        Key::Home => Some(window::VK_HOME),
+        Key::End => Some(window::VK_END),
+        Key::PageUp => Some(window::VK_PRIOR),
+        Key::PageDown => Some(window::VK_NEXT),
+        Key::Left => Some(window::VK_LEFT),
+        Key::Right => Some(window::VK_RIGHT),
+        Key::Up => Some(window::VK_UP),
+        Key::Down => Some(window::VK_DOWN),
+    };
+    if let Some(vk) = named {
+        return vec![vk];
+    }
+
+    let mut keys = Vec::new();
+    if let Key::Character(c) = key {
+        let mut unit = [0u16; 2];
+        if c.encode_utf16(&mut unit).len() == 1 {
+            // The low byte is the key, the high byte the shift state needed
+            // to type the character, which we leave to the accelerator
+            let scan = unsafe { window::VkKeyScanW(unit[0]) };
+            if scan != -1 {
+                keys.push((scan & 0xFF) as types::UINT);
+            }
+        }
+        match c {
+            '+' => keys.push(window::VK_ADD),
+            '-' => keys.push(window::VK_SUBTRACT),
+            _ => {}
+        }
+    }
+    return keys;
+}
+
+/// Creates a native accelerator table to pass to [`translate_accelerator`].
+///
+/// **Returns:** The table, null when no entry could be expressed, and the
+/// entries left out of it: native tables have no logo key, and keys
+/// missing from the keyboard layout can't be typed. Logo key shortcuts
+/// work with [`register_global_shortcut`] instead.
+///
+/// See [`CreateAcceleratorTableW`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-createacceleratortablew)
+pub fn create_accelerator_table(
+    table: &AcceleratorTable,
+) -> Result<(types::HACCEL, Vec<(Accelerator, MenuId)>), Win32Error> {
+    let (mut entries, mut skipped) = (Vec::new(), Vec::new());
+    for &(accelerator, command) in table.entries() {
+        let keys = virtual_keys(accelerator.key);
+        if accelerator.modifiers.logo || keys.is_empty() {
+            skipped.push((accelerator, command));
+            continue;
+        }
+
+        let mut flags = window::FVIRTKEY;
+        if accelerator.modifiers.ctrl {
+            flags |= window::FCONTROL;
+        }
+        if accelerator.modifiers.alt {
+            flags |= window::FALT;
+        }
+        if accelerator.modifiers.shift {
+            flags |= window::FSHIFT;
+        }
+        for key in keys {
+            entries.push(window::ACCEL { fVirt: flags, key: key as types::WORD, cmd: command.0 });
+        }
+    }
+
+    if entries.is_empty() {
+        return Ok((std::ptr::null_mut(), skipped));
+    }
+    let haccel = unsafe { window::CreateAcceleratorTableW(entries.as_ptr(), entries.len() as types::c_int) };
+    if haccel.is_null() {
+        return Err(get_last_error());
+    } else {
+        return Ok((haccel, skipped));
+    }
+}
+
+/// ## Safety
+///
+/// `haccel` must be a table created with [`create_accelerator_table`]. It must
+/// not be used after this call.
+///
+/// See [`DestroyAcceleratorTable`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-destroyacceleratortable)
+pub unsafe fn destroy_accelerator_table(haccel: types::HACCEL) {
+    window::DestroyAcceleratorTable(haccel);
+}
+
+/// Turns a key message matching the table into a `WM_COMMAND` sent to
+/// `hwnd`. Call this in the message loop before [`translate_message`], and
+/// skip translating and dispatching the message when it returns `true`.
+///
+/// `hwnd` should be the top level window so shortcuts work whichever of
+/// its child windows has the focus.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle and `haccel` a valid accelerator table.
+///
+/// See [`TranslateAcceleratorW`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-translateacceleratorw)
+pub unsafe fn translate_accelerator(hwnd: types::HWND, haccel: types::HACCEL, msg: &window::MSG) -> bool {
+    if haccel.is_null() {
+        return false;
+    }
+    return window::TranslateAcceleratorW(hwnd, haccel, msg) != 0;
+}
+
+/// Registers a shortcut that works system wide, even when another program
+/// has the focus. It is reported as [`WindowEvent::GlobalShortcut`] with
+/// `id`, which must be below `0xC000`. Holding the keys down doesn't repeat.
+///
+/// Fails with `ERROR_HOTKEY_ALREADY_REGISTERED` when another program has it.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle belonging to the calling thread.
+///
+/// See [`RegisterHotKey`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-registerhotkey)
+pub unsafe fn register_global_shortcut(
+    hwnd: types::HWND, id: MenuId, accelerator: &Accelerator,
+) -> Result<(), Win32Error> {
+    let vk = match virtual_keys(accelerator.key).first() {
+        Some(vk) if id.0 < 0xC000 => *vk,
+        _ => return Err(Win32Error(core::ERROR_INVALID_PARAMETER)),
+    };
+
+    let mut modifiers = window::MOD_NOREPEAT;
+    if accelerator.modifiers.ctrl {
+        modifiers |= window::MOD_CONTROL;
+    }
+    if accelerator.modifiers.alt {
+        modifiers |= window::MOD_ALT;
+    }
+    if accelerator.modifiers.shift {
+        modifiers |= window::MOD_SHIFT;
+    }
+    if accelerator.modifiers.logo {
+        modifiers |= window::MOD_WIN;
+    }
+    if window::RegisterHotKey(hwnd, id.0 as types::c_int, modifiers, vk) == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(());
+    }
+}
+
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle belonging to the calling thread.
+///
+/// See [`UnregisterHotKey`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-unregisterhotkey)
+pub unsafe fn unregister_global_shortcut(hwnd: types::HWND, id: MenuId) -> Result<(), Win32Error> {
+    if window::UnregisterHotKey(hwnd, id.0 as types::c_int) == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(());
+    }
+}
+
+/// Translates shortcut messages of a window procedure into events:
+/// `WM_COMMAND` from an accelerator into [`WindowEvent::Accelerator`] and
+/// `WM_HOTKEY` into [`WindowEvent::GlobalShortcut`]
+///
+/// **Returns:** `Some(result)` when the message was consumed and the window
+/// procedure should return `result`, `None` when it should fall through
+pub fn handle_shortcut_message(
+    msg: types::UINT,
+    wparam: types::WPARAM,
+    lparam: types::LPARAM,
+    events: &mut EventQueue,
+) -> Option<types::LRESULT> {
+    match msg {
+        window::WM_COMMAND if core::HIWORD(wparam) == 1 && lparam == 0 => {
+            events.push(WindowEvent::Accelerator(MenuId(core::LOWORD(wparam))));
+            return Some(0);
+        }
+        window::WM_HOTKEY => {
+            // Negative ids are the system's own hot keys
+            if let Ok(id) = u16::try_from(wparam) {
+                events.push(WindowEvent::GlobalShortcut(MenuId(id)));
+            }
+            return Some(0);
+        }
+        _ => return None,
+    }
+}
+
+/// Callback message of the tray icons created by [`Win32TrayIcon`]
+pub const WM_TRAY_ICON: types::UINT = window::WM_APP + 1;
+
+/// Copies as much of `text` as fits into a fixed size, NUL terminated
+/// buffer, without splitting surrogate pairs
+fn copy_wide_truncated(buffer: &mut [u16], text: &str) {
+    let mut len = 0;
+    for c in text.chars() {
+        let units = c.len_utf16();
+        if len + units >= buffer.len() {
+            break;
+        }
+        c.encode_utf16(&mut buffer[len..len + units]);
+        len += units;
+    }
+    buffer[len..].fill(0);
+}
+
+/// An icon in the notification area, owned by a window that receives its
+/// messages. The icon is removed when this is dropped.
+///
+/// Pass every message of the window procedure to [`Win32TrayIcon::handle_message`].
+///
+/// See [`Shell_NotifyIconW`](https://docs.microsoft.com/en-us/windows/win32/api/shellapi/nf-shellapi-shell_notifyiconw)
+pub struct Win32TrayIcon {
+    data: shell::NOTIFYICONDATAW,
+    menu: Option<Menu>,
+    taskbar_created: types::UINT,
+}
+
+impl Win32TrayIcon {
+    /// Adds an icon to the notification area. Its events go to `hwnd`.
+    ///
+    /// ## Safety
+    ///
+    /// `hwnd` must be a valid window handle belonging to the calling thread, and it
+    /// must outlive the tray icon.
+    pub unsafe fn add(hwnd: types::HWND, id: TrayId, tray: &TrayIcon) -> Result<Self, Win32Error> {
+        let mut data = shell::NOTIFYICONDATAW {
+            hWnd: hwnd,
+            uID: id.0 as types::UINT,
+            uFlags: shell::NIF_MESSAGE | shell::NIF_ICON | shell::NIF_TIP | shell::NIF_SHOWTIP,
+            uCallbackMessage: WM_TRAY_ICON,
+            hIcon: create_icon(&tray.icon, window::IconSize::Small)?,
+            ..Default::default()
+        };
+        copy_wide_truncated(&mut data.szTip, &tray.tooltip);
+
+        let tray_icon = Win32TrayIcon {
+            data,
+            menu: tray.menu.clone(),
+            // Sent to every top level window when Explorer restarts
+            taskbar_created: window::RegisterWindowMessageW(wide_null("TaskbarCreated").as_ptr()),
+        };
+        // Dropping cleans up the icon handle if adding fails
+        tray_icon.show()?;
+        return Ok(tray_icon);
+    }
+
+    unsafe fn show(&self) -> Result<(), Win32Error> {
+        if shell::Shell_NotifyIconW(shell::NIM_ADD, &self.data) == 0 {
+            return Err(get_last_error());
+        }
+        // Version 4 reports the click position and sends NIN_SELECT
+        let mut data = shell::NOTIFYICONDATAW::default();
+        data.hWnd = self.data.hWnd;
+        data.uID = self.data.uID;
+        data.uVersion = shell::NOTIFYICON_VERSION_4;
+        shell::Shell_NotifyIconW(shell::NIM_SETVERSION, &data);
+        return Ok(());
+    }
+
+    fn modify(&mut self, flags: types::UINT) -> Result<(), Win32Error> {
+        let saved = self.data.uFlags;
+        self.data.uFlags = flags;
+        let ok = unsafe { shell::Shell_NotifyIconW(shell::NIM_MODIFY, &self.data) } != 0;
+        self.data.uFlags = saved;
+        if !ok {
+            return Err(get_last_error());
+        } else {
+            return Ok(());
+        }
+    }
+
+    pub fn id(&self) -> TrayId {
+        return TrayId(self.data.uID as u16);
+    }
+
+    pub fn set_icon(&mut self, icon: &crate::icon::Icon) -> Result<(), Win32Error> {
+        let previous = self.data.hIcon;
+        self.data.hIcon = create_icon(icon, window::IconSize::Small)?;
+        let result = self.modify(shell::NIF_ICON);
+        unsafe {
+            let _ = destroy_icon(previous);
+        }
+        return result;
+    }
+
+    pub fn set_tooltip(&mut self, tooltip: &str) -> Result<(), Win32Error> {
+        copy_wide_truncated(&mut self.data.szTip, tooltip);
+        return self.modify(shell::NIF_TIP | shell::NIF_SHOWTIP);
+    }
+
+    /// Replaces the menu shown on right click
+    pub fn set_menu(&mut self, menu: Option<Menu>) {
+        self.menu = menu;
+    }
+
+    /// The attached menu, e.g. to change check marks. The native menu is
+    /// built each time it is shown, so no syncing is needed.
+    pub fn menu_mut(&mut self) -> Option<&mut Menu> {
+        return self.menu.as_mut();
+    }
+
+    /// Shows a balloon notification, replacing the previous one
+    pub fn notify(&mut self, notification: &Notification) -> Result<(), Win32Error> {
+        copy_wide_truncated(&mut self.data.szInfoTitle, &notification.title);
+        copy_wide_truncated(&mut self.data.szInfo, &notification.text);
+        self.data.dwInfoFlags = match notification.icon {
+            NotificationIcon::None => shell::NIIF_NONE,
+            NotificationIcon::Information => shell::NIIF_INFO,
+            NotificationIcon::Warning => shell::NIIF_WARNING,
+            NotificationIcon::Error => shell::NIIF_ERROR,
+            NotificationIcon::App => shell::NIIF_USER,
+        };
+        if notification.silent {
+            self.data.dwInfoFlags |= shell::NIIF_NOSOUND;
+        }
+        let result = self.modify(shell::NIF_INFO);
+        // So later modifications don't show the balloon again
+        self.data.szInfo[0] = 0;
+        return result;
+    }
+
+    /// Translates the icon's callback messages into [`WindowEvent::Tray`]
+    /// events and shows the attached menu on right click. Choosing a menu
+    /// item queues a [`WindowEvent::Menu`] after the `RightClick`.
+    ///
+    /// **Returns:** `Some(result)` when the message was consumed and the window
+    /// procedure should return `result`, `None` when it should fall through
+    ///
+    /// ## Safety
+    ///
+    /// Must be called from the window procedure of the window given to
+    /// [`Win32TrayIcon::add`], with the message and parameters it received.
+    pub unsafe fn handle_message(
+        &mut self,
+        msg: types::UINT,
+        wparam: types::WPARAM,
+        lparam: types::LPARAM,
+        events: &mut EventQueue,
+    ) -> Option<types::LRESULT> {
+        if self.taskbar_created != 0 && msg == self.taskbar_created {
+            // Explorer restarted and forgot every icon. Other icons of
+            // the window need the message too, so don't consume it.
+            let _ = self.show();
+            return None;
+        }
+        if msg != WM_TRAY_ICON || core::HIWORD(lparam as usize) as types::UINT != self.data.uID {
+            return None;
+        }
+
+        let id = self.id();
+        // With version 4 the anchor point of the event is in wparam
+        let position = Point::new(core::GET_X_LPARAM(wparam as types::LPARAM), core::GET_Y_LPARAM(wparam as types::LPARAM));
+        let event = match core::LOWORD(lparam as usize) as types::UINT {
+            shell::NIN_SELECT | shell::NIN_KEYSELECT => TrayEvent::LeftClick { position },
+            window::WM_LBUTTONDBLCLK => TrayEvent::DoubleClick { position },
+            window::WM_CONTEXTMENU => TrayEvent::RightClick { position },
+            shell::NIN_BALLOONUSERCLICK => TrayEvent::NotificationClicked,
+            shell::NIN_BALLOONTIMEOUT | shell::NIN_BALLOONHIDE => TrayEvent::NotificationDismissed,
+            _ => return Some(0),
+        };
+        let right_click = matches!(event, TrayEvent::RightClick { .. });
+        events.push(WindowEvent::Tray { id, event });
+
+        if let (true, Some(menu)) = (right_click, &self.menu) {
+            let point = window::POINT::from(position);
+            if let Ok(Some(chosen)) = track_popup_menu(self.data.hWnd, menu, point) {
+                events.push(WindowEvent::Menu(chosen));
+            }
+        }
+        return Some(0);
+    }
+}
+
+impl Drop for Win32TrayIcon {
+    fn drop(&mut self) {
+        unsafe {
+            shell::Shell_NotifyIconW(shell::NIM_DELETE, &self.data);
+            let _ = destroy_icon(self.data.hIcon);
+        }
+    }
+}
+
+/// Creates a child window of a class registered by the application, e.g.
+/// a viewport to render into inside a tool window. Its position is in the
+/// parent's client coordinates, and it moves and is destroyed with it.
+///
+/// ## Safety
+///
+/// `parent` must be a valid window handle belonging to the calling thread, and
+/// `class_name` a class registered with [`register_class`]. The window
+/// procedure of the class runs before this returns.
+pub unsafe fn create_child_window(
+    parent: types::HWND,
+    id: ControlId,
+    class_name: &str,
+    rect: Rect,
+    create_param: types::LPVOID,
+) -> Result<types::HWND, Win32Error> {
+    let class_name_null = wide_null(class_name);
+    return create_window_ex_w(
+        0,
+        class_name_null.as_ptr(),
+        std::ptr::null(),
+        window::WS_CHILD | window::WS_VISIBLE | window::WS_CLIPCHILDREN | window::WS_CLIPSIBLINGS,
+        rect.left,
+        rect.top,
+        rect.width(),
+        rect.height(),
+        parent,
+        // Child windows get their id instead of a menu
+        id.0 as usize as types::HMENU,
+        get_process_handle(),
+        create_param,
+    );
+}
+
+/// Creates a standard control as a child of `parent`. Its notifications
+/// arrive at the parent as `WM_COMMAND`, see [`handle_control_message`].
+///
+/// `text` is the label of buttons and check boxes, the initial text of
+/// edit boxes and ignored by list boxes.
+///
+/// ## Safety
+///
+/// `parent` must be a valid window handle belonging to the calling thread.
+pub unsafe fn create_control(
+    parent: types::HWND,
+    id: ControlId,
+    kind: ControlKind,
+    text: &str,
+    rect: Rect,
+) -> Result<types::HWND, Win32Error> {
+    let (class_name, ex_style, style) = match kind {
+        ControlKind::Button => ("BUTTON", 0, window::BS_PUSHBUTTON | window::BS_NOTIFY),
+        ControlKind::CheckBox => ("BUTTON", 0, window::BS_AUTOCHECKBOX | window::BS_NOTIFY),
+        ControlKind::Edit => ("EDIT", window::WS_EX_CLIENTEDGE, window::ES_LEFT | window::ES_AUTOHSCROLL),
+        ControlKind::MultilineEdit => (
+            "EDIT",
+            window::WS_EX_CLIENTEDGE,
+            window::ES_LEFT | window::ES_MULTILINE | window::ES_AUTOVSCROLL | window::ES_WANTRETURN | window::WS_VSCROLL,
+        ),
+        ControlKind::ListBox => (
+            "LISTBOX",
+            window::WS_EX_CLIENTEDGE,
+            window::LBS_NOTIFY | window::LBS_NOINTEGRALHEIGHT | window::WS_VSCROLL,
+        ),
+    };
+    let class_name_null = wide_null(class_name);
+    let text_null = wide_null(text);
+    let hwnd = create_window_ex_w(
+        ex_style,
+        class_name_null.as_ptr(),
+        text_null.as_ptr(),
+        window::WS_CHILD | window::WS_VISIBLE | window::WS_TABSTOP | style,
+        rect.left,
+        rect.top,
+        rect.width(),
+        rect.height(),
+        parent,
+        id.0 as usize as types::HMENU,
+        get_process_handle(),
+        std::ptr::null_mut(),
+    )?;
+
+    // Controls start with the bitmap "System" font otherwise
+    let font = gdi::GetStockObject(gdi::DEFAULT_GUI_FONT);
+    window::SendMessageW(hwnd, window::WM_SETFONT, font as types::WPARAM, 0);
+    return Ok(hwnd);
+}
+
+/// The child window or control of `parent` with an id
+///
+/// ## Safety
+///
+/// `parent` must be a valid window handle.
+///
+/// See [`GetDlgItem`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getdlgitem)
+pub unsafe fn get_control(parent: types::HWND, id: ControlId) -> Option<types::HWND> {
+    let hwnd = window::GetDlgItem(parent, id.0 as types::c_int);
+    if hwnd.is_null() {
+        return None;
+    } else {
+        return Some(hwnd);
+    }
+}
+
+/// Which standard control a window is, from its class and style
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+pub unsafe fn control_kind(hwnd: types::HWND) -> Option<ControlKind> {
+    let mut class_name = [0u16; 16];
+    let len = window::GetClassNameW(hwnd, class_name.as_mut_ptr(), class_name.len() as types::c_int);
+    let class_name = String::from_utf16_lossy(&class_name[..len.max(0) as usize]);
+    let style = window::GetWindowLongPtrW(hwnd, window::GWL_STYLE) as u32;
+
+    if class_name.eq_ignore_ascii_case("BUTTON") {
+        match style & window::BS_TYPEMASK {
+            window::BS_CHECKBOX | window::BS_AUTOCHECKBOX => return Some(ControlKind::CheckBox),
+            _ => return Some(ControlKind::Button),
+        }
+    } else if class_name.eq_ignore_ascii_case("EDIT") {
+        if style & window::ES_MULTILINE != 0 {
+            return Some(ControlKind::MultilineEdit);
+        }
+        return Some(ControlKind::Edit);
+    } else if class_name.eq_ignore_ascii_case("LISTBOX") {
+        return Some(ControlKind::ListBox);
+    } else {
+        return None;
+    }
+}
+
+/// Moves and resizes a child window, in its parent's client coordinates
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`MoveWindow`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-movewindow)
+pub unsafe fn move_window(hwnd: types::HWND, rect: Rect) -> Result<(), Win32Error> {
+    if window::MoveWindow(hwnd, rect.left, rect.top, rect.width(), rect.height(), 1) == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(());
+    }
+}
+
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`EnableWindow`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-enablewindow)
+pub unsafe fn set_window_enabled(hwnd: types::HWND, enabled: bool) {
+    window::EnableWindow(hwnd, enabled as types::BOOL);
+}
+
+/// The title of a window or the text of a control. Multiline edit boxes
+/// separate lines with `"\r\n"`.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`GetWindowTextW`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getwindowtextw)
+pub unsafe fn get_window_text(hwnd: types::HWND) -> String {
+    let len = window::GetWindowTextLengthW(hwnd);
+    if len <= 0 {
+        return String::new();
+    }
+    let mut buffer = vec![0u16; len as usize + 1];
+    let copied = window::GetWindowTextW(hwnd, buffer.as_mut_ptr(), buffer.len() as types::c_int);
+    return String::from_utf16_lossy(&buffer[..copied.max(0) as usize]);
+}
+
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`SetWindowTextW`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setwindowtextw)
+pub unsafe fn set_window_text(hwnd: types::HWND, text: &str) -> Result<(), Win32Error> {
+    let text_null = wide_null(text);
+    if window::SetWindowTextW(hwnd, text_null.as_ptr()) == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(());
+    }
+}
+
+/// `true` if a check box is checked
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid check box or radio button handle.
+pub unsafe fn is_checked(hwnd: types::HWND) -> bool {
+    return window::SendMessageW(hwnd, window::BM_GETCHECK, 0, 0) as types::WPARAM == window::BST_CHECKED;
+}
+
+/// Checks or unchecks a check box. This doesn't send a notification.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid check box or radio button handle.
+pub unsafe fn set_checked(hwnd: types::HWND, checked: bool) {
+    let state = if checked { window::BST_CHECKED } else { window::BST_UNCHECKED };
+    window::SendMessageW(hwnd, window::BM_SETCHECK, state, 0);
+}
+
+/// Appends an item to a list box
+///
+/// **Returns:** the index of the new item
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid list box handle.
+pub unsafe fn list_box_add(hwnd: types::HWND, item: &str) -> Result<usize, Win32Error> {
+    let item_null = wide_null(item);
+    match window::SendMessageW(hwnd, window::LB_ADDSTRING, 0, item_null.as_ptr() as types::LPARAM) {
+        window::LB_ERRSPACE => return Err(Win32Error(core::ERROR_NOT_ENOUGH_MEMORY)),
+        window::LB_ERR => return Err(Win32Error(core::ERROR_INVALID_PARAMETER)),
+        index => return Ok(index as usize),
+    }
+}
+
+/// Removes every item of a list box
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid list box handle.
+pub unsafe fn list_box_clear(hwnd: types::HWND) {
+    window::SendMessageW(hwnd, window::LB_RESETCONTENT, 0, 0);
+}
+
+/// The number of items in a list box
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid list box handle.
+pub unsafe fn list_box_len(hwnd: types::HWND) -> usize {
+    return window::SendMessageW(hwnd, window::LB_GETCOUNT, 0, 0).max(0) as usize;
+}
+
+/// The selected item of a list box
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid list box handle.
+pub unsafe fn list_box_selection(hwnd: types::HWND) -> Option<usize> {
+    match window::SendMessageW(hwnd, window::LB_GETCURSEL, 0, 0) {
+        window::LB_ERR => return None,
+        index => return Some(index as usize),
+    }
+}
+
+/// Selects an item of a list box, or nothing. This doesn't send a notification.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid list box handle.
+pub unsafe fn list_box_select(hwnd: types::HWND, index: Option<usize>) {
+    let index = index.map(|index| index as types::WPARAM).unwrap_or(usize::MAX);
+    window::SendMessageW(hwnd, window::LB_SETCURSEL, index, 0);
+}
+
+/// Translates the notifications controls send to their parent into
+/// [`WindowEvent::Control`] events
+///
+/// **Returns:** `Some(result)` when the message was consumed and the window
+/// procedure should return `result`, `None` when it should fall through
+///
+/// ## Safety
+///
+/// Must be called from the window procedure of the controls' parent, with the
+/// message and parameters it received.
+pub unsafe fn handle_control_message(
+    msg: types::UINT,
+    wparam: types::WPARAM,
+    lparam: types::LPARAM,
+    events: &mut EventQueue,
+) -> Option<types::LRESULT> {
+    // Menus and accelerators send WM_COMMAND without a window handle
+    if msg != window::WM_COMMAND || lparam == 0 {
+        return None;
+    }
+    let hwnd = lparam as types::HWND;
+    let id = ControlId(core::LOWORD(wparam));
+    let code = core::HIWORD(wparam);
+
+    // Notification codes overlap between classes, so look at the sender
+    let event = match control_kind(hwnd)? {
+        ControlKind::Button => match code {
+            window::BN_CLICKED => ControlEvent::Clicked,
+            window::BN_SETFOCUS => ControlEvent::FocusGained,
+            window::BN_KILLFOCUS => ControlEvent::FocusLost,
+            _ => return None,
+        },
+        ControlKind::CheckBox => match code {
+            window::BN_CLICKED => ControlEvent::Toggled { checked: is_checked(hwnd) },
+            window::BN_SETFOCUS => ControlEvent::FocusGained,
+            window::BN_KILLFOCUS => ControlEvent::FocusLost,
+            _ => return None,
+        },
+        ControlKind::Edit | ControlKind::MultilineEdit => match code {
+            window::EN_CHANGE => ControlEvent::TextChanged,
+            window::EN_SETFOCUS => ControlEvent::FocusGained,
+            window::EN_KILLFOCUS => ControlEvent::FocusLost,
+            _ => return None,
+        },
+        ControlKind::ListBox => match code {
+            window::LBN_SELCHANGE => ControlEvent::SelectionChanged { index: list_box_selection(hwnd) },
+            window::LBN_DBLCLK => match list_box_selection(hwnd) {
+                Some(index) => ControlEvent::ItemActivated { index },
+                None => return Some(0),
+            },
+            window::LBN_SETFOCUS => ControlEvent::FocusGained,
+            window::LBN_KILLFOCUS => ControlEvent::FocusLost,
+            _ => return None,
+        },
+    };
+    events.push(WindowEvent::Control { id, event });
+    return Some(0);
+}
+
+/// Adds and removes extended window styles
+unsafe fn change_ex_style(hwnd: types::HWND, add: types::DWORD, remove: types::DWORD) -> Result<(), Win32Error> {
+    let style = window::GetWindowLongPtrW(hwnd, window::GWL_EXSTYLE) as types::DWORD;
+    let changed = (style | add) & !remove;
+    if changed == style {
+        return Ok(());
+    }
+    // 0 is also a valid previous style, so clear the error to tell them apart
+    set_last_error(Win32Error(0));
+    if window::SetWindowLongPtrW(hwnd, window::GWL_EXSTYLE, changed as types::LONG_PTR) == 0 {
+        let e = get_last_error();
+        if e.0 != 0 {
+            return Err(e);
+        }
+    }
+    return Ok(());
+}
+
+/// Makes the whole window translucent, from `0.0` (invisible) to `1.0`
+///
+/// Can't be combined with [`update_layered_window`] on the same window.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid top level window handle.
+///
+/// See [`SetLayeredWindowAttributes`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setlayeredwindowattributes)
+pub unsafe fn set_window_opacity(hwnd: types::HWND, opacity: f32) -> Result<(), Win32Error> {
+    change_ex_style(hwnd, window::WS_EX_LAYERED, 0)?;
+    let alpha = (opacity.clamp(0.0, 1.0) * 255.0).round() as types::BYTE;
+    if window::SetLayeredWindowAttributes(hwnd, 0, alpha, window::LWA_ALPHA) == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(());
+    }
+}
+
+/// Lets every mouse click through the window to whatever is below it,
+/// including other programs. Meant for overlays that never take input.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid top level window handle.
+pub unsafe fn set_click_through(hwnd: types::HWND, click_through: bool) -> Result<(), Win32Error> {
+    if click_through {
+        // WS_EX_TRANSPARENT only passes clicks on for layered windows
+        return change_ex_style(hwnd, window::WS_EX_TRANSPARENT | window::WS_EX_LAYERED, 0);
+    } else {
+        return change_ex_style(hwnd, 0, window::WS_EX_TRANSPARENT);
+    }
+}
+
+/// Sets the contents of a window with per-pixel alpha. The window takes
+/// the size of the image, and moves to `position` (screen coordinates)
+/// when given. Pixels with zero alpha don't receive mouse input.
+///
+/// `bgra` is premultiplied, top row first, see
+/// [`crate::layered::premultiplied_bgra`]. Such windows don't get
+/// `WM_PAINT`; call this again whenever the contents change.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid top level window handle.
+///
+/// See [`UpdateLayeredWindow`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-updatelayeredwindow)
+pub unsafe fn update_layered_window(
+    hwnd: types::HWND,
+    position: Option<Point>,
+    Size { width, height }: Size,
+    bgra: &[u8],
+) -> Result<(), Win32Error> {
+    if width <= 0 || height <= 0 || bgra.len() as u64 != width as u64 * height as u64 * 4 {
+        return Err(Win32Error(core::ERROR_INVALID_PARAMETER));
+    }
+    change_ex_style(hwnd, window::WS_EX_LAYERED, 0)?;
+
+    let mut info = gdi::BITMAPINFO::default();
+    info.bmiHeader.biSize = std::mem::size_of::<gdi::BITMAPINFOHEADER>() as types::DWORD;
+    info.bmiHeader.biWidth = width;
+    info.bmiHeader.biHeight = -height;
+    info.bmiHeader.biPlanes = 1;
+    info.bmiHeader.biBitCount = 32;
+    info.bmiHeader.biCompression = gdi::BI_RGB;
+
+    let screen = window::GetDC(std::ptr::null_mut());
+    let memory_dc = gdi::CreateCompatibleDC(screen);
+    let mut bits: *mut std::ffi::c_void = std::ptr::null_mut();
+    let bitmap = gdi::CreateDIBSection(memory_dc, &info, gdi::DIB_RGB_COLORS, &mut bits, std::ptr::null_mut(), 0);
+    if memory_dc.is_null() || bitmap.is_null() || bits.is_null() {
+        let e = get_last_error();
+        if !bitmap.is_null() {
+            gdi::DeleteObject(bitmap);
+        }
+        if !memory_dc.is_null() {
+            gdi::DeleteDC(memory_dc);
+        }
+        window::ReleaseDC(std::ptr::null_mut(), screen);
+        return Err(e);
+    }
+    std::ptr::copy_nonoverlapping(bgra.as_ptr(), bits as *mut u8, bgra.len());
+    let previous = gdi::SelectObject(memory_dc, bitmap);
+
+    let size = window::SIZE { cx: width, cy: height };
+    let source = window::POINT::new(0, 0);
+    let destination = position.map(window::POINT::from);
+    let blend = gdi::BLENDFUNCTION {
+        BlendOp: gdi::AC_SRC_OVER,
+        BlendFlags: 0,
+        SourceConstantAlpha: 255,
+        AlphaFormat: gdi::AC_SRC_ALPHA,
+    };
+    let ok = window::UpdateLayeredWindow(
+        hwnd,
+        screen,
+        destination.as_ref().map_or(std::ptr::null(), |point| point as *const _),
+        &size,
+        memory_dc,
+        &source,
+        0,
+        &blend,
+        window::ULW_ALPHA,
+    ) != 0;
+    let e = get_last_error();
+
+    gdi::SelectObject(memory_dc, previous);
+    gdi::DeleteObject(bitmap);
+    gdi::DeleteDC(memory_dc);
+    window::ReleaseDC(std::ptr::null_mut(), screen);
+
+    if !ok {
+        return Err(e);
+    } else {
+        return Ok(());
+    }
+}
+
+/// Answers `WM_NCHITTEST` with a hook that gets the mouse position in
+/// client coordinates. `HitTest::Default` leaves the message to
+/// `DefWindowProcW`.
+///
+/// **Returns:** `Some(result)` when the message was consumed and the window
+/// procedure should return `result`, `None` when it should fall through
+///
+/// ## Safety
+///
+/// Must be called from the window procedure of `hwnd`, with the message and
+/// parameters it received.
+pub unsafe fn handle_hit_test_message(
+    hwnd: types::HWND,
+    msg: types::UINT,
+    lparam: types::LPARAM,
+    hook: impl FnOnce(Point) -> HitTest,
+) -> Option<types::LRESULT> {
+    if msg != window::WM_NCHITTEST {
+        return None;
+    }
+    let mut point = window::POINT::new(core::GET_X_LPARAM(lparam), core::GET_Y_LPARAM(lparam));
+    window::ScreenToClient(hwnd, &mut point);
+    match hook(Point::from(point)) {
+        HitTest::Default => return None,
+        HitTest::Client => return Some(window::HTCLIENT),
+        HitTest::Caption => return Some(window::HTCAPTION),
+        HitTest::PassThrough => return Some(window::HTTRANSPARENT),
+        HitTest::SystemMenu => return Some(window::HTSYSMENU),
+        HitTest::MinimizeButton => return Some(window::HTMINBUTTON),
+        HitTest::MaximizeButton => return Some(window::HTMAXBUTTON),
+        HitTest::CloseButton => return Some(window::HTCLOSE),
+        HitTest::Border(edge) => match edge {
+            ResizeEdge::Left => return Some(window::HTLEFT),
+            ResizeEdge::Right => return Some(window::HTRIGHT),
+            ResizeEdge::Top => return Some(window::HTTOP),
+            ResizeEdge::Bottom => return Some(window::HTBOTTOM),
+            ResizeEdge::TopLeft => return Some(window::HTTOPLEFT),
+            ResizeEdge::TopRight => return Some(window::HTTOPRIGHT),
+            ResizeEdge::BottomLeft => return Some(window::HTBOTTOMLEFT),
+            ResizeEdge::BottomRight => return Some(window::HTBOTTOMRIGHT),
+        },
+    }
+}
+
+/// Makes the window recalculate its frame. Call it after starting or
+/// stopping to pass messages to [`handle_custom_frame_message`].
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`SetWindowPos`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setwindowpos)
+pub unsafe fn refresh_window_frame(hwnd: types::HWND) -> Result<(), Win32Error> {
+    let flags = window::SWP_FRAMECHANGED | window::SWP_NOMOVE | window::SWP_NOSIZE
+        | window::SWP_NOZORDER | window::SWP_NOACTIVATE;
+    if window::SetWindowPos(hwnd, std::ptr::null_mut(), 0, 0, 0, 0, flags) == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(());
+    }
+}
+
+/// Removes the standard title bar and borders so the application can draw
+/// its own, while the window keeps its styles and with them resizing,
+/// snapping and the minimize/maximize animations. The client area covers
+/// the whole window, and the application's hit-test hook (see
+/// [`handle_hit_test_message`]) decides where the caption, borders and
+/// buttons are. The menu bar isn't drawn either.
+///
+/// Clicking the window buttons reported by the hook performs their
+/// action, without the classic buttons Windows would otherwise draw.
+///
+/// **Returns:** `Some(result)` when the message was consumed and the window
+/// procedure should return `result`, `None` when it should fall through
+///
+/// ## Safety
+///
+/// Must be called from the window procedure of `hwnd`, with the message and
+/// parameters it received.
+pub unsafe fn handle_custom_frame_message(
+    hwnd: types::HWND,
+    msg: types::UINT,
+    wparam: types::WPARAM,
+    lparam: types::LPARAM,
+) -> Option<types::LRESULT> {
+    match msg {
+        window::WM_NCCALCSIZE if wparam != 0 => {
+            // A maximized window hangs over the monitor edges by its frame
+            // size, so keep that part out of the client area
+            if is_maximized(hwnd) {
+                let params = &mut *(lparam as *mut window::NCCALCSIZE_PARAMS);
+                let padding = window::GetSystemMetrics(window::SM_CXPADDEDBORDER);
+                let frame_x = window::GetSystemMetrics(window::SM_CXFRAME) + padding;
+                let frame_y = window::GetSystemMetrics(window::SM_CYFRAME) + padding;
+                let proposed = &params.rgrc[0];
+                params.rgrc[0] = window::RECT::new(
+                    proposed.left() + frame_x,
+                    proposed.top() + frame_y,
+                    proposed.right() - frame_x,
+                    proposed.bottom() - frame_y,
+                );
+            }
+            return Some(0);
+        }
+        window::WM_NCLBUTTONDOWN => match wparam as types::LRESULT {
+            window::HTMINBUTTON | window::HTMAXBUTTON | window::HTCLOSE => return Some(0),
+            _ => return None,
+        },
+        window::WM_NCLBUTTONUP => {
+            match wparam as types::LRESULT {
+                window::HTMINBUTTON => minimize_window(hwnd),
+                window::HTMAXBUTTON => toggle_maximize_window(hwnd),
+                window::HTCLOSE => close_window(hwnd),
+                _ => return None,
+            }
+            return Some(0);
+        }
+        _ => return None,
+    }
+}
+
+/// `true` if the window is maximized
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`IsZoomed`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-iszoomed)
+pub unsafe fn is_maximized(hwnd: types::HWND) -> bool {
+    return window::IsZoomed(hwnd) != 0;
+}
+
+/// Minimizes the window the way its minimize button would
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+pub unsafe fn minimize_window(hwnd: types::HWND) {
+    window::PostMessageW(hwnd, window::WM_SYSCOMMAND, window::SC_MINIMIZE, 0);
+}
+
+/// Maximizes the window the way its maximize button would
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+pub unsafe fn maximize_window(hwnd: types::HWND) {
+    window::PostMessageW(hwnd, window::WM_SYSCOMMAND, window::SC_MAXIMIZE, 0);
+}
+
+/// Restores a minimized or maximized window
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+pub unsafe fn restore_window(hwnd: types::HWND) {
+    window::PostMessageW(hwnd, window::WM_SYSCOMMAND, window::SC_RESTORE, 0);
+}
+
+/// Maximizes the window, or restores it if it already is
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+pub unsafe fn toggle_maximize_window(hwnd: types::HWND) {
+    if is_maximized(hwnd) {
+        restore_window(hwnd);
+    } else {
+        maximize_window(hwnd);
+    }
+}
+
+/// Asks the window to close the way its close button would, so it still
+/// gets `WM_CLOSE` and can refuse
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+pub unsafe fn close_window(hwnd: types::HWND) {
+    window::PostMessageW(hwnd, window::WM_SYSCOMMAND, window::SC_CLOSE, 0);
+}
+
+/// Shows the system menu (Restore, Move, Size, ..., Close) at a position
+/// in client coordinates and carries out the chosen command
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle belonging to the calling thread. Its
+/// window procedure keeps running while the menu is open.
+///
+/// See [`GetSystemMenu`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getsystemmenu)
+pub unsafe fn show_system_menu(hwnd: types::HWND, position: Point) -> Result<(), Win32Error> {
+    let hmenu = window::GetSystemMenu(hwnd, 0);
+    if hmenu.is_null() {
+        return Err(get_last_error());
+    }
+    let mut point = window::POINT::from(position);
+    window::ClientToScreen(hwnd, &mut point);
+
+    let command = window::TrackPopupMenu(
+        hmenu,
+        window::TPM_LEFTALIGN | window::TPM_TOPALIGN | window::TPM_RIGHTBUTTON | window::TPM_RETURNCMD,
+        point.x(),
+        point.y(),
+        0,
+        hwnd,
+        std::ptr::null(),
+    );
+    if command != 0 {
+        window::PostMessageW(hwnd, window::WM_SYSCOMMAND, command as types::WPARAM, 0);
+    }
+    return Ok(());
+}
+
+/// The client area, in client coordinates so its origin is always 0, 0
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`GetClientRect`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getclientrect)
+pub unsafe fn get_client_rect(hwnd: types::HWND) -> Result<Rect, Win32Error> {
+    let mut rect = window::RECT::default();
+    if window::GetClientRect(hwnd, &mut rect) == 0 {
+        return Err(get_last_error());
+    } else {
+        return Ok(Rect::from(rect));
+    }
+}
+
+/// Width and height of the client area
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+pub unsafe fn get_client_size(hwnd: types::HWND) -> Result<Size, Win32Error> {
+    return get_client_rect(hwnd).map(|rect| rect.size());
+}
+
+/// The DPI of the monitor a window is on, to scale logical pixels with
+/// [`Rect::to_physical`] and friends. Windows that aren't DPI aware are
+/// scaled by the system and always get [`DEFAULT_DPI`].
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`GetDpiForWindow`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getdpiforwindow)
+pub unsafe fn get_window_dpi(hwnd: types::HWND) -> u32 {
+    let dpi = window::GetDpiForWindow(hwnd);
+    return if dpi == 0 { DEFAULT_DPI } else { dpi };
+}
+
+/// Fills a rectangle with an RGB colour
+///
+/// ## Safety
+///
+/// `hdc` must be a valid device context.
+///
+/// See [`CreateSolidBrush`](https://docs.microsoft.com/en-us/windows/win32/api/wingdi/nf-wingdi-createsolidbrush)
+pub unsafe fn fill_rect_with_color(
+    hdc: types::HDC, rect: Rect, [r, g, b]: [u8; 3],
+) -> Result<(), Win32Error> {
+    let brush = gdi::CreateSolidBrush(gdi::RGB(r, g, b));
+    if brush.is_null() {
+        return Err(get_last_error());
+    }
+    let rect = window::RECT::from(rect);
+    let filled = window::FillRect(hdc, &rect, brush) != 0;
+    gdi::DeleteObject(brush);
+    if !filled {
+        return Err(get_last_error());
+    } else {
+        return Ok(());
+    }
+}
+
+fn monitor_from_handle(hmonitor: types::HMONITOR) -> Option<Monitor> {
+    let mut info = window::MONITORINFOEXW::default();
+    if hmonitor.is_null() || unsafe { window::GetMonitorInfoW(hmonitor, &mut info) } == 0 {
+        return None;
+    }
+    let name_len = info.szDevice.iter().position(|&c| c == 0).unwrap_or(info.szDevice.len());
+    return Some(Monitor {
+        name: String::from_utf16_lossy(&info.szDevice[..name_len]),
+        rect: Rect::from(info.rcMonitor),
+        work_rect: Rect::from(info.rcWork),
+        primary: info.dwFlags & window::MONITORINFOF_PRIMARY != 0,
+    });
+}
+
+/// Every monitor attached to the desktop
+///
+/// See [`EnumDisplayMonitors`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-enumdisplaymonitors)
+pub fn enumerate_monitors() -> Vec<Monitor> {
+    unsafe extern "system" fn callback(
+        hmonitor: types::HMONITOR, _hdc: types::HDC, _rect: *mut window::RECT, data: types::LPARAM,
+    ) -> types::BOOL {
+        let monitors = &mut *(data as *mut Vec<Monitor>);
+        if let Some(monitor) = monitor_from_handle(hmonitor) {
+            monitors.push(monitor);
+        }
+        return 1;
+    }
+
+    let mut monitors: Vec<Monitor> = Vec::new();
+    unsafe {
+        window::EnumDisplayMonitors(
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            Some(callback),
+            &mut monitors as *mut Vec<Monitor> as types::LPARAM,
+        );
+    }
+    return monitors;
+}
+
+/// The monitor a window is on, or the nearest one when it is off screen
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`MonitorFromWindow`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-monitorfromwindow)
+pub unsafe fn window_monitor(hwnd: types::HWND) -> Option<Monitor> {
+    return monitor_from_handle(window::MonitorFromWindow(hwnd, window::MONITOR_DEFAULTTONEAREST));
+}
+
+thread_local! {
+    /// Placement of each fullscreen window from before it went fullscreen.
+    /// Windows belong to the thread that created them, so this is per thread.
+    static FULLSCREEN_WINDOWS: std::cell::RefCell<std::collections::HashMap<usize, WindowPlacement>> =
+        std::cell::RefCell::new(std::collections::HashMap::new());
+}
+
+/// `true` if the window was made fullscreen with [`set_fullscreen`]
+pub fn is_fullscreen(hwnd: types::HWND) -> bool {
+    return FULLSCREEN_WINDOWS.with(|windows| windows.borrow().contains_key(&(hwnd as usize)));
+}
+
+/// Forgets the fullscreen state of a window when it is destroyed, so a
+/// later window that gets the same handle doesn't start out fullscreen.
+/// Call it from the window procedure of every window passed to
+/// [`set_fullscreen`], after [`get_window_placement`] if the placement
+/// is saved on `WM_DESTROY`. It never consumes the message.
+pub fn handle_fullscreen_message(hwnd: types::HWND, msg: types::UINT) {
+    if msg == window::WM_DESTROY {
+        FULLSCREEN_WINDOWS.with(|windows| windows.borrow_mut().remove(&(hwnd as usize)));
+    }
+}
+
+/// Makes a window cover its whole monitor without a frame, or brings it
+/// back to where it was before. See [`handle_fullscreen_message`].
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid top level window handle belonging to the calling
+/// thread.
+pub unsafe fn set_fullscreen(hwnd: types::HWND, fullscreen: bool) -> Result<(), Win32Error> {
+    if fullscreen == is_fullscreen(hwnd) {
+        return Ok(());
+    }
+    let style = window::GetWindowLongPtrW(hwnd, window::GWL_STYLE) as u32;
+
+    if fullscreen {
+        let previous = get_window_placement(hwnd)?;
+        let monitor = window_monitor(hwnd).ok_or_else(get_last_error)?;
+        window::SetWindowLongPtrW(hwnd, window::GWL_STYLE, (style & !window::WS_OVERLAPPEDWINDOW) as types::LONG_PTR);
+        FULLSCREEN_WINDOWS.with(|windows| windows.borrow_mut().insert(hwnd as usize, previous));
+        let Rect { left, top, .. } = monitor.rect;
+        let Size { width, height } = monitor.rect.size();
+        if window::SetWindowPos(
+            hwnd, std::ptr::null_mut(), left, top, width, height,
+            window::SWP_NOOWNERZORDER | window::SWP_FRAMECHANGED,
+        ) == 0 {
+            return Err(get_last_error());
+        }
+        return Ok(());
+    } else {
+        window::SetWindowLongPtrW(hwnd, window::GWL_STYLE, (style | window::WS_OVERLAPPEDWINDOW) as types::LONG_PTR);
+        let previous = FULLSCREEN_WINDOWS.with(|windows| windows.borrow_mut().remove(&(hwnd as usize)));
+        refresh_window_frame(hwnd)?;
+        match previous {
+            Some(previous) => return set_window_placement(hwnd, &previous),
+            None => return Ok(()),
+        }
+    }
+}
+
+/// Captures where a window is so it can be restored with
+/// [`set_window_placement`], e.g. in the next run
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`GetWindowPlacement`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getwindowplacement)
+pub unsafe fn get_window_placement(hwnd: types::HWND) -> Result<WindowPlacement, Win32Error> {
+    let fullscreen = FULLSCREEN_WINDOWS.with(|windows| windows.borrow().get(&(hwnd as usize)).cloned());
+    if let Some(mut placement) = fullscreen {
+        placement.fullscreen = true;
+        placement.minimized = window::IsIconic(hwnd) != 0;
+        return Ok(placement);
+    }
+
+    let mut wp = window::WINDOWPLACEMENT::default();
+    if window::GetWindowPlacement(hwnd, &mut wp) == 0 {
+        return Err(get_last_error());
+    }
+    let monitor = window_monitor(hwnd);
+    // The normal rectangle is relative to the monitor's work area
+    let offset = monitor
+        .as_ref()
+        .map(|m| m.work_rect.origin() - m.rect.origin())
+        .unwrap_or_default();
+    let minimized = wp.showCmd == window::SW_SHOWMINIMIZED as types::UINT;
+
+    return Ok(WindowPlacement {
+        monitor: monitor.map(|m| m.name),
+        rect: Rect::from(wp.rcNormalPosition).offset(offset.x, offset.y),
+        maximized: wp.showCmd == window::SW_SHOWMAXIMIZED as types::UINT
+            || (minimized && wp.flags & window::WPF_RESTORETOMAXIMIZED != 0),
+        minimized,
+        fullscreen: false,
+    });
+}
+
+/// Moves a window to a saved placement, clamped to the monitors there are
+/// now, and shows it in the saved state
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`SetWindowPlacement`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-setwindowplacement)
+pub unsafe fn set_window_placement(hwnd: types::HWND, placement: &WindowPlacement) -> Result<(), Win32Error> {
+    let monitors = enumerate_monitors();
+    let placement = placement.clamped(&monitors);
+    let offset = monitors
+        .iter()
+        .find(|m| Some(&m.name) == placement.monitor.as_ref())
+        .map(|m| m.work_rect.origin() - m.rect.origin())
+        .unwrap_or_default();
+
+    let mut wp = window::WINDOWPLACEMENT {
+        rcNormalPosition: placement.rect.offset(-offset.x, -offset.y).into(),
+        showCmd: if placement.minimized {
+            window::SW_SHOWMINIMIZED
+        } else if placement.maximized && !placement.fullscreen {
+            window::SW_SHOWMAXIMIZED
+        } else {
+            window::SW_SHOWNORMAL
+        } as types::UINT,
+        ..Default::default()
+    };
+    if placement.minimized && placement.maximized {
+        wp.flags = window::WPF_RESTORETOMAXIMIZED;
+    }
+    if window::SetWindowPlacement(hwnd, &wp) == 0 {
+        return Err(get_last_error());
+    }
+
+    if placement.fullscreen {
+        // Go back to the normal placement when leaving fullscreen
+        set_fullscreen(hwnd, true)?;
+        FULLSCREEN_WINDOWS.with(|windows| {
+            let mut normal = placement.clone();
+            normal.fullscreen = false;
+            normal.minimized = false;
+            windows.borrow_mut().insert(hwnd as usize, normal);
+        });
+    }
+    return Ok(());
+}
+
+/// Creates a top level window of a registered class and shows it, at the
+/// saved placement when the builder has one
+///
+/// ## Safety
+///
+/// `class_name` must be a class registered with [`register_class`] and
+/// `create_param` whatever its window procedure expects in `WM_NCCREATE` and
+/// `WM_CREATE`. The window procedure runs before this returns.
+pub unsafe fn create_window(
+    class_name: &str,
+    builder: &WindowBuilder,
+    create_param: types::LPVOID,
+) -> Result<types::HWND, Win32Error> {
+    let hwnd = create_app_window(class_name, &builder.title, builder.position, builder.size, create_param)?;
+    let placed = match &builder.placement {
+        Some(placement) => set_window_placement(hwnd, placement).is_ok(),
+        None => false,
+    };
+    if !placed {
+        window::ShowWindow(hwnd, window::SW_SHOW);
+    }
+    return Ok(hwnd);
+}
+
+/// Timer that polls the low memory notification of [`Win32SystemEvents`]
+const LOW_MEMORY_TIMER: types::UINT_PTR = 0x4C4D;
+const LOW_MEMORY_POLL_MS: types::UINT = 2000;
+
+/// Subscribes a top level window to the system changes it doesn't get by
+/// default, and translates those and the broadcast ones into
+/// [`WindowEvent::System`] events
+///
+/// Session lock notifications are registered with the window; low memory
+/// is checked on a timer of the window. Both stop when this is dropped,
+/// which must happen before the window is destroyed (e.g. in `WM_DESTROY`).
+pub struct Win32SystemEvents {
+    hwnd: types::HWND,
+    low_memory: types::HANDLE,
+    was_low_on_memory: bool,
+}
+
+impl Win32SystemEvents {
+    /// ## Safety
+    ///
+    /// `hwnd` must be a valid top level window handle belonging to the calling
+    /// thread, and the returned value must be dropped before the window is
+    /// destroyed.
+    ///
+    /// See [`WTSRegisterSessionNotification`](https://docs.microsoft.com/en-us/windows/win32/api/wtsapi32/nf-wtsapi32-wtsregistersessionnotification)
+    /// and [`CreateMemoryResourceNotification`](https://docs.microsoft.com/en-us/windows/win32/api/memoryapi/nf-memoryapi-creatememoryresourcenotification)
+    pub unsafe fn register(hwnd: types::HWND) -> Result<Self, Win32Error> {
+        if window::WTSRegisterSessionNotification(hwnd, window::NOTIFY_FOR_THIS_SESSION) == 0 {
+            return Err(get_last_error());
+        }
+        let events = Win32SystemEvents {
+            hwnd,
+            low_memory: core::CreateMemoryResourceNotification(core::LOW_MEMORY_RESOURCE_NOTIFICATION),
+            was_low_on_memory: false,
+        };
+        if events.low_memory.is_null() {
+            let error = get_last_error();
+            drop(events);
+            return Err(error);
+        }
+        if window::SetTimer(hwnd, LOW_MEMORY_TIMER, LOW_MEMORY_POLL_MS, None) == 0 {
+            let error = get_last_error();
+            drop(events);
+            return Err(error);
+        }
+        return Ok(events);
+    }
+
+    fn is_low_on_memory(&self) -> bool {
+        let mut low: types::BOOL = 0;
+        let queried = unsafe { core::QueryMemoryResourceNotification(self.low_memory, &mut low) };
+        return queried != 0 && low != 0;
+    }
+
+    /// Translates display, power, session and theme messages, and the low
+    /// memory timer, into events. Broadcast messages that other code of
+    /// the window might need are not consumed.
+    ///
+    /// **Returns:** `Some(result)` when the message was consumed and the window
+    /// procedure should return `result`, `None` when it should fall through
+    ///
+    /// ## Safety
+    ///
+    /// Must be called from the window procedure of the window given to
+    /// [`Win32SystemEvents::register`], with the message and parameters it
+    /// received.
+    pub unsafe fn handle_message(
+        &mut self,
+        msg: types::UINT,
+        wparam: types::WPARAM,
+        lparam: types::LPARAM,
+        events: &mut EventQueue,
+    ) -> Option<types::LRESULT> {
+        match msg {
+            window::WM_DISPLAYCHANGE => {
+                events.push(WindowEvent::System(SystemEvent::DisplayChanged));
+                return None;
+            }
+            window::WM_POWERBROADCAST => {
+                // A resume started by the user also sends PBT_APMRESUMESUSPEND,
+                // but the automatic one is sent for every resume
+                match wparam {
+                    window::PBT_APMSUSPEND => events.push(WindowEvent::System(SystemEvent::Suspend)),
+                    window::PBT_APMRESUMEAUTOMATIC => events.push(WindowEvent::System(SystemEvent::Resume)),
+                    _ => {}
+                }
+                return Some(1);
+            }
+            window::WM_WTSSESSION_CHANGE => {
+                match wparam {
+                    window::WTS_SESSION_LOCK => events.push(WindowEvent::System(SystemEvent::SessionLocked)),
+                    window::WTS_SESSION_UNLOCK => events.push(WindowEvent::System(SystemEvent::SessionUnlocked)),
+                    _ => {}
+                }
+                return Some(0);
+            }
+            window::WM_SETTINGCHANGE => {
+                // Switching between light and dark mode changes the "ImmersiveColorSet" setting
+                if lparam != 0 && wide_str_eq(lparam as types::LPCWSTR, "ImmersiveColorSet") {
+                    events.push(WindowEvent::System(SystemEvent::ThemeChanged));
+                }
+                return None;
+            }
+            window::WM_SYSCOLORCHANGE | window::WM_THEMECHANGED => {
+                events.push(WindowEvent::System(SystemEvent::ThemeChanged));
+                return None;
+            }
+            window::WM_TIMER if wparam == LOW_MEMORY_TIMER => {
+                let low = self.is_low_on_memory();
+                if low && !self.was_low_on_memory {
+                    events.push(WindowEvent::System(SystemEvent::LowMemory));
+                }
+                self.was_low_on_memory = low;
+                return Some(0);
+            }
+            _ => return None,
+        }
+    }
+}
+
+impl Drop for Win32SystemEvents {
+    fn drop(&mut self) {
+        unsafe {
+            window::KillTimer(self.hwnd, LOW_MEMORY_TIMER);
+            window::WTSUnRegisterSessionNotification(self.hwnd);
+            if !self.low_memory.is_null() {
+                core::CloseHandle(self.low_memory);
+            }
+        }
+    }
+}
+
+/// Compares a NUL terminated wide string with `text`
+unsafe fn wide_str_eq(mut wide: types::LPCWSTR, text: &str) -> bool {
+    for unit in text.encode_utf16() {
+        if *wide != unit {
+            return false;
+        }
+        wide = wide.add(1);
+    }
+    return *wide == 0;
+}
+
+/// The RGB value of a system colour
+///
+/// See [`GetSysColor`](https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getsyscolor)
+pub fn sys_color(color: SysColor) -> [u8; 3] {
+    let colorref = unsafe { window::GetSysColor(color as types::c_int) };
+    return [colorref as u8, (colorref >> 8) as u8, (colorref >> 16) as u8];
+}
+
+/// Reads a `DWORD` value of the current user's registry
+///
+/// See [`RegGetValueW`](https://docs.microsoft.com/en-us/windows/win32/api/winreg/nf-winreg-reggetvaluew)
+fn read_user_dword(key: &str, value: &str) -> Option<types::DWORD> {
+    let key_null = wide_null(key);
+    let value_null = wide_null(value);
+    let mut data: types::DWORD = 0;
+    let mut size = std::mem::size_of::<types::DWORD>() as types::DWORD;
+    let status = unsafe {
+        registry::RegGetValueW(
+            registry::HKEY_CURRENT_USER,
+            key_null.as_ptr(),
+            value_null.as_ptr(),
+            registry::RRF_RT_REG_DWORD,
+            std::ptr::null_mut(),
+            &mut data as *mut types::DWORD as types::PVOID,
+            &mut size,
+        )
+    };
+    if status == registry::ERROR_SUCCESS {
+        return Some(data);
+    } else {
+        return None;
+    }
+}
+
+/// Whether the user chose light or dark mode for applications. Windows
+/// versions without the setting are light.
+pub fn color_scheme() -> ColorScheme {
+    let key = "Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize";
+    match read_user_dword(key, "AppsUseLightTheme") {
+        Some(0) => return ColorScheme::Dark,
+        _ => return ColorScheme::Light,
+    }
+}
+
+/// The colour the desktop window manager paints accents and title bars with
+///
+/// See [`DwmGetColorizationColor`](https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/nf-dwmapi-dwmgetcolorizationcolor)
+pub fn accent_color() -> Option<[u8; 3]> {
+    let mut argb: types::DWORD = 0;
+    let mut opaque: types::BOOL = 0;
+    if unsafe { dwm::DwmGetColorizationColor(&mut argb, &mut opaque) } < 0 {
+        return None;
+    }
+    return Some([(argb >> 16) as u8, (argb >> 8) as u8, argb as u8]);
+}
+
+/// The user's current colours. Read it again on
+/// [`crate::event::SystemEvent::ThemeChanged`].
+///
+/// `GetSysColor` keeps returning the classic light colours in dark mode,
+/// so it is only read for the light scheme; the dark one uses
+/// [`Palette::dark`](crate::theme::Palette::dark).
+pub fn current_theme() -> Theme {
+    let mut theme = Theme::new(color_scheme());
+    if !theme.is_dark() {
+        for color in SysColor::ALL {
+            theme.palette.set(color, sys_color(color));
+        }
+    }
+    theme.accent = accent_color();
+    return theme;
+}
+
+/// Switches the title bar and frame of a window between the light and
+/// dark look. Has no effect before Windows 10 1809.
+///
+/// ## Safety
+///
+/// `hwnd` must be a valid window handle.
+///
+/// See [`DwmSetWindowAttribute`](https://docs.microsoft.com/en-us/windows/win32/api/dwmapi/nf-dwmapi-dwmsetwindowattribute)
+pub unsafe fn set_dark_title_bar(hwnd: types::HWND, dark: bool) -> Result<(), Win32Error> {
+    let value: types::BOOL = dark as types::BOOL;
+    let set = |attribute| dwm::DwmSetWindowAttribute(
+        hwnd,
+        attribute,
+        &value as *const types::BOOL as types::LPCVOID,
+        std::mem::size_of::<types::BOOL>() as types::DWORD,
+    );
+    let mut hr = set(dwm::DWMWA_USE_IMMERSIVE_DARK_MODE);
+    if hr < 0 {
+        hr = set(dwm::DWMWA_USE_IMMERSIVE_DARK_MODE_BEFORE_20H1);
+    }
+    if hr < 0 {
+        return Err(Win32Error(hr as types::DWORD));
+    } else {
+        return Ok(());
+    }
+}
+
+thread_local! {
+    /// Tracer of the window procedures of this thread, see [`trace_message`]
+    static MESSAGE_TRACER: std::cell::RefCell<Option<MessageTracer>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Installs the tracer that [`trace_message`] records into for the
+/// windows of this thread, or removes it with `None`
+///
+/// **Returns:** the previous tracer, with what it recorded
+pub fn set_message_tracer(tracer: Option<MessageTracer>) -> Option<MessageTracer> {
+    return MESSAGE_TRACER.with(|current| current.replace(tracer));
+}
+
+/// Runs `f` with the installed tracer, e.g. to change its filter or
+/// export the records
+///
+/// **Returns:** `None` if no tracer is installed
+pub fn with_message_tracer<T>(f: impl FnOnce(&mut MessageTracer) -> T) -> Option<T> {
+    return MESSAGE_TRACER.with(|current| current.try_borrow_mut().ok()?.as_mut().map(f));
+}
+
+/// Calls a window procedure and records the message and its result in
+/// the installed tracer. Wrap the body of the window procedure with it;
+/// without a tracer it only calls `procedure`.
+pub fn trace_message(
+    hwnd: types::HWND,
+    msg: types::UINT,
+    wparam: types::WPARAM,
+    lparam: types::LPARAM,
+    procedure: impl FnOnce() -> types::LRESULT,
+) -> types::LRESULT {
+    let tracing = with_message_tracer(|tracer| tracer.begin(hwnd as usize, msg, wparam, lparam));
+    let result = procedure();
+    if let Some(token) = tracing {
+        with_message_tracer(|tracer| tracer.finish(token, result));
+    }
+    return result;
+}
@@ -0,0 +1,46 @@
+//! Description of a top level window to create
+//!
+//! Backends create the window from a [`WindowBuilder`] and show it. A saved
+//! [`WindowPlacement`] takes precedence over the position and size.
+
+use crate::geometry::{Point, Size};
+use crate::placement::WindowPlacement;
+
+/// Title, size and placement of a new window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowBuilder {
+    pub title: String,
+    /// Outer position in screen coordinates, or `None` to let the system pick
+    pub position: Option<Point>,
+    /// Outer size
+    pub size: Size,
+    /// Where the window was last time. It is clamped to the current
+    /// monitors before it is applied.
+    pub placement: Option<WindowPlacement>,
+}
+
+impl WindowBuilder {
+    pub fn new(title: &str) -> Self {
+        return WindowBuilder {
+            title: title.to_owned(),
+            position: None,
+            size: Size::new(800, 600),
+            placement: None,
+        };
+    }
+
+    pub fn position(mut self, position: Point) -> Self {
+        self.position = Some(position);
+        return self;
+    }
+
+    pub fn size(mut self, size: Size) -> Self {
+        self.size = size;
+        return self;
+    }
+
+    pub fn placement(mut self, placement: WindowPlacement) -> Self {
+        self.placement = Some(placement);
+        return self;
+    }
+}